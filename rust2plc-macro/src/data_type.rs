@@ -0,0 +1,248 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DataEnum, DeriveInput, Expr, ExprLit, Fields, Lit, LitStr, UnOp};
+
+use crate::st::plc_type;
+use crate::tokens;
+
+/// The `#[plc(description = "...", namespace = "...", version = "...")]` of the type.
+#[derive(Default)]
+struct TypeMeta {
+    description: Option<String>,
+    namespace: Option<String>,
+    version: Option<String>,
+}
+
+fn parse_type_meta(input: &DeriveInput) -> syn::Result<TypeMeta> {
+    let mut meta = TypeMeta::default();
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("plc")) {
+        attr.parse_nested_meta(|nested| {
+            let value = || nested.value()?.parse::<LitStr>().map(|s| Some(s.value()));
+            if nested.path.is_ident("description") {
+                meta.description = value()?;
+            } else if nested.path.is_ident("namespace") {
+                meta.namespace = value()?;
+            } else if nested.path.is_ident("version") {
+                meta.version = value()?;
+            } else {
+                return Err(nested.error("expected description, namespace or version"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(meta)
+}
+
+/// The `#[plc(init = ...)]` of a field.
+fn parse_init(attrs: &[syn::Attribute]) -> syn::Result<Option<Expr>> {
+    let mut init = None;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("plc")) {
+        attr.parse_nested_meta(|nested| {
+            if nested.path.is_ident("init") {
+                init = Some(nested.value()?.parse::<Expr>()?);
+                Ok(())
+            } else {
+                Err(nested.error("expected init"))
+            }
+        })?;
+    }
+    Ok(init)
+}
+
+fn meta_setters(meta: &TypeMeta) -> TokenStream {
+    let description = tokens::opt_string(meta.description.as_deref());
+    let namespace = tokens::opt_string(meta.namespace.as_deref());
+    let version = tokens::opt_string(meta.version.as_deref());
+    quote! {
+        .with_description(#description)
+        .with_namespace(#namespace)
+        .with_version(#version)
+    }
+}
+
+/// Implements `PlcType` for the type and registers it in the type table.
+pub(crate) fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    if !input.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.generics,
+            "generic types cannot be mapped to PLC types",
+        ));
+    }
+    let meta = parse_type_meta(&input)?;
+    let name = &input.ident;
+    let name_str = name.to_string();
+
+    let mut extra = TokenStream::new();
+    let declaration = match &input.data {
+        Data::Struct(data) => {
+            let Fields::Named(fields) = &data.fields else {
+                return Err(syn::Error::new_spanned(
+                    &data.fields,
+                    "PlcType requires a struct with named fields",
+                ));
+            };
+            let mut layout = vec![];
+            for field in fields.named.iter() {
+                let field_name = field
+                    .ident
+                    .as_ref()
+                    .map(|i| i.to_string())
+                    .unwrap_or_default();
+                let ty = &field.ty;
+                let value = match parse_init(&field.attrs)? {
+                    Some(Expr::Lit(ExprLit {
+                        lit: Lit::Str(s), ..
+                    })) => quote! {{
+                        let value: #ty = #s.to_string();
+                        ::rust2plc::types::TypedValue::from(value)
+                    }},
+                    Some(init) => quote! {{
+                        let value: #ty = #init;
+                        ::rust2plc::types::TypedValue::from(value)
+                    }},
+                    None => tokens::typed_value(&plc_type(ty)?),
+                };
+                layout.push(quote!((#field_name.to_string(), #value)));
+            }
+            quote! {
+                ::rust2plc::st::data_type::DataType::new_struct(#name_str, vec![#(#layout),*])
+            }
+        }
+        Data::Enum(data) => {
+            let (declaration, conversion) = expand_enum(&input, data)?;
+            extra = conversion;
+            declaration
+        }
+        Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "PlcType can be derived for structs and enums only",
+            ))
+        }
+    };
+    let meta = meta_setters(&meta);
+
+    Ok(quote! {
+        impl ::rust2plc::st::data_type::PlcType for #name {
+            fn plc_type() -> ::rust2plc::st::data_type::DataType {
+                #declaration
+                    #meta
+            }
+        }
+
+        ::rust2plc::inventory::submit! {
+            ::rust2plc::registry::TypeEntry::new(
+                #name_str,
+                <#name as ::rust2plc::st::data_type::PlcType>::plc_type,
+            )
+        }
+
+        #extra
+    })
+}
+
+/// The explicit discriminant of a variant, a possibly negated integer literal.
+fn discriminant(expr: &Expr) -> syn::Result<i64> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(int), ..
+        }) => int.base10_parse(),
+        Expr::Unary(unary) if matches!(unary.op, UnOp::Neg(_)) => {
+            discriminant(&unary.expr).map(|v| -v)
+        }
+        Expr::Group(group) => discriminant(&group.expr),
+        _ => Err(syn::Error::new_spanned(
+            expr,
+            "enumerated values must be integer literals",
+        )),
+    }
+}
+
+/// The integer type of `#[repr(..)]`, the base type of the enumerated type.
+fn repr_type(input: &DeriveInput) -> syn::Result<Option<syn::Type>> {
+    let mut repr = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("repr")) {
+        attr.parse_nested_meta(|nested| {
+            if let Some(ident) = nested.path.get_ident() {
+                if ident.to_string().starts_with(['i', 'u']) {
+                    repr = Some(syn::parse_str::<syn::Type>(&ident.to_string())?);
+                }
+            }
+            Ok(())
+        })?;
+    }
+    Ok(repr)
+}
+
+/// The declaration of a fieldless enum and its conversion into a `TypedValue`.
+fn expand_enum(input: &DeriveInput, data: &DataEnum) -> syn::Result<(TokenStream, TokenStream)> {
+    let name = &input.ident;
+    let name_str = name.to_string();
+    if data.variants.is_empty() {
+        return Err(syn::Error::new_spanned(
+            name,
+            "an enumerated type needs at least one variant",
+        ));
+    }
+
+    let mut variants = vec![];
+    let mut initial = None;
+    for variant in data.variants.iter() {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "only fieldless variants can be mapped to an enumerated type",
+            ));
+        }
+        let value = match &variant.discriminant {
+            Some((_, expr)) => {
+                let value = discriminant(expr)?;
+                quote!(Some(#value))
+            }
+            None => quote!(None),
+        };
+        let variant_str = variant.ident.to_string();
+        if variant.attrs.iter().any(|a| a.path().is_ident("default")) {
+            initial = Some(variant_str.clone());
+        }
+        variants.push((variant.ident.clone(), variant_str, value));
+    }
+    let initial = initial.unwrap_or_else(|| variants[0].1.clone());
+    let base = match repr_type(input)? {
+        Some(ty) => {
+            let base = tokens::typed_value(&plc_type(&ty)?);
+            quote!(Some(#base))
+        }
+        None => quote!(None),
+    };
+
+    let layout = variants
+        .iter()
+        .map(|(_, variant, value)| quote!((#variant.to_string(), #value)));
+    let declaration = quote! {
+        ::rust2plc::st::data_type::DataType::new_enum(#name_str, vec![#(#layout),*])
+            .with_base(#base)
+            .with_initial(#initial)
+    };
+
+    let names = variants.iter().map(|(_, variant, _)| variant);
+    let arms = variants
+        .iter()
+        .map(|(ident, variant, _)| quote!(#name::#ident => #variant));
+    let conversion = quote! {
+        impl ::std::convert::From<#name> for ::rust2plc::types::TypedValue {
+            fn from(value: #name) -> Self {
+                let value = match value {
+                    #(#arms),*
+                };
+                ::rust2plc::types::TypedValue::Enum(
+                    #name_str.to_string(),
+                    vec![#(#names.to_string()),*],
+                    value.to_string(),
+                    #initial.to_string(),
+                )
+            }
+        }
+    };
+    Ok((declaration, conversion))
+}
@@ -1,15 +1,30 @@
+mod data_type;
+mod pou;
+mod st;
+mod tokens;
+
+use pou::PouKind;
 use proc_macro::{TokenStream, TokenTree};
+use quote::{format_ident, quote};
+use rust2plc::awl::{BlockKind, BlockNumber};
 use rust2plc::langs::PLCLang;
-use syn::{parse_macro_input, ItemFn};
+use syn::{parse_macro_input, DeriveInput, Item, ItemFn};
+
+struct AttributeArgs {
+    dialect: PLCLang,
+    description: Option<String>,
+    namespace: Option<String>,
+    version: Option<String>,
+    block: Option<String>,
+}
 
-fn parse_attribute_args(
-    mut tokens: impl Iterator<Item = TokenTree>,
-) -> Option<(PLCLang, Option<String>, Option<String>, Option<String>)> {
+fn parse_attribute_args(mut tokens: impl Iterator<Item = TokenTree>) -> Option<AttributeArgs> {
     if let Some(TokenTree::Ident(ident)) = tokens.next() {
         let dialect: PLCLang = ident.to_string().as_str().into();
         let mut dsc = None;
         let mut ver = None;
         let mut namespace = None;
+        let mut block = None;
 
         let mut tokens = tokens.peekable();
 
@@ -46,31 +61,279 @@ fn parse_attribute_args(
                             }
                         }
                     }
+                    "block" => {
+                        if let Some(TokenTree::Punct(punct)) = tokens.peek() {
+                            if punct.as_char() == '=' {
+                                tokens.next();
+                                if let Some(TokenTree::Literal(lit)) = tokens.next() {
+                                    block = Some(lit.to_string().trim_matches('"').to_string());
+                                }
+                            }
+                        }
+                    }
                     _ => {}
                 }
             }
         }
 
-        Some((dialect, dsc, namespace, ver))
+        Some(AttributeArgs {
+            dialect,
+            description: dsc,
+            namespace,
+            version: ver,
+            block,
+        })
     } else {
         None
     }
 }
 
+/// Checks that `block = "..."` comes with awl and names a block of the expected kind, e.g. `FC10`.
+fn check_block(args: &AttributeArgs, kind: BlockKind, ident: &syn::Ident) -> syn::Result<()> {
+    let Some(block) = args.block.as_deref() else {
+        return Ok(());
+    };
+    if args.dialect != PLCLang::StatementList {
+        return Err(syn::Error::new_spanned(
+            ident,
+            format!(
+                "`block = \"{}\"` numbers Siemens STL blocks, it is only accepted with awl, not {}",
+                block, args.dialect
+            ),
+        ));
+    }
+    match block.parse::<BlockNumber>() {
+        Ok(number) if number.kind == kind => Ok(()),
+        Ok(number) => Err(syn::Error::new_spanned(
+            ident,
+            format!(
+                "{} is numbered among the {} blocks, not as {}",
+                ident,
+                kind.prefix(),
+                number
+            ),
+        )),
+        Err(e) => Err(syn::Error::new_spanned(ident, e.to_string())),
+    }
+}
+
+/// Translates the annotated function into Structured Text.
+///
+/// The function itself is left untouched. Next to it the macro emits
+/// a `<NAME>_ST` constant holding the generated `FUNCTION ... END_FUNCTION` block
+/// and a `<name>_plc()` function returning the [`rust2plc::st::function::Function`] model.
+/// With `#[plc_fn(scl)]` the constant is `<NAME>_SCL` and holds the Siemens SCL block,
+/// with `#[plc_fn(ld)]` it is `<NAME>_LD` and holds the rungs drawn as ASCII art,
+/// with `#[plc_fn(fbd)]` it is `<NAME>_FBD` and holds the networks drawn as SVG,
+/// with `#[plc_fn(il)]` it is `<NAME>_IL` and holds the function in Instruction List
+/// and with `#[plc_fn(awl)]` it is `<NAME>_AWL` and holds the Siemens STL block,
+/// numbered by `block = "FC10"` or addressed by its symbol without it.
+/// SCL names the arguments of a call after the inputs of the function called, a function
+/// calling another with several arguments is rendered with
+/// [`rust2plc::scl::SclPrinter::with_function`] instead of `#[plc_fn(scl)]`.
+/// The model is registered in [`rust2plc::registry::PLCRegistry::all`].
 #[proc_macro_attribute]
 pub fn plc_fn(attr: TokenStream, item: TokenStream) -> TokenStream {
-    if let Some((dialect, description, namespace, version)) = parse_attribute_args(attr.into_iter())
-    {
-        // Use the parsed values here
-        eprintln!(
-            "Dialect: {:?}, Desc: {:?}, NS: {:?}, Ver: {:?}",
-            dialect, description, namespace, version
-        );
-        let cloned_item = item.clone();
-        let func = parse_macro_input!(cloned_item as ItemFn);
-        let func_name = func.sig.ident.to_string();
-        eprintln!("Function name: {}", func_name);
+    let Some(args) = parse_attribute_args(attr.into_iter()) else {
+        return syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "expected a PLC language as the first argument, e.g. #[plc_fn(st)]",
+        )
+        .to_compile_error()
+        .into();
+    };
+    let func = parse_macro_input!(item as ItemFn);
+
+    let generated = match &args.dialect {
+        PLCLang::StructuredText
+        | PLCLang::StructuredControlLanguage
+        | PLCLang::LadderDiagram
+        | PLCLang::FunctionBlockDiagram
+        | PLCLang::InstructionList
+        | PLCLang::StatementList => check_block(&args, BlockKind::Fc, &func.sig.ident)
+            .and_then(|_| {
+                st::translate_fn(
+                    &func,
+                    args.description.clone(),
+                    args.namespace.clone(),
+                    args.version.clone(),
+                )
+            })
+            .map(|function| function.with_block(args.block.clone())),
+        other => Err(syn::Error::new_spanned(
+            &func.sig.ident,
+            format!("{} generation is not supported yet", other),
+        )),
+    };
+    let generated = generated.and_then(|function| {
+        let (suffix, text, language) = match args.dialect {
+            PLCLang::StructuredControlLanguage => (
+                "SCL",
+                function
+                    .to_scl()
+                    .map_err(|e| syn::Error::new_spanned(&func.sig.ident, e.to_string()))?,
+                "SCL",
+            ),
+            PLCLang::LadderDiagram => (
+                "LD",
+                function
+                    .to_ladder()
+                    .map_err(|e| syn::Error::new_spanned(&func.sig.ident, e.to_string()))?
+                    .to_ascii(),
+                "Ladder Diagram",
+            ),
+            PLCLang::FunctionBlockDiagram => (
+                "FBD",
+                function
+                    .to_fbd()
+                    .map_err(|e| syn::Error::new_spanned(&func.sig.ident, e.to_string()))?
+                    .to_svg(),
+                "Function Block Diagram",
+            ),
+            PLCLang::InstructionList => (
+                "IL",
+                function
+                    .to_il()
+                    .map_err(|e| syn::Error::new_spanned(&func.sig.ident, e.to_string()))?,
+                "Instruction List",
+            ),
+            PLCLang::StatementList => (
+                "AWL",
+                function
+                    .to_awl()
+                    .map_err(|e| syn::Error::new_spanned(&func.sig.ident, e.to_string()))?,
+                "Statement List",
+            ),
+            _ => ("ST", function.to_st(), "Structured Text"),
+        };
+        Ok((function, suffix, text, language))
+    });
+
+    match generated {
+        Ok((function, suffix, text, language)) => {
+            let vis = &func.vis;
+            let text_const =
+                format_ident!("{}_{}", func.sig.ident.to_string().to_uppercase(), suffix);
+            let doc = format!("{} generated from [`{}`].", language, func.sig.ident);
+            let model_fn = format_ident!("{}_plc", func.sig.ident);
+            let model = tokens::function(&function);
+            let model_doc = format!("PLC model of [`{}`].", func.sig.ident);
+            quote! {
+                #func
+
+                #[doc = #doc]
+                #vis const #text_const: &str = #text;
+
+                #[doc = #model_doc]
+                #vis fn #model_fn() -> ::rust2plc::st::function::Function {
+                    #model
+                }
+
+                ::rust2plc::inventory::submit! {
+                    ::rust2plc::registry::RegistryEntry::new(|| {
+                        ::rust2plc::registry::RegistryItem::StFn(#model_fn())
+                    })
+                }
+            }
+            .into()
+        }
+        Err(err) => {
+            let err = err.to_compile_error();
+            quote! {
+                #func
+                #err
+            }
+            .into()
+        }
     }
+}
+
+/// Turns a struct and its impl block into an ST function block.
+///
+/// On the struct, `#[plc_fb(st, ...)]` takes the same arguments as `#[plc_fn]`
+/// and maps the fields marked `#[input]` and `#[output]` to `VAR_INPUT` and `VAR_OUTPUT`,
+/// every other field (optionally marked `#[internal]`) becomes a `VAR` kept between scans.
+///
+/// On the impl block, `#[plc_fb]` translates the `cycle` method (or the one marked `#[cycle]`)
+/// into the block body, emits `Name::plc()` returning the
+/// [`rust2plc::st::function_block::FunctionBlock`] model and registers it in the registry.
+#[proc_macro_attribute]
+pub fn plc_fb(attr: TokenStream, item: TokenStream) -> TokenStream {
+    expand_pou(PouKind::FunctionBlock, attr, item)
+}
+
+/// Turns a struct and its impl block, or a plain function, into an ST program.
+///
+/// The struct form works exactly like `#[plc_fb]` and emits `Name::plc()`.
+/// A function without arguments and return value becomes a program without own variables,
+/// `#[plc_program(st, ...)]` on it emits `<name>_plc()`.
+/// Either way the [`rust2plc::st::program::Program`] is registered in the registry
+/// and can be bound to a task with [`rust2plc::st::config::Configuration`].
+#[proc_macro_attribute]
+pub fn plc_program(attr: TokenStream, item: TokenStream) -> TokenStream {
+    expand_pou(PouKind::Program, attr, item)
+}
 
-    item
+fn expand_pou(kind: PouKind, attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_attribute_args(attr.into_iter());
+    let item = parse_macro_input!(item as Item);
+
+    let generated = match (args, &item) {
+        (Some(args), Item::Struct(item)) => pou::expand_struct(kind, args, item.clone()),
+        (Some(args), Item::Fn(func)) if matches!(kind, PouKind::Program) => {
+            pou::expand_fn(kind, args, func.clone())
+        }
+        (None, Item::Struct(_) | Item::Fn(_)) => Err(syn::Error::new_spanned(
+            &item,
+            format!(
+                "expected a PLC language as the first argument, e.g. #[{}(st)]",
+                kind.attr()
+            ),
+        )),
+        (None, Item::Impl(item)) => pou::expand_impl(kind, item.clone()),
+        (Some(_), Item::Impl(item)) => Err(syn::Error::new_spanned(
+            &item.self_ty,
+            "the attribute arguments belong on the struct",
+        )),
+        (_, other) => Err(syn::Error::new_spanned(
+            other,
+            "expected a struct and its impl block",
+        )),
+    };
+
+    match generated {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Translates a Rust expression into a [`rust2plc::st::ast::Expr`],
+/// e.g. the condition of a [`rust2plc::sfc::Transition`].
+///
+/// `plc_expr!(level >= 80 && !stop)` gives the ST expression `level >= 80 AND NOT stop`,
+/// `self.level` stands for the variable `level`.
+#[proc_macro]
+pub fn plc_expr(item: TokenStream) -> TokenStream {
+    let expr = parse_macro_input!(item as syn::Expr);
+    match st::translate_expr(&expr) {
+        Ok(expr) => tokens::expr(&expr).into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Maps a Rust struct to an ST `TYPE ... STRUCT ... END_STRUCT END_TYPE` declaration
+/// and a fieldless enum to an enumerated type `TYPE Mode : (Idle, Running) := Idle; END_TYPE`.
+///
+/// Explicit discriminants become the enumerated values and `#[repr(i16)]` the base type,
+/// the variant marked with `#[default]` is the initial value.
+/// The type is registered in the registry and resolved by `TypedValue::from_rust_type`.
+/// `#[plc(description = "...", namespace = "...", version = "...")]` on the struct
+/// and `#[plc(init = ...)]` on a field set the metadata and the initial values.
+#[proc_macro_derive(PlcType, attributes(plc))]
+pub fn derive_plc_type(item: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(item as DeriveInput);
+    match data_type::expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
 }
@@ -0,0 +1,271 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use rust2plc::awl::BlockKind;
+use rust2plc::langs::PLCLang;
+use rust2plc::var::Value;
+use syn::{Fields, ImplItem, ItemFn, ItemImpl, ItemStruct, Type};
+
+use crate::st::{plc_type, translate_cycle};
+use crate::{check_block, tokens, AttributeArgs};
+
+const ROLES: [&str; 3] = ["input", "output", "internal"];
+
+/// The stateful POUs built from a struct and its impl block.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum PouKind {
+    FunctionBlock,
+    Program,
+}
+
+impl PouKind {
+    pub(crate) fn attr(&self) -> &'static str {
+        match self {
+            PouKind::FunctionBlock => "plc_fb",
+            PouKind::Program => "plc_program",
+        }
+    }
+
+    fn model(&self) -> TokenStream {
+        match self {
+            PouKind::FunctionBlock => quote!(::rust2plc::st::function_block::FunctionBlock),
+            PouKind::Program => quote!(::rust2plc::st::program::Program),
+        }
+    }
+
+    fn declaration(&self) -> TokenStream {
+        match self {
+            PouKind::FunctionBlock => quote!(::rust2plc::st::function_block::PlcFunctionBlock),
+            PouKind::Program => quote!(::rust2plc::st::program::PlcProgram),
+        }
+    }
+
+    fn registry_item(&self) -> TokenStream {
+        match self {
+            PouKind::FunctionBlock => quote!(::rust2plc::registry::RegistryItem::StFb),
+            PouKind::Program => quote!(::rust2plc::registry::RegistryItem::StProgram),
+        }
+    }
+}
+
+fn check_dialect(kind: PouKind, args: &AttributeArgs, ident: &syn::Ident) -> syn::Result<()> {
+    match args.dialect {
+        // The model is shared, SCL, LD, FBD, IL and STL are rendered from it with `to_scl`,
+        // `to_ladder`, `to_fbd`, `to_il` and `to_awl`.
+        PLCLang::StructuredText
+        | PLCLang::StructuredControlLanguage
+        | PLCLang::LadderDiagram
+        | PLCLang::FunctionBlockDiagram
+        | PLCLang::InstructionList
+        | PLCLang::StatementList => check_block(args, BlockKind::Fb, ident),
+        _ => Err(syn::Error::new_spanned(
+            ident,
+            format!(
+                "{} generation is not supported yet for #[{}]",
+                args.dialect,
+                kind.attr()
+            ),
+        )),
+    }
+}
+
+fn meta_setters(args: &AttributeArgs) -> TokenStream {
+    let description = tokens::opt_string(args.description.as_deref());
+    let namespace = tokens::opt_string(args.namespace.as_deref());
+    let version = tokens::opt_string(args.version.as_deref());
+    let block = tokens::opt_string(args.block.as_deref());
+    quote! {
+        .with_description(#description)
+        .with_namespace(#namespace)
+        .with_version(#version)
+        .with_block(#block)
+    }
+}
+
+/// Strips the field roles and implements the declaration trait for the struct.
+pub(crate) fn expand_struct(
+    kind: PouKind,
+    args: AttributeArgs,
+    mut item: ItemStruct,
+) -> syn::Result<TokenStream> {
+    check_dialect(kind, &args, &item.ident)?;
+    if !item.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &item.generics,
+            format!("generic types are not supported by #[{}]", kind.attr()),
+        ));
+    }
+    let Fields::Named(fields) = &mut item.fields else {
+        return Err(syn::Error::new_spanned(
+            &item,
+            format!("#[{}] requires a struct with named fields", kind.attr()),
+        ));
+    };
+
+    let mut vars = vec![];
+    for field in fields.named.iter_mut() {
+        let mut role = None;
+        for attr in field.attrs.iter() {
+            let Some(ident) = attr.path().get_ident() else {
+                continue;
+            };
+            if ROLES.iter().any(|r| ident == r) {
+                if role.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        attr,
+                        "a field can have only one of #[input], #[output] or #[internal]",
+                    ));
+                }
+                role = Some(ident.to_string());
+            }
+        }
+        field
+            .attrs
+            .retain(|attr| !ROLES.iter().any(|r| attr.path().is_ident(r)));
+
+        let name = field
+            .ident
+            .as_ref()
+            .map(|i| i.to_string())
+            .unwrap_or_default();
+        let tv = plc_type(&field.ty)?;
+        let var = match role.as_deref() {
+            Some("input") => Value::Input(name, tv),
+            Some("output") => Value::Output(name, tv),
+            _ => Value::Local(name, tv),
+        };
+        vars.push(var);
+    }
+
+    let name = &item.ident;
+    let name_str = name.to_string();
+    let inputs = vars
+        .iter()
+        .filter(|v| matches!(v, Value::Input(_, _)))
+        .map(tokens::value);
+    let outputs = vars
+        .iter()
+        .filter(|v| matches!(v, Value::Output(_, _)))
+        .map(tokens::value);
+    let locals = vars
+        .iter()
+        .filter(|v| matches!(v, Value::Local(_, _)))
+        .map(tokens::value);
+    let meta = meta_setters(&args);
+    let model = kind.model();
+    let declaration = kind.declaration();
+
+    Ok(quote! {
+        #item
+
+        impl #declaration for #name {
+            fn plc_declaration() -> #model {
+                #model::new(#name_str)
+                    #(.with_input(#inputs))*
+                    #(.with_output(#outputs))*
+                    #(.with_var(#locals))*
+                    #meta
+            }
+        }
+    })
+}
+
+/// Translates the cycle method into the POU body and registers the POU.
+pub(crate) fn expand_impl(kind: PouKind, mut item: ItemImpl) -> syn::Result<TokenStream> {
+    let Type::Path(self_ty) = item.self_ty.as_ref() else {
+        return Err(syn::Error::new_spanned(
+            &item.self_ty,
+            format!("#[{}] expects an impl of a struct", kind.attr()),
+        ));
+    };
+    let Some(name) = self_ty.path.segments.last().map(|s| s.ident.clone()) else {
+        return Err(syn::Error::new_spanned(self_ty, "expected a struct name"));
+    };
+
+    let mut cycle = None;
+    for impl_item in item.items.iter_mut() {
+        let ImplItem::Fn(method) = impl_item else {
+            continue;
+        };
+        let marked = method.attrs.iter().any(|a| a.path().is_ident("cycle"));
+        method.attrs.retain(|a| !a.path().is_ident("cycle"));
+        if marked || (cycle.is_none() && method.sig.ident == "cycle") {
+            cycle = Some(method.clone());
+        }
+    }
+    let Some(cycle) = cycle else {
+        return Err(syn::Error::new_spanned(
+            &item.self_ty,
+            format!(
+                "#[{}] needs a `cycle` method or a method marked with #[cycle]",
+                kind.attr()
+            ),
+        ));
+    };
+
+    let cycle = translate_cycle(&cycle.sig, &cycle.block, kind.attr(), true)?;
+    let body = tokens::stmts(&cycle.body);
+    let temps = cycle
+        .temps
+        .into_iter()
+        .map(|(name, tv)| tokens::value(&Value::Temporary(name, tv)));
+    let doc = format!("PLC model of [`{}`].", name);
+    let model = kind.model();
+    let declaration = kind.declaration();
+    let registry_item = kind.registry_item();
+
+    Ok(quote! {
+        #item
+
+        impl #name {
+            #[doc = #doc]
+            pub fn plc() -> #model {
+                <Self as #declaration>::plc_declaration()
+                    #(.with_var(#temps))*
+                    .with_body(#body)
+            }
+        }
+
+        ::rust2plc::inventory::submit! {
+            ::rust2plc::registry::RegistryEntry::new(|| #registry_item(#name::plc()))
+        }
+    })
+}
+
+/// A stateless program written as a plain function, emits `<name>_plc()` and registers it.
+pub(crate) fn expand_fn(
+    kind: PouKind,
+    args: AttributeArgs,
+    func: ItemFn,
+) -> syn::Result<TokenStream> {
+    check_dialect(kind, &args, &func.sig.ident)?;
+    let cycle = translate_cycle(&func.sig, &func.block, kind.attr(), false)?;
+    let body = tokens::stmts(&cycle.body);
+    let temps = cycle
+        .temps
+        .into_iter()
+        .map(|(name, tv)| tokens::value(&Value::Temporary(name, tv)));
+
+    let vis = &func.vis;
+    let name_str = func.sig.ident.to_string();
+    let model_fn = format_ident!("{}_plc", func.sig.ident);
+    let doc = format!("PLC model of [`{}`].", func.sig.ident);
+    let meta = meta_setters(&args);
+    let model = kind.model();
+    let registry_item = kind.registry_item();
+
+    Ok(quote! {
+        #func
+
+        #[doc = #doc]
+        #vis fn #model_fn() -> #model {
+            #model::new(#name_str)
+                #(.with_var(#temps))*
+                .with_body(#body)
+                #meta
+        }
+
+        ::rust2plc::inventory::submit! {
+            ::rust2plc::registry::RegistryEntry::new(|| #registry_item(#model_fn()))
+        }
+    })
+}
@@ -0,0 +1,1814 @@
+use std::collections::HashMap;
+
+use quote::ToTokens;
+use rust2plc::st::ast::{self, BinaryOp, CaseLabel, Literal, Printer, UnaryOp};
+use rust2plc::st::function::Function;
+use rust2plc::types::TypedValue;
+use rust2plc::var::Value;
+use syn::visit::Visit;
+use syn::{
+    BinOp, Block, Expr, ExprClosure, ExprContinue, ExprForLoop, ExprIf, ExprLoop, ExprMatch,
+    ExprPath, ExprWhile, FnArg, ItemFn, Lit, Local, Pat, RangeLimits, ReturnType, Signature, Stmt,
+    Type, UnOp,
+};
+
+/// A translated ST expression.
+/// `weak` marks unsuffixed literals whose type is only a guess and yields to the other operand,
+/// `guesses` the variables bound to such values the expression follows.
+struct StExpr {
+    expr: ast::Expr,
+    ty: Option<TypedValue>,
+    weak: bool,
+    guesses: Vec<String>,
+}
+
+impl StExpr {
+    fn new(expr: ast::Expr, ty: Option<TypedValue>) -> Self {
+        StExpr {
+            expr,
+            ty,
+            weak: false,
+            guesses: vec![],
+        }
+    }
+
+    fn weak(expr: ast::Expr, ty: TypedValue) -> Self {
+        StExpr {
+            expr,
+            ty: Some(ty),
+            weak: true,
+            guesses: vec![],
+        }
+    }
+
+    fn typing(&self) -> Typing {
+        Typing {
+            ty: self.ty.clone(),
+            weak: self.weak,
+            guesses: self.guesses.clone(),
+        }
+    }
+}
+
+/// The type of a value stored by `if`, `match` and blocks, see [`StExpr`].
+#[derive(Default)]
+struct Typing {
+    ty: Option<TypedValue>,
+    weak: bool,
+    guesses: Vec<String>,
+}
+
+impl Typing {
+    fn strong(ty: Option<TypedValue>) -> Self {
+        Typing {
+            ty,
+            ..Default::default()
+        }
+    }
+}
+
+pub(crate) fn rust_type_name(ty: &Type) -> String {
+    ty.to_token_stream().to_string().replace(' ', "")
+}
+
+pub(crate) fn plc_type(ty: &Type) -> syn::Result<TypedValue> {
+    if let Type::Reference(reference) = ty {
+        return plc_type(&reference.elem);
+    }
+    let name = rust_type_name(ty);
+    TypedValue::from_rust_type(&name).ok_or_else(|| {
+        syn::Error::new_spanned(ty, format!("type `{}` has no PLC counterpart", name))
+    })
+}
+
+pub(crate) fn translate_fn(
+    func: &ItemFn,
+    description: Option<String>,
+    namespace: Option<String>,
+    version: Option<String>,
+) -> syn::Result<Function> {
+    let name = func.sig.ident.to_string();
+    let return_value = match &func.sig.output {
+        ReturnType::Type(_, ty) => plc_type(ty)?,
+        ReturnType::Default => {
+            return Err(syn::Error::new_spanned(
+                &func.sig,
+                "#[plc_fn] requires a return type, PLC functions always return a value",
+            ))
+        }
+    };
+
+    let mut function = Function::new(name.clone(), return_value.clone())
+        .with_description(description)
+        .with_namespace(namespace)
+        .with_version(version);
+
+    let mut params = vec![];
+    for arg in func.sig.inputs.iter() {
+        let FnArg::Typed(arg) = arg else {
+            return Err(syn::Error::new_spanned(
+                arg,
+                "#[plc_fn] does not support methods",
+            ));
+        };
+        let Pat::Ident(ident) = arg.pat.as_ref() else {
+            return Err(syn::Error::new_spanned(
+                &arg.pat,
+                "expected a plain parameter name",
+            ));
+        };
+        let param = ident.ident.to_string();
+        let tv = plc_type(&arg.ty)?;
+        params.push((param.clone(), tv.clone()));
+        function = match arg.ty.as_ref() {
+            Type::Reference(r) if r.mutability.is_some() => {
+                function.with_input(Value::InOut(param, tv))
+            }
+            _ => function.with_input(Value::Input(param, tv)),
+        };
+    }
+
+    let translator = settle(Some(name.clone()), |translator| {
+        // The result is assigned to the function name.
+        translator.scope.insert(name.clone(), return_value.clone());
+        for (param, tv) in params.iter() {
+            translator.scope.insert(param.clone(), tv.clone());
+            translator.bindings[0].insert(param.clone(), param.clone());
+        }
+        let stmts = &func.block.stmts;
+        for (idx, stmt) in stmts.iter().enumerate() {
+            translator.stmt(stmt, idx + 1 == stmts.len())?;
+        }
+        Ok(())
+    })?;
+
+    for (name, tv) in translator.locals {
+        function = function.with_local(Value::Local(name, tv));
+    }
+    Ok(function.with_body(translator.body))
+}
+
+/// The translated cycle of a function block or program.
+pub(crate) struct Cycle {
+    pub(crate) body: Vec<ast::Stmt>,
+    pub(crate) temps: Vec<(String, TypedValue)>,
+}
+
+/// Translates the cycle of a function block or program into the ST body
+/// and the temporaries introduced by its `let` bindings.
+///
+/// Struct based POUs run a `&mut self` method, program functions take no arguments at all.
+pub(crate) fn translate_cycle(
+    sig: &Signature,
+    block: &Block,
+    attr: &str,
+    with_self: bool,
+) -> syn::Result<Cycle> {
+    let has_mut_self = matches!(
+        sig.inputs.first(),
+        Some(FnArg::Receiver(r)) if r.reference.is_some() && r.mutability.is_some()
+    );
+    if with_self && (!has_mut_self || sig.inputs.len() != 1) {
+        return Err(syn::Error::new_spanned(
+            sig,
+            format!(
+                "the cycle method of #[{}] must take `&mut self` and nothing else",
+                attr
+            ),
+        ));
+    }
+    if !with_self && !sig.inputs.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &sig.inputs,
+            format!("#[{}] functions cannot take arguments", attr),
+        ));
+    }
+    if let ReturnType::Type(_, ty) = &sig.output {
+        return Err(syn::Error::new_spanned(
+            ty,
+            format!(
+                "#[{}] cannot return a value, use output fields instead",
+                attr
+            ),
+        ));
+    }
+
+    let translator = settle(None, |translator| {
+        let stmts = &block.stmts;
+        for (idx, stmt) in stmts.iter().enumerate() {
+            translator.stmt(stmt, idx + 1 == stmts.len())?;
+        }
+        Ok(())
+    })?;
+    Ok(Cycle {
+        body: translator.body,
+        temps: translator.locals,
+    })
+}
+
+/// Translates a single expression, e.g. the condition of an SFC transition.
+///
+/// Expressions needing statements of their own, like `if` with a value, are rejected.
+pub(crate) fn translate_expr(expr: &Expr) -> syn::Result<ast::Expr> {
+    let mut translator = Translator::new(None, HashMap::new());
+    let translated = translator.expr(expr)?;
+    if !translator.body.is_empty() {
+        return Err(syn::Error::new_spanned(
+            expr,
+            "the expression needs statements of its own, keep it to operators and calls",
+        ));
+    }
+    Ok(translated.expr)
+}
+
+/// Runs `translate` until the types of the variables bound to unsuffixed literals settle.
+///
+/// Rust infers such a variable's type from its uses, e.g. `let mut n = 0; n += level;`
+/// makes `n` an `i16` with `level: i16`. Each run translates with the types the previous
+/// one learned from the uses, the variables nothing else constrains keep the types
+/// of their literals, DINT and LREAL.
+fn settle(
+    ret_target: Option<String>,
+    mut translate: impl FnMut(&mut Translator) -> syn::Result<()>,
+) -> syn::Result<Translator> {
+    let mut hints = HashMap::new();
+    loop {
+        let mut translator = Translator::new(ret_target.clone(), hints.clone());
+        translate(&mut translator)?;
+        let known = hints.len();
+        for (site, ty) in std::mem::take(&mut translator.inferred) {
+            hints.entry(site).or_insert(ty);
+        }
+        if hints.len() == known {
+            return Ok(translator);
+        }
+    }
+}
+
+struct Translator {
+    // The variable receiving the result, the function name for functions.
+    ret_target: Option<String>,
+    // The ST variables with their types: parameters, locals and temporaries.
+    scope: HashMap<String, TypedValue>,
+    // The Rust bindings visible in each enclosing block, with the ST variable each one names.
+    bindings: Vec<HashMap<String, String>>,
+    locals: Vec<(String, TypedValue)>,
+    // The statements of the block being translated.
+    body: Vec<ast::Stmt>,
+    // The number of temporaries holding the values of `if` and `match` expressions.
+    temps: usize,
+    // Loop variables standing for array elements, with the element they denote.
+    aliases: HashMap<String, (ast::Expr, Option<TypedValue>)>,
+    // The enclosing loops, whether each of them has been left with `break`.
+    loops: Vec<bool>,
+    // The number of bindings whose type may be inferred from their uses so far.
+    sites: usize,
+    // The types inferred by the previous run for these bindings.
+    hints: HashMap<usize, TypedValue>,
+    // The variables of bindings still typed by their literals, with their binding.
+    guessed: HashMap<String, usize>,
+    // The guessed variables that must share one type, e.g. `let b = a + 1;`.
+    links: Vec<(String, String)>,
+    // The types the uses gave the guessed bindings in this run.
+    inferred: HashMap<usize, TypedValue>,
+}
+
+/// What a `for` loop iterates over.
+enum Iteration<'a> {
+    Range {
+        start: &'a Expr,
+        end: &'a Expr,
+        inclusive: bool,
+        step: Option<&'a Expr>,
+    },
+    Array {
+        array: &'a Expr,
+        enumerate: bool,
+    },
+}
+
+impl Translator {
+    fn new(ret_target: Option<String>, hints: HashMap<usize, TypedValue>) -> Self {
+        Translator {
+            ret_target,
+            scope: HashMap::new(),
+            bindings: vec![HashMap::new()],
+            locals: vec![],
+            body: vec![],
+            temps: 0,
+            aliases: HashMap::new(),
+            loops: vec![],
+            sites: 0,
+            hints,
+            guessed: HashMap::new(),
+            links: vec![],
+            inferred: HashMap::new(),
+        }
+    }
+
+    /// The type of the next binding that may be inferred from its uses,
+    /// if the previous run learned it.
+    fn hint(&mut self) -> (usize, Option<TypedValue>) {
+        self.sites += 1;
+        (self.sites, self.hints.get(&self.sites).cloned())
+    }
+
+    /// Records the variable `st` of binding `site` as typed by the literal
+    /// it was bound to, following the guessed variables `guesses`.
+    fn guess(&mut self, st: &str, site: usize, guesses: &[String]) {
+        self.guessed.insert(st.to_string(), site);
+        for other in guesses.iter() {
+            self.links.push((st.to_string(), other.clone()));
+        }
+    }
+
+    /// Gives two values meeting in an operation or an assignment the same type:
+    /// the guessed variables of one take the type of the other, if both are integers
+    /// or both are floating point numbers.
+    fn unify(&mut self, a: &Typing, b: &Typing) {
+        for (a, b) in [(a, b), (b, a)] {
+            match &b.ty {
+                Some(ty) if a.weak && !b.weak && same_kind(a.ty.as_ref(), ty) => {
+                    self.infer(&a.guesses, ty)
+                }
+                _ => {}
+            }
+        }
+        if a.weak && b.weak {
+            for x in a.guesses.iter() {
+                for y in b.guesses.iter() {
+                    self.links.push((x.clone(), y.clone()));
+                }
+            }
+        }
+    }
+
+    /// Infers `ty` for the guessed variables `guesses` and the ones linked to them.
+    fn infer(&mut self, guesses: &[String], ty: &TypedValue) {
+        let mut pending = guesses.to_vec();
+        let mut seen = vec![];
+        while let Some(st) = pending.pop() {
+            if seen.contains(&st) {
+                continue;
+            }
+            for (a, b) in self.links.iter() {
+                if *a == st {
+                    pending.push(b.clone());
+                } else if *b == st {
+                    pending.push(a.clone());
+                }
+            }
+            if let Some(site) = self.guessed.get(&st) {
+                self.inferred.entry(*site).or_insert_with(|| ty.clone());
+            }
+            seen.push(st);
+        }
+    }
+
+    fn emit(&mut self, stmt: ast::Stmt) {
+        self.body.push(stmt);
+    }
+
+    /// Runs `f` collecting the statements it emits into a nested block,
+    /// the bindings made by `f` end with the block.
+    fn nested<R>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> syn::Result<R>,
+    ) -> syn::Result<(Vec<ast::Stmt>, R)> {
+        let outer = std::mem::take(&mut self.body);
+        self.bindings.push(HashMap::new());
+        let result = f(self);
+        self.bindings.pop();
+        let inner = std::mem::replace(&mut self.body, outer);
+        result.map(|r| (inner, r))
+    }
+
+    fn temp(&mut self) -> syn::Ident {
+        self.temps += 1;
+        syn::Ident::new(
+            &format!("_tmp{}", self.temps),
+            proc_macro2::Span::call_site(),
+        )
+    }
+
+    /// Declares the ST variable of a new binding and returns its name.
+    fn declare(&mut self, name: &syn::Ident, tv: TypedValue) -> String {
+        let st = self.st_name(&name.to_string(), Some(&tv));
+        self.bind(name, st.clone(), tv);
+        st
+    }
+
+    /// The ST variable for a new binding of `name`. A binding replacing one of the same block
+    /// keeps its variable if the type matches. A binding shadowing one of an enclosing block
+    /// gets a fresh variable, e.g. `x_1`, so the outer value survives the inner block.
+    /// Without a type the variable is always a fresh one.
+    fn st_name(&self, name: &str, tv: Option<&TypedValue>) -> String {
+        let same_type = |st: &str| matches!((self.scope.get(st), tv), (Some(existing), Some(tv)) if existing.to_plc_type() == tv.to_plc_type());
+        if let Some(st) = self.bindings.last().and_then(|b| b.get(name)) {
+            if same_type(st) {
+                return st.clone();
+            }
+        }
+        let visible = |st: &str| self.bindings.iter().any(|b| b.values().any(|v| v == st));
+        std::iter::once(name.to_string())
+            .chain((1..).map(|n| format!("{}_{}", name, n)))
+            .find(|st| !self.scope.contains_key(st) || (same_type(st) && !visible(st)))
+            .unwrap_or_default()
+    }
+
+    /// Binds `name` to the ST variable `st` in the current block, declaring the variable
+    /// if it is new.
+    fn bind(&mut self, name: &syn::Ident, st: String, tv: TypedValue) {
+        if !self.scope.contains_key(&st) {
+            self.scope.insert(st.clone(), tv.clone());
+            self.locals.push((st.clone(), tv));
+        }
+        if let Some(bindings) = self.bindings.last_mut() {
+            bindings.insert(name.to_string(), st);
+        }
+    }
+
+    /// The ST variable of the binding `name`, names bound outside the function are kept.
+    fn resolve(&self, name: &str) -> String {
+        self.bindings
+            .iter()
+            .rev()
+            .find_map(|b| b.get(name))
+            .cloned()
+            .unwrap_or_else(|| name.to_string())
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, is_tail: bool) -> syn::Result<()> {
+        match stmt {
+            Stmt::Local(local) => self.local(local),
+            Stmt::Expr(expr, None) if is_tail => self.tail(expr),
+            Stmt::Expr(expr, _) => self.expr_stmt(expr),
+            Stmt::Item(item) => Err(syn::Error::new_spanned(
+                item,
+                "nested items are not supported in PLC code",
+            )),
+            Stmt::Macro(mac) => Err(syn::Error::new_spanned(
+                mac,
+                "macros are not supported in PLC code",
+            )),
+        }
+    }
+
+    fn local(&mut self, local: &Local) -> syn::Result<()> {
+        let (ident, annotated) = match &local.pat {
+            Pat::Ident(pat) => (&pat.ident, None),
+            Pat::Type(pat) => match pat.pat.as_ref() {
+                Pat::Ident(inner) => (&inner.ident, Some(plc_type(&pat.ty)?)),
+                other => {
+                    return Err(syn::Error::new_spanned(
+                        other,
+                        "expected a plain variable name",
+                    ))
+                }
+            },
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "expected a plain variable name",
+                ))
+            }
+        };
+
+        let Some(init) = &local.init else {
+            let Some(tv) = annotated else {
+                return Err(syn::Error::new_spanned(
+                    local,
+                    "uninitialized variables require a type annotation",
+                ));
+            };
+            self.declare(ident, tv);
+            return Ok(());
+        };
+        if let Some((_, diverge)) = &init.diverge {
+            return Err(syn::Error::new_spanned(
+                diverge,
+                "let-else is not supported in PLC code",
+            ));
+        }
+
+        // Without an annotation the type may be inferred from the uses of the binding.
+        let (site, annotated) = match annotated {
+            Some(tv) => (None, Some(tv)),
+            None => match self.hint() {
+                (_, Some(hint)) => (None, Some(hint)),
+                (site, None) => (Some(site), None),
+            },
+        };
+        if is_control(&init.expr) {
+            let st = self.st_name(&ident.to_string(), annotated.as_ref());
+            let value = self.assign(&st, &init.expr)?;
+            if let Some(tv) = &annotated {
+                self.unify(&Typing::strong(Some(tv.clone())), &value);
+            }
+            let Some(tv) = annotated.or(value.ty.clone()) else {
+                return Err(syn::Error::new_spanned(
+                    local,
+                    format!(
+                        "cannot infer the PLC type of `{}`, add a type annotation",
+                        ident
+                    ),
+                ));
+            };
+            if let (Some(site), true) = (site, value.weak) {
+                self.guess(&st, site, &value.guesses);
+            }
+            self.bind(ident, st, tv);
+            return Ok(());
+        }
+
+        // `let x: T = Default::default();` sets the variable to the default value, each time
+        // the `let` is passed, e.g. in every iteration of a loop
+        if let (Some(tv), Expr::Call(call)) = (&annotated, init.expr.as_ref()) {
+            if call.args.is_empty()
+                && matches!(call.func.as_ref(), Expr::Path(p) if p.path.segments.last().is_some_and(|s| s.ident == "default"))
+            {
+                let st = self.declare(ident, tv.clone());
+                return self.reset(ast::Expr::var(st), tv, call);
+            }
+        }
+        let value = self.expr(&init.expr)?;
+        if let Some(tv) = &annotated {
+            self.unify(&Typing::strong(Some(tv.clone())), &value.typing());
+        }
+        let Some(tv) = annotated.or(value.ty) else {
+            return Err(syn::Error::new_spanned(
+                local,
+                format!(
+                    "cannot infer the PLC type of `{}`, add a type annotation",
+                    ident
+                ),
+            ));
+        };
+        let st = self.declare(ident, tv);
+        if let (Some(site), true) = (site, value.weak) {
+            self.guess(&st, site, &value.guesses);
+        }
+        self.emit(ast::Stmt::assign(ast::Expr::var(st), value.expr));
+        Ok(())
+    }
+
+    /// Emits the statements setting `target` to the default value of `tv`,
+    /// arrays element by element and structures field by field.
+    fn reset(
+        &mut self,
+        target: ast::Expr,
+        tv: &TypedValue,
+        span: &impl ToTokens,
+    ) -> syn::Result<()> {
+        let value = match tv.default_value() {
+            TypedValue::Array(values, element, _) => {
+                let index = self.temp();
+                let index = self.declare(&index, TypedValue::new_dint());
+                let element_target =
+                    ast::Expr::Index(Box::new(target), vec![ast::Expr::var(index.clone())]);
+                let (body, _) = self.nested(|t| t.reset(element_target, &element, span))?;
+                self.emit(ast::Stmt::For {
+                    var: index,
+                    from: ast::Expr::int(0),
+                    to: ast::Expr::int(values.len() as i128 - 1),
+                    by: None,
+                    body,
+                });
+                return Ok(());
+            }
+            TypedValue::Struct(_, fields) => {
+                for (field, value) in fields.iter() {
+                    let field_target = ast::Expr::Field(Box::new(target.clone()), field.clone());
+                    self.reset(field_target, value, span)?;
+                }
+                return Ok(());
+            }
+            TypedValue::UserDefined(_, Some(value)) => return self.reset(target, &value, span),
+            TypedValue::Bool(_) => ast::Expr::bool(false),
+            TypedValue::SInt(_)
+            | TypedValue::Int(_)
+            | TypedValue::DInt(_)
+            | TypedValue::LInt(_)
+            | TypedValue::USInt(_)
+            | TypedValue::UInt(_)
+            | TypedValue::UDInt(_)
+            | TypedValue::ULInt(_)
+            | TypedValue::Byte(_)
+            | TypedValue::Word(_)
+            | TypedValue::DWord(_)
+            | TypedValue::LWord(_) => ast::Expr::int(0),
+            TypedValue::Real(_) | TypedValue::LReal(_) => ast::Expr::Literal(Literal::Real(0.0)),
+            TypedValue::String(..) | TypedValue::WString(..) => {
+                ast::Expr::Literal(Literal::Str(String::new()))
+            }
+            TypedValue::Enum(name, _, variant, _) => ast::Expr::Literal(Literal::Typed {
+                ty: name,
+                value: variant,
+            }),
+            other => match other.to_plc_literal() {
+                Ok(literal) if literal.contains('#') => {
+                    let (ty, value) = literal.split_once('#').unwrap_or_default();
+                    ast::Expr::Literal(Literal::Typed {
+                        ty: ty.to_string(),
+                        value: value.to_string(),
+                    })
+                }
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        span,
+                        format!(
+                            "the default value of {} is unknown in PLC code, initialise the variable explicitly",
+                            other.to_plc_type()
+                        ),
+                    ))
+                }
+            },
+        };
+        self.emit(ast::Stmt::assign(target, value));
+        Ok(())
+    }
+
+    fn tail(&mut self, expr: &Expr) -> syn::Result<()> {
+        match self.ret_target.clone() {
+            Some(target) => self.assign(&target, expr).map(|_| ()),
+            None => self.expr_stmt(expr),
+        }
+    }
+
+    /// Emits the statements storing the value of `expr` in `target`,
+    /// `if` and `match` store it in every branch. Returns the type of the value.
+    fn assign(&mut self, target: &str, expr: &Expr) -> syn::Result<Typing> {
+        match expr {
+            Expr::Return(_) => {
+                self.expr_stmt(expr)?;
+                Ok(Typing::default())
+            }
+            _ if is_control(expr) => self.control(expr, Some(target)),
+            _ => {
+                let value = self.expr(expr)?;
+                let typing = value.typing();
+                self.unify(&self.var(target).typing(), &typing);
+                self.emit(ast::Stmt::assign(ast::Expr::var(target), value.expr));
+                Ok(typing)
+            }
+        }
+    }
+
+    /// The ST variable `st` as an expression, guessed variables are weak.
+    fn var(&self, st: &str) -> StExpr {
+        let mut var = StExpr::new(ast::Expr::var(st), self.scope.get(st).cloned());
+        if self.guessed.contains_key(st) {
+            var.weak = true;
+            var.guesses = vec![st.to_string()];
+        }
+        var
+    }
+
+    /// The type of a value coming from either of two branches.
+    fn merge(&mut self, a: Typing, b: Typing) -> Typing {
+        self.unify(&a, &b);
+        match (&a.ty, &b.ty) {
+            (_, None) => a,
+            (None, Some(_)) => b,
+            _ if a.weak && b.weak => {
+                let mut a = a;
+                a.guesses.extend(b.guesses);
+                a
+            }
+            _ if a.weak => b,
+            _ => a,
+        }
+    }
+
+    /// Translates the statements of a block, its tail expression is stored in `target`.
+    fn block(
+        &mut self,
+        block: &Block,
+        target: Option<&str>,
+    ) -> syn::Result<(Vec<ast::Stmt>, Typing)> {
+        self.nested(|this| {
+            let mut ty = Typing::default();
+            let stmts = &block.stmts;
+            for (idx, stmt) in stmts.iter().enumerate() {
+                match (stmt, target) {
+                    (Stmt::Expr(expr, None), Some(target)) if idx + 1 == stmts.len() => {
+                        ty = this.assign(target, expr)?;
+                    }
+                    _ => this.stmt(stmt, false)?,
+                }
+            }
+            Ok(ty)
+        })
+    }
+
+    /// Lowers `if`, `match` and blocks, storing their value in `target` if there is one.
+    fn control(&mut self, expr: &Expr, target: Option<&str>) -> syn::Result<Typing> {
+        match expr {
+            Expr::If(expr_if) => self.if_chain(expr_if, target),
+            Expr::Match(expr_match) => self.case(expr_match, target),
+            Expr::Block(block) => {
+                let (stmts, ty) = self.block(&block.block, target)?;
+                self.body.extend(stmts);
+                Ok(ty)
+            }
+            Expr::Paren(paren) => self.control(&paren.expr, target),
+            Expr::Group(group) => self.control(&group.expr, target),
+            other => Err(syn::Error::new_spanned(
+                other,
+                "expected `if`, `match` or a block",
+            )),
+        }
+    }
+
+    fn condition(&mut self, cond: &Expr) -> syn::Result<ast::Expr> {
+        if let Expr::Let(expr_let) = cond {
+            return Err(syn::Error::new_spanned(
+                expr_let,
+                "`if let` is not supported in PLC code, use `match` instead",
+            ));
+        }
+        Ok(self.expr(cond)?.expr)
+    }
+
+    fn if_chain(&mut self, expr_if: &ExprIf, target: Option<&str>) -> syn::Result<Typing> {
+        let cond = self.condition(&expr_if.cond)?;
+        let (body, mut ty) = self.block(&expr_if.then_branch, target)?;
+        let mut branches = vec![ast::Branch { cond, body }];
+        let mut otherwise = None;
+        let mut else_branch = &expr_if.else_branch;
+        while let Some((_, branch)) = else_branch {
+            match branch.as_ref() {
+                Expr::If(next) => {
+                    let emitted = self.body.len();
+                    let cond = self.condition(&next.cond)?;
+                    if self.body.len() != emitted {
+                        return Err(syn::Error::new_spanned(
+                            &next.cond,
+                            "`if` and `match` expressions are not supported in `else if` conditions",
+                        ));
+                    }
+                    let (body, branch_ty) = self.block(&next.then_branch, target)?;
+                    ty = self.merge(ty, branch_ty);
+                    branches.push(ast::Branch { cond, body });
+                    else_branch = &next.else_branch;
+                }
+                Expr::Block(block) => {
+                    let (body, branch_ty) = self.block(&block.block, target)?;
+                    ty = self.merge(ty, branch_ty);
+                    otherwise = Some(body);
+                    else_branch = &None;
+                }
+                other => {
+                    return Err(syn::Error::new_spanned(other, "expected a block"));
+                }
+            }
+        }
+        if target.is_some() && otherwise.is_none() {
+            return Err(syn::Error::new_spanned(
+                expr_if,
+                "an `if` without `else` has no value",
+            ));
+        }
+        self.emit(ast::Stmt::If {
+            branches,
+            otherwise,
+        });
+        Ok(ty)
+    }
+
+    fn case(&mut self, expr_match: &ExprMatch, target: Option<&str>) -> syn::Result<Typing> {
+        let selector = self.expr(&expr_match.expr)?.expr;
+        let mut ty = Typing::default();
+        let mut branches = vec![];
+        let mut otherwise = None;
+        for arm in expr_match.arms.iter() {
+            if let Some((_, guard)) = &arm.guard {
+                return Err(syn::Error::new_spanned(
+                    guard,
+                    "match guards are not supported in PLC code",
+                ));
+            }
+            if otherwise.is_some() {
+                return Err(syn::Error::new_spanned(
+                    &arm.pat,
+                    "the wildcard arm must be the last one",
+                ));
+            }
+            let labels = case_labels(&arm.pat)?;
+            let (body, arm_ty) = match arm.body.as_ref() {
+                Expr::Block(block) => self.block(&block.block, target)?,
+                Expr::Tuple(unit) if unit.elems.is_empty() => (vec![], Typing::default()),
+                body => self.nested(|this| match target {
+                    Some(target) => this.assign(target, body),
+                    None => this.expr_stmt(body).map(|_| Typing::default()),
+                })?,
+            };
+            ty = self.merge(ty, arm_ty);
+            match labels {
+                Some(labels) => branches.push(ast::CaseBranch { labels, body }),
+                None => otherwise = Some(body),
+            }
+        }
+        self.emit(ast::Stmt::Case {
+            selector,
+            branches,
+            otherwise,
+        });
+        Ok(ty)
+    }
+
+    fn expr_stmt(&mut self, expr: &Expr) -> syn::Result<()> {
+        match expr {
+            Expr::Assign(assign) => {
+                let lhs = self.expr(&assign.left)?;
+                let rhs = self.expr(&assign.right)?;
+                self.unify(&lhs.typing(), &rhs.typing());
+                self.emit(ast::Stmt::assign(lhs.expr, rhs.expr));
+            }
+            Expr::Binary(bin) if compound_op(&bin.op).is_some() => {
+                let op = compound_op(&bin.op).unwrap_or(bin.op);
+                let lhs = self.expr(&bin.left)?;
+                let rhs = self.expr(&bin.right)?;
+                if !matches!(op, BinOp::Shl(_) | BinOp::Shr(_)) {
+                    self.unify(&lhs.typing(), &rhs.typing());
+                }
+                let target = lhs.expr.clone();
+                let value = binary(&op, lhs, rhs, bin)?;
+                self.emit(ast::Stmt::assign(target, value.expr));
+            }
+            Expr::Return(ret) => {
+                if let Some(value) = &ret.expr {
+                    let Some(target) = self.ret_target.clone() else {
+                        return Err(syn::Error::new_spanned(
+                            value,
+                            "nothing to return a value to",
+                        ));
+                    };
+                    let value = self.expr(value)?;
+                    self.unify(&self.var(&target).typing(), &value.typing());
+                    self.emit(ast::Stmt::assign(ast::Expr::var(target), value.expr));
+                }
+                self.emit(ast::Stmt::Return);
+            }
+            Expr::Call(_) | Expr::MethodCall(_) => match self.expr(expr)?.expr {
+                ast::Expr::Call(call) => self.emit(ast::Stmt::Call(call)),
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        expr,
+                        "the value of the call must be used in PLC code",
+                    ))
+                }
+            },
+            _ if is_control(expr) => {
+                self.control(expr, None)?;
+            }
+            Expr::ForLoop(for_loop) => self.for_loop(for_loop)?,
+            Expr::While(while_loop) => self.while_loop(while_loop)?,
+            Expr::Loop(expr_loop) => self.endless_loop(expr_loop)?,
+            Expr::Break(brk) => {
+                if brk.label.is_some() || brk.expr.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        brk,
+                        "`break` with a label or a value is not supported in PLC code",
+                    ));
+                }
+                let Some(exited) = self.loops.last_mut() else {
+                    return Err(syn::Error::new_spanned(brk, "`break` outside of a loop"));
+                };
+                *exited = true;
+                self.emit(ast::Stmt::Exit);
+            }
+            Expr::Continue(cont) => {
+                if cont.label.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        cont,
+                        "`continue` with a label is not supported in PLC code",
+                    ));
+                }
+                if self.loops.is_empty() {
+                    return Err(syn::Error::new_spanned(
+                        cont,
+                        "`continue` outside of a loop",
+                    ));
+                }
+                self.emit(ast::Stmt::Continue);
+            }
+            other => {
+                return Err(syn::Error::new_spanned(
+                    other,
+                    "unsupported statement in PLC code",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Translates the body of a loop, returns it with whether it is left with `break`.
+    fn loop_body(&mut self, stmts: &[Stmt]) -> syn::Result<(Vec<ast::Stmt>, bool)> {
+        self.loops.push(false);
+        let body = self.nested(|this| {
+            for stmt in stmts.iter() {
+                this.stmt(stmt, false)?;
+            }
+            Ok(())
+        });
+        let exited = self.loops.pop().unwrap_or_default();
+        body.map(|(body, _)| (body, exited))
+    }
+
+    fn for_loop(&mut self, for_loop: &ExprForLoop) -> syn::Result<()> {
+        no_label(&for_loop.label)?;
+        match iteration(&for_loop.expr)? {
+            Iteration::Range {
+                start,
+                end,
+                inclusive,
+                step,
+            } => {
+                let var = match for_loop.pat.as_ref() {
+                    Pat::Ident(pat) => pat.ident.clone(),
+                    Pat::Wild(_) => self.temp(),
+                    other => {
+                        return Err(syn::Error::new_spanned(
+                            other,
+                            "expected a plain loop variable",
+                        ))
+                    }
+                };
+                let start = self.expr(start)?;
+                let end = self.expr(end)?;
+                let step = step.map(|step| self.expr(step)).transpose()?;
+                let (site, hint) = self.hint();
+                let bounds = self.merge(start.typing(), end.typing());
+                if hint.is_some() {
+                    self.unify(&Typing::strong(hint.clone()), &bounds);
+                }
+                let ty = hint
+                    .clone()
+                    .or(bounds.ty)
+                    .unwrap_or_else(TypedValue::new_dint);
+                // `TO end - 1` wraps around for an empty unsigned range, e.g. `0..n` with
+                // `n = 0`, so such a loop only runs if the range has elements.
+                let guard = match (&start.expr, &end.expr) {
+                    _ if inclusive || !is_unsigned(&ty) => None,
+                    (ast::Expr::Literal(Literal::Int(a)), ast::Expr::Literal(Literal::Int(b)))
+                        if b > a =>
+                    {
+                        None
+                    }
+                    (start, end) => {
+                        Some(ast::Expr::binary(BinaryOp::Gt, end.clone(), start.clone()))
+                    }
+                };
+                // The loop variable ends with the loop.
+                self.bindings.push(HashMap::new());
+                let var = self.declare(&var, ty);
+                if hint.is_none() && bounds.weak {
+                    self.guess(&var, site, &bounds.guesses);
+                }
+                let to = match end.expr {
+                    end if inclusive => end,
+                    ast::Expr::Literal(Literal::Int(end)) => ast::Expr::int(end - 1),
+                    end => ast::Expr::binary(BinaryOp::Sub, end, ast::Expr::int(1)),
+                };
+                let body = self.loop_body(&for_loop.body.stmts);
+                self.bindings.pop();
+                let (body, _) = body?;
+                let for_stmt = ast::Stmt::For {
+                    var,
+                    from: start.expr,
+                    to,
+                    by: step.map(|s| s.expr),
+                    body,
+                };
+                match guard {
+                    Some(cond) => self.emit(ast::Stmt::If {
+                        branches: vec![ast::Branch {
+                            cond,
+                            body: vec![for_stmt],
+                        }],
+                        otherwise: None,
+                    }),
+                    None => self.emit(for_stmt),
+                }
+            }
+            Iteration::Array { array, enumerate } => {
+                let (index, element) = match (for_loop.pat.as_ref(), enumerate) {
+                    (Pat::Tuple(pair), true) if pair.elems.len() == 2 => {
+                        let index = match &pair.elems[0] {
+                            Pat::Ident(pat) => pat.ident.clone(),
+                            Pat::Wild(_) => self.temp(),
+                            other => {
+                                return Err(syn::Error::new_spanned(
+                                    other,
+                                    "expected a plain index variable",
+                                ))
+                            }
+                        };
+                        (index, &pair.elems[1])
+                    }
+                    (pat, false) => (self.temp(), pat),
+                    (other, true) => {
+                        return Err(syn::Error::new_spanned(
+                            other,
+                            "expected an `(index, element)` pair",
+                        ))
+                    }
+                };
+                let array_expr = array;
+                let array = self.expr(array)?;
+                let (upper, element_ty) = match &array.ty {
+                    Some(TypedValue::Array(values, element, _)) if !values.is_empty() => (
+                        ast::Expr::int(values.len() as i128 - 1),
+                        Some(element.as_ref().clone()),
+                    ),
+                    Some(TypedValue::Array(..)) => {
+                        return Err(syn::Error::new_spanned(
+                            array_expr,
+                            "cannot iterate over an empty array",
+                        ))
+                    }
+                    _ => (
+                        ast::Expr::call("UPPER_BOUND", vec![array.expr.clone(), ast::Expr::int(1)]),
+                        None,
+                    ),
+                };
+                self.bindings.push(HashMap::new());
+                let index = self.declare(&index, TypedValue::new_dint());
+                match element {
+                    Pat::Ident(pat) => {
+                        // The element is read and written in place.
+                        let element = ast::Expr::Index(
+                            Box::new(array.expr.clone()),
+                            vec![ast::Expr::var(index.clone())],
+                        );
+                        self.aliases
+                            .insert(pat.ident.to_string(), (element, element_ty));
+                    }
+                    Pat::Wild(_) => {}
+                    other => {
+                        return Err(syn::Error::new_spanned(
+                            other,
+                            "expected a plain loop variable",
+                        ))
+                    }
+                }
+                let body = self.loop_body(&for_loop.body.stmts);
+                self.bindings.pop();
+                if let Pat::Ident(pat) = element {
+                    self.aliases.remove(&pat.ident.to_string());
+                }
+                let (body, _) = body?;
+                self.emit(ast::Stmt::For {
+                    var: index,
+                    from: ast::Expr::int(0),
+                    to: upper,
+                    by: None,
+                    body,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn while_loop(&mut self, while_loop: &ExprWhile) -> syn::Result<()> {
+        no_label(&while_loop.label)?;
+        if let Expr::Let(expr_let) = while_loop.cond.as_ref() {
+            return Err(syn::Error::new_spanned(
+                expr_let,
+                "`while let` is not supported in PLC code",
+            ));
+        }
+        let emitted = self.body.len();
+        let cond = self.expr(&while_loop.cond)?;
+        if self.body.len() != emitted {
+            return Err(syn::Error::new_spanned(
+                &while_loop.cond,
+                "`if` and `match` expressions are not supported in `while` conditions",
+            ));
+        }
+        let (body, _) = self.loop_body(&while_loop.body.stmts)?;
+        self.emit(ast::Stmt::While {
+            cond: cond.expr,
+            body,
+        });
+        Ok(())
+    }
+
+    /// `loop` ending with `if cond { break; }` becomes `REPEAT ... UNTIL cond`,
+    /// any other `loop` becomes `WHILE TRUE` left with `EXIT`.
+    /// A `continue` would skip the `UNTIL` check, so such loops stay `WHILE TRUE`.
+    fn endless_loop(&mut self, expr_loop: &ExprLoop) -> syn::Result<()> {
+        no_label(&expr_loop.label)?;
+        let stmts = &expr_loop.body.stmts;
+        if let Some((until, rest)) = stmts.split_last().and_then(|(last, rest)| {
+            let Stmt::Expr(Expr::If(expr_if), _) = last else {
+                return None;
+            };
+            let exits = expr_if.else_branch.is_none()
+                && matches!(
+                    expr_if.then_branch.stmts.as_slice(),
+                    [Stmt::Expr(Expr::Break(brk), _)] if brk.label.is_none() && brk.expr.is_none()
+                );
+            (exits && !continues(rest)).then_some((&expr_if.cond, rest))
+        }) {
+            let (mut body, _) = self.loop_body(rest)?;
+            // The temporaries of the condition are computed at the end of the body.
+            let (temps, until) = self.nested(|this| this.condition(until))?;
+            body.extend(temps);
+            self.emit(ast::Stmt::Repeat { body, until });
+            return Ok(());
+        }
+
+        let (body, exited) = self.loop_body(stmts)?;
+        if !exited {
+            return Err(syn::Error::new_spanned(
+                expr_loop,
+                "a `loop` without `break` never ends, a PLC scan must terminate",
+            ));
+        }
+        self.emit(ast::Stmt::While {
+            cond: ast::Expr::bool(true),
+            body,
+        });
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &Expr) -> syn::Result<StExpr> {
+        match expr {
+            Expr::Lit(lit) => literal(&lit.lit),
+            Expr::Path(path)
+                if path.path.segments.len() == 2
+                    && is_numeric(&path.path.segments[0].ident.to_string()) =>
+            {
+                numeric_constant(path)
+            }
+            Expr::Path(path) if path.path.segments.len() == 2 => {
+                let ty = path.path.segments[0].ident.to_string();
+                let variant = path.path.segments[1].ident.to_string();
+                Ok(StExpr::new(
+                    ast::Expr::Literal(Literal::Typed {
+                        ty: ty.clone(),
+                        value: variant,
+                    }),
+                    Some(TypedValue::new_user_defined(&ty)),
+                ))
+            }
+            Expr::Path(path) => {
+                let Some(ident) = path.path.get_ident() else {
+                    return Err(syn::Error::new_spanned(
+                        path,
+                        "only local names are supported in PLC code",
+                    ));
+                };
+                if ident == "self" {
+                    return Err(syn::Error::new_spanned(
+                        path,
+                        "`self` can only be used to access fields",
+                    ));
+                }
+                let name = ident.to_string();
+                if let Some((element, ty)) = self.aliases.get(&name) {
+                    return Ok(StExpr::new(element.clone(), ty.clone()));
+                }
+                Ok(self.var(&self.resolve(&name)))
+            }
+            Expr::Paren(paren) => {
+                let inner = self.expr(&paren.expr)?;
+                Ok(StExpr {
+                    expr: ast::Expr::Paren(Box::new(inner.expr)),
+                    ..inner
+                })
+            }
+            Expr::Group(group) => self.expr(&group.expr),
+            // The value of a nested `if` or `match` goes through a temporary.
+            Expr::If(_) | Expr::Match(_) | Expr::Block(_) => {
+                let temp = self.temp();
+                let (site, hint) = self.hint();
+                let value = self.assign(&temp.to_string(), expr)?;
+                if hint.is_some() {
+                    self.unify(&Typing::strong(hint.clone()), &value);
+                }
+                let Some(ty) = hint.clone().or(value.ty) else {
+                    return Err(syn::Error::new_spanned(
+                        expr,
+                        "cannot infer the PLC type of the expression, bind it to an annotated variable",
+                    ));
+                };
+                let temp = self.declare(&temp, ty);
+                if hint.is_none() && value.weak {
+                    self.guess(&temp, site, &value.guesses);
+                }
+                Ok(self.var(&temp))
+            }
+            Expr::Unary(unary) => {
+                let operand = self.expr(&unary.expr)?;
+                let op = match unary.op {
+                    UnOp::Deref(_) => return Ok(operand),
+                    UnOp::Neg(_) => UnaryOp::Neg,
+                    UnOp::Not(_) => UnaryOp::Not,
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            unary,
+                            "unsupported unary operator in PLC code",
+                        ))
+                    }
+                };
+                Ok(StExpr {
+                    expr: ast::Expr::unary(op, operand.expr),
+                    ..operand
+                })
+            }
+            Expr::Binary(bin) => {
+                let lhs = self.expr(&bin.left)?;
+                let rhs = self.expr(&bin.right)?;
+                if !matches!(bin.op, BinOp::Shl(_) | BinOp::Shr(_)) {
+                    self.unify(&lhs.typing(), &rhs.typing());
+                }
+                binary(&bin.op, lhs, rhs, bin)
+            }
+            Expr::Cast(cast) => {
+                let value = self.expr(&cast.expr)?;
+                let target = plc_type(&cast.ty)?;
+                let dst = target.to_plc_type();
+                let expr = match &value.ty {
+                    Some(src) if src.to_plc_type() == dst => value.expr,
+                    Some(_) if value.weak && value.guesses.is_empty() => {
+                        ast::Expr::Literal(Literal::Typed {
+                            ty: dst,
+                            value: Printer::default().expr(&value.expr),
+                        })
+                    }
+                    Some(src) => ast::Expr::call(
+                        format!("{}_TO_{}", src.to_plc_type(), dst),
+                        vec![value.expr],
+                    ),
+                    None => ast::Expr::call(format!("TO_{}", dst), vec![value.expr]),
+                };
+                Ok(StExpr::new(expr, Some(target)))
+            }
+            Expr::Call(call) => {
+                let Expr::Path(func) = call.func.as_ref() else {
+                    return Err(syn::Error::new_spanned(
+                        &call.func,
+                        "only calls by name are supported in PLC code",
+                    ));
+                };
+                if let Some(duration) = duration(func, &call.args)? {
+                    let duration = TypedValue::Time(duration);
+                    let literal = duration.to_plc_literal().unwrap_or_default();
+                    return Ok(StExpr::new(
+                        ast::Expr::Literal(Literal::Typed {
+                            ty: "T".to_string(),
+                            value: literal.trim_start_matches("T#").to_string(),
+                        }),
+                        Some(duration),
+                    ));
+                }
+                let name = callee(func)?;
+                let args = call
+                    .args
+                    .iter()
+                    .map(|a| self.expr(a).map(|a| a.expr))
+                    .collect::<syn::Result<Vec<_>>>()?;
+                Ok(StExpr::new(ast::Expr::call(name, args), None))
+            }
+            Expr::MethodCall(call) => {
+                let receiver = self.expr(&call.receiver)?;
+                let args = call
+                    .args
+                    .iter()
+                    .map(|a| self.expr(a))
+                    .collect::<syn::Result<Vec<_>>>()?;
+                let method = call.method.to_string();
+                // `min`, `max` and `clamp` take arguments of the receiver's type
+                let mut typing = receiver.typing();
+                if matches!(method.as_str(), "min" | "max" | "clamp") {
+                    for arg in args.iter() {
+                        typing = self.merge(typing, arg.typing());
+                    }
+                }
+                let args: Vec<ast::Expr> = args.into_iter().map(|a| a.expr).collect();
+                let receiver = receiver.expr;
+                let expr = match (method.as_str(), args.as_slice()) {
+                    (
+                        "abs" | "sqrt" | "ln" | "exp" | "sin" | "cos" | "tan" | "asin" | "acos"
+                        | "atan",
+                        [],
+                    ) => ast::Expr::call(method.to_uppercase(), vec![receiver]),
+                    ("log10", []) => ast::Expr::call("LOG", vec![receiver]),
+                    ("min" | "max", [other]) => {
+                        ast::Expr::call(method.to_uppercase(), vec![receiver, other.clone()])
+                    }
+                    ("pow" | "powi" | "powf", [exp]) => {
+                        ast::Expr::call("EXPT", vec![receiver, exp.clone()])
+                    }
+                    ("clamp", [lo, hi]) => {
+                        ast::Expr::call("LIMIT", vec![lo.clone(), receiver, hi.clone()])
+                    }
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            &call.method,
+                            format!("method `{}` has no PLC counterpart", method),
+                        ))
+                    }
+                };
+                Ok(StExpr {
+                    expr,
+                    ty: typing.ty,
+                    weak: typing.weak,
+                    guesses: typing.guesses,
+                })
+            }
+            Expr::Field(field) => {
+                let syn::Member::Named(member) = &field.member else {
+                    return Err(syn::Error::new_spanned(
+                        &field.member,
+                        "tuple fields are not supported in PLC code",
+                    ));
+                };
+                // Fields of a function block are plain variables in ST.
+                if matches!(field.base.as_ref(), Expr::Path(p) if p.path.is_ident("self")) {
+                    let name = member.to_string();
+                    let ty = self.scope.get(&name).cloned();
+                    return Ok(StExpr::new(ast::Expr::Var(name), ty));
+                }
+                let base = self.expr(&field.base)?;
+                Ok(StExpr::new(
+                    ast::Expr::Field(Box::new(base.expr), member.to_string()),
+                    None,
+                ))
+            }
+            Expr::Index(index) => {
+                let base = self.expr(&index.expr)?;
+                // `i as usize` only satisfies Rust, ST indexes with any integer
+                let idx = match index.index.as_ref() {
+                    Expr::Cast(cast) if rust_type_name(&cast.ty) == "usize" => {
+                        self.expr(&cast.expr)?
+                    }
+                    idx => self.expr(idx)?,
+                };
+                let ty = match &base.ty {
+                    Some(TypedValue::Array(_, elem, _)) => Some(elem.as_ref().clone()),
+                    _ => None,
+                };
+                Ok(StExpr::new(
+                    ast::Expr::Index(Box::new(base.expr), vec![idx.expr]),
+                    ty,
+                ))
+            }
+            other => Err(syn::Error::new_spanned(
+                other,
+                "unsupported expression in PLC code",
+            )),
+        }
+    }
+}
+
+/// `Duration::from_secs(5)` and `Duration::from_millis(500)` with a literal are TIME literals.
+fn duration(
+    func: &syn::ExprPath,
+    args: &syn::punctuated::Punctuated<Expr, syn::Token![,]>,
+) -> syn::Result<Option<std::time::Duration>> {
+    let segments: Vec<String> = func
+        .path
+        .segments
+        .iter()
+        .map(|s| s.ident.to_string())
+        .collect();
+    let [.., ty, ctor] = segments.as_slice() else {
+        return Ok(None);
+    };
+    let (
+        Some(Expr::Lit(syn::ExprLit {
+            lit: Lit::Int(value),
+            ..
+        })),
+        1,
+    ) = (args.first(), args.len())
+    else {
+        return Ok(None);
+    };
+    let value: u64 = value.base10_parse()?;
+    let duration = match (ty.as_str(), ctor.as_str()) {
+        ("Duration", "from_secs") => std::time::Duration::from_secs(value),
+        ("Duration", "from_millis") => std::time::Duration::from_millis(value),
+        _ => return Ok(None),
+    };
+    Ok(Some(duration))
+}
+
+fn no_label(label: &Option<syn::Label>) -> syn::Result<()> {
+    match label {
+        Some(label) => Err(syn::Error::new_spanned(
+            label,
+            "labeled loops are not supported in PLC code",
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Recognizes the iterators with a bound known before the loop starts.
+fn iteration(expr: &Expr) -> syn::Result<Iteration<'_>> {
+    match expr {
+        Expr::Paren(paren) => iteration(&paren.expr),
+        Expr::Group(group) => iteration(&group.expr),
+        Expr::Range(range) => match (&range.start, &range.end) {
+            (Some(start), Some(end)) => Ok(Iteration::Range {
+                start,
+                end,
+                inclusive: matches!(range.limits, RangeLimits::Closed(_)),
+                step: None,
+            }),
+            _ => Err(syn::Error::new_spanned(
+                range,
+                "unbounded ranges cannot be lowered to a PLC loop",
+            )),
+        },
+        Expr::Reference(reference) => iteration(&reference.expr).and_then(|it| match it {
+            Iteration::Array { .. } => Ok(it),
+            Iteration::Range { .. } => Err(syn::Error::new_spanned(
+                reference,
+                "expected a reference to an array",
+            )),
+        }),
+        Expr::Path(_) | Expr::Field(_) | Expr::Index(_) => Ok(Iteration::Array {
+            array: expr,
+            enumerate: false,
+        }),
+        Expr::MethodCall(call) => match (call.method.to_string().as_str(), call.args.len()) {
+            ("iter" | "iter_mut", 0) => Ok(Iteration::Array {
+                array: &call.receiver,
+                enumerate: false,
+            }),
+            ("enumerate", 0) => match iteration(&call.receiver)? {
+                Iteration::Array {
+                    array,
+                    enumerate: false,
+                } => Ok(Iteration::Array {
+                    array,
+                    enumerate: true,
+                }),
+                _ => Err(syn::Error::new_spanned(
+                    call,
+                    "only the elements of an array can be enumerated in PLC code",
+                )),
+            },
+            ("step_by", 1) => match iteration(&call.receiver)? {
+                Iteration::Range {
+                    start,
+                    end,
+                    inclusive,
+                    step: None,
+                } => Ok(Iteration::Range {
+                    start,
+                    end,
+                    inclusive,
+                    step: call.args.first(),
+                }),
+                _ => Err(syn::Error::new_spanned(
+                    call,
+                    "`step_by` is supported on ranges only in PLC code",
+                )),
+            },
+            (method, _) => Err(syn::Error::new_spanned(
+                &call.method,
+                format!(
+                    "`{}` cannot be lowered to a PLC loop, iterate over a range, \
+                     a stepped range or a fixed-size array",
+                    method
+                ),
+            )),
+        },
+        other => Err(syn::Error::new_spanned(
+            other,
+            "only ranges, stepped ranges and fixed-size arrays can be iterated in PLC code",
+        )),
+    }
+}
+
+fn is_control(expr: &Expr) -> bool {
+    match expr {
+        Expr::If(_) | Expr::Match(_) | Expr::Block(_) => true,
+        Expr::Paren(paren) => is_control(&paren.expr),
+        Expr::Group(group) => is_control(&group.expr),
+        _ => false,
+    }
+}
+
+/// The labels of a `CASE` branch, `None` for the wildcard becoming `ELSE`.
+fn case_labels(pat: &Pat) -> syn::Result<Option<Vec<CaseLabel>>> {
+    match pat {
+        Pat::Wild(_) => Ok(None),
+        Pat::Or(or) => {
+            let mut labels = vec![];
+            for case in or.cases.iter() {
+                let Some(case) = case_labels(case)? else {
+                    return Ok(None);
+                };
+                labels.extend(case);
+            }
+            Ok(Some(labels))
+        }
+        Pat::Paren(paren) => case_labels(&paren.pat),
+        Pat::Lit(lit) => Ok(Some(vec![CaseLabel::Value(case_value(&Expr::Lit(
+            lit.clone(),
+        ))?)])),
+        Pat::Path(path) => Ok(Some(vec![CaseLabel::Value(case_value(&Expr::Path(
+            path.clone(),
+        ))?)])),
+        Pat::Range(range) => {
+            let (Some(start), Some(end)) = (&range.start, &range.end) else {
+                return Err(syn::Error::new_spanned(
+                    range,
+                    "open ranges are not supported in CASE labels",
+                ));
+            };
+            let start = case_value(start)?;
+            let end = match range.limits {
+                RangeLimits::Closed(_) => case_value(end)?,
+                RangeLimits::HalfOpen(_) => match end.as_ref() {
+                    Expr::Lit(syn::ExprLit {
+                        lit: Lit::Int(int), ..
+                    }) => ast::Expr::int(int.base10_parse::<i128>()? - 1),
+                    other => {
+                        return Err(syn::Error::new_spanned(
+                            other,
+                            "exclusive ranges need an integer literal bound, use `..=` instead",
+                        ))
+                    }
+                },
+            };
+            Ok(Some(vec![CaseLabel::Range(start, end)]))
+        }
+        other => Err(syn::Error::new_spanned(
+            other,
+            "only literals, enum variants, ranges and `_` can be matched in PLC code",
+        )),
+    }
+}
+
+fn case_value(expr: &Expr) -> syn::Result<ast::Expr> {
+    match expr {
+        Expr::Lit(syn::ExprLit {
+            lit: Lit::Int(int), ..
+        }) => Ok(ast::Expr::int(int.base10_parse()?)),
+        Expr::Path(path) if path.path.segments.len() == 2 => {
+            Ok(ast::Expr::Literal(Literal::Typed {
+                ty: path.path.segments[0].ident.to_string(),
+                value: path.path.segments[1].ident.to_string(),
+            }))
+        }
+        other => Err(syn::Error::new_spanned(
+            other,
+            "CASE labels must be integer literals or enum variants",
+        )),
+    }
+}
+
+fn literal(lit: &Lit) -> syn::Result<StExpr> {
+    match lit {
+        Lit::Int(int) => {
+            let expr = ast::Expr::int(int.base10_parse()?);
+            match int.suffix() {
+                "" => Ok(StExpr::weak(expr, TypedValue::new_dint())),
+                suffix => Ok(StExpr::new(expr, TypedValue::from_rust_type(suffix))),
+            }
+        }
+        Lit::Float(float) => {
+            let expr = ast::Expr::Literal(Literal::Real(float.base10_parse()?));
+            match float.suffix() {
+                "" => Ok(StExpr::weak(expr, TypedValue::new_lreal())),
+                suffix => Ok(StExpr::new(expr, TypedValue::from_rust_type(suffix))),
+            }
+        }
+        Lit::Bool(b) => Ok(StExpr::new(
+            ast::Expr::bool(b.value),
+            Some(TypedValue::new_bool()),
+        )),
+        Lit::Str(s) => Ok(StExpr::new(
+            ast::Expr::Literal(Literal::Str(s.value())),
+            Some(TypedValue::new_string(None)),
+        )),
+        Lit::Char(c) => Ok(StExpr::new(
+            ast::Expr::Literal(Literal::Str(c.value().to_string())),
+            Some(TypedValue::new_char()),
+        )),
+        other => Err(syn::Error::new_spanned(
+            other,
+            "unsupported literal in PLC code",
+        )),
+    }
+}
+
+/// The ST name of a called function, a module prefix is kept as the namespace: `math::add` is `math.add`.
+fn callee(func: &ExprPath) -> syn::Result<String> {
+    let segments: Vec<_> = func
+        .path
+        .segments
+        .iter()
+        .skip_while(|s| s.ident == "crate" || s.ident == "self" || s.ident == "super")
+        .collect();
+    let Some((last, modules)) = segments.split_last() else {
+        return Err(syn::Error::new_spanned(func, "missing function name"));
+    };
+    let resolvable = func.qself.is_none()
+        && segments.iter().all(|s| s.arguments.is_none())
+        && modules.iter().all(|s| {
+            let module = s.ident.to_string();
+            module != "std" && module != "core" && module.chars().all(|c| !c.is_uppercase())
+        });
+    if !resolvable {
+        return Err(syn::Error::new_spanned(
+            func,
+            "only functions by name or by module path are supported in PLC code",
+        ));
+    }
+    Ok(modules
+        .iter()
+        .map(|s| format!("{}.", s.ident))
+        .chain(std::iter::once(last.ident.to_string()))
+        .collect())
+}
+
+/// Whether `stmts` contain a `continue` of the enclosing loop, nested loops are skipped.
+fn continues(stmts: &[Stmt]) -> bool {
+    #[derive(Default)]
+    struct Continues(bool);
+
+    impl<'ast> Visit<'ast> for Continues {
+        fn visit_expr_continue(&mut self, _: &'ast ExprContinue) {
+            self.0 = true;
+        }
+        fn visit_expr_for_loop(&mut self, for_loop: &'ast ExprForLoop) {
+            self.visit_expr(&for_loop.expr);
+        }
+        fn visit_expr_while(&mut self, while_loop: &'ast ExprWhile) {
+            self.visit_expr(&while_loop.cond);
+        }
+        fn visit_expr_loop(&mut self, _: &'ast ExprLoop) {}
+        fn visit_expr_closure(&mut self, _: &'ast ExprClosure) {}
+    }
+
+    let mut visitor = Continues::default();
+    stmts.iter().for_each(|stmt| visitor.visit_stmt(stmt));
+    visitor.0
+}
+
+fn is_numeric(ty: &str) -> bool {
+    matches!(
+        ty,
+        "i8" | "i16" | "i32" | "i64" | "u8" | "u16" | "u32" | "u64" | "f32" | "f64"
+    )
+}
+
+fn is_unsigned(tv: &TypedValue) -> bool {
+    matches!(
+        tv,
+        TypedValue::USInt(_) | TypedValue::UInt(_) | TypedValue::UDInt(_) | TypedValue::ULInt(_)
+    )
+}
+
+/// Whether both types are integers or both are floating point numbers.
+fn same_kind(a: Option<&TypedValue>, b: &TypedValue) -> bool {
+    fn is_real(tv: &TypedValue) -> Option<bool> {
+        match tv {
+            TypedValue::SInt(_)
+            | TypedValue::Int(_)
+            | TypedValue::DInt(_)
+            | TypedValue::LInt(_)
+            | TypedValue::USInt(_)
+            | TypedValue::UInt(_)
+            | TypedValue::UDInt(_)
+            | TypedValue::ULInt(_) => Some(false),
+            TypedValue::Real(_) | TypedValue::LReal(_) => Some(true),
+            _ => None,
+        }
+    }
+    a.and_then(is_real).is_some_and(|a| is_real(b) == Some(a))
+}
+
+/// `MIN` and `MAX` of a primitive numeric type as a typed ST literal.
+fn numeric_constant(path: &ExprPath) -> syn::Result<StExpr> {
+    let ty = path.path.segments[0].ident.to_string();
+    let constant = path.path.segments[1].ident.to_string();
+    let (min, max) = match ty.as_str() {
+        "i8" => (i8::MIN as i128, i8::MAX as i128),
+        "i16" => (i16::MIN as i128, i16::MAX as i128),
+        "i32" => (i32::MIN as i128, i32::MAX as i128),
+        "i64" => (i64::MIN as i128, i64::MAX as i128),
+        "u8" => (0, u8::MAX as i128),
+        "u16" => (0, u16::MAX as i128),
+        "u32" => (0, u32::MAX as i128),
+        "u64" => (0, u64::MAX as i128),
+        "f32" => return real_constant(path, &ty, &constant, f32::MAX as f64),
+        _ => return real_constant(path, &ty, &constant, f64::MAX),
+    };
+    let value = match constant.as_str() {
+        "MIN" => min,
+        "MAX" => max,
+        _ => return Err(unsupported_constant(path, &ty, &constant)),
+    };
+    Ok(StExpr::new(
+        ast::Expr::int(value),
+        TypedValue::from_rust_type(&ty),
+    ))
+}
+
+fn real_constant(path: &ExprPath, ty: &str, constant: &str, max: f64) -> syn::Result<StExpr> {
+    let value = match constant {
+        "MIN" => -max,
+        "MAX" => max,
+        _ => return Err(unsupported_constant(path, ty, constant)),
+    };
+    Ok(StExpr::new(
+        ast::Expr::Literal(Literal::Real(value)),
+        TypedValue::from_rust_type(ty),
+    ))
+}
+
+fn unsupported_constant(path: &ExprPath, ty: &str, constant: &str) -> syn::Error {
+    syn::Error::new_spanned(
+        path,
+        format!(
+            "{}::{} has no PLC counterpart, only MIN and MAX are supported",
+            ty, constant
+        ),
+    )
+}
+
+fn compound_op(op: &BinOp) -> Option<BinOp> {
+    match op {
+        BinOp::AddAssign(_) => Some(BinOp::Add(Default::default())),
+        BinOp::SubAssign(_) => Some(BinOp::Sub(Default::default())),
+        BinOp::MulAssign(_) => Some(BinOp::Mul(Default::default())),
+        BinOp::DivAssign(_) => Some(BinOp::Div(Default::default())),
+        BinOp::RemAssign(_) => Some(BinOp::Rem(Default::default())),
+        BinOp::BitAndAssign(_) => Some(BinOp::BitAnd(Default::default())),
+        BinOp::BitOrAssign(_) => Some(BinOp::BitOr(Default::default())),
+        BinOp::BitXorAssign(_) => Some(BinOp::BitXor(Default::default())),
+        BinOp::ShlAssign(_) => Some(BinOp::Shl(Default::default())),
+        BinOp::ShrAssign(_) => Some(BinOp::Shr(Default::default())),
+        _ => None,
+    }
+}
+
+fn binary(op: &BinOp, lhs: StExpr, rhs: StExpr, origin: &impl ToTokens) -> syn::Result<StExpr> {
+    let st_op = match op {
+        BinOp::Add(_) => BinaryOp::Add,
+        BinOp::Sub(_) => BinaryOp::Sub,
+        BinOp::Mul(_) => BinaryOp::Mul,
+        BinOp::Div(_) => BinaryOp::Div,
+        BinOp::Rem(_) => BinaryOp::Mod,
+        BinOp::And(_) | BinOp::BitAnd(_) => BinaryOp::And,
+        BinOp::Or(_) | BinOp::BitOr(_) => BinaryOp::Or,
+        BinOp::BitXor(_) => BinaryOp::Xor,
+        BinOp::Eq(_) => BinaryOp::Eq,
+        BinOp::Ne(_) => BinaryOp::Ne,
+        BinOp::Lt(_) => BinaryOp::Lt,
+        BinOp::Le(_) => BinaryOp::Le,
+        BinOp::Gt(_) => BinaryOp::Gt,
+        BinOp::Ge(_) => BinaryOp::Ge,
+        BinOp::Shl(_) => {
+            return Ok(StExpr::new(
+                ast::Expr::call("SHL", vec![lhs.expr, rhs.expr]),
+                lhs.ty,
+            ))
+        }
+        BinOp::Shr(_) => {
+            return Ok(StExpr::new(
+                ast::Expr::call("SHR", vec![lhs.expr, rhs.expr]),
+                lhs.ty,
+            ))
+        }
+        _ => {
+            return Err(syn::Error::new_spanned(
+                origin,
+                "unsupported operator in PLC code",
+            ))
+        }
+    };
+    let is_bool = matches!(
+        st_op,
+        BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge
+    );
+
+    let (ty, weak) = if is_bool {
+        (Some(TypedValue::new_bool()), false)
+    } else if lhs.ty.is_none() || (lhs.weak && !rhs.weak && rhs.ty.is_some()) {
+        (rhs.ty.clone(), rhs.weak)
+    } else {
+        (lhs.ty.clone(), lhs.weak && (rhs.weak || rhs.ty.is_none()))
+    };
+    let guesses = match weak {
+        true => lhs.guesses.into_iter().chain(rhs.guesses).collect(),
+        false => vec![],
+    };
+
+    Ok(StExpr {
+        expr: ast::Expr::binary(st_op, lhs.expr, rhs.expr),
+        ty,
+        weak,
+        guesses,
+    })
+}
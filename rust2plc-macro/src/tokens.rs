@@ -0,0 +1,348 @@
+use chrono::{Datelike, NaiveDate, NaiveTime, Timelike};
+use proc_macro2::TokenStream;
+use quote::quote;
+use rust2plc::st::ast::{Arg, BinaryOp, Call, CaseLabel, Expr, Literal, Stmt, UnaryOp};
+use rust2plc::st::function::Function;
+use rust2plc::types::TypedValue;
+use rust2plc::var::Value;
+
+/// Code that builds the value at runtime, the types of `#[derive(PlcType)]` are looked up
+/// in the registry and stay a bare reference when they are not linked in.
+pub(crate) fn typed_value(tv: &TypedValue) -> TokenStream {
+    let ty = quote!(::rust2plc::types::TypedValue);
+    match tv {
+        TypedValue::Bool(v) => quote!(#ty::Bool(#v)),
+        TypedValue::SInt(v) => quote!(#ty::SInt(#v)),
+        TypedValue::Int(v) => quote!(#ty::Int(#v)),
+        TypedValue::DInt(v) => quote!(#ty::DInt(#v)),
+        TypedValue::LInt(v) => quote!(#ty::LInt(#v)),
+        TypedValue::USInt(v) => quote!(#ty::USInt(#v)),
+        TypedValue::UInt(v) => quote!(#ty::UInt(#v)),
+        TypedValue::UDInt(v) => quote!(#ty::UDInt(#v)),
+        TypedValue::ULInt(v) => quote!(#ty::ULInt(#v)),
+        TypedValue::Real(v) => quote!(#ty::Real(#v)),
+        TypedValue::LReal(v) => quote!(#ty::LReal(#v)),
+        TypedValue::Time(d) => {
+            let (secs, nanos) = (d.as_secs(), d.subsec_nanos());
+            quote!(#ty::Time(::std::time::Duration::new(#secs, #nanos)))
+        }
+        TypedValue::Date(date) => {
+            let date = naive_date(date);
+            quote!(#ty::Date(#date))
+        }
+        TypedValue::TimeOfDay(time) => {
+            let time = naive_time(time);
+            quote!(#ty::TimeOfDay(#time))
+        }
+        TypedValue::DateTime(dt) => {
+            let (date, time) = (naive_date(&dt.date()), naive_time(&dt.time()));
+            quote!(#ty::DateTime(::rust2plc::chrono::NaiveDateTime::new(#date, #time)))
+        }
+        TypedValue::Char(c) => quote!(#ty::Char(#c)),
+        TypedValue::WChar(c) => quote!(#ty::WChar(#c)),
+        TypedValue::String(s, len) => {
+            let len = opt(len.as_ref(), |len| quote!(#len));
+            quote!(#ty::String(#s.to_string(), #len))
+        }
+        TypedValue::WString(s, len) => {
+            let len = opt(len.as_ref(), |len| quote!(#len));
+            quote!(#ty::WString(#s.to_string(), #len))
+        }
+        TypedValue::Byte(v) => quote!(#ty::Byte(#v)),
+        TypedValue::Word(v) => quote!(#ty::Word(#v)),
+        TypedValue::DWord(v) => quote!(#ty::DWord(#v)),
+        TypedValue::LWord(v) => quote!(#ty::LWord(#v)),
+        TypedValue::UserDefined(name, value) => {
+            let short = name.rsplit("::").next().unwrap_or(name);
+            let value = opt(value.as_deref(), |v| {
+                let v = typed_value(v);
+                quote!(Box::new(#v))
+            });
+            quote! {
+                ::rust2plc::registry::find_data_type(#short)
+                    .map(|data_type| data_type.value().clone())
+                    .unwrap_or_else(|| #ty::UserDefined(#name.to_string(), #value))
+            }
+        }
+        TypedValue::Struct(name, fields) => {
+            let fields = fields.iter().map(|(field, v)| {
+                let v = typed_value(v);
+                quote!((#field.to_string(), #v))
+            });
+            quote!(#ty::Struct(#name.to_string(), vec![#(#fields),*]))
+        }
+        TypedValue::Enum(name, variants, current, initial) => {
+            quote!(#ty::Enum(
+                #name.to_string(),
+                vec![#(#variants.to_string()),*],
+                #current.to_string(),
+                #initial.to_string()
+            ))
+        }
+        TypedValue::Array(values, elem, lower) => {
+            let values = values.iter().map(|v| typed_value(v));
+            let elem = typed_value(elem);
+            quote!(#ty::Array(vec![#(Box::new(#values)),*], Box::new(#elem), #lower))
+        }
+    }
+}
+
+fn naive_date(date: &NaiveDate) -> TokenStream {
+    let days = date.num_days_from_ce();
+    quote!(::rust2plc::chrono::NaiveDate::from_num_days_from_ce_opt(#days).unwrap_or_default())
+}
+
+fn naive_time(time: &NaiveTime) -> TokenStream {
+    let (secs, nanos) = (time.num_seconds_from_midnight(), time.nanosecond());
+    quote! {
+        ::rust2plc::chrono::NaiveTime::from_num_seconds_from_midnight_opt(#secs, #nanos)
+            .unwrap_or_default()
+    }
+}
+
+pub(crate) fn value(value: &Value) -> TokenStream {
+    let name = value.name().unwrap_or_default();
+    let tv = typed_value(value.typed_value());
+    match value {
+        Value::Input(_, _) => quote!(::rust2plc::var::Value::Input(#name.to_string(), #tv)),
+        Value::Output(_, _) => quote!(::rust2plc::var::Value::Output(#name.to_string(), #tv)),
+        Value::InOut(_, _) => quote!(::rust2plc::var::Value::InOut(#name.to_string(), #tv)),
+        Value::Local(_, _) => quote!(::rust2plc::var::Value::Local(#name.to_string(), #tv)),
+        Value::Global(_, _) => quote!(::rust2plc::var::Value::Global(#name.to_string(), #tv)),
+        Value::External(_, _) => {
+            quote!(::rust2plc::var::Value::External(#name.to_string(), #tv))
+        }
+        Value::Temporary(_, _) => {
+            quote!(::rust2plc::var::Value::Temporary(#name.to_string(), #tv))
+        }
+        Value::Return(_) => quote!(::rust2plc::var::Value::Return(#tv)),
+        Value::Constant(_, _) => {
+            quote!(::rust2plc::var::Value::Constant(#name.to_string(), #tv))
+        }
+    }
+}
+
+pub(crate) fn opt_string(value: Option<&str>) -> TokenStream {
+    match value {
+        Some(v) => quote!(Some(#v.to_string())),
+        None => quote!(None),
+    }
+}
+
+/// Code that rebuilds the function at runtime.
+pub(crate) fn function(function: &Function) -> TokenStream {
+    let name = function.name();
+    let return_value = typed_value(function.return_value());
+    let inputs = function.inputs().iter().map(value);
+    let outputs = function.outputs().iter().map(value);
+    let locals = function.locals().iter().map(value);
+    let body = stmts(function.body());
+    let description = opt_string(function.description());
+    let namespace = opt_string(function.namespace());
+    let version = opt_string(function.version());
+    let block = opt_string(function.block());
+
+    quote! {
+        ::rust2plc::st::function::Function::new(#name, #return_value)
+            #(.with_input(#inputs))*
+            #(.with_output(#outputs))*
+            #(.with_local(#locals))*
+            .with_body(#body)
+            .with_description(#description)
+            .with_namespace(#namespace)
+            .with_version(#version)
+            .with_block(#block)
+    }
+}
+
+fn opt<T>(value: Option<&T>, f: impl Fn(&T) -> TokenStream) -> TokenStream {
+    match value {
+        Some(v) => {
+            let v = f(v);
+            quote!(Some(#v))
+        }
+        None => quote!(None),
+    }
+}
+
+/// Code that rebuilds the statements at runtime.
+pub(crate) fn stmts(stmts: &[Stmt]) -> TokenStream {
+    let stmts = stmts.iter().map(stmt);
+    quote!(vec![#(#stmts),*])
+}
+
+fn stmt(stmt: &Stmt) -> TokenStream {
+    let ast = quote!(::rust2plc::st::ast);
+    match stmt {
+        Stmt::Assign { target, value } => {
+            let target = expr(target);
+            let value = expr(value);
+            quote!(#ast::Stmt::Assign { target: #target, value: #value })
+        }
+        Stmt::Call(c) => {
+            let c = call(c);
+            quote!(#ast::Stmt::Call(#c))
+        }
+        Stmt::If {
+            branches,
+            otherwise,
+        } => {
+            let branches = branches.iter().map(|b| {
+                let cond = expr(&b.cond);
+                let body = stmts(&b.body);
+                quote!(#ast::Branch { cond: #cond, body: #body })
+            });
+            let otherwise = opt(otherwise.as_ref(), |s| stmts(s));
+            quote!(#ast::Stmt::If { branches: vec![#(#branches),*], otherwise: #otherwise })
+        }
+        Stmt::Case {
+            selector,
+            branches,
+            otherwise,
+        } => {
+            let selector = expr(selector);
+            let branches = branches.iter().map(|b| {
+                let labels = b.labels.iter().map(|label| match label {
+                    CaseLabel::Value(value) => {
+                        let value = expr(value);
+                        quote!(#ast::CaseLabel::Value(#value))
+                    }
+                    CaseLabel::Range(from, to) => {
+                        let from = expr(from);
+                        let to = expr(to);
+                        quote!(#ast::CaseLabel::Range(#from, #to))
+                    }
+                });
+                let body = stmts(&b.body);
+                quote!(#ast::CaseBranch { labels: vec![#(#labels),*], body: #body })
+            });
+            let otherwise = opt(otherwise.as_ref(), |s| stmts(s));
+            quote! {
+                #ast::Stmt::Case {
+                    selector: #selector,
+                    branches: vec![#(#branches),*],
+                    otherwise: #otherwise,
+                }
+            }
+        }
+        Stmt::For {
+            var,
+            from,
+            to,
+            by,
+            body,
+        } => {
+            let from = expr(from);
+            let to = expr(to);
+            let by = opt(by.as_ref(), expr);
+            let body = stmts(body);
+            quote! {
+                #ast::Stmt::For {
+                    var: #var.to_string(),
+                    from: #from,
+                    to: #to,
+                    by: #by,
+                    body: #body,
+                }
+            }
+        }
+        Stmt::While { cond, body } => {
+            let cond = expr(cond);
+            let body = stmts(body);
+            quote!(#ast::Stmt::While { cond: #cond, body: #body })
+        }
+        Stmt::Repeat { body, until } => {
+            let body = stmts(body);
+            let until = expr(until);
+            quote!(#ast::Stmt::Repeat { body: #body, until: #until })
+        }
+        Stmt::Return => quote!(#ast::Stmt::Return),
+        Stmt::Exit => quote!(#ast::Stmt::Exit),
+        Stmt::Continue => quote!(#ast::Stmt::Continue),
+    }
+}
+
+fn call(call: &Call) -> TokenStream {
+    let name = &call.name;
+    let args = call.args.iter().map(|arg| match arg {
+        Arg::Value(value) => {
+            let value = expr(value);
+            quote!(::rust2plc::st::ast::Arg::Value(#value))
+        }
+        Arg::Input(name, value) => {
+            let value = expr(value);
+            quote!(::rust2plc::st::ast::Arg::Input(#name.to_string(), #value))
+        }
+        Arg::Output(name, value) => {
+            let value = expr(value);
+            quote!(::rust2plc::st::ast::Arg::Output(#name.to_string(), #value))
+        }
+    });
+    quote!(::rust2plc::st::ast::Call { name: #name.to_string(), args: vec![#(#args),*] })
+}
+
+pub(crate) fn expr(expr: &Expr) -> TokenStream {
+    let ast = quote!(::rust2plc::st::ast);
+    match expr {
+        Expr::Literal(literal) => {
+            let literal = match literal {
+                Literal::Bool(v) => quote!(#ast::Literal::Bool(#v)),
+                Literal::Int(v) => quote!(#ast::Literal::Int(#v)),
+                Literal::Real(v) => quote!(#ast::Literal::Real(#v)),
+                Literal::Str(v) => quote!(#ast::Literal::Str(#v.to_string())),
+                Literal::Typed { ty, value } => quote! {
+                    #ast::Literal::Typed { ty: #ty.to_string(), value: #value.to_string() }
+                },
+            };
+            quote!(#ast::Expr::Literal(#literal))
+        }
+        Expr::Var(name) => quote!(#ast::Expr::Var(#name.to_string())),
+        Expr::Field(base, member) => {
+            let base = self::expr(base);
+            quote!(#ast::Expr::Field(Box::new(#base), #member.to_string()))
+        }
+        Expr::Index(base, indices) => {
+            let base = self::expr(base);
+            let indices = indices.iter().map(self::expr);
+            quote!(#ast::Expr::Index(Box::new(#base), vec![#(#indices),*]))
+        }
+        Expr::Unary(op, operand) => {
+            let op = match op {
+                UnaryOp::Neg => quote!(#ast::UnaryOp::Neg),
+                UnaryOp::Not => quote!(#ast::UnaryOp::Not),
+            };
+            let operand = self::expr(operand);
+            quote!(#ast::Expr::Unary(#op, Box::new(#operand)))
+        }
+        Expr::Binary(op, lhs, rhs) => {
+            let op = match op {
+                BinaryOp::Or => quote!(Or),
+                BinaryOp::Xor => quote!(Xor),
+                BinaryOp::And => quote!(And),
+                BinaryOp::Eq => quote!(Eq),
+                BinaryOp::Ne => quote!(Ne),
+                BinaryOp::Lt => quote!(Lt),
+                BinaryOp::Le => quote!(Le),
+                BinaryOp::Gt => quote!(Gt),
+                BinaryOp::Ge => quote!(Ge),
+                BinaryOp::Add => quote!(Add),
+                BinaryOp::Sub => quote!(Sub),
+                BinaryOp::Mul => quote!(Mul),
+                BinaryOp::Div => quote!(Div),
+                BinaryOp::Mod => quote!(Mod),
+                BinaryOp::Pow => quote!(Pow),
+            };
+            let lhs = self::expr(lhs);
+            let rhs = self::expr(rhs);
+            quote!(#ast::Expr::Binary(#ast::BinaryOp::#op, Box::new(#lhs), Box::new(#rhs)))
+        }
+        Expr::Call(c) => {
+            let c = call(c);
+            quote!(#ast::Expr::Call(#c))
+        }
+        Expr::Paren(inner) => {
+            let inner = self::expr(inner);
+            quote!(#ast::Expr::Paren(Box::new(#inner)))
+        }
+    }
+}
@@ -0,0 +1,1693 @@
+//! Siemens STL (AWL), the statement list of S7-300/400 as compiled from STEP 7 sources.
+//!
+//! Blocks are addressed by number: functions become `FC`, function blocks `FB`, structures
+//! `UDT` and programs a function block with an instance `DB` called from `OB 1`.
+//! The numbers come from the models, e.g. `#[plc_fn(awl, block = "FC10")]`, or from
+//! [`AwlProject::with_block`], the remaining blocks of a project are numbered from 1.
+//!
+//! Every top-level statement of a body is a network. Boolean values are computed in the
+//! result of logic operation and assigned with `=`, numbers go through the accumulators
+//! and are transferred with `T`, control statements jump to the labels `J001`, `J002`, ...
+//!
+//! | ST                       | STL                                              |
+//! |--------------------------|--------------------------------------------------|
+//! | `q := a AND NOT b;`      | `A #a`, `AN #b`, `= #q`                          |
+//! | `y := a + b * c;`        | `L #b`, `L #c`, `*I`, `L #a`, `+I`, `T #y`       |
+//! | `IF a > 5 THEN ...`      | `L #a`, `L 5`, `>I`, `JCN J001`, ..., `J001: NOP 0` |
+//! | `timer(IN := run);`      | `CALL #timer (IN := #run)`                       |
+//!
+//! ```
+//! use rust2plc::st::ast::{BinaryOp, Expr, Stmt};
+//! use rust2plc::st::function::Function;
+//! use rust2plc::types::TypedValue;
+//! use rust2plc::var::Value;
+//!
+//! let twice = Function::new("twice", TypedValue::new_int())
+//!     .with_input(Value::Input("x".to_string(), TypedValue::new_int()))
+//!     .with_body(vec![Stmt::assign(
+//!         Expr::var("twice"),
+//!         Expr::binary(BinaryOp::Mul, Expr::var("x"), Expr::int(2)),
+//!     )])
+//!     .with_block(Some("FC10".to_string()));
+//! assert_eq!(
+//!     twice.to_awl().unwrap(),
+//!     "FUNCTION FC 10 : INT\n\
+//!      VERSION : 0.1\n\n\
+//!      VAR_INPUT\n  x : INT;\nEND_VAR\n\
+//!      BEGIN\n\
+//!      NETWORK\n\
+//!      TITLE = twice := x * 2;\n\
+//!      \x20     L     #x;\n\
+//!      \x20     L     2;\n\
+//!      \x20     *I    ;\n\
+//!      \x20     T     #RET_VAL;\n\
+//!      END_FUNCTION\n"
+//! );
+//! ```
+//!
+//! S7-300/400 has no 8- and 64-bit integers, no unsigned types, `LREAL` or wide characters,
+//! values of these types are rejected. There is no instruction for `**`, array elements
+//! are addressed by constant indexes and only the functions of the project can be called.
+//!
+//! With the `unverified-mc7` feature, `s7::mc7` assembles the source into MC7 block files
+//! that have not been checked against STEP 7 or a CPU.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::str::FromStr;
+
+use crate::error::Rust2PlcError;
+use crate::registry::{PLCRegistry, RegistryItem};
+use crate::scl;
+use crate::st::ast::{Arg, BinaryOp, Call, CaseLabel, Expr, Literal, Printer, Stmt, UnaryOp};
+use crate::st::data_type::DataType;
+use crate::st::function::Function;
+use crate::st::function_block::FunctionBlock;
+use crate::st::program::Program;
+use crate::types::TypedValue;
+use crate::var::Value;
+
+/// The IEC timers and counters, system function blocks of the CPU.
+const SYSTEM_BLOCKS: [(&str, u16); 6] = [
+    ("CTU", 0),
+    ("CTD", 1),
+    ("CTUD", 2),
+    ("TP", 3),
+    ("TON", 4),
+    ("TOF", 5),
+];
+
+/// The start information of `OB 1`, the first 20 bytes of its local data.
+const OB1_TEMPS: [(&str, &str); 10] = [
+    ("OB1_EV_CLASS", "BYTE"),
+    ("OB1_SCAN_1", "BYTE"),
+    ("OB1_PRIORITY", "BYTE"),
+    ("OB1_OB_NUMBR", "BYTE"),
+    ("OB1_RESERVED_1", "BYTE"),
+    ("OB1_RESERVED_2", "BYTE"),
+    ("OB1_PREV_CYCLE", "INT"),
+    ("OB1_MIN_CYCLE", "INT"),
+    ("OB1_MAX_CYCLE", "INT"),
+    ("OB1_DATE_TIME", "DATE_AND_TIME"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlockKind {
+    /// Organization block, called by the operating system.
+    Ob,
+    /// Function.
+    Fc,
+    /// Function block.
+    Fb,
+    /// Data block, the instance data of a function block.
+    Db,
+    /// User-defined data type.
+    Udt,
+}
+
+impl BlockKind {
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            BlockKind::Ob => "OB",
+            BlockKind::Fc => "FC",
+            BlockKind::Fb => "FB",
+            BlockKind::Db => "DB",
+            BlockKind::Udt => "UDT",
+        }
+    }
+}
+
+/// The address of a block, written `FC 10`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BlockNumber {
+    pub kind: BlockKind,
+    pub number: u16,
+}
+
+impl BlockNumber {
+    pub fn new(kind: BlockKind, number: u16) -> Self {
+        BlockNumber { kind, number }
+    }
+}
+
+impl FromStr for BlockNumber {
+    type Err = Rust2PlcError;
+
+    /// Parses `FC10`, `FC 10` or `fc10`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            Rust2PlcError::Other(format!(
+                "{} is not a block number, expected e.g. FC10 or FB 2",
+                s
+            ))
+        };
+        let text = s.trim();
+        let digits = text
+            .find(|c: char| c.is_ascii_digit())
+            .ok_or_else(invalid)?;
+        let (prefix, number) = text.split_at(digits);
+        let kind = match prefix.trim().to_uppercase().as_str() {
+            "OB" => BlockKind::Ob,
+            "FC" => BlockKind::Fc,
+            "FB" => BlockKind::Fb,
+            "DB" => BlockKind::Db,
+            "UDT" => BlockKind::Udt,
+            _ => return Err(invalid()),
+        };
+        let number = number.parse::<u16>().map_err(|_| invalid())?;
+        Ok(BlockNumber { kind, number })
+    }
+}
+
+impl Display for BlockNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.kind.prefix(), self.number)
+    }
+}
+
+/// An STL source with the blocks of a project, numbered and ordered for compilation.
+#[derive(Debug, Clone, Default)]
+pub struct AwlProject {
+    items: Vec<RegistryItem>,
+    blocks: Vec<(String, BlockNumber)>,
+    instance_dbs: Vec<(String, BlockNumber)>,
+}
+
+impl AwlProject {
+    pub fn new() -> Self {
+        AwlProject::default()
+    }
+
+    /// Adds an item, configurations are skipped as the CPU runs `OB 1` cyclically.
+    pub fn with_item(mut self, item: RegistryItem) -> Self {
+        self.items.push(item);
+        self
+    }
+
+    /// Numbers a block, overriding the number of the model.
+    pub fn with_block(mut self, name: impl Into<String>, block: BlockNumber) -> Self {
+        self.blocks.push((name.into(), block));
+        self
+    }
+
+    /// The instance data block of a program, by default the number of its function block.
+    pub fn with_instance_db(mut self, program: impl Into<String>, block: BlockNumber) -> Self {
+        self.instance_dbs.push((program.into(), block));
+        self
+    }
+
+    pub fn items(&self) -> &[RegistryItem] {
+        &self.items
+    }
+
+    /// The number of every block, explicit numbers first, then the next free ones.
+    pub fn numbers(&self) -> Result<Vec<(String, BlockNumber)>, Rust2PlcError> {
+        let mut numbers: Vec<(String, BlockNumber)> = vec![];
+        let mut pending = vec![];
+        let assign = |numbers: &mut Vec<(String, BlockNumber)>, name: &str, block: BlockNumber| {
+            if let Some((other, _)) = numbers.iter().find(|(_, b)| *b == block) {
+                return Err(Rust2PlcError::Other(format!(
+                    "{} is assigned to both {} and {}",
+                    block, other, name
+                )));
+            }
+            numbers.push((name.to_string(), block));
+            Ok(())
+        };
+        let next = |numbers: &[(String, BlockNumber)], kind: BlockKind| {
+            let number = (1..=u16::MAX)
+                .find(|n| {
+                    !numbers
+                        .iter()
+                        .any(|(_, b)| *b == BlockNumber::new(kind, *n))
+                })
+                .unwrap_or(u16::MAX);
+            BlockNumber::new(kind, number)
+        };
+
+        for item in self.items.iter() {
+            let Some(kind) = kind(item) else {
+                continue;
+            };
+            let explicit = match self.blocks.iter().find(|(n, _)| n == item.name()) {
+                Some((_, block)) => Some(*block),
+                None => model_block(item)?,
+            };
+            match explicit {
+                Some(block) if block.kind != kind => {
+                    return Err(Rust2PlcError::Other(format!(
+                        "{} is numbered among the {} blocks, not as {}",
+                        item.name(),
+                        kind.prefix(),
+                        block
+                    )))
+                }
+                Some(block) => assign(&mut numbers, item.name(), block)?,
+                None => pending.push((item.name(), kind)),
+            }
+        }
+        for (name, kind) in pending {
+            let block = next(&numbers, kind);
+            assign(&mut numbers, name, block)?;
+        }
+
+        let mut instances = vec![];
+        for item in self.items.iter() {
+            let RegistryItem::StProgram(p) = item else {
+                continue;
+            };
+            let explicit = self
+                .instance_dbs
+                .iter()
+                .find(|(n, _)| n == p.name())
+                .map(|(_, b)| *b);
+            if let Some(block) = explicit {
+                if block.kind != BlockKind::Db {
+                    return Err(Rust2PlcError::Other(format!(
+                        "the instance of {} is a data block, not {}",
+                        p.name(),
+                        block
+                    )));
+                }
+                assign(&mut numbers, &instance_name(p.name()), block)?;
+            } else {
+                instances.push(p.name());
+            }
+        }
+        for name in instances {
+            let fb = numbers
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, b)| BlockNumber::new(BlockKind::Db, b.number));
+            let block = match fb {
+                Some(db) if !numbers.iter().any(|(_, b)| *b == db) => db,
+                _ => next(&numbers, BlockKind::Db),
+            };
+            assign(&mut numbers, &instance_name(name), block)?;
+        }
+        Ok(numbers)
+    }
+
+    /// The source with the types, the functions, the function blocks, the instance data
+    /// and `OB 1` calling the programs, each block before the blocks using it.
+    pub fn to_awl(&self) -> Result<String, Rust2PlcError> {
+        let numbers = self.numbers()?;
+        let blocks = Blocks::new(numbers, &self.items);
+        let number = |name: &str| blocks.numbers.get(name).copied();
+
+        let mut parts = vec![];
+        for item in self.items.iter() {
+            if let RegistryItem::StType(t) = item {
+                parts.push(blocks.data_type(t)?);
+            }
+        }
+        for item in self.items.iter() {
+            if let RegistryItem::StFn(f) = item {
+                parts.push(blocks.function(f)?);
+            }
+        }
+        for item in self.items.iter() {
+            match item {
+                RegistryItem::StFb(fb) => parts.push(blocks.function_block(fb)?),
+                RegistryItem::StProgram(p) => parts.push(blocks.program(p)?),
+                _ => {}
+            }
+        }
+
+        let programs: Vec<&Program> = self
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                RegistryItem::StProgram(p) => Some(p),
+                _ => None,
+            })
+            .collect();
+        let mut calls = vec![];
+        for p in programs.iter() {
+            let (Some(fb), Some(db)) = (number(p.name()), number(&instance_name(p.name()))) else {
+                continue;
+            };
+            let mut awl = format!("DATA_BLOCK {}\n", db);
+            awl.push_str(&header(p.description(), p.version())?);
+            awl.push_str(&format!("  {}\nBEGIN\nEND_DATA_BLOCK\n", fb));
+            parts.push(awl);
+            calls.push(format!(
+                "NETWORK\nTITLE = {}\n{}",
+                p.name(),
+                instruction("CALL", format!("{} , {}", fb, db))
+            ));
+        }
+        if !calls.is_empty() {
+            let mut awl = format!(
+                "ORGANIZATION_BLOCK {}\n",
+                BlockNumber::new(BlockKind::Ob, 1)
+            );
+            awl.push_str(&header(Some("Main program cycle"), None)?);
+            awl.push_str("VAR_TEMP\n");
+            for (name, ty) in OB1_TEMPS {
+                awl.push_str(&format!("  {} : {};\n", name, ty));
+            }
+            awl.push_str("END_VAR\nBEGIN\n");
+            awl.push_str(&calls.concat());
+            awl.push_str("END_ORGANIZATION_BLOCK\n");
+            parts.push(awl);
+        }
+        Ok(parts.join("\n"))
+    }
+}
+
+/// The block kind the item is compiled to, `None` for configurations.
+fn kind(item: &RegistryItem) -> Option<BlockKind> {
+    match item {
+        RegistryItem::StFn(_) => Some(BlockKind::Fc),
+        RegistryItem::StFb(_) | RegistryItem::StProgram(_) => Some(BlockKind::Fb),
+        RegistryItem::StType(_) => Some(BlockKind::Udt),
+        RegistryItem::StConfig(_) => None,
+    }
+}
+
+/// The block number set on the model.
+fn model_block(item: &RegistryItem) -> Result<Option<BlockNumber>, Rust2PlcError> {
+    let block = match item {
+        RegistryItem::StFn(f) => f.block(),
+        RegistryItem::StFb(fb) => fb.block(),
+        RegistryItem::StProgram(p) => p.block(),
+        RegistryItem::StType(_) | RegistryItem::StConfig(_) => None,
+    };
+    block.map(str::parse).transpose()
+}
+
+/// The key of a program's instance data block among the block numbers.
+fn instance_name(program: &str) -> String {
+    format!("{}.DB", program)
+}
+
+fn header(description: Option<&str>, version: Option<&str>) -> Result<String, Rust2PlcError> {
+    let mut awl = String::new();
+    if let Some(description) = description {
+        awl.push_str(&format!("TITLE = {}\n", description));
+    }
+    awl.push_str(&format!("VERSION : {}\n\n", scl::version(version)?));
+    Ok(awl)
+}
+
+/// An instruction line, the operator in a column of six as in STEP 7 sources.
+fn instruction(operator: &str, operand: impl Display) -> String {
+    format!("      {:<6}{};\n", operator, operand)
+}
+
+/// `2.500000e+000`, the notation of `REAL` constants.
+fn real_literal(value: f64) -> String {
+    let text = format!("{:.6e}", value);
+    let (mantissa, exponent) = text.split_once('e').unwrap_or((&text, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    format!(
+        "{}e{}{:03}",
+        mantissa,
+        if exponent < 0 { '-' } else { '+' },
+        exponent.abs()
+    )
+}
+
+/// The blocks known by name, what the bodies need to resolve types and calls.
+struct Blocks<'a> {
+    numbers: HashMap<String, BlockNumber>,
+    functions: HashMap<&'a str, &'a Function>,
+    /// The variables of the function blocks and the fields of the structures.
+    members: HashMap<&'a str, Vec<(&'a str, &'a TypedValue)>>,
+}
+
+impl<'a> Blocks<'a> {
+    fn new(numbers: Vec<(String, BlockNumber)>, items: &'a [RegistryItem]) -> Self {
+        let mut functions = HashMap::new();
+        let mut members = HashMap::new();
+        let vars = |vars: Vec<&'a Value>| {
+            vars.into_iter()
+                .filter_map(|v| Some((v.name()?, v.typed_value())))
+                .collect::<Vec<_>>()
+        };
+        for item in items {
+            match item {
+                RegistryItem::StFn(f) => {
+                    functions.insert(f.name(), f);
+                }
+                RegistryItem::StFb(fb) => {
+                    let all = fb.inputs().iter().chain(fb.outputs()).chain(fb.vars());
+                    members.insert(fb.name(), vars(all.collect()));
+                }
+                RegistryItem::StProgram(p) => {
+                    let all = p.inputs().iter().chain(p.outputs()).chain(p.vars());
+                    members.insert(p.name(), vars(all.collect()));
+                }
+                RegistryItem::StType(t) => {
+                    if let TypedValue::Struct(_, fields) = t.value() {
+                        members.insert(
+                            t.name(),
+                            fields.iter().map(|(n, tv)| (n.as_str(), tv)).collect(),
+                        );
+                    }
+                }
+                RegistryItem::StConfig(_) => {}
+            }
+        }
+        Blocks {
+            numbers: numbers.into_iter().collect(),
+            functions,
+            members,
+        }
+    }
+
+    /// The blocks of a single item, its own number if the model has one.
+    fn single(item: &RegistryItem) -> Result<Blocks<'a>, Rust2PlcError> {
+        let numbers = match model_block(item)? {
+            Some(block) if Some(block.kind) != kind(item) => {
+                return Err(Rust2PlcError::Other(format!(
+                    "{} is numbered among the {} blocks, not as {}",
+                    item.name(),
+                    kind(item).map(|k| k.prefix()).unwrap_or_default(),
+                    block
+                )))
+            }
+            Some(block) => vec![(item.name().to_string(), block)],
+            None => vec![],
+        };
+        Ok(Blocks::new(numbers, &[]))
+    }
+
+    /// `FC 10`, or the quoted symbol of a block without number.
+    fn reference(&self, name: &str) -> String {
+        match self.numbers.get(name) {
+            Some(block) => block.to_string(),
+            None => format!("\"{}\"", name),
+        }
+    }
+
+    /// The STEP 7 name of the type, e.g. `INT`, `UDT 1` or `SFB 4`.
+    fn type_name(&self, tv: &TypedValue) -> Result<String, Rust2PlcError> {
+        Ok(match tv {
+            TypedValue::Bool(_) => "BOOL".to_string(),
+            TypedValue::Int(_) => "INT".to_string(),
+            TypedValue::DInt(_) => "DINT".to_string(),
+            TypedValue::Real(_) => "REAL".to_string(),
+            TypedValue::Time(_) => "TIME".to_string(),
+            TypedValue::Date(_) => "DATE".to_string(),
+            TypedValue::TimeOfDay(_) => "TIME_OF_DAY".to_string(),
+            TypedValue::DateTime(_) => "DATE_AND_TIME".to_string(),
+            TypedValue::Char(_) => "CHAR".to_string(),
+            TypedValue::String(_, len) => format!("STRING [{}]", len.unwrap_or(254)),
+            TypedValue::Byte(_) => "BYTE".to_string(),
+            TypedValue::Word(_) => "WORD".to_string(),
+            TypedValue::DWord(_) => "DWORD".to_string(),
+            TypedValue::UserDefined(name, _) | TypedValue::Struct(name, _) => {
+                match SYSTEM_BLOCKS.iter().find(|(n, _)| n == name) {
+                    Some((_, number)) => format!("SFB {}", number),
+                    None => self.reference(name),
+                }
+            }
+            TypedValue::Array(values, elem, lower) => format!(
+                "ARRAY [{} .. {}] OF {}",
+                lower,
+                lower + values.len() as i64 - 1,
+                self.type_name(elem)?
+            ),
+            TypedValue::Enum(name, ..) => {
+                return Err(Rust2PlcError::Other(format!(
+                    "the enumerated type {} has no S7-300/400 counterpart",
+                    name
+                )))
+            }
+            other => {
+                return Err(Rust2PlcError::Other(format!(
+                    "{} has no S7-300/400 counterpart",
+                    other.to_plc_type()
+                )))
+            }
+        })
+    }
+
+    /// The initial value in a declaration.
+    fn literal(&self, tv: &TypedValue) -> Result<String, Rust2PlcError> {
+        match tv {
+            TypedValue::Real(value) => Ok(real_literal(*value as f64)),
+            TypedValue::UserDefined(_, Some(value)) => self.literal(value),
+            TypedValue::Array(values, ..) => Ok(values
+                .iter()
+                .map(|v| self.literal(v))
+                .collect::<Result<Vec<_>, _>>()?
+                .join(", ")),
+            TypedValue::Struct(name, _) | TypedValue::UserDefined(name, None) => {
+                Err(Rust2PlcError::Other(format!(
+                    "{} values are set in the data block, STL declarations take no structure values",
+                    name
+                )))
+            }
+            other => {
+                self.type_name(other)?;
+                other.to_plc_literal()
+            }
+        }
+    }
+
+    /// The type of a member of a block or a structure.
+    fn member(&self, ty: &str, member: &str) -> Option<TypedValue> {
+        if SYSTEM_BLOCKS.iter().any(|(n, _)| *n == ty) {
+            return match member.to_uppercase().as_str() {
+                "IN" | "Q" | "CU" | "CD" | "R" | "LOAD" | "QU" | "QD" => {
+                    Some(TypedValue::new_bool())
+                }
+                "PT" | "ET" => Some(TypedValue::new_time()),
+                "PV" | "CV" => Some(TypedValue::new_int()),
+                _ => None,
+            };
+        }
+        self.members
+            .get(ty)?
+            .iter()
+            .find(|(n, _)| *n == member)
+            .map(|(_, tv)| (*tv).clone())
+    }
+
+    /// A `FUNCTION`, the local variables become `VAR_TEMP` as functions keep no state.
+    fn function(&self, f: &Function) -> Result<String, Rust2PlcError> {
+        let vars: Vec<&Value> = f
+            .inputs()
+            .iter()
+            .chain(f.outputs())
+            .chain(f.locals())
+            .collect();
+        let mut awl = format!(
+            "FUNCTION {} : {}\n",
+            self.reference(f.name()),
+            self.type_name(f.return_value())?
+        );
+        awl.push_str(&header(f.description(), f.version())?);
+        let sections = self.sections(f.name(), &vars, false)?;
+        let mut body = Body::new(self, &vars, Some((f.name(), f.return_value())), false);
+        let networks = body.networks(f.body())?;
+        awl.push_str(&self.declarations(sections, &body.temps)?);
+        awl.push_str(&networks);
+        awl.push_str("END_FUNCTION\n");
+        Ok(awl)
+    }
+
+    fn function_block(&self, fb: &FunctionBlock) -> Result<String, Rust2PlcError> {
+        let vars: Vec<&Value> = fb
+            .inputs()
+            .iter()
+            .chain(fb.outputs())
+            .chain(fb.vars())
+            .collect();
+        self.stateful(fb.name(), &vars, fb.body(), fb.description(), fb.version())
+    }
+
+    /// A program, compiled to a `FUNCTION_BLOCK`.
+    fn program(&self, p: &Program) -> Result<String, Rust2PlcError> {
+        if p.chart().is_some() {
+            return Err(Rust2PlcError::Other(format!(
+                "the chart of program {} has no STL counterpart",
+                p.name()
+            )));
+        }
+        let vars: Vec<&Value> = p
+            .inputs()
+            .iter()
+            .chain(p.outputs())
+            .chain(p.vars())
+            .collect();
+        self.stateful(p.name(), &vars, p.body(), p.description(), p.version())
+    }
+
+    fn stateful(
+        &self,
+        name: &str,
+        vars: &[&Value],
+        body: &[Stmt],
+        description: Option<&str>,
+        version: Option<&str>,
+    ) -> Result<String, Rust2PlcError> {
+        let mut awl = format!("FUNCTION_BLOCK {}\n", self.reference(name));
+        awl.push_str(&header(description, version)?);
+        let sections = self.sections(name, vars, true)?;
+        let mut lowering = Body::new(self, vars, None, true);
+        let networks = lowering.networks(body)?;
+        awl.push_str(&self.declarations(sections, &lowering.temps)?);
+        awl.push_str(&networks);
+        awl.push_str("END_FUNCTION_BLOCK\n");
+        Ok(awl)
+    }
+
+    /// A structure as a user-defined type.
+    fn data_type(&self, t: &DataType) -> Result<String, Rust2PlcError> {
+        let TypedValue::Struct(_, fields) = t.value() else {
+            return Err(Rust2PlcError::Other(format!(
+                "the enumerated type {} has no S7-300/400 counterpart",
+                t.name()
+            )));
+        };
+        let mut awl = format!("TYPE {}\n", self.reference(t.name()));
+        awl.push_str(&header(t.description(), t.version())?);
+        awl.push_str("  STRUCT\n");
+        for (field, value) in fields.iter() {
+            awl.push_str(&format!("    {}\n", self.decl(field, value, true)?));
+        }
+        awl.push_str("  END_STRUCT ;\nEND_TYPE\n");
+        Ok(awl)
+    }
+
+    /// The declarations by section, initial values are kept where the block holds the data.
+    fn sections(
+        &self,
+        pou: &str,
+        vars: &[&Value],
+        stateful: bool,
+    ) -> Result<Vec<(&'static str, Vec<String>)>, Rust2PlcError> {
+        let mut sections: Vec<(&str, Vec<String>)> =
+            ["VAR_INPUT", "VAR_OUTPUT", "VAR_IN_OUT", "VAR", "VAR_TEMP"]
+                .iter()
+                .map(|s| (*s, vec![]))
+                .collect();
+        for var in vars {
+            let name = var.name().unwrap_or_default();
+            let (section, init) = match var {
+                Value::Input(_, _) => (0, stateful),
+                Value::Output(_, _) => (1, stateful),
+                Value::InOut(_, _) => (2, false),
+                Value::Local(_, _) if stateful => (3, true),
+                Value::Local(_, _) | Value::Temporary(_, _) => (4, false),
+                Value::Constant(_, _) => {
+                    return Err(Rust2PlcError::Other(format!(
+                        "{} of {} is a constant, STL blocks declare no constants",
+                        name, pou
+                    )))
+                }
+                Value::Global(_, _) | Value::External(_, _) | Value::Return(_) => {
+                    return Err(Rust2PlcError::Other(format!(
+                        "{} of {} is declared {}, STL blocks reach global data by its symbol",
+                        name,
+                        pou,
+                        var.st_section().unwrap_or("as a return value")
+                    )))
+                }
+            };
+            sections[section]
+                .1
+                .push(self.decl(name, var.typed_value(), init)?);
+        }
+        Ok(sections)
+    }
+
+    /// The declaration sections with the temporaries added by the lowering.
+    fn declarations(
+        &self,
+        mut sections: Vec<(&'static str, Vec<String>)>,
+        temps: &[(String, TypedValue)],
+    ) -> Result<String, Rust2PlcError> {
+        for (name, tv) in temps {
+            sections[4].1.push(self.decl(name, tv, false)?);
+        }
+
+        let mut awl = String::new();
+        for (section, decls) in sections.iter().filter(|(_, d)| !d.is_empty()) {
+            awl.push_str(&format!("{}\n", section));
+            for decl in decls {
+                awl.push_str(&format!("  {}\n", decl));
+            }
+            awl.push_str("END_VAR\n");
+        }
+        Ok(awl)
+    }
+
+    fn decl(&self, name: &str, tv: &TypedValue, init: bool) -> Result<String, Rust2PlcError> {
+        if init && *tv != tv.default_value() {
+            Ok(format!(
+                "{} : {} := {};",
+                name,
+                self.type_name(tv)?,
+                self.literal(tv)?
+            ))
+        } else {
+            Ok(format!("{} : {};", name, self.type_name(tv)?))
+        }
+    }
+}
+
+/// How a value is held: in the result of logic operation or in an accumulator,
+/// as 16-bit, 32-bit or floating point number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Kind {
+    Bool,
+    Int,
+    DInt,
+    Real,
+}
+
+impl Kind {
+    fn of(tv: &TypedValue) -> Option<Kind> {
+        match tv {
+            TypedValue::Bool(_) => Some(Kind::Bool),
+            TypedValue::Int(_)
+            | TypedValue::Byte(_)
+            | TypedValue::Word(_)
+            | TypedValue::Char(_)
+            | TypedValue::Date(_) => Some(Kind::Int),
+            TypedValue::DInt(_)
+            | TypedValue::DWord(_)
+            | TypedValue::Time(_)
+            | TypedValue::TimeOfDay(_) => Some(Kind::DInt),
+            TypedValue::Real(_) => Some(Kind::Real),
+            TypedValue::UserDefined(_, Some(value)) => Kind::of(value),
+            _ => None,
+        }
+    }
+
+    /// The wider of two numbers, `None` when neither is a number.
+    fn widest(a: Option<Kind>, b: Option<Kind>) -> Option<Kind> {
+        [a, b]
+            .into_iter()
+            .flatten()
+            .filter(|k| *k != Kind::Bool)
+            .max()
+    }
+
+    fn suffix(&self) -> &'static str {
+        match self {
+            Kind::Bool | Kind::Int => "I",
+            Kind::DInt => "D",
+            Kind::Real => "R",
+        }
+    }
+
+    fn temp(&self) -> TypedValue {
+        match self {
+            Kind::Bool => TypedValue::new_bool(),
+            Kind::Int => TypedValue::new_int(),
+            Kind::DInt => TypedValue::new_dint(),
+            Kind::Real => TypedValue::new_real(),
+        }
+    }
+}
+
+fn print(expr: &Expr) -> String {
+    Printer::default().expr(expr)
+}
+
+/// The lowering of a block body into networks.
+struct Body<'a, 'b> {
+    blocks: &'b Blocks<'a>,
+    vars: HashMap<String, TypedValue>,
+    /// The static variables, the instances a function block can call.
+    statics: HashSet<String>,
+    /// The name of the function, its result is `RET_VAL`.
+    result: Option<String>,
+    lines: String,
+    labels: usize,
+    /// The temporary variables added for intermediate results and returned values.
+    temps: Vec<(String, TypedValue)>,
+    /// The enclosing loops, the labels `CONTINUE` and `EXIT` jump to.
+    loops: Vec<(String, String)>,
+}
+
+impl<'a, 'b> Body<'a, 'b> {
+    fn new(
+        blocks: &'b Blocks<'a>,
+        vars: &[&Value],
+        result: Option<(&str, &TypedValue)>,
+        stateful: bool,
+    ) -> Self {
+        let mut names: HashMap<String, TypedValue> = vars
+            .iter()
+            .filter_map(|v| Some((v.name()?.to_string(), v.typed_value().clone())))
+            .collect();
+        if let Some((name, tv)) = result {
+            names.insert(name.to_string(), tv.clone());
+        }
+        let statics = vars
+            .iter()
+            .filter(|v| stateful && matches!(v, Value::Local(_, _)))
+            .filter_map(|v| v.name().map(String::from))
+            .collect();
+        Body {
+            blocks,
+            vars: names,
+            statics,
+            result: result.map(|(name, _)| name.to_string()),
+            lines: String::new(),
+            labels: 0,
+            temps: vec![],
+            loops: vec![],
+        }
+    }
+
+    /// `BEGIN` and a network per statement titled with its first line of ST.
+    fn networks(&mut self, body: &[Stmt]) -> Result<String, Rust2PlcError> {
+        let mut awl = "BEGIN\n".to_string();
+        for stmt in body {
+            self.stmt(stmt)?;
+            let st = Printer::default().stmt(stmt, 0);
+            awl.push_str(&format!(
+                "NETWORK\nTITLE = {}\n",
+                st.lines().next().unwrap_or_default()
+            ));
+            awl.push_str(&std::mem::take(&mut self.lines));
+        }
+        Ok(awl)
+    }
+
+    fn emit(&mut self, operator: &str, operand: impl Display) {
+        self.lines.push_str(&instruction(operator, operand));
+    }
+
+    fn label(&mut self, label: &str) {
+        self.lines.push_str(&format!("{}: {:<6}0;\n", label, "NOP"));
+    }
+
+    fn next_label(&mut self) -> String {
+        self.labels += 1;
+        format!("J{:03}", self.labels)
+    }
+
+    /// A new temporary variable, returned as operand.
+    fn temp(&mut self, tv: TypedValue) -> String {
+        let name = (1..)
+            .map(|n| format!("tmp{}", n))
+            .find(|n| !self.vars.contains_key(n))
+            .unwrap_or_default();
+        self.vars.insert(name.clone(), tv.clone());
+        self.temps.push((name.clone(), tv));
+        format!("#{}", name)
+    }
+
+    /// Own variables are written `#name`, everything else is a quoted symbol.
+    fn name(&self, name: &str) -> String {
+        if self.result.as_deref() == Some(name) {
+            "#RET_VAL".to_string()
+        } else if self.vars.contains_key(name) {
+            format!("#{}", name)
+        } else {
+            format!("\"{}\"", name)
+        }
+    }
+
+    fn stmts(&mut self, body: &[Stmt]) -> Result<(), Rust2PlcError> {
+        for stmt in body {
+            self.stmt(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result<(), Rust2PlcError> {
+        match stmt {
+            Stmt::Assign { target, value } => {
+                let value = self.hoist(value)?;
+                let operand = self.operand(target, None).ok_or_else(|| {
+                    Rust2PlcError::Other(format!("{} cannot be assigned in STL", print(target)))
+                })?;
+                let kind = self
+                    .kind(target)
+                    .or_else(|| self.kind(&value))
+                    .ok_or_else(|| {
+                        Rust2PlcError::Other(format!(
+                            "the type of {} is unknown, STL moves only elementary values",
+                            print(target)
+                        ))
+                    })?;
+                if kind == Kind::Bool {
+                    self.chain(&value)?;
+                    self.emit("=", operand);
+                } else {
+                    self.word(&value, kind)?;
+                    self.emit("T", operand);
+                }
+            }
+            Stmt::Call(call) if self.blocks.functions.contains_key(call.name.as_str()) => {
+                self.hoist(&Expr::Call(call.clone()))?;
+            }
+            Stmt::Call(call) => self.instance(call)?,
+            Stmt::If {
+                branches,
+                otherwise,
+            } => {
+                let end = self.next_label();
+                for (i, branch) in branches.iter().enumerate() {
+                    let last = i + 1 == branches.len() && otherwise.is_none();
+                    let next = if last { end.clone() } else { self.next_label() };
+                    let cond = self.hoist(&branch.cond)?;
+                    self.chain(&cond)?;
+                    self.emit("JCN", &next);
+                    self.stmts(&branch.body)?;
+                    if !last {
+                        self.emit("JU", &end);
+                        self.label(&next);
+                    }
+                }
+                if let Some(otherwise) = otherwise {
+                    self.stmts(otherwise)?;
+                }
+                self.label(&end);
+            }
+            Stmt::Case {
+                selector,
+                branches,
+                otherwise,
+            } => {
+                let selector = self.hoist(selector)?;
+                let kind = match self.kind(&selector) {
+                    Some(Kind::DInt) => Kind::DInt,
+                    _ => Kind::Int,
+                };
+                let suffix = kind.suffix();
+                let end = self.next_label();
+                let targets: Vec<String> = branches.iter().map(|_| self.next_label()).collect();
+                for (branch, target) in branches.iter().zip(targets.iter()) {
+                    for label in branch.labels.iter() {
+                        match label {
+                            CaseLabel::Value(value) => {
+                                self.pair(&selector, value, kind, &format!("=={}", suffix), true)?
+                            }
+                            CaseLabel::Range(from, to) => {
+                                self.pair(&selector, from, kind, &format!(">={}", suffix), false)?;
+                                self.emit("A(", "");
+                                self.pair(&selector, to, kind, &format!("<={}", suffix), false)?;
+                                self.emit(")", "");
+                            }
+                        }
+                        self.emit("JC", target);
+                    }
+                }
+                let otherwise_label = otherwise.as_ref().map(|_| self.next_label());
+                self.emit("JU", otherwise_label.as_ref().unwrap_or(&end));
+                for (branch, target) in branches.iter().zip(targets.iter()) {
+                    self.label(target);
+                    self.stmts(&branch.body)?;
+                    self.emit("JU", &end);
+                }
+                if let (Some(label), Some(otherwise)) = (otherwise_label, otherwise) {
+                    self.label(&label);
+                    self.stmts(otherwise)?;
+                }
+                self.label(&end);
+            }
+            Stmt::For {
+                var,
+                from,
+                to,
+                by,
+                body,
+            } => {
+                let counter = Expr::var(var.clone());
+                let kind = match self.kind(&counter) {
+                    Some(Kind::DInt) => Kind::DInt,
+                    _ => Kind::Int,
+                };
+                let suffix = kind.suffix();
+                let from = self.hoist(from)?;
+                let to = self.hoist(to)?;
+                let by = self.hoist(by.as_ref().unwrap_or(&Expr::int(1)))?;
+                let (head, next, end) = (self.next_label(), self.next_label(), self.next_label());
+                self.word(&from, kind)?;
+                self.emit("T", self.name(var));
+                self.label(&head);
+                // A negative step counts down, a step of unknown sign is taken for positive.
+                let descending = matches!(
+                    &by,
+                    Expr::Unary(UnaryOp::Neg, _) | Expr::Literal(Literal::Int(i128::MIN..=-1))
+                );
+                let test = if descending { "<" } else { ">" };
+                self.pair(&counter, &to, kind, &format!("{}{}", test, suffix), false)?;
+                self.emit("JC", &end);
+                self.loops.push((next.clone(), end.clone()));
+                self.stmts(body)?;
+                self.loops.pop();
+                self.label(&next);
+                self.pair(&counter, &by, kind, &format!("+{}", suffix), true)?;
+                self.emit("T", self.name(var));
+                self.emit("JU", &head);
+                self.label(&end);
+            }
+            Stmt::While { cond, body } => {
+                let (head, end) = (self.next_label(), self.next_label());
+                self.label(&head);
+                let cond = self.hoist(cond)?;
+                self.chain(&cond)?;
+                self.emit("JCN", &end);
+                self.loops.push((head.clone(), end.clone()));
+                self.stmts(body)?;
+                self.loops.pop();
+                self.emit("JU", &head);
+                self.label(&end);
+            }
+            Stmt::Repeat { body, until } => {
+                let (head, next, end) = (self.next_label(), self.next_label(), self.next_label());
+                self.label(&head);
+                self.loops.push((next.clone(), end.clone()));
+                self.stmts(body)?;
+                self.loops.pop();
+                self.label(&next);
+                let until = self.hoist(until)?;
+                self.chain(&until)?;
+                self.emit("JCN", &head);
+                self.label(&end);
+            }
+            Stmt::Return => self.emit("BEU", ""),
+            Stmt::Exit | Stmt::Continue => {
+                let Some((next, end)) = self.loops.last().cloned() else {
+                    return Err(Rust2PlcError::Other(
+                        "EXIT and CONTINUE are only allowed in loops".to_string(),
+                    ));
+                };
+                let target = if matches!(stmt, Stmt::Exit) {
+                    end
+                } else {
+                    next
+                };
+                self.emit("JU", target);
+            }
+        }
+        Ok(())
+    }
+
+    /// `CALL #timer (IN := #run)`, a function block instance in the statics.
+    fn instance(&mut self, call: &Call) -> Result<(), Rust2PlcError> {
+        let ty = match self.vars.get(&call.name) {
+            Some(TypedValue::UserDefined(ty, _)) | Some(TypedValue::Struct(ty, _))
+                if self.statics.contains(&call.name) =>
+            {
+                ty.clone()
+            }
+            _ => {
+                return Err(Rust2PlcError::Other(format!(
+                    "{} is not an instance in the statics of the block, STL calls function blocks as multi-instances",
+                    call.name
+                )))
+            }
+        };
+        let mut params = vec![];
+        for arg in call.args.iter() {
+            match arg {
+                Arg::Input(name, value) => {
+                    let tv = self.blocks.member(&ty, name);
+                    let value = self.hoist(value)?;
+                    params.push(format!("{} := {}", name, self.param(&value, tv)?));
+                }
+                Arg::Output(name, target) => {
+                    let target = self.operand(target, None).ok_or_else(|| {
+                        Rust2PlcError::Other(format!(
+                            "{} cannot receive {}.{} in STL",
+                            print(target),
+                            call.name,
+                            name
+                        ))
+                    })?;
+                    params.push(format!("{} := {}", name, target));
+                }
+                Arg::Value(_) => {
+                    return Err(Rust2PlcError::Other(format!(
+                        "{} is invoked with positional arguments, CALL needs formal ones",
+                        call.name
+                    )))
+                }
+            }
+        }
+        let instance = self.name(&call.name);
+        if params.is_empty() {
+            self.emit("CALL", instance);
+        } else {
+            self.emit("CALL", format!("{} ({})", instance, params.join(", ")));
+        }
+        Ok(())
+    }
+
+    /// The actual parameter of a call, compound values are computed into a temporary.
+    fn param(&mut self, value: &Expr, tv: Option<TypedValue>) -> Result<String, Rust2PlcError> {
+        let kind = tv
+            .as_ref()
+            .and_then(Kind::of)
+            .or_else(|| self.kind(value))
+            .unwrap_or(Kind::Int);
+        if let Expr::Literal(Literal::Bool(value)) = value {
+            return Ok(if *value { "TRUE" } else { "FALSE" }.to_string());
+        }
+        if let Some(operand) = self.operand(value, Some(kind)) {
+            return Ok(operand);
+        }
+        let temp = self.temp(tv.unwrap_or_else(|| kind.temp()));
+        if kind == Kind::Bool {
+            self.chain(value)?;
+            self.emit("=", &temp);
+        } else {
+            self.word(value, kind)?;
+            self.emit("T", &temp);
+        }
+        Ok(temp)
+    }
+
+    /// Calls the functions of the project ahead of the expression, their results are
+    /// read from temporaries as a call ends the logic operation.
+    fn hoist(&mut self, expr: &Expr) -> Result<Expr, Rust2PlcError> {
+        Ok(match expr {
+            Expr::Call(call) => {
+                let args = call
+                    .args
+                    .iter()
+                    .map(|arg| {
+                        Ok(match arg {
+                            Arg::Value(value) => Arg::Value(self.hoist(value)?),
+                            Arg::Input(name, value) => Arg::Input(name.clone(), self.hoist(value)?),
+                            Arg::Output(name, target) => Arg::Output(name.clone(), target.clone()),
+                        })
+                    })
+                    .collect::<Result<Vec<_>, Rust2PlcError>>()?;
+                let Some(function) = self.blocks.functions.get(call.name.as_str()).copied() else {
+                    return Ok(Expr::Call(Call {
+                        name: call.name.clone(),
+                        args,
+                    }));
+                };
+                let mut params = vec![];
+                for (i, arg) in args.iter().enumerate() {
+                    let (name, value) = match arg {
+                        Arg::Value(value) => match function.inputs().get(i).and_then(Value::name) {
+                            Some(name) => (name.to_string(), value),
+                            None => {
+                                return Err(Rust2PlcError::Other(format!(
+                                    "{} takes {} arguments",
+                                    call.name,
+                                    function.inputs().len()
+                                )))
+                            }
+                        },
+                        Arg::Input(name, value) => (name.clone(), value),
+                        Arg::Output(name, target) => {
+                            let target = self.operand(target, None).ok_or_else(|| {
+                                Rust2PlcError::Other(format!(
+                                    "{} cannot receive {} in STL",
+                                    print(target),
+                                    name
+                                ))
+                            })?;
+                            params.push(format!("{} := {}", name, target));
+                            continue;
+                        }
+                    };
+                    let tv = function
+                        .inputs()
+                        .iter()
+                        .chain(function.outputs())
+                        .find(|v| v.name() == Some(name.as_str()))
+                        .map(|v| v.typed_value().clone());
+                    params.push(format!("{} := {}", name, self.param(value, tv)?));
+                }
+                let result = self.temp(function.return_value().clone());
+                params.push(format!("RET_VAL := {}", result));
+                let block = self.blocks.reference(function.name());
+                self.emit("CALL", format!("{} ({})", block, params.join(", ")));
+                Expr::Var(result.trim_start_matches('#').to_string())
+            }
+            Expr::Unary(op, inner) => Expr::Unary(*op, Box::new(self.hoist(inner)?)),
+            Expr::Binary(op, lhs, rhs) => {
+                Expr::Binary(*op, Box::new(self.hoist(lhs)?), Box::new(self.hoist(rhs)?))
+            }
+            Expr::Paren(inner) => Expr::Paren(Box::new(self.hoist(inner)?)),
+            other => other.clone(),
+        })
+    }
+
+    fn type_of(&self, expr: &Expr) -> Option<TypedValue> {
+        match expr {
+            Expr::Var(name) => self.vars.get(name).cloned(),
+            Expr::Field(base, member) => match self.type_of(base)? {
+                TypedValue::Struct(_, fields) => fields
+                    .into_iter()
+                    .find(|(n, _)| n == member)
+                    .map(|(_, tv)| tv),
+                TypedValue::UserDefined(ty, _) => self.blocks.member(&ty, member),
+                _ => None,
+            },
+            Expr::Index(base, _) => match self.type_of(base)? {
+                TypedValue::Array(_, elem, _) => Some(*elem),
+                _ => None,
+            },
+            Expr::Paren(inner) => self.type_of(inner),
+            _ => None,
+        }
+    }
+
+    fn kind(&self, expr: &Expr) -> Option<Kind> {
+        match expr {
+            Expr::Literal(Literal::Bool(_)) => Some(Kind::Bool),
+            Expr::Literal(Literal::Int(value)) => Some(if i16::try_from(*value).is_ok() {
+                Kind::Int
+            } else {
+                Kind::DInt
+            }),
+            Expr::Literal(Literal::Real(_)) => Some(Kind::Real),
+            Expr::Literal(Literal::Typed { ty, .. }) => match ty.to_uppercase().as_str() {
+                "T" | "TIME" | "TOD" | "TIME_OF_DAY" => Some(Kind::DInt),
+                "D" | "DATE" => Some(Kind::Int),
+                upper => TypedValue::from_plc_type(upper).as_ref().and_then(Kind::of),
+            },
+            Expr::Literal(Literal::Str(_)) => None,
+            Expr::Var(_) | Expr::Field(_, _) | Expr::Index(_, _) => {
+                self.type_of(expr).as_ref().and_then(Kind::of)
+            }
+            Expr::Paren(inner) | Expr::Unary(UnaryOp::Neg, inner) => self.kind(inner),
+            Expr::Unary(UnaryOp::Not, inner) => self.kind(inner).or(Some(Kind::Bool)),
+            Expr::Binary(op, lhs, rhs) => match op {
+                BinaryOp::Eq
+                | BinaryOp::Ne
+                | BinaryOp::Lt
+                | BinaryOp::Le
+                | BinaryOp::Gt
+                | BinaryOp::Ge => Some(Kind::Bool),
+                BinaryOp::And | BinaryOp::Or | BinaryOp::Xor => {
+                    Kind::widest(self.kind(lhs), self.kind(rhs)).or(Some(Kind::Bool))
+                }
+                _ => Kind::widest(self.kind(lhs), self.kind(rhs)),
+            },
+            Expr::Call(call) => {
+                let upper = call.name.to_uppercase();
+                if let Some((_, to)) = upper.split_once("_TO_") {
+                    return TypedValue::from_plc_type(to).as_ref().and_then(Kind::of);
+                }
+                match upper.as_str() {
+                    "ROUND" | "TRUNC" | "CEIL" | "FLOOR" => Some(Kind::DInt),
+                    "ABS" | "SQRT" | "SQR" | "LN" | "EXP" | "SIN" | "COS" | "TAN" | "ASIN"
+                    | "ACOS" | "ATAN" => Some(Kind::Real),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    fn is_bool(&self, expr: &Expr) -> bool {
+        self.kind(expr).is_none_or(|k| k == Kind::Bool)
+    }
+
+    /// The text of a variable or constant, `None` for compound expressions.
+    /// Numbers are written for the accumulator width of `kind`.
+    fn operand(&self, expr: &Expr, kind: Option<Kind>) -> Option<String> {
+        let number = |value: i128, kind: Option<Kind>| match kind {
+            Some(Kind::Real) => real_literal(value as f64),
+            Some(Kind::DInt) => format!("L#{}", value),
+            _ if i16::try_from(value).is_ok() => value.to_string(),
+            _ => format!("L#{}", value),
+        };
+        match expr {
+            Expr::Literal(Literal::Int(value)) => Some(number(*value, kind)),
+            Expr::Literal(Literal::Real(value)) => Some(real_literal(*value)),
+            Expr::Literal(Literal::Typed { ty, value }) => match ty.to_uppercase().as_str() {
+                "T" | "TIME" => Some(format!("T#{}", value)),
+                "D" | "DATE" => Some(format!("D#{}", value)),
+                "TOD" | "TIME_OF_DAY" => Some(format!("TOD#{}", value)),
+                _ => match value.parse::<i128>() {
+                    Ok(value) => Some(number(value, kind.or(self.kind(expr)))),
+                    Err(_) => value.parse::<f64>().ok().map(real_literal),
+                },
+            },
+            Expr::Unary(UnaryOp::Neg, inner) => match &**inner {
+                Expr::Literal(Literal::Int(value)) => Some(number(-value, kind)),
+                Expr::Literal(Literal::Real(value)) => Some(real_literal(-value)),
+                _ => None,
+            },
+            Expr::Var(name) => Some(self.name(name)),
+            Expr::Field(base, member) => match &**base {
+                Expr::Var(_) | Expr::Field(_, _) => {
+                    Some(format!("{}.{}", self.operand(base, None)?, member))
+                }
+                _ => None,
+            },
+            Expr::Index(base, indices) => {
+                let indices = indices
+                    .iter()
+                    .map(|i| match i {
+                        Expr::Literal(Literal::Int(i)) => Some(i.to_string()),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                Some(format!(
+                    "{}[{}]",
+                    self.operand(base, None)?,
+                    indices.join(", ")
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    fn is_literal(expr: &Expr) -> bool {
+        match expr {
+            Expr::Literal(_) => true,
+            Expr::Unary(UnaryOp::Neg, inner) => Body::is_literal(inner),
+            _ => false,
+        }
+    }
+
+    /// Starts a new logic operation with the expression, its value is left in the RLO.
+    fn chain(&mut self, expr: &Expr) -> Result<(), Rust2PlcError> {
+        match expr {
+            Expr::Paren(inner) => self.chain(inner),
+            Expr::Literal(Literal::Bool(value)) => {
+                self.emit(if *value { "SET" } else { "CLR" }, "");
+                Ok(())
+            }
+            Expr::Binary(op, lhs, rhs) if comparison(*op) => self.compare(*op, lhs, rhs),
+            Expr::Binary(op @ (BinaryOp::And | BinaryOp::Or | BinaryOp::Xor), _, _)
+                if self.is_bool(expr) =>
+            {
+                self.logic(logic_operator(*op), expr)
+            }
+            _ => self.logic("A", expr),
+        }
+    }
+
+    /// Combines the RLO with the expression, `operator` is `A`, `O` or `X`.
+    fn logic(&mut self, operator: &str, expr: &Expr) -> Result<(), Rust2PlcError> {
+        match expr {
+            Expr::Paren(inner) => self.logic(operator, inner),
+            Expr::Unary(UnaryOp::Not, inner) if self.is_bool(inner) => {
+                match self.operand(inner, None) {
+                    Some(operand) => self.emit(&format!("{}N", operator), operand),
+                    None => {
+                        self.emit(&format!("{}N(", operator), "");
+                        self.chain(inner)?;
+                        self.emit(")", "");
+                    }
+                }
+                Ok(())
+            }
+            Expr::Binary(op @ (BinaryOp::And | BinaryOp::Or | BinaryOp::Xor), lhs, rhs)
+                if self.is_bool(expr) && logic_operator(*op) == operator =>
+            {
+                self.logic(operator, lhs)?;
+                self.logic(operator, rhs)
+            }
+            Expr::Literal(_) | Expr::Binary(_, _, _) => {
+                self.emit(&format!("{}(", operator), "");
+                self.chain(expr)?;
+                self.emit(")", "");
+                Ok(())
+            }
+            expr => match self.operand(expr, None) {
+                Some(operand) if self.is_bool(expr) => {
+                    self.emit(operator, operand);
+                    Ok(())
+                }
+                _ => Err(Rust2PlcError::Other(format!(
+                    "{} is not a BOOL value",
+                    print(expr)
+                ))),
+            },
+        }
+    }
+
+    /// A comparison in the RLO, `BOOL` values are compared with exclusive or.
+    fn compare(&mut self, op: BinaryOp, lhs: &Expr, rhs: &Expr) -> Result<(), Rust2PlcError> {
+        let kind = match Kind::widest(self.kind(lhs), self.kind(rhs)) {
+            Some(kind) => kind,
+            None if self.is_bool(lhs) && self.is_bool(rhs) => Kind::Bool,
+            None => Kind::Int,
+        };
+        let symbol = match op {
+            BinaryOp::Eq => "==",
+            BinaryOp::Ne => "<>",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            _ => ">=",
+        };
+        if kind != Kind::Bool {
+            let operator = format!("{}{}", symbol, kind.suffix());
+            return self.pair(
+                lhs,
+                rhs,
+                kind,
+                &operator,
+                matches!(op, BinaryOp::Eq | BinaryOp::Ne),
+            );
+        }
+        match op {
+            BinaryOp::Eq => {
+                self.logic("X", lhs)?;
+                self.logic("X", &Expr::unary(UnaryOp::Not, rhs.clone()))
+            }
+            BinaryOp::Ne => {
+                self.logic("X", lhs)?;
+                self.logic("X", rhs)
+            }
+            _ => Err(Rust2PlcError::Other(format!(
+                "BOOL values are compared with = and <> only, not {}",
+                symbol
+            ))),
+        }
+    }
+
+    /// Leaves the value of the expression in accumulator 1, computed as `at`.
+    fn word(&mut self, expr: &Expr, at: Kind) -> Result<(), Rust2PlcError> {
+        if self.operand(expr, Some(at)).is_some() {
+            return self.load(expr, at);
+        }
+        match expr {
+            Expr::Paren(inner) => self.word(inner, at),
+            Expr::Unary(UnaryOp::Neg, inner) => {
+                self.word(inner, at)?;
+                let neg = format!("NEG{}", at.suffix());
+                self.emit(&neg, "");
+                Ok(())
+            }
+            Expr::Unary(UnaryOp::Not, inner) => {
+                let inv = match at {
+                    Kind::Int => "INVI",
+                    Kind::DInt => "INVD",
+                    _ => {
+                        return Err(Rust2PlcError::Other(format!(
+                            "{} cannot be inverted in an accumulator",
+                            print(expr)
+                        )))
+                    }
+                };
+                self.word(inner, at)?;
+                self.emit(inv, "");
+                Ok(())
+            }
+            Expr::Binary(op, lhs, rhs) if !comparison(*op) && *op != BinaryOp::Pow => {
+                let own = self.kind(expr).filter(|k| *k != Kind::Bool).unwrap_or(at);
+                let computed = match (op, own) {
+                    (BinaryOp::Mod, Kind::Int) => Kind::DInt,
+                    _ => own,
+                };
+                let suffix = computed.suffix();
+                let (operator, commutative) = match (op, computed) {
+                    (BinaryOp::Add, _) => (format!("+{}", suffix), true),
+                    (BinaryOp::Sub, _) => (format!("-{}", suffix), false),
+                    (BinaryOp::Mul, _) => (format!("*{}", suffix), true),
+                    (BinaryOp::Div, _) => (format!("/{}", suffix), false),
+                    (BinaryOp::Mod, Kind::DInt) => ("MOD".to_string(), false),
+                    (BinaryOp::And, Kind::Int) => ("AW".to_string(), true),
+                    (BinaryOp::Or, Kind::Int) => ("OW".to_string(), true),
+                    (BinaryOp::Xor, Kind::Int) => ("XOW".to_string(), true),
+                    (BinaryOp::And, Kind::DInt) => ("AD".to_string(), true),
+                    (BinaryOp::Or, Kind::DInt) => ("OD".to_string(), true),
+                    (BinaryOp::Xor, Kind::DInt) => ("XOD".to_string(), true),
+                    _ => {
+                        return Err(Rust2PlcError::Other(format!(
+                            "{} has no STL instruction for {} values",
+                            print(expr),
+                            suffix
+                        )))
+                    }
+                };
+                self.pair(lhs, rhs, computed, &operator, commutative)?;
+                self.convert(computed, at, expr)
+            }
+            Expr::Binary(BinaryOp::Pow, _, _) => Err(Rust2PlcError::Other(format!(
+                "{} has no STL instruction, compute the power with LN and EXP",
+                print(expr)
+            ))),
+            Expr::Call(call) => self.function(call, at),
+            other => Err(Rust2PlcError::Other(format!(
+                "{} cannot be loaded in STL",
+                print(other)
+            ))),
+        }
+    }
+
+    /// Loads a variable or a constant, converting the variable to `at`.
+    fn load(&mut self, expr: &Expr, at: Kind) -> Result<(), Rust2PlcError> {
+        let Some(operand) = self.operand(expr, Some(at)) else {
+            return self.word(expr, at);
+        };
+        self.emit("L", operand);
+        if Body::is_literal(expr) {
+            return Ok(());
+        }
+        let own = self.kind(expr).unwrap_or(at);
+        self.convert(own, at, expr)
+    }
+
+    fn convert(&mut self, from: Kind, to: Kind, expr: &Expr) -> Result<(), Rust2PlcError> {
+        match (from, to) {
+            (from, to) if from == to => {}
+            (Kind::Int, Kind::DInt) => self.emit("ITD", ""),
+            (Kind::Int, Kind::Real) => {
+                self.emit("ITD", "");
+                self.emit("DTR", "");
+            }
+            (Kind::DInt, Kind::Real) => self.emit("DTR", ""),
+            // The low word of the accumulator holds the 16-bit value.
+            (Kind::DInt, Kind::Int) => {}
+            (from, _) => {
+                return Err(Rust2PlcError::Other(format!(
+                    "{} is {}, convert it explicitly",
+                    print(expr),
+                    match from {
+                        Kind::Real => "REAL",
+                        _ => "BOOL",
+                    }
+                )))
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies an operator to two values, the left one ends up in accumulator 2.
+    fn pair(
+        &mut self,
+        lhs: &Expr,
+        rhs: &Expr,
+        at: Kind,
+        operator: &str,
+        commutative: bool,
+    ) -> Result<(), Rust2PlcError> {
+        let simple = |e: &Expr| self.operand(e, Some(at)).is_some();
+        match (simple(lhs), simple(rhs)) {
+            (_, true) => {
+                self.word(lhs, at)?;
+                self.load(rhs, at)?;
+            }
+            (true, false) => {
+                self.word(rhs, at)?;
+                self.load(lhs, at)?;
+                if !commutative {
+                    self.emit("TAK", "");
+                }
+            }
+            (false, false) => {
+                self.word(rhs, at)?;
+                let temp = self.temp(at.temp());
+                self.emit("T", &temp);
+                self.word(lhs, at)?;
+                self.emit("L", temp);
+            }
+        }
+        self.emit(operator, "");
+        Ok(())
+    }
+
+    /// The standard functions with an instruction of their own.
+    fn function(&mut self, call: &Call, at: Kind) -> Result<(), Rust2PlcError> {
+        let [Arg::Value(arg)] = call.args.as_slice() else {
+            return Err(Rust2PlcError::Other(format!(
+                "{} is not a function of the project, STL calls blocks by number",
+                call.name
+            )));
+        };
+        let upper = call.name.to_uppercase();
+        if let Some((from, to)) = upper.split_once("_TO_") {
+            let kind = |ty: &str| TypedValue::from_plc_type(ty).as_ref().and_then(Kind::of);
+            let (Some(from), Some(to)) = (kind(from), kind(to)) else {
+                return Err(Rust2PlcError::Other(format!(
+                    "{} has no STL instruction",
+                    call.name
+                )));
+            };
+            self.word(arg, from)?;
+            if from == Kind::Real && to != Kind::Real {
+                self.emit("RND", "");
+                return self.convert(Kind::DInt, at, arg);
+            }
+            self.convert(from, to, arg)?;
+            return self.convert(to, at, arg);
+        }
+        let (instruction, result) = match upper.as_str() {
+            "ROUND" => ("RND", Kind::DInt),
+            "TRUNC" => ("TRUNC", Kind::DInt),
+            "CEIL" => ("RND+", Kind::DInt),
+            "FLOOR" => ("RND-", Kind::DInt),
+            "ABS" | "SQRT" | "SQR" | "LN" | "EXP" | "SIN" | "COS" | "TAN" | "ASIN" | "ACOS"
+            | "ATAN" => (upper.as_str(), Kind::Real),
+            _ => {
+                return Err(Rust2PlcError::Other(format!(
+                    "{} is not a function of the project, STL calls blocks by number",
+                    call.name
+                )))
+            }
+        };
+        self.word(arg, Kind::Real)?;
+        self.emit(instruction, "");
+        self.convert(result, at, arg)
+    }
+}
+
+fn comparison(op: BinaryOp) -> bool {
+    matches!(
+        op,
+        BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge
+    )
+}
+
+fn logic_operator(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Or => "O",
+        BinaryOp::Xor => "X",
+        _ => "A",
+    }
+}
+
+impl Function {
+    /// Renders the function as an STL `FUNCTION`, numbered by its block if it has one.
+    pub fn to_awl(&self) -> Result<String, Rust2PlcError> {
+        Blocks::single(&RegistryItem::StFn(self.clone()))?.function(self)
+    }
+}
+
+impl FunctionBlock {
+    /// Renders the block as an STL `FUNCTION_BLOCK`.
+    pub fn to_awl(&self) -> Result<String, Rust2PlcError> {
+        Blocks::single(&RegistryItem::StFb(self.clone()))?.function_block(self)
+    }
+}
+
+impl Program {
+    /// Renders the program as an STL `FUNCTION_BLOCK`, see [`AwlProject`] for the
+    /// instance data block and `OB 1`.
+    pub fn to_awl(&self) -> Result<String, Rust2PlcError> {
+        Blocks::single(&RegistryItem::StProgram(self.clone()))?.program(self)
+    }
+}
+
+impl RegistryItem {
+    pub fn to_awl(&self) -> Result<String, Rust2PlcError> {
+        let blocks = Blocks::single(self)?;
+        match self {
+            RegistryItem::StFn(f) => blocks.function(f),
+            RegistryItem::StFb(fb) => blocks.function_block(fb),
+            RegistryItem::StProgram(p) => blocks.program(p),
+            RegistryItem::StType(t) => blocks.data_type(t),
+            RegistryItem::StConfig(c) => Err(Rust2PlcError::Other(format!(
+                "the configuration {} has no STL counterpart, OB 1 runs the programs",
+                c.name()
+            ))),
+        }
+    }
+}
+
+impl PLCRegistry {
+    /// Renders the latest version of every item as one STL source,
+    /// following [`PLCRegistry::declaration_order`].
+    pub fn to_awl(&self) -> Result<String, Rust2PlcError> {
+        self.declaration_order()
+            .into_iter()
+            .fold(AwlProject::new(), |p, item| p.with_item(item.clone()))
+            .to_awl()
+    }
+}
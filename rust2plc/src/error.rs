@@ -1,11 +1,57 @@
+use std::fmt;
+
+#[derive(Debug)]
 pub enum Rust2PlcError {
     IoError(std::io::Error),
-    ParseError(String),
+    /// A failed parse, with the place in the source when it is known.
+    ParseError(String, Option<Position>),
     Other(String),
 }
 
+/// A 1-based line and column in a source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
 impl Rust2PlcError {
     pub fn parse(msg: String) -> Self {
-        Rust2PlcError::ParseError(msg)
+        Rust2PlcError::ParseError(msg, None)
+    }
+
+    pub fn parse_at(msg: String, line: usize, column: usize) -> Self {
+        Rust2PlcError::ParseError(msg, Some(Position { line, column }))
+    }
+
+    /// The place of a parse error in the source.
+    pub fn position(&self) -> Option<Position> {
+        match self {
+            Rust2PlcError::ParseError(_, position) => *position,
+            _ => None,
+        }
     }
-}
\ No newline at end of file
+}
+
+impl fmt::Display for Rust2PlcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rust2PlcError::IoError(e) => write!(f, "io error: {}", e),
+            Rust2PlcError::ParseError(msg, Some(pos)) => write!(
+                f,
+                "parse error at line {}, column {}: {}",
+                pos.line, pos.column, msg
+            ),
+            Rust2PlcError::ParseError(msg, None) => write!(f, "parse error: {}", msg),
+            Rust2PlcError::Other(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Rust2PlcError {}
+
+impl From<std::io::Error> for Rust2PlcError {
+    fn from(e: std::io::Error) -> Self {
+        Rust2PlcError::IoError(e)
+    }
+}
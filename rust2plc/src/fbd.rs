@@ -0,0 +1,700 @@
+//! Function Block Diagram, the expression trees of a body drawn as networks.
+//!
+//! Every assignment becomes a network: the operators and function calls are blocks named
+//! after `docs/fbd/predefined_blocks.md`, the variables and literals they read are input
+//! variables and the assigned variable is an output variable. Chains of `+`, `*`, `AND`,
+//! `OR` and `XOR` become one block with an input per operand, an `IF` choosing between
+//! two values of one variable becomes a `SEL` block and a function block call draws
+//! the instance with its inputs and the outputs it assigns.
+//!
+//! The layout is computed from the tree alone. The output variable is the rightmost column,
+//! every block sits one column left of the block it feeds and its inputs are stacked
+//! row by row, so the same body always gives the same drawing.
+//!
+//! ```
+//! use rust2plc::st::ast::{BinaryOp, Expr, Stmt};
+//! use rust2plc::st::function::Function;
+//! use rust2plc::types::TypedValue;
+//! use rust2plc::var::Value;
+//!
+//! let offset = Function::new("offset", TypedValue::new_int())
+//!     .with_input(Value::Input("raw".to_string(), TypedValue::new_int()))
+//!     .with_body(vec![Stmt::assign(
+//!         Expr::var("offset"),
+//!         Expr::binary(BinaryOp::Add, Expr::var("raw"), Expr::int(10)),
+//!     )]);
+//! let fbd = offset.to_fbd().unwrap();
+//! let names: Vec<_> = fbd.nodes().iter().map(|n| n.label()).collect();
+//! assert_eq!(names, vec!["raw", "10", "ADD", "offset"]);
+//! assert!(fbd.to_svg().starts_with("<svg"));
+//! ```
+
+use std::collections::HashMap;
+
+use crate::error::Rust2PlcError;
+use crate::plcopen::escape;
+use crate::registry::RegistryItem;
+use crate::st::ast::{Arg, BinaryOp, Branch, Call, Expr, Printer, Stmt, UnaryOp};
+use crate::st::function::Function;
+use crate::st::function_block::FunctionBlock;
+use crate::st::program::Program;
+use crate::types::TypedValue;
+use crate::var::Value;
+
+/// The distance between two columns of the layout.
+pub const COLUMN_WIDTH: usize = 140;
+
+/// The height of a row, a variable takes one row and a block one row per pin.
+pub const ROW_HEIGHT: usize = 30;
+
+const VARIABLE_WIDTH: usize = 100;
+const VARIABLE_HEIGHT: usize = 20;
+const BLOCK_WIDTH: usize = 80;
+
+/// Blocks with as many `IN1`, `IN2`, ... inputs as operands.
+const EXTENSIBLE: [&str; 10] = [
+    "ADD", "MUL", "AND", "OR", "XOR", "NAND", "NOR", "MAX", "MIN", "CONCAT",
+];
+
+/// The inputs of the predefined functions and function blocks with fixed pins.
+const PINS: [(&str, &[&str]); 39] = [
+    ("NOT", &["IN"]),
+    ("SQRT", &["IN"]),
+    ("ABS", &["IN"]),
+    ("EXP", &["IN"]),
+    ("LN", &["IN"]),
+    ("LOG", &["IN"]),
+    ("SIN", &["IN"]),
+    ("COS", &["IN"]),
+    ("TAN", &["IN"]),
+    ("ASIN", &["IN"]),
+    ("ACOS", &["IN"]),
+    ("ATAN", &["IN"]),
+    ("MOVE", &["IN"]),
+    ("LEN", &["IN"]),
+    ("SUB", &["IN1", "IN2"]),
+    ("DIV", &["IN1", "IN2"]),
+    ("MOD", &["IN1", "IN2"]),
+    ("GT", &["IN1", "IN2"]),
+    ("GE", &["IN1", "IN2"]),
+    ("EQ", &["IN1", "IN2"]),
+    ("LT", &["IN1", "IN2"]),
+    ("LE", &["IN1", "IN2"]),
+    ("NE", &["IN1", "IN2"]),
+    ("SEL", &["G", "IN0", "IN1"]),
+    ("LIMIT", &["MN", "IN", "MX"]),
+    ("SHL", &["IN", "N"]),
+    ("SHR", &["IN", "N"]),
+    ("ROL", &["IN", "N"]),
+    ("ROR", &["IN", "N"]),
+    ("LEFT", &["IN", "L"]),
+    ("RIGHT", &["IN", "L"]),
+    ("MID", &["IN", "P", "L"]),
+    ("R_TRIG", &["CLK"]),
+    ("F_TRIG", &["CLK"]),
+    ("TON", &["IN", "PT"]),
+    ("TOF", &["IN", "PT"]),
+    ("TP", &["IN", "PT"]),
+    ("RS", &["S", "R1"]),
+    ("SR", &["S1", "R"]),
+];
+
+/// The output a connection starts from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Connection {
+    /// The local id of the source.
+    pub node: usize,
+    /// The output of a block, `None` for an input variable.
+    pub pin: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Element {
+    /// A box reading a variable or a literal.
+    InVariable(String),
+    /// A box writing a variable.
+    OutVariable(String, Connection),
+    Block {
+        type_name: String,
+        /// The instance of a function block, `None` for a function.
+        instance: Option<String>,
+        inputs: Vec<(String, Connection)>,
+        outputs: Vec<String>,
+    },
+}
+
+/// An element placed on the grid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub id: usize,
+    pub element: Element,
+    pub column: usize,
+    pub row: usize,
+}
+
+impl Node {
+    /// The expression of a variable or the type of a block.
+    pub fn label(&self) -> &str {
+        match &self.element {
+            Element::InVariable(expr) | Element::OutVariable(expr, _) => expr,
+            Element::Block { type_name, .. } => type_name,
+        }
+    }
+
+    /// The top left corner.
+    pub fn position(&self) -> (usize, usize) {
+        let y = self.row * ROW_HEIGHT;
+        match self.element {
+            Element::Block { .. } => (self.column * COLUMN_WIDTH, y),
+            _ => (
+                self.column * COLUMN_WIDTH,
+                y + (ROW_HEIGHT - VARIABLE_HEIGHT) / 2,
+            ),
+        }
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        match &self.element {
+            Element::Block {
+                inputs, outputs, ..
+            } => (
+                BLOCK_WIDTH,
+                inputs.len().max(outputs.len()).max(1) * ROW_HEIGHT,
+            ),
+            _ => (VARIABLE_WIDTH, VARIABLE_HEIGHT),
+        }
+    }
+
+    /// The n-th input relative to the position.
+    pub fn input_pin(&self, n: usize) -> (usize, usize) {
+        match self.element {
+            Element::Block { .. } => (0, n * ROW_HEIGHT + ROW_HEIGHT / 2),
+            _ => (0, VARIABLE_HEIGHT / 2),
+        }
+    }
+
+    /// The n-th output relative to the position.
+    pub fn output_pin(&self, n: usize) -> (usize, usize) {
+        match self.element {
+            Element::Block { .. } => (BLOCK_WIDTH, n * ROW_HEIGHT + ROW_HEIGHT / 2),
+            _ => (VARIABLE_WIDTH, VARIABLE_HEIGHT / 2),
+        }
+    }
+}
+
+/// The networks of a POU, the nodes numbered in drawing order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fbd {
+    nodes: Vec<Node>,
+}
+
+impl Fbd {
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Lowers a body into networks, `vars` tell the function block instances apart.
+    pub fn lower<'a>(
+        body: &[Stmt],
+        vars: impl IntoIterator<Item = &'a Value>,
+    ) -> Result<Fbd, Rust2PlcError> {
+        let instances: HashMap<String, String> = vars
+            .into_iter()
+            .filter_map(|v| match (v.name(), v.typed_value()) {
+                (Some(name), TypedValue::UserDefined(ty, _)) => {
+                    Some((name.to_string(), ty.clone()))
+                }
+                _ => None,
+            })
+            .collect();
+        let mut layout = Layout { nodes: vec![] };
+        let mut row = 0;
+        for stmt in body {
+            let network = network(stmt, &instances)?;
+            row += layout.network(network, row) + 1;
+        }
+        Ok(Fbd {
+            nodes: layout.nodes,
+        })
+    }
+
+    /// Renders the networks as an SVG drawing for reviews.
+    pub fn to_svg(&self) -> String {
+        let width = self
+            .nodes
+            .iter()
+            .map(|n| n.position().0 + n.size().0)
+            .max()
+            .unwrap_or(0)
+            + ROW_HEIGHT;
+        let height = self
+            .nodes
+            .iter()
+            .map(|n| n.position().1 + n.size().1)
+            .max()
+            .unwrap_or(0)
+            + ROW_HEIGHT;
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+             viewBox=\"-{m} -{m} {w} {h}\" font-family=\"monospace\" font-size=\"11\">\n",
+            w = width,
+            h = height,
+            m = ROW_HEIGHT / 2
+        );
+        svg.push_str(
+            "  <style>rect, polyline { fill: none; stroke: black; } .pin { font-size: 9px; }</style>\n",
+        );
+        for node in self.nodes.iter() {
+            let (x, y) = node.position();
+            let (w, h) = node.size();
+            svg.push_str(&format!(
+                "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>\n",
+                x, y, w, h
+            ));
+            match &node.element {
+                Element::InVariable(expr) | Element::OutVariable(expr, _) => {
+                    svg.push_str(&text(x + w / 2, y + h / 2 + 4, "middle", "", expr));
+                }
+                Element::Block {
+                    type_name,
+                    instance,
+                    inputs,
+                    outputs,
+                } => {
+                    svg.push_str(&text(x + w / 2, y + h / 2 + 4, "middle", "", type_name));
+                    if let Some(instance) = instance {
+                        svg.push_str(&text(x + w / 2, y - 4, "middle", "", instance));
+                    }
+                    for (i, (pin, _)) in inputs.iter().enumerate() {
+                        let (px, py) = node.input_pin(i);
+                        svg.push_str(&text(x + px + 3, y + py + 3, "start", "pin", pin));
+                    }
+                    for (i, pin) in outputs.iter().enumerate() {
+                        let (px, py) = node.output_pin(i);
+                        svg.push_str(&text(x + px - 3, y + py + 3, "end", "pin", pin));
+                    }
+                }
+            }
+        }
+        for node in self.nodes.iter() {
+            let inputs: Vec<&Connection> = match &node.element {
+                Element::InVariable(_) => vec![],
+                Element::OutVariable(_, from) => vec![from],
+                Element::Block { inputs, .. } => inputs.iter().map(|(_, from)| from).collect(),
+            };
+            for (i, from) in inputs.into_iter().enumerate() {
+                let (x1, y1) = self.source(from);
+                let (x, y) = node.position();
+                let (px, py) = node.input_pin(i);
+                let (x2, y2) = (x + px, y + py);
+                let xm = (x1 + x2) / 2;
+                svg.push_str(&format!(
+                    "  <polyline points=\"{},{} {},{} {},{} {},{}\"/>\n",
+                    x1, y1, xm, y1, xm, y2, x2, y2
+                ));
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+
+    /// The absolute position of the output a connection starts from.
+    fn source(&self, from: &Connection) -> (usize, usize) {
+        let node = &self.nodes[from.node - 1];
+        let n = match (&node.element, &from.pin) {
+            (Element::Block { outputs, .. }, Some(pin)) => {
+                outputs.iter().position(|o| o == pin).unwrap_or(0)
+            }
+            _ => 0,
+        };
+        let (x, y) = node.position();
+        let (px, py) = node.output_pin(n);
+        (x + px, y + py)
+    }
+}
+
+impl Function {
+    pub fn to_fbd(&self) -> Result<Fbd, Rust2PlcError> {
+        Fbd::lower(
+            self.body(),
+            self.inputs()
+                .iter()
+                .chain(self.outputs())
+                .chain(self.locals()),
+        )
+    }
+}
+
+impl FunctionBlock {
+    pub fn to_fbd(&self) -> Result<Fbd, Rust2PlcError> {
+        Fbd::lower(
+            self.body(),
+            self.inputs()
+                .iter()
+                .chain(self.outputs())
+                .chain(self.vars()),
+        )
+    }
+}
+
+impl Program {
+    pub fn to_fbd(&self) -> Result<Fbd, Rust2PlcError> {
+        if self.chart().is_some() {
+            return Err(Rust2PlcError::Other(format!(
+                "program {} runs a chart, it has no body to lower",
+                self.name()
+            )));
+        }
+        Fbd::lower(
+            self.body(),
+            self.inputs()
+                .iter()
+                .chain(self.outputs())
+                .chain(self.vars()),
+        )
+    }
+}
+
+impl RegistryItem {
+    /// Lowers the body of a POU into networks, data types and configurations have none.
+    pub fn to_fbd(&self) -> Result<Fbd, Rust2PlcError> {
+        match self {
+            RegistryItem::StFn(f) => f.to_fbd(),
+            RegistryItem::StFb(fb) => fb.to_fbd(),
+            RegistryItem::StProgram(p) => p.to_fbd(),
+            RegistryItem::StType(_) | RegistryItem::StConfig(_) => Err(Rust2PlcError::Other(
+                format!("{} has no body to draw as FBD", self.name()),
+            )),
+        }
+    }
+}
+
+/// An expression before the layout.
+enum Tree {
+    Variable(String),
+    Block {
+        type_name: String,
+        instance: Option<String>,
+        inputs: Vec<(String, Tree)>,
+        outputs: Vec<String>,
+    },
+}
+
+impl Tree {
+    fn function(type_name: &str, inputs: Vec<(String, Tree)>) -> Tree {
+        Tree::Block {
+            type_name: type_name.to_string(),
+            instance: None,
+            inputs,
+            outputs: vec!["OUT".to_string()],
+        }
+    }
+
+    /// The columns between the leaves and the tree's output.
+    fn depth(&self) -> usize {
+        match self {
+            Tree::Variable(_) => 0,
+            Tree::Block { inputs, .. } => {
+                1 + inputs.iter().map(|(_, t)| t.depth()).max().unwrap_or(0)
+            }
+        }
+    }
+}
+
+/// A tree and the variables its outputs are assigned to.
+struct Network {
+    tree: Tree,
+    targets: Vec<(Option<String>, String)>,
+}
+
+fn network(stmt: &Stmt, instances: &HashMap<String, String>) -> Result<Network, Rust2PlcError> {
+    let printer = Printer::default();
+    match stmt {
+        Stmt::Assign { target, value } => Ok(Network {
+            tree: tree(value)?,
+            targets: vec![(None, printer.expr(target))],
+        }),
+        Stmt::If {
+            branches,
+            otherwise: Some(otherwise),
+        } => match (branches.as_slice(), otherwise.as_slice()) {
+            (
+                [Branch { cond, body }],
+                [Stmt::Assign {
+                    target: other,
+                    value: value0,
+                }],
+            ) => match body.as_slice() {
+                [Stmt::Assign {
+                    target,
+                    value: value1,
+                }] if target == other => Ok(Network {
+                    tree: Tree::function(
+                        "SEL",
+                        vec![
+                            ("G".to_string(), tree(cond)?),
+                            ("IN0".to_string(), tree(value0)?),
+                            ("IN1".to_string(), tree(value1)?),
+                        ],
+                    ),
+                    targets: vec![(None, printer.expr(target))],
+                }),
+                _ => Err(not_fbd(stmt)),
+            },
+            _ => Err(not_fbd(stmt)),
+        },
+        Stmt::Call(call) => {
+            let Some(type_name) = instances.get(&call.name) else {
+                return Err(not_fbd(stmt));
+            };
+            let mut inputs = vec![];
+            let mut outputs = vec![];
+            let mut targets = vec![];
+            for (i, arg) in call.args.iter().enumerate() {
+                match arg {
+                    Arg::Input(pin, value) => inputs.push((pin.clone(), tree(value)?)),
+                    Arg::Value(value) => inputs.push((pin(type_name, i, stmt)?, tree(value)?)),
+                    Arg::Output(pin, target) => {
+                        outputs.push(pin.clone());
+                        targets.push((Some(pin.clone()), printer.expr(target)));
+                    }
+                }
+            }
+            Ok(Network {
+                tree: Tree::Block {
+                    type_name: type_name.clone(),
+                    instance: Some(call.name.clone()),
+                    inputs,
+                    outputs,
+                },
+                targets,
+            })
+        }
+        _ => Err(not_fbd(stmt)),
+    }
+}
+
+fn tree(expr: &Expr) -> Result<Tree, Rust2PlcError> {
+    match expr {
+        Expr::Literal(_) | Expr::Var(_) | Expr::Field(_, _) | Expr::Index(_, _) => {
+            Ok(Tree::Variable(Printer::default().expr(expr)))
+        }
+        Expr::Paren(inner) => tree(inner),
+        Expr::Unary(UnaryOp::Not, operand) => Ok(Tree::function(
+            "NOT",
+            vec![("IN".to_string(), tree(operand)?)],
+        )),
+        Expr::Unary(UnaryOp::Neg, _) => Err(Rust2PlcError::Other(format!(
+            "{} has no predefined block, write it as a subtraction",
+            Printer::default().expr(expr)
+        ))),
+        Expr::Binary(op, lhs, rhs) => {
+            let name = block_name(*op).ok_or_else(|| {
+                Rust2PlcError::Other(format!(
+                    "{} has no predefined block",
+                    Printer::default().expr(expr)
+                ))
+            })?;
+            if EXTENSIBLE.contains(&name) {
+                let mut operands = vec![];
+                chain(*op, expr, &mut operands);
+                let inputs = operands
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, operand)| Ok((format!("IN{}", i + 1), tree(operand)?)))
+                    .collect::<Result<Vec<_>, Rust2PlcError>>()?;
+                Ok(Tree::function(name, inputs))
+            } else {
+                Ok(Tree::function(
+                    name,
+                    vec![
+                        ("IN1".to_string(), tree(lhs)?),
+                        ("IN2".to_string(), tree(rhs)?),
+                    ],
+                ))
+            }
+        }
+        Expr::Call(call) => function(call),
+    }
+}
+
+/// The operands of a chain of the same operator, `a + b + c` gives `a`, `b` and `c`.
+fn chain<'a>(op: BinaryOp, expr: &'a Expr, operands: &mut Vec<&'a Expr>) {
+    match expr {
+        Expr::Binary(inner, lhs, rhs) if *inner == op => {
+            chain(op, lhs, operands);
+            chain(op, rhs, operands);
+        }
+        Expr::Paren(inner) if matches!(inner.as_ref(), Expr::Binary(i, _, _) if *i == op) => {
+            chain(op, inner, operands)
+        }
+        other => operands.push(other),
+    }
+}
+
+fn block_name(op: BinaryOp) -> Option<&'static str> {
+    match op {
+        BinaryOp::Or => Some("OR"),
+        BinaryOp::Xor => Some("XOR"),
+        BinaryOp::And => Some("AND"),
+        BinaryOp::Eq => Some("EQ"),
+        BinaryOp::Ne => Some("NE"),
+        BinaryOp::Lt => Some("LT"),
+        BinaryOp::Le => Some("LE"),
+        BinaryOp::Gt => Some("GT"),
+        BinaryOp::Ge => Some("GE"),
+        BinaryOp::Add => Some("ADD"),
+        BinaryOp::Sub => Some("SUB"),
+        BinaryOp::Mul => Some("MUL"),
+        BinaryOp::Div => Some("DIV"),
+        BinaryOp::Mod => Some("MOD"),
+        BinaryOp::Pow => None,
+    }
+}
+
+/// A function call, positional arguments need a predefined block to name the inputs.
+fn function(call: &Call) -> Result<Tree, Rust2PlcError> {
+    let mut inputs = vec![];
+    for (i, arg) in call.args.iter().enumerate() {
+        match arg {
+            Arg::Input(pin, value) => inputs.push((pin.clone(), tree(value)?)),
+            Arg::Value(value) => {
+                let pin = if EXTENSIBLE.contains(&call.name.as_str()) {
+                    format!("IN{}", i + 1)
+                } else if call.name == "MUX" {
+                    if i == 0 {
+                        "K".to_string()
+                    } else {
+                        format!("IN{}", i - 1)
+                    }
+                } else {
+                    pin(&call.name, i, &Stmt::Call(call.clone()))?
+                };
+                inputs.push((pin, tree(value)?));
+            }
+            Arg::Output(_, _) => {
+                return Err(Rust2PlcError::Other(format!(
+                    "{} assigns outputs inside an expression",
+                    Printer::default().expr(&Expr::Call(call.clone()))
+                )))
+            }
+        }
+    }
+    Ok(Tree::function(&call.name, inputs))
+}
+
+/// The name of the n-th input of a predefined block, conversions take `IN`.
+fn pin(type_name: &str, n: usize, stmt: &Stmt) -> Result<String, Rust2PlcError> {
+    let pins = PINS
+        .iter()
+        .find(|(name, _)| *name == type_name)
+        .map(|(_, pins)| *pins)
+        .or_else(|| type_name.contains("_TO_").then_some(&["IN"][..]));
+    match pins.and_then(|pins| pins.get(n)) {
+        Some(pin) => Ok(pin.to_string()),
+        None => Err(Rust2PlcError::Other(format!(
+            "{} passes arguments by position, FBD needs the input names of {}",
+            Printer::default().stmt(stmt, 0).trim_end(),
+            type_name
+        ))),
+    }
+}
+
+fn not_fbd(stmt: &Stmt) -> Rust2PlcError {
+    Rust2PlcError::Other(format!(
+        "{} has no FBD counterpart",
+        Printer::default().stmt(stmt, 0).trim_end()
+    ))
+}
+
+struct Layout {
+    nodes: Vec<Node>,
+}
+
+impl Layout {
+    /// Places a network from `row` on, gives the rows it takes.
+    fn network(&mut self, network: Network, row: usize) -> usize {
+        let column = network.tree.depth();
+        let outputs = match &network.tree {
+            Tree::Block { outputs, .. } => outputs.clone(),
+            Tree::Variable(_) => vec![],
+        };
+        let (root, rows) = self.tree(network.tree, column, row);
+        for (i, (pin, target)) in network.targets.into_iter().enumerate() {
+            let n = match &pin {
+                Some(pin) => outputs.iter().position(|o| o == pin).unwrap_or(i),
+                None => 0,
+            };
+            let pin = pin.or_else(|| (!outputs.is_empty()).then(|| outputs[0].clone()));
+            self.push(
+                Element::OutVariable(target, Connection { node: root, pin }),
+                column + 1,
+                row + n,
+            );
+        }
+        rows.max(outputs.len())
+    }
+
+    /// Places a tree with its output at `row`, gives its id and the rows it takes.
+    fn tree(&mut self, tree: Tree, column: usize, row: usize) -> (usize, usize) {
+        match tree {
+            Tree::Variable(expr) => (self.push(Element::InVariable(expr), column, row), 1),
+            Tree::Block {
+                type_name,
+                instance,
+                inputs,
+                outputs,
+            } => {
+                let mut cursor = row;
+                let mut connections = vec![];
+                for (i, (pin, input)) in inputs.into_iter().enumerate() {
+                    let out = match &input {
+                        Tree::Block { outputs, .. } => outputs.first().cloned(),
+                        Tree::Variable(_) => None,
+                    };
+                    // The input is drawn level with its pin unless the ones above need the room.
+                    let top = cursor.max(row + i);
+                    let (node, rows) = self.tree(input, column - 1, top);
+                    cursor = top + rows;
+                    connections.push((pin, Connection { node, pin: out }));
+                }
+                let element = Element::Block {
+                    type_name,
+                    instance,
+                    inputs: connections,
+                    outputs,
+                };
+                let id = self.push(element, column, row);
+                let (_, height) = self.nodes[id - 1].size();
+                (id, (cursor - row).max(height / ROW_HEIGHT))
+            }
+        }
+    }
+
+    fn push(&mut self, element: Element, column: usize, row: usize) -> usize {
+        let id = self.nodes.len() + 1;
+        self.nodes.push(Node {
+            id,
+            element,
+            column,
+            row,
+        });
+        id
+    }
+}
+
+fn text(x: usize, y: usize, anchor: &str, class: &str, text: &str) -> String {
+    let class = if class.is_empty() {
+        String::new()
+    } else {
+        format!(" class=\"{}\"", class)
+    };
+    format!(
+        "  <text x=\"{}\" y=\"{}\" text-anchor=\"{}\"{}>{}</text>\n",
+        x,
+        y,
+        anchor,
+        class,
+        escape(text)
+    )
+}
@@ -0,0 +1,623 @@
+//! Instruction List, the accumulator code of older controllers.
+//!
+//! Expressions are evaluated in the current result: `LD` loads the left operand, every
+//! operator combines the current result with its operand and `ST` stores it.
+//! A compound right operand is deferred with `OP(` and the instructions up to `)`.
+//! Control statements become conditional jumps to labels numbered per statement kind.
+//!
+//! | ST                              | IL                                          |
+//! |---------------------------------|---------------------------------------------|
+//! | `q := a AND NOT b;`             | `LD a`, `ANDN b`, `ST q`                    |
+//! | `y := a * (b + c);`             | `LD a`, `MUL(`, `LD b`, `ADD c`, `)`, `ST y`|
+//! | `y := LIMIT(0, x, 100);`        | `LD 0`, `LIMIT x, 100`, `ST y`              |
+//! | `timer(IN := run, PT := T#1s);` | `CAL timer(IN := run, PT := T#1s)`          |
+//! | `IF a THEN ... END_IF;`         | `LD a`, `JMPCN IF1_END`, ..., `IF1_END:`    |
+//!
+//! ```
+//! use rust2plc::st::ast::{BinaryOp, Expr, Stmt};
+//! use rust2plc::st::function::Function;
+//! use rust2plc::types::TypedValue;
+//! use rust2plc::var::Value;
+//!
+//! let twice = Function::new("twice", TypedValue::new_int())
+//!     .with_input(Value::Input("x".to_string(), TypedValue::new_int()))
+//!     .with_body(vec![Stmt::assign(
+//!         Expr::var("twice"),
+//!         Expr::binary(BinaryOp::Mul, Expr::var("x"), Expr::int(2)),
+//!     )]);
+//! assert_eq!(
+//!     twice.to_il().unwrap(),
+//!     "FUNCTION twice : INT\n\
+//!      VAR_INPUT\n    x : INT;\nEND_VAR\n\
+//!      \x20   LD     x\n\
+//!      \x20   MUL    2\n\
+//!      \x20   ST     twice\n\
+//!      END_FUNCTION\n"
+//! );
+//! ```
+//!
+//! Further arguments of function calls are operands, calls of functions with compound
+//! arguments other than the first need the arguments computed into variables first.
+
+use std::collections::HashMap;
+
+use crate::error::Rust2PlcError;
+use crate::registry::RegistryItem;
+use crate::st;
+use crate::st::ast::{Arg, BinaryOp, CaseLabel, Expr, Literal, Printer, Stmt, UnaryOp, VarBlock};
+use crate::st::function::Function;
+use crate::st::function_block::FunctionBlock;
+use crate::st::program::Program;
+
+/// The width of the operator column, as in the IL listings of `docs/safety`.
+const OPERATOR_WIDTH: usize = 6;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    None,
+    /// A variable or a literal, written as in ST.
+    Value(String),
+    /// The jump target.
+    Label(String),
+    /// `OP(` and the instructions evaluated before the operator is applied, up to `)`.
+    Deferred(Vec<Line>),
+    /// The further arguments of a function called with the first one in the current result.
+    Args(Vec<String>),
+    /// A function block instance and its input parameters, `CAL timer(IN := run)`.
+    Instance(String, Vec<(String, String)>),
+}
+
+/// An operator with its modifiers and operand, e.g. `ANDN b` or `JMPC IF1_END`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub operator: String,
+    pub operand: Operand,
+}
+
+impl Instruction {
+    pub fn new(operator: impl Into<String>, operand: Operand) -> Self {
+        Instruction {
+            operator: operator.into(),
+            operand,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Line {
+    Label(String),
+    Instruction(Instruction),
+}
+
+/// The instructions of a POU body.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Il {
+    lines: Vec<Line>,
+}
+
+impl Il {
+    /// Lowers an ST body into instructions.
+    pub fn lower(body: &[Stmt]) -> Result<Il, Rust2PlcError> {
+        let mut lowering = Lowering::default();
+        lowering.stmts(body)?;
+        Ok(Il {
+            lines: lowering.lines,
+        })
+    }
+
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// The instructions indented by `level`, the labels on lines of their own one level less.
+    pub fn to_text(&self, printer: &Printer, level: usize) -> String {
+        let mut text = String::new();
+        write_lines(&mut text, &self.lines, printer, level);
+        text
+    }
+}
+
+fn write_lines(text: &mut String, lines: &[Line], printer: &Printer, level: usize) {
+    let pad = printer.pad(level);
+    for line in lines {
+        let instruction = match line {
+            Line::Label(label) => {
+                text.push_str(&format!(
+                    "{}{}:\n",
+                    printer.pad(level.saturating_sub(1)),
+                    label
+                ));
+                continue;
+            }
+            Line::Instruction(instruction) => instruction,
+        };
+        let operator = &instruction.operator;
+        let operand = match &instruction.operand {
+            Operand::None => {
+                text.push_str(&format!("{}{}\n", pad, operator));
+                continue;
+            }
+            Operand::Deferred(nested) => {
+                text.push_str(&format!("{}{}(\n", pad, operator));
+                write_lines(text, nested, printer, level + 1);
+                text.push_str(&format!("{})\n", pad));
+                continue;
+            }
+            Operand::Value(value) | Operand::Label(value) => value.clone(),
+            Operand::Args(args) => args.join(", "),
+            Operand::Instance(name, params) if params.is_empty() => name.clone(),
+            Operand::Instance(name, params) => format!(
+                "{}({})",
+                name,
+                params
+                    .iter()
+                    .map(|(name, value)| format!("{} := {}", name, value))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        text.push_str(&format!(
+            "{}{:<width$} {}\n",
+            pad,
+            operator,
+            operand,
+            width = OPERATOR_WIDTH
+        ));
+    }
+}
+
+#[derive(Default)]
+struct Lowering {
+    lines: Vec<Line>,
+    /// The number of statements of each kind so far, numbering their labels.
+    counters: HashMap<&'static str, usize>,
+    /// The enclosing loops, the labels `CONTINUE` and `EXIT` jump to.
+    loops: Vec<(String, String)>,
+}
+
+impl Lowering {
+    fn emit(&mut self, operator: impl Into<String>, operand: Operand) {
+        self.lines
+            .push(Line::Instruction(Instruction::new(operator, operand)));
+    }
+
+    fn label(&mut self, label: &str) {
+        self.lines.push(Line::Label(label.to_string()));
+    }
+
+    /// `IF1`, `WHILE2`, ...
+    fn next_label(&mut self, kind: &'static str) -> String {
+        let counter = self.counters.entry(kind).or_default();
+        *counter += 1;
+        format!("{}{}", kind, counter)
+    }
+
+    fn stmts(&mut self, body: &[Stmt]) -> Result<(), Rust2PlcError> {
+        for stmt in body {
+            self.stmt(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result<(), Rust2PlcError> {
+        match stmt {
+            Stmt::Assign { target, value } => match value {
+                Expr::Unary(UnaryOp::Not, inner) if operand(inner).is_none() => {
+                    self.load(inner)?;
+                    self.emit("STN", Operand::Value(target_operand(target)?));
+                }
+                value => {
+                    self.load(value)?;
+                    self.store(target)?;
+                }
+            },
+            Stmt::Call(call) => {
+                let mut params = vec![];
+                let mut outputs = vec![];
+                for arg in call.args.iter() {
+                    match arg {
+                        Arg::Input(name, value) => match operand(value) {
+                            Some(value) => params.push((name.clone(), value)),
+                            None => {
+                                self.load(value)?;
+                                self.emit("ST", Operand::Value(format!("{}.{}", call.name, name)));
+                            }
+                        },
+                        Arg::Output(name, target) => outputs.push((name, target)),
+                        Arg::Value(_) => {
+                            return Err(Rust2PlcError::Other(format!(
+                                "{} is invoked with positional arguments, CAL needs formal ones",
+                                call.name
+                            )))
+                        }
+                    }
+                }
+                self.emit("CAL", Operand::Instance(call.name.clone(), params));
+                for (name, target) in outputs {
+                    self.emit("LD", Operand::Value(format!("{}.{}", call.name, name)));
+                    self.store(target)?;
+                }
+            }
+            Stmt::If {
+                branches,
+                otherwise,
+            } => {
+                let id = self.next_label("IF");
+                let end = format!("{}_END", id);
+                for (i, branch) in branches.iter().enumerate() {
+                    let last = i + 1 == branches.len() && otherwise.is_none();
+                    let next = if last {
+                        end.clone()
+                    } else {
+                        format!("{}_{}", id, i + 1)
+                    };
+                    self.load(&branch.cond)?;
+                    self.emit("JMPCN", Operand::Label(next.clone()));
+                    self.stmts(&branch.body)?;
+                    if !last {
+                        self.emit("JMP", Operand::Label(end.clone()));
+                        self.label(&next);
+                    }
+                }
+                if let Some(otherwise) = otherwise {
+                    self.stmts(otherwise)?;
+                }
+                self.label(&end);
+            }
+            Stmt::Case {
+                selector,
+                branches,
+                otherwise,
+            } => {
+                let id = self.next_label("CASE");
+                let end = format!("{}_END", id);
+                for (i, branch) in branches.iter().enumerate() {
+                    let target = format!("{}_{}", id, i + 1);
+                    for label in branch.labels.iter() {
+                        self.load(selector)?;
+                        match label {
+                            CaseLabel::Value(value) => self.apply("EQ", value)?,
+                            CaseLabel::Range(from, to) => {
+                                self.apply("GE", from)?;
+                                let mut nested = Lowering::default();
+                                nested.load(selector)?;
+                                nested.apply("LE", to)?;
+                                self.emit("AND", Operand::Deferred(nested.lines));
+                            }
+                        }
+                        self.emit("JMPC", Operand::Label(target.clone()));
+                    }
+                }
+                let otherwise_label = format!("{}_ELSE", id);
+                match otherwise {
+                    Some(_) => self.emit("JMP", Operand::Label(otherwise_label.clone())),
+                    None => self.emit("JMP", Operand::Label(end.clone())),
+                }
+                for (i, branch) in branches.iter().enumerate() {
+                    self.label(&format!("{}_{}", id, i + 1));
+                    self.stmts(&branch.body)?;
+                    self.emit("JMP", Operand::Label(end.clone()));
+                }
+                if let Some(otherwise) = otherwise {
+                    self.label(&otherwise_label);
+                    self.stmts(otherwise)?;
+                }
+                self.label(&end);
+            }
+            Stmt::For {
+                var,
+                from,
+                to,
+                by,
+                body,
+            } => {
+                let id = self.next_label("FOR");
+                let (next, end) = (format!("{}_NEXT", id), format!("{}_END", id));
+                self.load(from)?;
+                self.emit("ST", Operand::Value(var.clone()));
+                self.label(&id);
+                self.emit("LD", Operand::Value(var.clone()));
+                // A negative step counts down, a step of unknown sign is taken for positive.
+                let descending = matches!(
+                    by,
+                    Some(Expr::Literal(Literal::Int(step))) if *step < 0
+                );
+                self.apply(if descending { "LT" } else { "GT" }, to)?;
+                self.emit("JMPC", Operand::Label(end.clone()));
+                self.loops.push((next.clone(), end.clone()));
+                self.stmts(body)?;
+                self.loops.pop();
+                self.label(&next);
+                self.emit("LD", Operand::Value(var.clone()));
+                self.apply("ADD", by.as_ref().unwrap_or(&Expr::int(1)))?;
+                self.emit("ST", Operand::Value(var.clone()));
+                self.emit("JMP", Operand::Label(id));
+                self.label(&end);
+            }
+            Stmt::While { cond, body } => {
+                let id = self.next_label("WHILE");
+                let end = format!("{}_END", id);
+                self.label(&id);
+                self.load(cond)?;
+                self.emit("JMPCN", Operand::Label(end.clone()));
+                self.loops.push((id.clone(), end.clone()));
+                self.stmts(body)?;
+                self.loops.pop();
+                self.emit("JMP", Operand::Label(id));
+                self.label(&end);
+            }
+            Stmt::Repeat { body, until } => {
+                let id = self.next_label("REPEAT");
+                let (next, end) = (format!("{}_NEXT", id), format!("{}_END", id));
+                self.label(&id);
+                self.loops.push((next.clone(), end.clone()));
+                self.stmts(body)?;
+                self.loops.pop();
+                self.label(&next);
+                self.load(until)?;
+                self.emit("JMPCN", Operand::Label(id));
+                self.label(&end);
+            }
+            Stmt::Return => self.emit("RET", Operand::None),
+            Stmt::Exit | Stmt::Continue => {
+                let Some((next, end)) = self.loops.last() else {
+                    return Err(Rust2PlcError::Other(
+                        "EXIT and CONTINUE are only allowed in loops".to_string(),
+                    ));
+                };
+                let target = if matches!(stmt, Stmt::Exit) {
+                    end
+                } else {
+                    next
+                };
+                self.emit("JMP", Operand::Label(target.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    fn store(&mut self, target: &Expr) -> Result<(), Rust2PlcError> {
+        self.emit("ST", Operand::Value(target_operand(target)?));
+        Ok(())
+    }
+
+    /// Leaves the value of the expression in the current result.
+    fn load(&mut self, expr: &Expr) -> Result<(), Rust2PlcError> {
+        if let Some(value) = operand(expr) {
+            self.emit("LD", Operand::Value(value));
+            return Ok(());
+        }
+        match expr {
+            Expr::Paren(inner) => self.load(inner),
+            Expr::Unary(UnaryOp::Not, inner) => match operand(inner) {
+                Some(value) => {
+                    self.emit("LDN", Operand::Value(value));
+                    Ok(())
+                }
+                None => {
+                    self.load(inner)?;
+                    self.emit("NOT", Operand::None);
+                    Ok(())
+                }
+            },
+            Expr::Unary(UnaryOp::Neg, inner) => {
+                self.emit("LD", Operand::Value("0".to_string()));
+                self.apply("SUB", inner)
+            }
+            Expr::Binary(BinaryOp::Pow, base, exponent) => {
+                self.load(base)?;
+                self.call_rest("EXPT", &[exponent.as_ref().clone()])
+            }
+            Expr::Binary(op, lhs, rhs) => {
+                self.load(lhs)?;
+                self.apply(operator(*op), rhs)
+            }
+            Expr::Call(call) => {
+                let args = call
+                    .args
+                    .iter()
+                    .map(|arg| match arg {
+                        Arg::Value(value) => Ok(value.clone()),
+                        _ => Err(Rust2PlcError::Other(format!(
+                            "{} is called with formal arguments, IL passes them in order",
+                            call.name
+                        ))),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                let Some((first, rest)) = args.split_first() else {
+                    return Err(Rust2PlcError::Other(format!(
+                        "{} takes no arguments, IL calls a function on the current result",
+                        call.name
+                    )));
+                };
+                self.load(first)?;
+                self.call_rest(&call.name, rest)
+            }
+            other => Err(Rust2PlcError::Other(format!(
+                "{} cannot be loaded in IL",
+                Printer::default().expr(other)
+            ))),
+        }
+    }
+
+    /// Calls a function on the current result with the further arguments as operands.
+    fn call_rest(&mut self, name: &str, rest: &[Expr]) -> Result<(), Rust2PlcError> {
+        let operands = rest
+            .iter()
+            .map(|arg| {
+                operand(arg).ok_or_else(|| {
+                    Rust2PlcError::Other(format!(
+                        "the argument {} of {} must be a variable or a literal in IL",
+                        Printer::default().expr(arg),
+                        name
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.emit(
+            name,
+            if operands.is_empty() {
+                Operand::None
+            } else {
+                Operand::Args(operands)
+            },
+        );
+        Ok(())
+    }
+
+    /// Combines the current result with the expression, deferring a compound one.
+    fn apply(&mut self, operator: &str, expr: &Expr) -> Result<(), Rust2PlcError> {
+        let negatable = matches!(operator, "AND" | "OR" | "XOR");
+        let (operator, expr) = match expr {
+            Expr::Unary(UnaryOp::Not, inner) if negatable => (format!("{}N", operator), &**inner),
+            Expr::Paren(inner) => (operator.to_string(), &**inner),
+            expr => (operator.to_string(), expr),
+        };
+        match operand(expr) {
+            Some(value) => self.emit(operator, Operand::Value(value)),
+            None => {
+                let mut nested = Lowering::default();
+                nested.load(expr)?;
+                self.emit(operator, Operand::Deferred(nested.lines));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The IL operator of an ST operator, `**` is the function `EXPT`.
+fn operator(op: BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Or => "OR",
+        BinaryOp::Xor => "XOR",
+        BinaryOp::And => "AND",
+        BinaryOp::Eq => "EQ",
+        BinaryOp::Ne => "NE",
+        BinaryOp::Lt => "LT",
+        BinaryOp::Le => "LE",
+        BinaryOp::Gt => "GT",
+        BinaryOp::Ge => "GE",
+        BinaryOp::Add => "ADD",
+        BinaryOp::Sub => "SUB",
+        BinaryOp::Mul => "MUL",
+        BinaryOp::Div => "DIV",
+        BinaryOp::Mod => "MOD",
+        BinaryOp::Pow => "EXPT",
+    }
+}
+
+/// The text of a variable or literal usable as an operand, `None` for compound expressions.
+fn operand(expr: &Expr) -> Option<String> {
+    let simple = match expr {
+        Expr::Literal(_) | Expr::Var(_) => true,
+        Expr::Unary(UnaryOp::Neg, inner) => {
+            matches!(**inner, Expr::Literal(Literal::Int(_) | Literal::Real(_)))
+        }
+        Expr::Field(base, _) => operand(base).is_some(),
+        Expr::Index(base, indices) => {
+            operand(base).is_some() && indices.iter().all(|i| operand(i).is_some())
+        }
+        _ => false,
+    };
+    simple.then(|| Printer::default().expr(expr))
+}
+
+fn target_operand(target: &Expr) -> Result<String, Rust2PlcError> {
+    operand(target).ok_or_else(|| {
+        Rust2PlcError::Other(format!(
+            "{} cannot be stored to in IL",
+            Printer::default().expr(target)
+        ))
+    })
+}
+
+/// The declaration in ST and the body in IL.
+fn pou(
+    keyword: &str,
+    signature: &str,
+    vars: Vec<VarBlock>,
+    body: &[Stmt],
+    header: String,
+) -> Result<String, Rust2PlcError> {
+    let printer = Printer::default();
+    let mut il = header;
+    il.push_str(&format!("{} {}\n", keyword, signature));
+    il.push_str(&printer.var_blocks(&vars, 0));
+    il.push_str(&Il::lower(body)?.to_text(&printer, 1));
+    il.push_str(&format!("END_{}\n", keyword));
+    Ok(il)
+}
+
+impl Function {
+    /// Renders the function with its body in IL.
+    pub fn to_il(&self) -> Result<String, Rust2PlcError> {
+        pou(
+            "FUNCTION",
+            &format!("{} : {}", self.name(), self.return_value().to_plc_type()),
+            VarBlock::group(
+                self.inputs()
+                    .iter()
+                    .chain(self.outputs())
+                    .chain(self.locals()),
+            ),
+            self.body(),
+            st::header(self.description(), self.namespace(), self.version()),
+        )
+    }
+}
+
+impl FunctionBlock {
+    /// Renders the block with its body in IL.
+    pub fn to_il(&self) -> Result<String, Rust2PlcError> {
+        pou(
+            "FUNCTION_BLOCK",
+            self.name(),
+            VarBlock::group(
+                self.inputs()
+                    .iter()
+                    .chain(self.outputs())
+                    .chain(self.vars()),
+            ),
+            self.body(),
+            st::header(self.description(), self.namespace(), self.version()),
+        )
+    }
+}
+
+impl Program {
+    /// Renders the program with its body in IL.
+    pub fn to_il(&self) -> Result<String, Rust2PlcError> {
+        if self.chart().is_some() {
+            return Err(Rust2PlcError::Other(format!(
+                "program {} runs a chart, it has no body to lower",
+                self.name()
+            )));
+        }
+        pou(
+            "PROGRAM",
+            self.name(),
+            VarBlock::group(
+                self.inputs()
+                    .iter()
+                    .chain(self.outputs())
+                    .chain(self.vars()),
+            ),
+            self.body(),
+            st::header(self.description(), self.namespace(), self.version()),
+        )
+    }
+}
+
+impl RegistryItem {
+    /// Renders a POU with its body in IL, data types and configurations have none.
+    pub fn to_il(&self) -> Result<String, Rust2PlcError> {
+        match self {
+            RegistryItem::StFn(f) => f.to_il(),
+            RegistryItem::StFb(fb) => fb.to_il(),
+            RegistryItem::StProgram(p) => p.to_il(),
+            RegistryItem::StType(_) | RegistryItem::StConfig(_) => Err(Rust2PlcError::Other(
+                format!("{} has no body to write in IL", self.name()),
+            )),
+        }
+    }
+}
@@ -0,0 +1,1243 @@
+//! Rockwell Studio 5000 Logix Designer L5X projects.
+//!
+//! Function blocks become Add-On Instructions, functions and programs become Logix
+//! programs with an ST routine named `Logic`, structures become user-defined types and
+//! a configuration turns into the controller tasks and tags. The tags of a function
+//! program are its parameters, the result is the output tag named after the function.
+//!
+//! ```
+//! use rust2plc::l5x::L5xProject;
+//! use rust2plc::types::TypedValue;
+//!
+//! let project = L5xProject::new("Line1");
+//! assert_eq!(project.logix_type(&TypedValue::new_string(None)).unwrap(), "STRING");
+//! assert_eq!(project.logix_type(&TypedValue::new_string(Some(20))).unwrap(), "STRING20");
+//! assert!(project
+//!     .with_software_revision(30, 11)
+//!     .logix_type(&TypedValue::new_lint())
+//!     .is_err());
+//! ```
+//!
+//! Logix has no `TIME`, durations are `DINT` milliseconds like the timer presets, and
+//! no bit strings, `BYTE` to `LWORD` are stored in the signed integer of the same width.
+//! ST bodies may call the Logix functions and the Add-On Instructions of the project,
+//! a call `timer(IN := start)` becomes `Timer(timer, start)` with the instance first.
+
+use std::collections::{BTreeSet, HashMap};
+
+use chrono::NaiveDateTime;
+
+use crate::error::Rust2PlcError;
+use crate::plcopen::XmlWriter;
+use crate::registry::{PLCRegistry, RegistryItem};
+use crate::st::ast::{
+    Arg, Branch, Call, CaseBranch, CaseLabel, Expr, Literal, Printer, Stmt, VarBlock, VarSection,
+};
+use crate::st::config::{Configuration, TaskTrigger};
+use crate::st::data_type::DataType;
+use crate::st::function_block::FunctionBlock;
+use crate::st::parser;
+use crate::types::TypedValue;
+use crate::var::Value;
+
+/// The first major revision with `LINT` arithmetic, `LREAL` and the unsigned integers.
+pub const EXTENDED_TYPES_REVISION: u32 = 32;
+
+/// The length of the predefined `STRING` type.
+pub const STRING_LENGTH: usize = 82;
+
+/// The functions of the IEC library and their Logix names.
+const FUNCTIONS: [(&str, &str); 11] = [
+    ("ABS", "ABS"),
+    ("SQRT", "SQRT"),
+    ("SIN", "SIN"),
+    ("COS", "COS"),
+    ("TAN", "TAN"),
+    ("ASIN", "ASN"),
+    ("ACOS", "ACS"),
+    ("ATAN", "ATN"),
+    ("LN", "LN"),
+    ("LOG", "LOG"),
+    ("TRUNC", "TRN"),
+];
+
+/// An L5X document with a controller and its components.
+#[derive(Debug, Clone)]
+pub struct L5xProject {
+    controller: String,
+    processor: String,
+    revision: (u32, u32),
+    exported: NaiveDateTime,
+    items: Vec<RegistryItem>,
+}
+
+impl L5xProject {
+    /// An empty project for a ControlLogix 5580 controller at revision 33.
+    pub fn new(controller: impl Into<String>) -> Self {
+        L5xProject {
+            controller: controller.into(),
+            processor: "1756-L85E".to_string(),
+            revision: (33, 0),
+            exported: chrono::Local::now().naive_local(),
+            items: vec![],
+        }
+    }
+
+    /// The catalog number of the controller, e.g. `5069-L330ER`.
+    pub fn with_processor(mut self, processor: impl Into<String>) -> Self {
+        self.processor = processor.into();
+        self
+    }
+
+    /// The firmware revision of the controller, it decides the available data types.
+    pub fn with_software_revision(mut self, major: u32, minor: u32) -> Self {
+        self.revision = (major, minor);
+        self
+    }
+
+    pub fn with_exported(mut self, exported: NaiveDateTime) -> Self {
+        self.exported = exported;
+        self
+    }
+
+    /// Adds an item, the components are written in the order they are added.
+    pub fn with_item(mut self, item: RegistryItem) -> Self {
+        self.items.push(item);
+        self
+    }
+
+    pub fn controller(&self) -> &str {
+        &self.controller
+    }
+
+    pub fn items(&self) -> &[RegistryItem] {
+        &self.items
+    }
+
+    /// The Logix data type of the value, the element type for arrays.
+    pub fn logix_type(&self, tv: &TypedValue) -> Result<String, Rust2PlcError> {
+        let extended = |name: &str| {
+            if self.revision.0 >= EXTENDED_TYPES_REVISION {
+                Ok(name.to_string())
+            } else {
+                Err(Rust2PlcError::Other(format!(
+                    "{} needs firmware revision {} or later, the controller runs v{}.{:02}",
+                    tv.to_plc_type(),
+                    EXTENDED_TYPES_REVISION,
+                    self.revision.0,
+                    self.revision.1
+                )))
+            }
+        };
+        match tv {
+            TypedValue::Bool(_) => Ok("BOOL".to_string()),
+            TypedValue::SInt(_) | TypedValue::Byte(_) | TypedValue::Char(_) => {
+                Ok("SINT".to_string())
+            }
+            TypedValue::Int(_) | TypedValue::Word(_) => Ok("INT".to_string()),
+            TypedValue::DInt(_) | TypedValue::DWord(_) | TypedValue::Time(_) => {
+                Ok("DINT".to_string())
+            }
+            TypedValue::Real(_) => Ok("REAL".to_string()),
+            TypedValue::LInt(_) | TypedValue::LWord(_) => extended("LINT"),
+            TypedValue::USInt(_) => extended("USINT"),
+            TypedValue::UInt(_) => extended("UINT"),
+            TypedValue::UDInt(_) => extended("UDINT"),
+            TypedValue::ULInt(_) => extended("ULINT"),
+            TypedValue::LReal(_) => extended("LREAL"),
+            TypedValue::String(_, None) => Ok("STRING".to_string()),
+            TypedValue::String(_, Some(len)) if *len == STRING_LENGTH => Ok("STRING".to_string()),
+            TypedValue::String(_, Some(len)) => Ok(format!("STRING{}", len)),
+            TypedValue::UserDefined(name, _) | TypedValue::Struct(name, _) => Ok(name.clone()),
+            TypedValue::Array(_, elem, _) if matches!(**elem, TypedValue::Array(..)) => {
+                Err(Rust2PlcError::Other(format!(
+                    "{} has more than one dimension, Logix members and parameters have one",
+                    tv.to_plc_type()
+                )))
+            }
+            TypedValue::Array(_, _, lower) if *lower != 0 => Err(Rust2PlcError::Other(format!(
+                "{} does not start at 0, Logix arrays do",
+                tv.to_plc_type()
+            ))),
+            TypedValue::Array(_, elem, _) => self.logix_type(elem),
+            TypedValue::Date(_)
+            | TypedValue::TimeOfDay(_)
+            | TypedValue::DateTime(_)
+            | TypedValue::WChar(_)
+            | TypedValue::WString(_, _)
+            | TypedValue::Enum(..) => Err(Rust2PlcError::Other(format!(
+                "{} has no Logix counterpart",
+                tv.to_plc_type()
+            ))),
+        }
+    }
+
+    /// Renders the `RSLogix5000Content` document.
+    pub fn to_xml(&self) -> Result<String, Rust2PlcError> {
+        let revision = format!("{}.{:02}", self.revision.0, self.revision.1);
+        let mut w = XmlWriter::new();
+        w.open(
+            "RSLogix5000Content",
+            &[
+                ("SchemaRevision", "1.0"),
+                ("SoftwareRevision", &revision),
+                ("TargetName", &self.controller),
+                ("TargetType", "Controller"),
+                ("ContainsContext", "false"),
+                (
+                    "ExportDate",
+                    &self.exported.format("%a %b %d %H:%M:%S %Y").to_string(),
+                ),
+                ("ExportOptions", "NoRawData L5KData DecoratedData"),
+            ],
+        );
+        w.open(
+            "Controller",
+            &[
+                ("Use", "Target"),
+                ("Name", &self.controller),
+                ("ProcessorType", &self.processor),
+                ("MajorRev", &self.revision.0.to_string()),
+                ("MinorRev", &self.revision.1.to_string()),
+            ],
+        );
+
+        w.open("DataTypes", &[]);
+        for len in self.string_lengths() {
+            self.write_string_type(&mut w, len);
+        }
+        for item in self.items.iter() {
+            if let RegistryItem::StType(t) = item {
+                self.write_data_type(&mut w, t)?;
+            }
+        }
+        w.close("DataTypes");
+
+        w.open("AddOnInstructionDefinitions", &[]);
+        for item in self.items.iter() {
+            if let RegistryItem::StFb(fb) = item {
+                self.write_aoi(&mut w, fb)?;
+            }
+        }
+        w.close("AddOnInstructionDefinitions");
+
+        let configs: Vec<&Configuration> = self
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                RegistryItem::StConfig(c) => Some(c),
+                _ => None,
+            })
+            .collect();
+        if configs.len() > 1 {
+            return Err(Rust2PlcError::Other(format!(
+                "the configurations {} share the controller {}, Logix has one per controller",
+                configs
+                    .iter()
+                    .map(|c| c.name())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                self.controller
+            )));
+        }
+        w.open("Tags", &[]);
+        for config in configs.iter() {
+            for global in config.globals() {
+                self.write_tag(&mut w, global, None)?;
+            }
+        }
+        w.close("Tags");
+
+        w.open("Programs", &[]);
+        for item in self.items.iter() {
+            match item {
+                RegistryItem::StFn(f) => {
+                    let result = Value::Output(f.name().to_string(), f.return_value().clone());
+                    let vars: Vec<&Value> = f
+                        .inputs()
+                        .iter()
+                        .chain(f.outputs())
+                        .chain(f.locals())
+                        .chain([&result])
+                        .collect();
+                    self.write_program(&mut w, f.name(), f.description(), &vars, f.body())?
+                }
+                RegistryItem::StProgram(p) => {
+                    if p.chart().is_some() {
+                        return Err(Rust2PlcError::Other(format!(
+                            "the chart of program {} is not exported to L5X yet",
+                            p.name()
+                        )));
+                    }
+                    let vars: Vec<&Value> = p
+                        .inputs()
+                        .iter()
+                        .chain(p.outputs())
+                        .chain(p.vars())
+                        .collect();
+                    self.write_program(&mut w, p.name(), p.description(), &vars, p.body())?
+                }
+                _ => {}
+            }
+        }
+        w.close("Programs");
+
+        w.open("Tasks", &[]);
+        for config in configs {
+            self.write_tasks(&mut w, config)?;
+        }
+        w.close("Tasks");
+
+        w.close("Controller");
+        w.close("RSLogix5000Content");
+        Ok(w.finish())
+    }
+
+    /// The lengths of the custom string types used by the items.
+    fn string_lengths(&self) -> BTreeSet<usize> {
+        fn collect(tv: &TypedValue, lengths: &mut BTreeSet<usize>) {
+            match tv {
+                TypedValue::String(_, Some(len)) if *len != STRING_LENGTH => {
+                    lengths.insert(*len);
+                }
+                TypedValue::Array(_, elem, _) => collect(elem, lengths),
+                _ => {}
+            }
+        }
+        let mut lengths = BTreeSet::new();
+        for item in self.items.iter() {
+            match item {
+                RegistryItem::StFn(f) => {
+                    let vars = f.inputs().iter().chain(f.outputs()).chain(f.locals());
+                    for tv in vars.map(|v| v.typed_value()).chain([f.return_value()]) {
+                        collect(tv, &mut lengths);
+                    }
+                }
+                RegistryItem::StFb(fb) => {
+                    let vars = fb.inputs().iter().chain(fb.outputs()).chain(fb.vars());
+                    vars.for_each(|v| collect(v.typed_value(), &mut lengths));
+                }
+                RegistryItem::StProgram(p) => {
+                    let vars = p.inputs().iter().chain(p.outputs()).chain(p.vars());
+                    vars.for_each(|v| collect(v.typed_value(), &mut lengths));
+                }
+                RegistryItem::StConfig(c) => {
+                    c.globals()
+                        .iter()
+                        .for_each(|v| collect(v.typed_value(), &mut lengths));
+                }
+                RegistryItem::StType(t) => {
+                    if let TypedValue::Struct(_, fields) = t.value() {
+                        fields.iter().for_each(|(_, tv)| collect(tv, &mut lengths));
+                    }
+                }
+            }
+        }
+        lengths
+    }
+
+    fn write_string_type(&self, w: &mut XmlWriter, len: usize) {
+        w.open(
+            "DataType",
+            &[
+                ("Name", &format!("STRING{}", len)),
+                ("Family", "StringFamily"),
+                ("Class", "User"),
+            ],
+        );
+        w.open("Members", &[]);
+        w.empty(
+            "Member",
+            &[
+                ("Name", "LEN"),
+                ("DataType", "DINT"),
+                ("Dimension", "0"),
+                ("Radix", "Decimal"),
+                ("Hidden", "false"),
+                ("ExternalAccess", "Read/Write"),
+            ],
+        );
+        w.empty(
+            "Member",
+            &[
+                ("Name", "DATA"),
+                ("DataType", "SINT"),
+                ("Dimension", &len.to_string()),
+                ("Radix", "ASCII"),
+                ("Hidden", "false"),
+                ("ExternalAccess", "Read/Write"),
+            ],
+        );
+        w.close("Members");
+        w.close("DataType");
+    }
+
+    /// A structure as a user-defined type, the `BOOL` members are bits of hidden `SINT` hosts.
+    ///
+    /// Logix types carry no initial values, they are set on the tags.
+    fn write_data_type(&self, w: &mut XmlWriter, t: &DataType) -> Result<(), Rust2PlcError> {
+        let TypedValue::Struct(name, fields) = t.value() else {
+            return Err(Rust2PlcError::Other(format!(
+                "the enumerated type {} has no Logix counterpart",
+                t.name()
+            )));
+        };
+        w.open(
+            "DataType",
+            &[("Name", name), ("Family", "NoFamily"), ("Class", "User")],
+        );
+        if let Some(description) = t.description() {
+            w.cdata("Description", &[], description);
+        }
+        w.open("Members", &[]);
+        let mut hosts = 0;
+        let mut bit = 8;
+        for (field, tv) in fields.iter() {
+            if let TypedValue::Bool(_) = tv {
+                let host = format!("ZZZZZZZZZZ{:.10}{}", name, hosts);
+                if bit == 8 {
+                    w.empty(
+                        "Member",
+                        &[
+                            ("Name", &host),
+                            ("DataType", "SINT"),
+                            ("Dimension", "0"),
+                            ("Radix", "Decimal"),
+                            ("Hidden", "true"),
+                            ("ExternalAccess", "Read/Write"),
+                        ],
+                    );
+                    bit = 0;
+                }
+                w.empty(
+                    "Member",
+                    &[
+                        ("Name", field),
+                        ("DataType", "BIT"),
+                        ("Dimension", "0"),
+                        ("Radix", "Decimal"),
+                        ("Hidden", "false"),
+                        ("Target", &host),
+                        ("BitNumber", &bit.to_string()),
+                        ("ExternalAccess", "Read/Write"),
+                    ],
+                );
+                bit += 1;
+                if bit == 8 {
+                    hosts += 1;
+                }
+                continue;
+            }
+            let dimension = match tv {
+                // BOOL arrays are allocated in 32-bit words.
+                TypedValue::Array(values, elem, _) if matches!(**elem, TypedValue::Bool(_)) => {
+                    values.len().div_ceil(32) * 32
+                }
+                TypedValue::Array(values, ..) => values.len(),
+                _ => 0,
+            };
+            w.empty(
+                "Member",
+                &[
+                    ("Name", field),
+                    ("DataType", &self.logix_type(tv)?),
+                    ("Dimension", &dimension.to_string()),
+                    ("Radix", radix(tv)),
+                    ("Hidden", "false"),
+                    ("ExternalAccess", "Read/Write"),
+                ],
+            );
+        }
+        w.close("Members");
+        w.close("DataType");
+        Ok(())
+    }
+
+    /// An Add-On Instruction with the `EnableIn` and `EnableOut` system parameters.
+    ///
+    /// Inputs and outputs must be atomic, structures, arrays and strings are passed `InOut`.
+    fn write_aoi(&self, w: &mut XmlWriter, fb: &FunctionBlock) -> Result<(), Rust2PlcError> {
+        let edited = self.exported.format("%Y-%m-%dT%H:%M:%S.000Z").to_string();
+        let revision = format!("v{}.{:02}", self.revision.0, self.revision.1);
+        w.open(
+            "AddOnInstructionDefinition",
+            &[
+                ("Name", fb.name()),
+                ("Revision", &aoi_revision(fb.name(), fb.version())?),
+                ("ExecutePrescan", "false"),
+                ("ExecutePostscan", "false"),
+                ("ExecuteEnableInFalse", "false"),
+                ("CreatedDate", &edited),
+                ("CreatedBy", env!("CARGO_PKG_NAME")),
+                ("EditedDate", &edited),
+                ("EditedBy", env!("CARGO_PKG_NAME")),
+                ("SoftwareRevision", &revision),
+            ],
+        );
+        if let Some(description) = fb.description() {
+            w.cdata("Description", &[], description);
+        }
+
+        w.open("Parameters", &[]);
+        for (name, usage, access, text) in [
+            (
+                "EnableIn",
+                "Input",
+                "Read Only",
+                "Enable Input - System Defined Parameter",
+            ),
+            (
+                "EnableOut",
+                "Output",
+                "Read Only",
+                "Enable Output - System Defined Parameter",
+            ),
+        ] {
+            w.open(
+                "Parameter",
+                &[
+                    ("Name", name),
+                    ("TagType", "Base"),
+                    ("DataType", "BOOL"),
+                    ("Usage", usage),
+                    ("Radix", "Decimal"),
+                    ("Required", "false"),
+                    ("Visible", "false"),
+                    ("ExternalAccess", access),
+                ],
+            );
+            w.cdata("Description", &[], text);
+            w.close("Parameter");
+        }
+        let vars = fb.inputs().iter().chain(fb.outputs()).chain(fb.vars());
+        let mut locals = vec![];
+        for block in VarBlock::group(vars) {
+            for var in block.vars.iter() {
+                let name = var.name().unwrap_or_default();
+                let tv = var.typed_value();
+                let (usage, required, access) = match block.section {
+                    VarSection::Input => ("Input", "true", "Read/Write"),
+                    VarSection::Output => ("Output", "false", "Read Only"),
+                    VarSection::InOut => {
+                        w.empty(
+                            "Parameter",
+                            &[
+                                ("Name", name),
+                                ("TagType", "Base"),
+                                ("DataType", &self.logix_type(tv)?),
+                                ("Dimensions", &tv.array_size().to_string()),
+                                ("Usage", "InOut"),
+                                ("Required", "true"),
+                                ("Visible", "true"),
+                                ("Constant", "false"),
+                            ],
+                        );
+                        continue;
+                    }
+                    VarSection::Var | VarSection::Temp | VarSection::Constant => {
+                        locals.push(var.clone());
+                        continue;
+                    }
+                    VarSection::External | VarSection::Global => {
+                        return Err(Rust2PlcError::Other(format!(
+                            "{} of {} is declared {}, Add-On Instructions reach controller tags through InOut parameters",
+                            name,
+                            fb.name(),
+                            block.section.keyword()
+                        )))
+                    }
+                };
+                if !is_atomic(tv) {
+                    return Err(Rust2PlcError::Other(format!(
+                        "the {} parameter {} of {} is a {}, Add-On Instructions pass it as InOut",
+                        usage.to_lowercase(),
+                        name,
+                        fb.name(),
+                        tv.to_plc_type()
+                    )));
+                }
+                let attrs = [
+                    ("Name", name),
+                    ("TagType", "Base"),
+                    ("DataType", &self.logix_type(tv)?),
+                    ("Usage", usage),
+                    ("Radix", radix(tv)),
+                    ("Required", required),
+                    ("Visible", "true"),
+                    ("ExternalAccess", access),
+                ];
+                if *tv == tv.default_value() {
+                    w.empty("Parameter", &attrs);
+                } else {
+                    w.open("Parameter", &attrs);
+                    self.write_data(w, "DefaultData", tv)?;
+                    w.close("Parameter");
+                }
+            }
+        }
+        w.close("Parameters");
+
+        w.open("LocalTags", &[]);
+        for var in locals.iter() {
+            let tv = var.typed_value();
+            let mut attrs = vec![
+                ("Name", var.name().unwrap_or_default().to_string()),
+                ("DataType", self.logix_type(tv)?),
+            ];
+            if let TypedValue::Array(values, ..) = tv {
+                attrs.push(("Dimensions", values.len().to_string()));
+            }
+            attrs.push(("Radix", radix(tv).to_string()));
+            attrs.push(("ExternalAccess", "None".to_string()));
+            let attrs: Vec<(&str, &str)> = attrs.iter().map(|(n, v)| (*n, v.as_str())).collect();
+            if *tv == tv.default_value() {
+                w.empty("LocalTag", &attrs);
+            } else {
+                w.open("LocalTag", &attrs);
+                self.write_data(w, "DefaultData", tv)?;
+                w.close("LocalTag");
+            }
+        }
+        w.close("LocalTags");
+
+        let vars: Vec<&Value> = fb
+            .inputs()
+            .iter()
+            .chain(fb.outputs())
+            .chain(fb.vars())
+            .collect();
+        w.open("Routines", &[]);
+        self.write_routine(w, &vars, fb.body())?;
+        w.close("Routines");
+        w.close("AddOnInstructionDefinition");
+        Ok(())
+    }
+
+    /// A program with its tags and the `Logic` routine, the parameters are program parameters.
+    fn write_program(
+        &self,
+        w: &mut XmlWriter,
+        name: &str,
+        description: Option<&str>,
+        vars: &[&Value],
+        body: &[Stmt],
+    ) -> Result<(), Rust2PlcError> {
+        w.open(
+            "Program",
+            &[
+                ("Name", name),
+                ("TestEdits", "false"),
+                ("MainRoutineName", "Logic"),
+                ("Disabled", "false"),
+                ("UseAsFolder", "false"),
+            ],
+        );
+        if let Some(description) = description {
+            w.cdata("Description", &[], description);
+        }
+        w.open("Tags", &[]);
+        for var in vars.iter() {
+            let usage = match var {
+                Value::Input(_, _) => Some("Input"),
+                Value::Output(_, _) => Some("Output"),
+                Value::InOut(_, _) => Some("InOut"),
+                Value::Local(_, _) | Value::Temporary(_, _) | Value::Constant(_, _) => None,
+                // Controller tags are reached by their names.
+                Value::External(_, _) => continue,
+                Value::Global(global, _) => {
+                    return Err(Rust2PlcError::Other(format!(
+                    "{} is declared VAR_GLOBAL in {}, controller tags come from the configuration",
+                    global, name
+                )))
+                }
+                Value::Return(_) => continue,
+            };
+            self.write_tag(w, var, usage)?;
+        }
+        w.close("Tags");
+        w.open("Routines", &[]);
+        self.write_routine(w, vars, body)?;
+        w.close("Routines");
+        w.close("Program");
+        Ok(())
+    }
+
+    fn write_tag(
+        &self,
+        w: &mut XmlWriter,
+        var: &Value,
+        usage: Option<&str>,
+    ) -> Result<(), Rust2PlcError> {
+        let tv = var.typed_value();
+        let mut attrs = vec![
+            ("Name", var.name().unwrap_or_default().to_string()),
+            ("TagType", "Base".to_string()),
+            ("DataType", self.logix_type(tv)?),
+        ];
+        if let TypedValue::Array(values, ..) = tv {
+            attrs.push(("Dimensions", values.len().to_string()));
+        }
+        attrs.push(("Radix", radix(tv).to_string()));
+        attrs.push(("Constant", matches!(var, Value::Constant(_, _)).to_string()));
+        attrs.push(("ExternalAccess", "Read/Write".to_string()));
+        if let Some(usage) = usage {
+            attrs.push(("Usage", usage.to_string()));
+        }
+        let attrs: Vec<(&str, &str)> = attrs.iter().map(|(n, v)| (*n, v.as_str())).collect();
+        if *tv == tv.default_value() {
+            w.empty("Tag", &attrs);
+        } else {
+            w.open("Tag", &attrs);
+            self.write_data(w, "Data", tv)?;
+            w.close("Tag");
+        }
+        Ok(())
+    }
+
+    fn write_routine(
+        &self,
+        w: &mut XmlWriter,
+        vars: &[&Value],
+        body: &[Stmt],
+    ) -> Result<(), Rust2PlcError> {
+        let vars: HashMap<&str, &TypedValue> = vars
+            .iter()
+            .filter_map(|v| Some((v.name()?, v.typed_value())))
+            .collect();
+        let mut stmts = vec![];
+        for stmt in body {
+            stmts.extend(self.stmt(stmt, &vars)?);
+        }
+        w.open("Routine", &[("Name", "Logic"), ("Type", "ST")]);
+        w.open("STContent", &[]);
+        let text = Printer::default().stmts(&stmts, 0);
+        for (number, line) in text.lines().enumerate() {
+            w.cdata("Line", &[("Number", &number.to_string())], line);
+        }
+        w.close("STContent");
+        w.close("Routine");
+        Ok(())
+    }
+
+    fn write_tasks(&self, w: &mut XmlWriter, c: &Configuration) -> Result<(), Rust2PlcError> {
+        c.validate()?;
+        if c.resources().len() > 1 {
+            return Err(Rust2PlcError::Other(format!(
+                "the configuration {} has {} resources, a Logix controller is one resource",
+                c.name(),
+                c.resources().len()
+            )));
+        }
+        for resource in c.resources() {
+            for program in resource.programs() {
+                if program.name() != program.program() {
+                    return Err(Rust2PlcError::Other(format!(
+                        "the instance {} of {} is renamed, a Logix program is its own single instance",
+                        program.name(),
+                        program.program()
+                    )));
+                }
+            }
+            for task in resource.tasks() {
+                let TaskTrigger::Cyclic(interval) = task.trigger() else {
+                    return Err(Rust2PlcError::Other(format!(
+                        "the task {} is triggered by a variable, Logix event tasks are triggered by modules or the EVENT instruction",
+                        task.name()
+                    )));
+                };
+                if !(1..=15).contains(&task.priority()) {
+                    return Err(Rust2PlcError::Other(format!(
+                        "the task {} has priority {}, Logix accepts 1 to 15",
+                        task.name(),
+                        task.priority()
+                    )));
+                }
+                let rate = (interval.as_micros() as f64 / 1000.0).to_string();
+                w.open(
+                    "Task",
+                    &[
+                        ("Name", task.name()),
+                        ("Type", "PERIODIC"),
+                        ("Rate", &rate),
+                        ("Priority", &task.priority().to_string()),
+                        ("Watchdog", "500"),
+                        ("DisableUpdateOutputs", "false"),
+                        ("InhibitTask", "false"),
+                    ],
+                );
+                w.open("ScheduledPrograms", &[]);
+                for program in resource.programs() {
+                    if program.task() == Some(task.name()) {
+                        w.empty("ScheduledProgram", &[("Name", program.name())]);
+                    }
+                }
+                w.close("ScheduledPrograms");
+                w.close("Task");
+            }
+        }
+        Ok(())
+    }
+
+    /// The decorated value of a tag or a parameter, strings are structures of `LEN` and `DATA`.
+    fn write_data(
+        &self,
+        w: &mut XmlWriter,
+        tag: &str,
+        tv: &TypedValue,
+    ) -> Result<(), Rust2PlcError> {
+        w.open(tag, &[("Format", "Decorated")]);
+        self.write_value(w, None, tv)?;
+        w.close(tag);
+        Ok(())
+    }
+
+    fn write_value(
+        &self,
+        w: &mut XmlWriter,
+        member: Option<&str>,
+        tv: &TypedValue,
+    ) -> Result<(), Rust2PlcError> {
+        let data_type = self.logix_type(tv)?;
+        let mut attrs = vec![];
+        if let Some(member) = member {
+            attrs.push(("Name", member));
+        }
+        attrs.push(("DataType", &data_type));
+        match tv {
+            TypedValue::Struct(_, fields) => {
+                let tag = member.map_or("Structure", |_| "StructureMember");
+                w.open(tag, &attrs);
+                for (field, value) in fields.iter() {
+                    self.write_value(w, Some(field), value)?;
+                }
+                w.close(tag);
+            }
+            TypedValue::String(text, _) => {
+                let tag = member.map_or("Structure", |_| "StructureMember");
+                w.open(tag, &attrs);
+                w.empty(
+                    "DataValueMember",
+                    &[
+                        ("Name", "LEN"),
+                        ("DataType", "DINT"),
+                        ("Radix", "Decimal"),
+                        ("Value", &text.len().to_string()),
+                    ],
+                );
+                w.cdata(
+                    "DataValueMember",
+                    &[("Name", "DATA"), ("DataType", "STRING"), ("Radix", "ASCII")],
+                    &format!("'{}'", text.replace('\'', "$'")),
+                );
+                w.close(tag);
+            }
+            TypedValue::Array(values, elem, _) => {
+                let tag = member.map_or("Array", |_| "ArrayMember");
+                let dimensions = values.len().to_string();
+                attrs.push(("Dimensions", &dimensions));
+                if is_atomic(elem) {
+                    attrs.push(("Radix", radix(elem)));
+                }
+                w.open(tag, &attrs);
+                for (idx, value) in values.iter().enumerate() {
+                    let index = format!("[{}]", idx);
+                    if is_atomic(value) {
+                        w.empty(
+                            "Element",
+                            &[("Index", &index), ("Value", &self.scalar(value)?)],
+                        );
+                    } else {
+                        w.open("Element", &[("Index", &index)]);
+                        self.write_value(w, None, value)?;
+                        w.close("Element");
+                    }
+                }
+                w.close(tag);
+            }
+            TypedValue::UserDefined(_, Some(value)) => self.write_value(w, member, value)?,
+            TypedValue::UserDefined(name, None) => {
+                return Err(Rust2PlcError::Other(format!(
+                    "the value of {} is unknown",
+                    name
+                )))
+            }
+            atomic => {
+                let tag = member.map_or("DataValue", |_| "DataValueMember");
+                let value = self.scalar(atomic)?;
+                attrs.push(("Radix", radix(atomic)));
+                attrs.push(("Value", &value));
+                w.empty(tag, &attrs);
+            }
+        }
+        Ok(())
+    }
+
+    /// The decorated text of an atomic value.
+    fn scalar(&self, tv: &TypedValue) -> Result<String, Rust2PlcError> {
+        self.logix_type(tv)?;
+        Ok(match tv {
+            TypedValue::Bool(v) => (*v as u8).to_string(),
+            TypedValue::SInt(v) => v.to_string(),
+            TypedValue::Int(v) => v.to_string(),
+            TypedValue::DInt(v) => v.to_string(),
+            TypedValue::LInt(v) => v.to_string(),
+            TypedValue::USInt(v) => v.to_string(),
+            TypedValue::UInt(v) => v.to_string(),
+            TypedValue::UDInt(v) => v.to_string(),
+            TypedValue::ULInt(v) => v.to_string(),
+            TypedValue::Real(v) => v.to_string(),
+            TypedValue::LReal(v) => v.to_string(),
+            TypedValue::Byte(v) => (*v as i8).to_string(),
+            TypedValue::Word(v) => (*v as i16).to_string(),
+            TypedValue::DWord(v) => (*v as i32).to_string(),
+            TypedValue::LWord(v) => (*v as i64).to_string(),
+            TypedValue::Char(c) => (*c as u32 as u8 as i8).to_string(),
+            TypedValue::Time(duration) => millis(*duration, &tv.to_plc_literal()?)?.to_string(),
+            other => {
+                return Err(Rust2PlcError::Other(format!(
+                    "{} is not an atomic Logix value",
+                    other.to_plc_type()
+                )))
+            }
+        })
+    }
+
+    fn stmt(
+        &self,
+        stmt: &Stmt,
+        vars: &HashMap<&str, &TypedValue>,
+    ) -> Result<Vec<Stmt>, Rust2PlcError> {
+        let stmts = |body: &[Stmt]| -> Result<Vec<Stmt>, Rust2PlcError> {
+            let mut out = vec![];
+            for s in body {
+                out.extend(self.stmt(s, vars)?);
+            }
+            Ok(out)
+        };
+        let otherwise = |otherwise: &Option<Vec<Stmt>>| match otherwise {
+            Some(body) => stmts(body).map(Some),
+            None => Ok(None),
+        };
+        Ok(vec![match stmt {
+            Stmt::Assign { target, value } => Stmt::Assign {
+                target: self.expr(target, vars)?,
+                value: self.expr(value, vars)?,
+            },
+            Stmt::Call(call) => return self.call_stmt(call, vars),
+            Stmt::If {
+                branches,
+                otherwise: other,
+            } => Stmt::If {
+                branches: branches
+                    .iter()
+                    .map(|b| {
+                        Ok(Branch {
+                            cond: self.expr(&b.cond, vars)?,
+                            body: stmts(&b.body)?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, Rust2PlcError>>()?,
+                otherwise: otherwise(other)?,
+            },
+            Stmt::Case {
+                selector,
+                branches,
+                otherwise: other,
+            } => Stmt::Case {
+                selector: self.expr(selector, vars)?,
+                branches: branches
+                    .iter()
+                    .map(|b| {
+                        let labels = b
+                            .labels
+                            .iter()
+                            .map(|label| match label {
+                                CaseLabel::Value(v) => Ok(CaseLabel::Value(self.expr(v, vars)?)),
+                                CaseLabel::Range(from, to) => Ok(CaseLabel::Range(
+                                    self.expr(from, vars)?,
+                                    self.expr(to, vars)?,
+                                )),
+                            })
+                            .collect::<Result<Vec<_>, Rust2PlcError>>()?;
+                        Ok(CaseBranch {
+                            labels,
+                            body: stmts(&b.body)?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, Rust2PlcError>>()?,
+                otherwise: otherwise(other)?,
+            },
+            Stmt::For {
+                var,
+                from,
+                to,
+                by,
+                body,
+            } => Stmt::For {
+                var: var.clone(),
+                from: self.expr(from, vars)?,
+                to: self.expr(to, vars)?,
+                by: match by {
+                    Some(by) => Some(self.expr(by, vars)?),
+                    None => None,
+                },
+                body: stmts(body)?,
+            },
+            Stmt::While { cond, body } => Stmt::While {
+                cond: self.expr(cond, vars)?,
+                body: stmts(body)?,
+            },
+            Stmt::Repeat { body, until } => Stmt::Repeat {
+                body: stmts(body)?,
+                until: self.expr(until, vars)?,
+            },
+            Stmt::Return => Stmt::Call(Call {
+                name: "RET".to_string(),
+                args: vec![],
+            }),
+            Stmt::Exit => Stmt::Exit,
+            Stmt::Continue => {
+                return Err(Rust2PlcError::Other(
+                    "CONTINUE has no Logix counterpart".to_string(),
+                ))
+            }
+        }])
+    }
+
+    /// An instance call, `timer(IN := start, Q => done)` becomes
+    /// `Timer(timer, start); done := timer.Q;` with the required parameters in declaration order.
+    fn call_stmt(
+        &self,
+        call: &Call,
+        vars: &HashMap<&str, &TypedValue>,
+    ) -> Result<Vec<Stmt>, Rust2PlcError> {
+        let Some(fb) = self.instance_type(&call.name, vars) else {
+            return Ok(vec![Stmt::Call(self.function_call(call, vars)?)]);
+        };
+        let instance = Expr::var(call.name.clone());
+        let params = fb.inputs().iter().chain(fb.outputs()).chain(fb.vars());
+        let required: Vec<&str> = params
+            .clone()
+            .filter(|v| matches!(v, Value::Input(_, _)))
+            .chain(params.filter(|v| matches!(v, Value::InOut(_, _))))
+            .filter_map(|v| v.name())
+            .collect();
+        let mut values: Vec<Option<Expr>> = vec![None; required.len()];
+        let mut outputs = vec![];
+        let mut positional = 0;
+        for arg in call.args.iter() {
+            match arg {
+                Arg::Value(value) => {
+                    if positional >= values.len() {
+                        return Err(Rust2PlcError::Other(format!(
+                            "{} takes {} arguments",
+                            fb.name(),
+                            values.len()
+                        )));
+                    }
+                    values[positional] = Some(self.expr(value, vars)?);
+                    positional += 1;
+                }
+                Arg::Input(name, value) => {
+                    let Some(idx) = required.iter().position(|p| p.eq_ignore_ascii_case(name))
+                    else {
+                        return Err(Rust2PlcError::Other(format!(
+                            "{} has no input {}",
+                            fb.name(),
+                            name
+                        )));
+                    };
+                    values[idx] = Some(self.expr(value, vars)?);
+                }
+                Arg::Output(name, target) => outputs.push(Stmt::assign(
+                    self.expr(target, vars)?,
+                    Expr::Field(Box::new(instance.clone()), name.clone()),
+                )),
+            }
+        }
+        let mut args = vec![Arg::Value(instance.clone())];
+        for (param, value) in required.iter().zip(values) {
+            // An input left out keeps the value of the instance member.
+            let value =
+                value.unwrap_or_else(|| Expr::Field(Box::new(instance.clone()), param.to_string()));
+            args.push(Arg::Value(value));
+        }
+        let mut stmts = vec![Stmt::Call(Call {
+            name: fb.name().to_string(),
+            args,
+        })];
+        stmts.extend(outputs);
+        Ok(stmts)
+    }
+
+    /// The function block of an instance variable.
+    fn instance_type(
+        &self,
+        name: &str,
+        vars: &HashMap<&str, &TypedValue>,
+    ) -> Option<&FunctionBlock> {
+        let TypedValue::UserDefined(type_name, _) = vars.get(name)? else {
+            return None;
+        };
+        self.items.iter().find_map(|item| match item {
+            RegistryItem::StFb(fb) if fb.name() == type_name => Some(fb),
+            _ => None,
+        })
+    }
+
+    fn function_call(
+        &self,
+        call: &Call,
+        vars: &HashMap<&str, &TypedValue>,
+    ) -> Result<Call, Rust2PlcError> {
+        if self.instance_type(&call.name, vars).is_some() {
+            return Err(Rust2PlcError::Other(format!(
+                "the instance {} is called in an expression, Add-On Instructions are called as statements",
+                call.name
+            )));
+        }
+        let upper = call.name.to_uppercase();
+        let Some((_, name)) = FUNCTIONS.iter().find(|(iec, _)| *iec == upper) else {
+            return Err(Rust2PlcError::Other(format!(
+                "{} has no Logix counterpart, ST routines call Logix functions and Add-On Instructions",
+                call.name
+            )));
+        };
+        let args = call
+            .args
+            .iter()
+            .map(|arg| match arg {
+                Arg::Value(value) | Arg::Input(_, value) => Ok(Arg::Value(self.expr(value, vars)?)),
+                Arg::Output(_, _) => Err(Rust2PlcError::Other(format!(
+                    "{} has no outputs",
+                    call.name
+                ))),
+            })
+            .collect::<Result<Vec<_>, Rust2PlcError>>()?;
+        Ok(Call {
+            name: name.to_string(),
+            args,
+        })
+    }
+
+    fn expr(&self, expr: &Expr, vars: &HashMap<&str, &TypedValue>) -> Result<Expr, Rust2PlcError> {
+        let boxed = |e: &Expr| self.expr(e, vars).map(Box::new);
+        Ok(match expr {
+            Expr::Literal(Literal::Typed { ty, value }) => typed_literal(ty, value)?,
+            Expr::Literal(Literal::Str(text)) => {
+                return Err(Rust2PlcError::Other(format!(
+                "the string literal '{}' has no Logix ST counterpart, strings are set on the tags",
+                text
+            )))
+            }
+            Expr::Literal(literal) => Expr::Literal(literal.clone()),
+            Expr::Var(name) => Expr::Var(name.clone()),
+            Expr::Field(base, member) => Expr::Field(boxed(base)?, member.clone()),
+            Expr::Index(base, indices) => Expr::Index(
+                boxed(base)?,
+                indices
+                    .iter()
+                    .map(|i| self.expr(i, vars))
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            Expr::Unary(op, operand) => Expr::Unary(*op, boxed(operand)?),
+            Expr::Binary(op, lhs, rhs) => Expr::Binary(*op, boxed(lhs)?, boxed(rhs)?),
+            // Logix converts on assignment, the conversion functions are dropped.
+            Expr::Call(call) if call.name.to_uppercase().contains("_TO_") => {
+                match call.args.as_slice() {
+                    [Arg::Value(value)] | [Arg::Input(_, value)] => self.expr(value, vars)?,
+                    _ => {
+                        return Err(Rust2PlcError::Other(format!(
+                            "{} takes one argument",
+                            call.name
+                        )))
+                    }
+                }
+            }
+            Expr::Call(call) => Expr::Call(self.function_call(call, vars)?),
+            Expr::Paren(inner) => Expr::Paren(boxed(inner)?),
+        })
+    }
+}
+
+impl PLCRegistry {
+    /// Renders the latest version of every item as an L5X project of the controller,
+    /// following [`PLCRegistry::declaration_order`].
+    pub fn to_l5x(&self, controller: &str) -> Result<String, Rust2PlcError> {
+        self.declaration_order()
+            .into_iter()
+            .fold(L5xProject::new(controller), |p, item| {
+                p.with_item(item.clone())
+            })
+            .to_xml()
+    }
+}
+
+fn is_atomic(tv: &TypedValue) -> bool {
+    !matches!(
+        tv,
+        TypedValue::String(_, _)
+            | TypedValue::WString(_, _)
+            | TypedValue::UserDefined(_, _)
+            | TypedValue::Struct(_, _)
+            | TypedValue::Array(..)
+    )
+}
+
+fn radix(tv: &TypedValue) -> &'static str {
+    match tv {
+        TypedValue::Real(_) | TypedValue::LReal(_) => "Float",
+        TypedValue::Array(_, elem, _) => radix(elem),
+        tv if is_atomic(tv) => "Decimal",
+        _ => "NullType",
+    }
+}
+
+/// The `major.minor` revision of an Add-On Instruction, `1.0` when the block has none.
+fn aoi_revision(name: &str, version: Option<&str>) -> Result<String, Rust2PlcError> {
+    let Some(version) = version else {
+        return Ok("1.0".to_string());
+    };
+    let parts: Vec<&str> = version.split('.').collect();
+    match parts.as_slice() {
+        [major, minor, ..] if [major, minor].iter().all(|p| p.parse::<u16>().is_ok()) => {
+            Ok(format!("{}.{}", major, minor))
+        }
+        _ => Err(Rust2PlcError::Other(format!(
+            "the version {} of {} is not an Add-On Instruction revision, Logix expects major.minor",
+            version, name
+        ))),
+    }
+}
+
+fn millis(duration: std::time::Duration, text: &str) -> Result<i32, Rust2PlcError> {
+    i32::try_from(duration.as_millis()).map_err(|_| {
+        Rust2PlcError::Other(format!(
+            "{} exceeds the DINT milliseconds of a Logix duration",
+            text
+        ))
+    })
+}
+
+/// Typed literals lose the prefix, durations become milliseconds.
+fn typed_literal(ty: &str, value: &str) -> Result<Expr, Rust2PlcError> {
+    let text = format!("{}#{}", ty, value);
+    match ty.to_uppercase().as_str() {
+        "T" | "TIME" => match parser::parse_value(&TypedValue::new_time(), &text)? {
+            TypedValue::Time(duration) => Ok(Expr::int(millis(duration, &text)? as i128)),
+            _ => unreachable!("a TIME literal is parsed into a duration"),
+        },
+        upper => match TypedValue::from_plc_type(upper) {
+            Some(TypedValue::Bool(_)) => Ok(Expr::bool(
+                value == "1" || value.eq_ignore_ascii_case("TRUE"),
+            )),
+            Some(tv) if is_atomic(&tv) && !matches!(tv, TypedValue::Time(_)) => {
+                parser::parse_expr(value)
+            }
+            _ => Err(Rust2PlcError::Other(format!(
+                "the literal {} has no Logix counterpart",
+                text
+            ))),
+        },
+    }
+}
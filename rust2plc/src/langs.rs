@@ -1,11 +1,14 @@
 use std::fmt::Display;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PLCLang {
     StructuredText,
     LadderDiagram,
     FunctionBlockDiagram,
     SequentialFunctionChart,
+    StructuredControlLanguage,
+    InstructionList,
+    StatementList,
 }
 
 impl Display for PLCLang {
@@ -15,6 +18,9 @@ impl Display for PLCLang {
             PLCLang::LadderDiagram => write!(f, "LadderDiagram"),
             PLCLang::FunctionBlockDiagram => write!(f, "FunctionBlockDiagram"),
             PLCLang::SequentialFunctionChart => write!(f, "SequentialFunctionChart"),
+            PLCLang::StructuredControlLanguage => write!(f, "StructuredControlLanguage"),
+            PLCLang::InstructionList => write!(f, "InstructionList"),
+            PLCLang::StatementList => write!(f, "StatementList"),
         }
     }
 }
@@ -26,7 +32,12 @@ impl From<&str> for PLCLang {
             "ld" => PLCLang::LadderDiagram,
             "fbd" => PLCLang::FunctionBlockDiagram,
             "sfc" => PLCLang::SequentialFunctionChart,
-            _ => panic!("Unsupported PLC language: {value}, available: st, ld, fbd, sfc"),
+            "scl" => PLCLang::StructuredControlLanguage,
+            "il" => PLCLang::InstructionList,
+            "awl" | "stl" => PLCLang::StatementList,
+            _ => panic!(
+                "Unsupported PLC language: {value}, available: st, ld, fbd, sfc, scl, il, awl"
+            ),
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,490 @@
+//! Ladder Diagram, the rungs of boolean logic.
+//!
+//! Bodies made of boolean assignments are lowered into rungs: the variables become contacts,
+//! `AND` puts them in series, `OR` into parallel branches and the assigned variable is the coil.
+//! `NOT` is pushed down to the contacts, which then open on a `TRUE` signal.
+//!
+//! | ST                                           | Rung                          |
+//! |----------------------------------------------|-------------------------------|
+//! | `q := a AND NOT b;`                          | `-[a]-[/b]-(q)-`              |
+//! | `IF a THEN q := TRUE; END_IF;`               | `-[a]-(S q)-`                 |
+//! | `IF a THEN q := FALSE; END_IF;`              | `-[a]-(R q)-`                 |
+//! | `edge(CLK := a);` and `q := edge.Q;`         | `-[P a]-(q)-`                 |
+//!
+//! Edges are detected by `R_TRIG` and `F_TRIG` instances, the call itself gives no rung
+//! and reading the `Q` output of the instance is a transition-sensing contact.
+//!
+//! ```
+//! use rust2plc::st::ast::{BinaryOp, Expr, Stmt, UnaryOp};
+//! use rust2plc::st::function::Function;
+//! use rust2plc::types::TypedValue;
+//! use rust2plc::var::Value;
+//!
+//! let interlock = Function::new("interlock", TypedValue::new_bool())
+//!     .with_input(Value::Input("run".to_string(), TypedValue::new_bool()))
+//!     .with_input(Value::Input("stop".to_string(), TypedValue::new_bool()))
+//!     .with_body(vec![Stmt::assign(
+//!         Expr::var("interlock"),
+//!         Expr::binary(
+//!             BinaryOp::And,
+//!             Expr::var("run"),
+//!             Expr::unary(UnaryOp::Not, Expr::var("stop")),
+//!         ),
+//!     )]);
+//! assert_eq!(
+//!     interlock.to_ladder().unwrap().to_ascii(),
+//!     "|-[run]-[/stop]-(interlock)-|\n"
+//! );
+//! ```
+
+use std::collections::HashMap;
+
+use crate::error::Rust2PlcError;
+use crate::registry::RegistryItem;
+use crate::st::ast::{Arg, BinaryOp, Expr, Literal, Printer, Stmt, UnaryOp};
+use crate::st::function::Function;
+use crate::st::function_block::FunctionBlock;
+use crate::st::program::Program;
+use crate::types::TypedValue;
+use crate::var::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContactKind {
+    /// `-[a]`, closed while the variable is `TRUE`.
+    NormallyOpen,
+    /// `-[/a]`, closed while the variable is `FALSE`.
+    NormallyClosed,
+    /// `-[P a]`, closed for one scan when the variable turns `TRUE`.
+    Rising,
+    /// `-[N a]`, closed for one scan when the variable turns `FALSE`.
+    Falling,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoilKind {
+    /// `-(q)-`, the variable follows the rung.
+    Normal,
+    /// `-(S q)-`, the variable is set while the rung is powered.
+    Set,
+    /// `-(R q)-`, the variable is reset while the rung is powered.
+    Reset,
+}
+
+/// The contacts between the left power rail and the coils.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Circuit {
+    Contact(ContactKind, String),
+    /// Contacts in a row, an empty row connects the coils to the rail directly.
+    Series(Vec<Circuit>),
+    /// Branches of which one has to be closed.
+    Parallel(Vec<Circuit>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coil {
+    pub kind: CoilKind,
+    pub var: String,
+}
+
+/// A rung, the coils are powered in parallel by the circuit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rung {
+    pub circuit: Circuit,
+    pub coils: Vec<Coil>,
+}
+
+/// The rungs of a POU, evaluated top to bottom.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ladder {
+    rungs: Vec<Rung>,
+}
+
+impl Ladder {
+    pub fn new(rungs: Vec<Rung>) -> Self {
+        Ladder { rungs }
+    }
+
+    pub fn rungs(&self) -> &[Rung] {
+        &self.rungs
+    }
+
+    /// Lowers a body into rungs, `vars` are the variables the body may use.
+    ///
+    /// Contacts and coils need `BOOL` variables, edges an `R_TRIG` or `F_TRIG` instance.
+    pub fn lower<'a>(
+        body: &[Stmt],
+        vars: impl IntoIterator<Item = &'a Value>,
+    ) -> Result<Ladder, Rust2PlcError> {
+        let vars = vars
+            .into_iter()
+            .filter_map(|v| {
+                v.name()
+                    .map(|name| (name.to_string(), v.typed_value().clone()))
+            })
+            .collect();
+        Lowering {
+            vars,
+            edges: HashMap::new(),
+        }
+        .body(body)
+    }
+
+    /// Renders the rungs as ASCII art between the power rails, the coils aligned in one column.
+    pub fn to_ascii(&self) -> String {
+        let rungs: Vec<(Block, Block)> = self
+            .rungs
+            .iter()
+            .map(|rung| {
+                let coils: Vec<Block> = rung.coils.iter().map(Block::coil).collect();
+                (Block::circuit(&rung.circuit), Block::parallel(coils))
+            })
+            .collect();
+        let circuit_width = rungs.iter().map(|(c, _)| c.width()).max().unwrap_or(0);
+        let coil_width = rungs.iter().map(|(_, c)| c.width()).max().unwrap_or(0);
+        let mut lines = vec![];
+        for (i, (circuit, coils)) in rungs.iter().enumerate() {
+            if i > 0 {
+                lines.push(format!("|{}|", " ".repeat(circuit_width + coil_width)));
+            }
+            for row in 0..circuit.height().max(coils.height()) {
+                let fill = if row == 0 { '-' } else { ' ' };
+                lines.push(format!(
+                    "|{}{}|",
+                    circuit.row(row, circuit_width, fill),
+                    coils.row(row, coil_width, fill),
+                ));
+            }
+        }
+        lines.into_iter().map(|line| line + "\n").collect()
+    }
+}
+
+impl Function {
+    /// Lowers the body into rungs, the function returns `BOOL` or only assigns outputs.
+    pub fn to_ladder(&self) -> Result<Ladder, Rust2PlcError> {
+        let result = Value::Output(self.name().to_string(), self.return_value().clone());
+        Ladder::lower(
+            self.body(),
+            self.inputs()
+                .iter()
+                .chain(self.outputs())
+                .chain(self.locals())
+                .chain([&result]),
+        )
+    }
+}
+
+impl FunctionBlock {
+    pub fn to_ladder(&self) -> Result<Ladder, Rust2PlcError> {
+        Ladder::lower(
+            self.body(),
+            self.inputs()
+                .iter()
+                .chain(self.outputs())
+                .chain(self.vars()),
+        )
+    }
+}
+
+impl Program {
+    pub fn to_ladder(&self) -> Result<Ladder, Rust2PlcError> {
+        if self.chart().is_some() {
+            return Err(Rust2PlcError::Other(format!(
+                "program {} runs a chart, it has no body to lower",
+                self.name()
+            )));
+        }
+        Ladder::lower(
+            self.body(),
+            self.inputs()
+                .iter()
+                .chain(self.outputs())
+                .chain(self.vars()),
+        )
+    }
+}
+
+impl RegistryItem {
+    /// Lowers the body of a POU into rungs, data types and configurations have none.
+    pub fn to_ladder(&self) -> Result<Ladder, Rust2PlcError> {
+        match self {
+            RegistryItem::StFn(f) => f.to_ladder(),
+            RegistryItem::StFb(fb) => fb.to_ladder(),
+            RegistryItem::StProgram(p) => p.to_ladder(),
+            RegistryItem::StType(_) | RegistryItem::StConfig(_) => Err(Rust2PlcError::Other(
+                format!("{} has no body to draw as ladder", self.name()),
+            )),
+        }
+    }
+}
+
+struct Lowering {
+    vars: HashMap<String, TypedValue>,
+    /// The edge instances called so far and the contacts they stand for.
+    edges: HashMap<String, (ContactKind, String)>,
+}
+
+impl Lowering {
+    fn body(mut self, body: &[Stmt]) -> Result<Ladder, Rust2PlcError> {
+        let mut rungs = vec![];
+        for stmt in body {
+            if let Some(rung) = self.stmt(stmt)? {
+                rungs.push(rung);
+            }
+        }
+        Ok(Ladder::new(rungs))
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Result<Option<Rung>, Rust2PlcError> {
+        match stmt {
+            Stmt::Assign { target, value } => Ok(Some(Rung {
+                circuit: self.circuit(value, false)?,
+                coils: vec![self.coil(CoilKind::Normal, target)?],
+            })),
+            Stmt::If {
+                branches,
+                otherwise: None,
+            } if branches.len() == 1 => {
+                let coils = branches[0]
+                    .body
+                    .iter()
+                    .map(|stmt| match stmt {
+                        Stmt::Assign {
+                            target,
+                            value: Expr::Literal(Literal::Bool(true)),
+                        } => self.coil(CoilKind::Set, target),
+                        Stmt::Assign {
+                            target,
+                            value: Expr::Literal(Literal::Bool(false)),
+                        } => self.coil(CoilKind::Reset, target),
+                        _ => Err(not_ladder(stmt)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                if coils.is_empty() {
+                    return Ok(None);
+                }
+                Ok(Some(Rung {
+                    circuit: self.circuit(&branches[0].cond, false)?,
+                    coils,
+                }))
+            }
+            Stmt::Call(call) => {
+                let kind = match self.vars.get(&call.name) {
+                    Some(TypedValue::UserDefined(ty, _)) if ty == "R_TRIG" => ContactKind::Rising,
+                    Some(TypedValue::UserDefined(ty, _)) if ty == "F_TRIG" => ContactKind::Falling,
+                    _ => return Err(not_ladder(stmt)),
+                };
+                let clk = match call.args.as_slice() {
+                    [Arg::Value(Expr::Var(clk))] => clk,
+                    [Arg::Input(param, Expr::Var(clk))] if param.eq_ignore_ascii_case("CLK") => clk,
+                    _ => return Err(not_ladder(stmt)),
+                };
+                self.bool_var(clk)?;
+                self.edges
+                    .insert(call.name.clone(), (kind, clk.to_string()));
+                Ok(None)
+            }
+            _ => Err(not_ladder(stmt)),
+        }
+    }
+
+    fn coil(&self, kind: CoilKind, target: &Expr) -> Result<Coil, Rust2PlcError> {
+        match target {
+            Expr::Var(var) => {
+                self.bool_var(var)?;
+                Ok(Coil {
+                    kind,
+                    var: var.clone(),
+                })
+            }
+            _ => Err(Rust2PlcError::Other(format!(
+                "the coil {} is not a variable",
+                Printer::default().expr(target)
+            ))),
+        }
+    }
+
+    /// The circuit closed when the expression is `TRUE`, or `FALSE` when negated.
+    fn circuit(&self, expr: &Expr, negated: bool) -> Result<Circuit, Rust2PlcError> {
+        match expr {
+            Expr::Var(var) => {
+                self.bool_var(var)?;
+                let kind = if negated {
+                    ContactKind::NormallyClosed
+                } else {
+                    ContactKind::NormallyOpen
+                };
+                Ok(Circuit::Contact(kind, var.clone()))
+            }
+            Expr::Field(instance, output) if output.eq_ignore_ascii_case("Q") => {
+                let edge = match instance.as_ref() {
+                    Expr::Var(instance) => self.edges.get(instance),
+                    _ => None,
+                };
+                match (edge, negated) {
+                    (Some((kind, clk)), false) => Ok(Circuit::Contact(*kind, clk.clone())),
+                    (Some(_), true) => Err(Rust2PlcError::Other(format!(
+                        "the edge {} cannot be negated in ladder",
+                        Printer::default().expr(expr)
+                    ))),
+                    (None, _) => Err(not_boolean(expr)),
+                }
+            }
+            Expr::Literal(Literal::Bool(value)) if *value != negated => Ok(Circuit::Series(vec![])),
+            Expr::Unary(UnaryOp::Not, operand) => self.circuit(operand, !negated),
+            Expr::Paren(inner) => self.circuit(inner, negated),
+            // De Morgan turns a negated series into parallel negated contacts and vice versa.
+            Expr::Binary(op @ (BinaryOp::And | BinaryOp::Or), lhs, rhs) => {
+                let circuits = vec![self.circuit(lhs, negated)?, self.circuit(rhs, negated)?];
+                if (*op == BinaryOp::And) != negated {
+                    Ok(series(circuits))
+                } else {
+                    Ok(parallel(circuits))
+                }
+            }
+            _ => Err(not_boolean(expr)),
+        }
+    }
+
+    fn bool_var(&self, var: &str) -> Result<(), Rust2PlcError> {
+        match self.vars.get(var) {
+            Some(TypedValue::Bool(_)) => Ok(()),
+            Some(tv) => Err(Rust2PlcError::Other(format!(
+                "{} is {}, contacts and coils need BOOL",
+                var,
+                tv.to_plc_type()
+            ))),
+            None => Err(Rust2PlcError::Other(format!("{} is not declared", var))),
+        }
+    }
+}
+
+/// Joins the circuits in a row, nested rows are flattened.
+fn series(circuits: Vec<Circuit>) -> Circuit {
+    let mut flat = vec![];
+    for circuit in circuits {
+        match circuit {
+            Circuit::Series(items) => flat.extend(items),
+            other => flat.push(other),
+        }
+    }
+    Circuit::Series(flat)
+}
+
+/// Joins the circuits as branches, nested branches are flattened.
+fn parallel(circuits: Vec<Circuit>) -> Circuit {
+    let mut flat = vec![];
+    for circuit in circuits {
+        match circuit {
+            Circuit::Parallel(items) => flat.extend(items),
+            other => flat.push(other),
+        }
+    }
+    Circuit::Parallel(flat)
+}
+
+fn not_ladder(stmt: &Stmt) -> Rust2PlcError {
+    Rust2PlcError::Other(format!(
+        "{} has no ladder counterpart",
+        Printer::default().stmt(stmt, 0).trim_end()
+    ))
+}
+
+fn not_boolean(expr: &Expr) -> Rust2PlcError {
+    Rust2PlcError::Other(format!(
+        "{} is not boolean logic of contacts",
+        Printer::default().expr(expr)
+    ))
+}
+
+/// A piece of ASCII art, the signal enters and leaves on the first line.
+struct Block {
+    lines: Vec<String>,
+}
+
+impl Block {
+    fn circuit(circuit: &Circuit) -> Block {
+        match circuit {
+            Circuit::Contact(kind, var) => {
+                let symbol = match kind {
+                    ContactKind::NormallyOpen => format!("-[{}]", var),
+                    ContactKind::NormallyClosed => format!("-[/{}]", var),
+                    ContactKind::Rising => format!("-[P {}]", var),
+                    ContactKind::Falling => format!("-[N {}]", var),
+                };
+                Block {
+                    lines: vec![symbol],
+                }
+            }
+            Circuit::Series(items) if items.is_empty() => Block {
+                lines: vec!["-".to_string()],
+            },
+            Circuit::Series(items) => {
+                let blocks: Vec<Block> = items.iter().map(Block::circuit).collect();
+                let height = blocks.iter().map(Block::height).max().unwrap_or(1);
+                let lines = (0..height)
+                    .map(|row| blocks.iter().map(|b| b.row(row, b.width(), ' ')).collect())
+                    .collect();
+                Block { lines }
+            }
+            Circuit::Parallel(branches) => {
+                Block::parallel(branches.iter().map(Block::circuit).collect())
+            }
+        }
+    }
+
+    fn coil(coil: &Coil) -> Block {
+        let symbol = match coil.kind {
+            CoilKind::Normal => format!("-({})-", coil.var),
+            CoilKind::Set => format!("-(S {})-", coil.var),
+            CoilKind::Reset => format!("-(R {})-", coil.var),
+        };
+        Block {
+            lines: vec![symbol],
+        }
+    }
+
+    /// Stacks the branches between two vertical bars, a single branch stays as it is.
+    fn parallel(mut branches: Vec<Block>) -> Block {
+        if branches.len() == 1 {
+            return branches.remove(0);
+        }
+        let width = branches.iter().map(Block::width).max().unwrap_or(0);
+        let last = branches.len() - 1;
+        let mut lines = vec![];
+        for (i, branch) in branches.iter().enumerate() {
+            for row in 0..branch.height() {
+                let (fill, joint) = match (row, i < last) {
+                    (0, _) => ('-', '+'),
+                    (_, true) => (' ', '|'),
+                    (_, false) => (' ', ' '),
+                };
+                let lead = if i == 0 && row == 0 { '-' } else { ' ' };
+                lines.push(format!(
+                    "{}{}{}{}",
+                    lead,
+                    joint,
+                    branch.row(row, width, fill),
+                    joint
+                ));
+            }
+        }
+        Block { lines }
+    }
+
+    fn width(&self) -> usize {
+        self.lines[0].chars().count()
+    }
+
+    fn height(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// The line padded to `width`, rows below the block are blank.
+    fn row(&self, row: usize, width: usize, fill: char) -> String {
+        let line = self.lines.get(row).map(String::as_str).unwrap_or("");
+        let pad = width - line.chars().count();
+        let fill = if row < self.height() { fill } else { ' ' };
+        format!("{}{}", line, fill.to_string().repeat(pad))
+    }
+}
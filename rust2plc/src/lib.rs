@@ -1,6 +1,21 @@
+pub mod awl;
 pub mod error;
+pub mod fbd;
+pub mod il;
+pub mod l5x;
 pub mod langs;
+pub mod ld;
+pub mod plcopen;
 pub mod registry;
+pub mod s7;
+pub mod scl;
+pub mod sfc;
 pub mod st;
+pub mod twincat;
 pub mod types;
 pub mod var;
+
+#[doc(hidden)]
+pub use chrono;
+#[doc(hidden)]
+pub use inventory;
@@ -0,0 +1,401 @@
+use crate::error::{Position, Rust2PlcError};
+
+pub mod codesys;
+pub mod export;
+pub mod import;
+
+/// The namespace of PLCopen TC6 XML 2.01 documents.
+pub const TC6_NAMESPACE: &str = "http://www.plcopen.org/xml/tc6_0201";
+
+/// The namespace of the formatted text in bodies and documentation.
+pub const XHTML_NAMESPACE: &str = "http://www.w3.org/1999/xhtml";
+
+/// The name of the `addData` entry keeping the namespace and version of a POU,
+/// which TC6 XML has no place for.
+pub const METADATA: &str = "urn:rust2plc:metadata";
+
+/// An indenting writer for the XML documents, the text is escaped on the way in.
+pub(crate) struct XmlWriter {
+    xml: String,
+    level: usize,
+}
+
+impl XmlWriter {
+    pub(crate) fn new() -> Self {
+        XmlWriter {
+            xml: String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n"),
+            level: 0,
+        }
+    }
+
+    pub(crate) fn open(&mut self, tag: &str, attrs: &[(&str, &str)]) {
+        self.line(&format!("<{}{}>", tag, attributes(attrs)));
+        self.level += 1;
+    }
+
+    pub(crate) fn close(&mut self, tag: &str) {
+        self.level -= 1;
+        self.line(&format!("</{}>", tag));
+    }
+
+    pub(crate) fn empty(&mut self, tag: &str, attrs: &[(&str, &str)]) {
+        self.line(&format!("<{}{}/>", tag, attributes(attrs)));
+    }
+
+    /// An element holding escaped text, e.g. `<variable>start</variable>`.
+    pub(crate) fn text(&mut self, tag: &str, text: &str) {
+        self.line(&format!("<{}>{}</{}>", tag, escape(text), tag));
+    }
+
+    /// An xhtml paragraph keeping the text verbatim in a CDATA section.
+    pub(crate) fn paragraph(&mut self, text: &str) {
+        self.cdata("xhtml:p", &[], text);
+    }
+
+    /// An element keeping the text verbatim in a CDATA section.
+    pub(crate) fn cdata(&mut self, tag: &str, attrs: &[(&str, &str)], text: &str) {
+        self.line(&format!(
+            "<{}{}><![CDATA[{}]]></{}>",
+            tag,
+            attributes(attrs),
+            text.replace("]]>", "]]]]><![CDATA[>"),
+            tag
+        ));
+    }
+
+    fn line(&mut self, line: &str) {
+        self.xml.push_str(&"  ".repeat(self.level));
+        self.xml.push_str(line);
+        self.xml.push('\n');
+    }
+
+    pub(crate) fn finish(self) -> String {
+        self.xml
+    }
+}
+
+fn attributes(attrs: &[(&str, &str)]) -> String {
+    attrs
+        .iter()
+        .map(|(name, value)| format!(" {}=\"{}\"", name, escape(value)))
+        .collect()
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// A node of a parsed XML document.
+#[derive(Debug, Clone)]
+pub(crate) enum Node {
+    Element(Element),
+    /// Character data with the position of its first character.
+    Text(String, Position),
+}
+
+/// An element of a parsed XML document, the names are kept without namespace prefixes.
+#[derive(Debug, Clone)]
+pub(crate) struct Element {
+    pub(crate) name: String,
+    pub(crate) attrs: Vec<(String, String)>,
+    pub(crate) nodes: Vec<Node>,
+    pub(crate) position: Position,
+}
+
+impl Element {
+    pub(crate) fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub(crate) fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.nodes.iter().filter_map(|node| match node {
+            Node::Element(e) => Some(e),
+            Node::Text(_, _) => None,
+        })
+    }
+
+    pub(crate) fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.elements().filter(move |e| e.name == name)
+    }
+
+    pub(crate) fn child(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|e| e.name == name)
+    }
+
+    /// The text of the element and its descendants, in document order.
+    pub(crate) fn text(&self) -> String {
+        self.nodes
+            .iter()
+            .map(|node| match node {
+                Node::Element(e) => e.text(),
+                Node::Text(text, _) => text.clone(),
+            })
+            .collect()
+    }
+
+    /// The position of the first character of the text.
+    pub(crate) fn text_position(&self) -> Position {
+        self.nodes
+            .iter()
+            .find_map(|node| match node {
+                Node::Element(e) if !e.text().is_empty() => Some(e.text_position()),
+                Node::Text(text, position) if !text.is_empty() => Some(*position),
+                _ => None,
+            })
+            .unwrap_or(self.position)
+    }
+
+    pub(crate) fn error(&self, msg: impl Into<String>) -> Rust2PlcError {
+        Rust2PlcError::parse_at(msg.into(), self.position.line, self.position.column)
+    }
+}
+
+/// Reads the root element of an XML document.
+///
+/// The declaration, comments, processing instructions and the doctype are skipped.
+pub(crate) fn read(xml: &str) -> Result<Element, Rust2PlcError> {
+    let mut reader = XmlReader {
+        chars: xml.trim_start_matches('\u{feff}').chars().collect(),
+        idx: 0,
+        line: 1,
+        column: 1,
+    };
+    reader.misc()?;
+    if !reader.starts_with("<") {
+        return Err(reader.error("expected the root element"));
+    }
+    let root = reader.element()?;
+    reader.misc()?;
+    if reader.idx < reader.chars.len() {
+        return Err(reader.error("unexpected content after the root element"));
+    }
+    Ok(root)
+}
+
+struct XmlReader {
+    chars: Vec<char>,
+    idx: usize,
+    line: usize,
+    column: usize,
+}
+
+impl XmlReader {
+    fn position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn error(&self, msg: impl Into<String>) -> Rust2PlcError {
+        Rust2PlcError::parse_at(msg.into(), self.line, self.column)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.idx).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.idx += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn starts_with(&self, prefix: &str) -> bool {
+        prefix
+            .chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.idx + i) == Some(&c))
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        if self.starts_with(prefix) {
+            prefix.chars().for_each(|_| {
+                self.bump();
+            });
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    /// Reads up to the terminator and consumes it.
+    fn until(&mut self, terminator: &str) -> Result<String, Rust2PlcError> {
+        let mut text = String::new();
+        while !self.eat(terminator) {
+            match self.bump() {
+                Some(c) => text.push(c),
+                None => return Err(self.error(format!("expected {}", terminator))),
+            }
+        }
+        Ok(text)
+    }
+
+    /// Skips whitespace, comments, processing instructions and the doctype.
+    fn misc(&mut self) -> Result<(), Rust2PlcError> {
+        loop {
+            self.skip_ws();
+            if self.eat("<?") {
+                self.until("?>")?;
+            } else if self.eat("<!--") {
+                self.until("-->")?;
+            } else if self.eat("<!DOCTYPE") {
+                self.until(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn name(&mut self) -> Result<String, Rust2PlcError> {
+        let mut name = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':') {
+                name.push(c);
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        if name.is_empty() {
+            Err(self.error("expected a name"))
+        } else {
+            Ok(name)
+        }
+    }
+
+    fn element(&mut self) -> Result<Element, Rust2PlcError> {
+        let position = self.position();
+        self.eat("<");
+        let tag = self.name()?;
+        let mut attrs = vec![];
+        loop {
+            self.skip_ws();
+            if self.eat("/>") {
+                return Ok(Element {
+                    name: local(&tag),
+                    attrs,
+                    nodes: vec![],
+                    position,
+                });
+            }
+            if self.eat(">") {
+                break;
+            }
+            let name = self.name()?;
+            self.skip_ws();
+            if !self.eat("=") {
+                return Err(self.error(format!("expected = after the attribute {}", name)));
+            }
+            self.skip_ws();
+            let quote = match self.bump() {
+                Some(q @ ('"' | '\'')) => q,
+                _ => return Err(self.error(format!("expected a quoted value of {}", name))),
+            };
+            let value = self.until(&quote.to_string())?;
+            attrs.push((name, self.unescape(&value)?));
+        }
+
+        let mut nodes = vec![];
+        loop {
+            if self.eat("</") {
+                let end = self.name()?;
+                if end != tag {
+                    return Err(self.error(format!("expected </{}>, found </{}>", tag, end)));
+                }
+                self.skip_ws();
+                if !self.eat(">") {
+                    return Err(self.error("expected >"));
+                }
+                return Ok(Element {
+                    name: local(&tag),
+                    attrs,
+                    nodes,
+                    position,
+                });
+            } else if self.eat("<!--") {
+                self.until("-->")?;
+            } else if self.eat("<?") {
+                self.until("?>")?;
+            } else if self.eat("<![CDATA[") {
+                let position = self.position();
+                nodes.push(Node::Text(self.until("]]>")?, position));
+            } else if self.starts_with("<") {
+                nodes.push(Node::Element(self.element()?));
+            } else if self.peek().is_none() {
+                return Err(self.error(format!("expected </{}>", tag)));
+            } else {
+                let position = self.position();
+                let mut text = String::new();
+                while self.peek().is_some_and(|c| c != '<') {
+                    text.extend(self.bump());
+                }
+                nodes.push(Node::Text(self.unescape(&text)?, position));
+            }
+        }
+    }
+
+    fn unescape(&self, text: &str) -> Result<String, Rust2PlcError> {
+        let mut unescaped = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(idx) = rest.find('&') {
+            unescaped.push_str(&rest[..idx]);
+            let Some(end) = rest[idx..].find(';') else {
+                return Err(self.error("unterminated character reference"));
+            };
+            let entity = &rest[idx + 1..idx + end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => match entity.strip_prefix("#x").or(entity.strip_prefix("#X")) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                    None => entity
+                        .strip_prefix('#')
+                        .and_then(|dec| dec.parse().ok())
+                        .and_then(char::from_u32),
+                },
+            };
+            match c {
+                Some(c) => unescaped.push(c),
+                None => return Err(self.error(format!("unknown entity &{};", entity))),
+            }
+            rest = &rest[idx + end + 1..];
+        }
+        unescaped.push_str(rest);
+        Ok(unescaped)
+    }
+}
+
+fn local(name: &str) -> String {
+    name.rsplit(':').next().unwrap_or(name).to_string()
+}
@@ -0,0 +1,598 @@
+//! CODESYS projects, PLCopen XML with the CODESYS extensions.
+//!
+//! Namespaces follow the CODESYS library conventions. The items of a namespace that is
+//! referenced as a library are provided by the library and left out, the project refers
+//! to the library through a placeholder and reaches its items as `Math.add`.
+//! The items of the other namespaces are exported into a folder named after the namespace.
+//!
+//! ```
+//! use rust2plc::plcopen::codesys::{CodesysProject, LibraryReference};
+//! use rust2plc::registry::RegistryItem;
+//! use rust2plc::st::ast::{Expr, Stmt};
+//! use rust2plc::st::function::Function;
+//! use rust2plc::types::TypedValue;
+//!
+//! let add = Function::new("add", TypedValue::new_int()).with_namespace(Some("Math".to_string()));
+//! let twice = Function::new("twice", TypedValue::new_int()).with_body(vec![Stmt::assign(
+//!     Expr::var("twice"),
+//!     Expr::call("add", vec![Expr::int(1), Expr::int(1)]),
+//! )]);
+//! let xml = CodesysProject::new("Demo")
+//!     .with_library(LibraryReference::new("Math", "Math Utilities", "ACME"))
+//!     .with_qualified_only(true)
+//!     .with_item(RegistryItem::StFn(add))
+//!     .with_item(RegistryItem::StFn(twice))
+//!     .to_xml()
+//!     .unwrap();
+//! assert!(xml.contains("twice := Math.add(1, 1);"));
+//! assert!(!xml.contains("<pou name=\"add\""));
+//! ```
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::NaiveDateTime;
+use uuid::Uuid;
+
+use crate::error::Rust2PlcError;
+use crate::plcopen::export::{DataEntry, Extension, PlcOpenProject};
+use crate::plcopen::XmlWriter;
+use crate::registry::RegistryItem;
+use crate::sfc::{Action, Sfc, Transition};
+use crate::st::ast::{Arg, Branch, Call, CaseBranch, CaseLabel, Expr, Literal, Stmt};
+use crate::st::config::Configuration;
+use crate::st::data_type::DataType;
+use crate::st::function::Function;
+use crate::st::function_block::FunctionBlock;
+use crate::st::program::Program;
+use crate::types::TypedValue;
+use crate::var::Value;
+
+/// The `addData` entry placing the objects into folders.
+pub const PROJECT_STRUCTURE: &str = "http://www.3s-software.com/plcopenxml/projectstructure";
+
+/// The `addData` entry with the id of an object.
+pub const OBJECT_ID: &str = "http://www.3s-software.com/plcopenxml/objectid";
+
+/// The `addData` entry with the library references of the project.
+pub const LIBRARIES: &str = "http://www.3s-software.com/plcopenxml/libraries";
+
+/// The `addData` entry with the attribute pragmas of an object.
+pub const ATTRIBUTES: &str = "http://www.3s-software.com/plcopenxml/attributes";
+
+/// A placeholder reference to the library providing a namespace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LibraryReference {
+    namespace: String,
+    title: String,
+    version: String,
+    company: String,
+}
+
+impl LibraryReference {
+    /// A reference resolved to the newest installed version of the library.
+    pub fn new(
+        namespace: impl Into<String>,
+        title: impl Into<String>,
+        company: impl Into<String>,
+    ) -> Self {
+        LibraryReference {
+            namespace: namespace.into(),
+            title: title.into(),
+            version: "*".to_string(),
+            company: company.into(),
+        }
+    }
+
+    /// Pins the library version, e.g. `1.2.0.0`.
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = version.into();
+        self
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    /// The placeholder name, the title prefixed with `#`.
+    pub fn placeholder(&self) -> String {
+        format!("#{}", self.title)
+    }
+
+    /// The library the placeholder resolves to, e.g. `Math Utilities, * (ACME)`.
+    pub fn default_resolution(&self) -> String {
+        format!("{}, {} ({})", self.title, self.version, self.company)
+    }
+}
+
+/// A PLCopen XML project for the CODESYS import.
+#[derive(Debug, Clone)]
+pub struct CodesysProject {
+    project: PlcOpenProject,
+    items: Vec<RegistryItem>,
+    libraries: Vec<LibraryReference>,
+    qualified_only: bool,
+}
+
+impl CodesysProject {
+    pub fn new(name: impl Into<String>) -> Self {
+        CodesysProject {
+            project: PlcOpenProject::new(name),
+            items: vec![],
+            libraries: vec![],
+            qualified_only: false,
+        }
+    }
+
+    pub fn with_company(mut self, company: impl Into<String>) -> Self {
+        self.project = self.project.with_company(company);
+        self
+    }
+
+    pub fn with_created(mut self, created: NaiveDateTime) -> Self {
+        self.project = self.project.with_created(created);
+        self
+    }
+
+    /// Adds an item, the items of library namespaces only resolve the references to them.
+    pub fn with_item(mut self, item: RegistryItem) -> Self {
+        self.items.push(item);
+        self
+    }
+
+    pub fn with_library(mut self, library: LibraryReference) -> Self {
+        self.libraries.push(library);
+        self
+    }
+
+    /// Whether library items are reached by qualified names only, off by default.
+    ///
+    /// The references get the `Qualified access only` option, the bodies and declarations
+    /// name the library items as `Namespace.name` and the exported enumerations get
+    /// the `qualified_only` attribute.
+    pub fn with_qualified_only(mut self, qualified_only: bool) -> Self {
+        self.qualified_only = qualified_only;
+        self
+    }
+
+    pub fn libraries(&self) -> &[LibraryReference] {
+        &self.libraries
+    }
+
+    /// The items exported into the project, the ones not provided by a library.
+    pub fn exported(&self) -> impl Iterator<Item = &RegistryItem> {
+        self.items.iter().filter(|item| {
+            !item
+                .namespace()
+                .is_some_and(|ns| self.libraries.iter().any(|l| l.namespace == ns))
+        })
+    }
+
+    /// Renders the project document.
+    pub fn to_xml(&self) -> Result<String, Rust2PlcError> {
+        let mut library_items = HashMap::new();
+        for item in self.items.iter() {
+            if let Some(ns) = item.namespace() {
+                if self.libraries.iter().any(|l| l.namespace == ns) {
+                    library_items.insert(item.name().to_string(), ns.to_string());
+                }
+            }
+        }
+        let qualifier = Qualifier {
+            names: if self.qualified_only {
+                library_items
+            } else {
+                HashMap::new()
+            },
+        };
+        let project = self.exported().fold(self.project.clone(), |p, item| {
+            p.with_item(qualifier.item(item))
+        });
+        project.to_xml_with(self)
+    }
+}
+
+impl Extension for CodesysProject {
+    fn item_data(&self, item: &RegistryItem) -> Vec<DataEntry<'_>> {
+        let id = object_id(item.namespace(), item.name());
+        let mut data = vec![DataEntry {
+            name: OBJECT_ID,
+            handle_unknown: "discard",
+            content: Box::new(move |w| w.cdata("ObjectId", &[], &id)),
+        }];
+        let is_enum =
+            matches!(item, RegistryItem::StType(t) if matches!(t.value(), TypedValue::Enum(..)));
+        if self.qualified_only && is_enum {
+            data.push(DataEntry {
+                name: ATTRIBUTES,
+                handle_unknown: "implementation",
+                content: Box::new(|w| {
+                    w.open("Attributes", &[]);
+                    w.empty("Attribute", &[("Name", "qualified_only"), ("Value", "")]);
+                    w.close("Attributes");
+                }),
+            });
+        }
+        data
+    }
+
+    fn project_data(&self) -> Vec<DataEntry<'_>> {
+        let mut data = vec![DataEntry {
+            name: PROJECT_STRUCTURE,
+            handle_unknown: "discard",
+            content: Box::new(|w| self.write_structure(w)),
+        }];
+        if !self.libraries.is_empty() {
+            data.push(DataEntry {
+                name: LIBRARIES,
+                handle_unknown: "implementation",
+                content: Box::new(|w| self.write_libraries(w)),
+            });
+        }
+        data
+    }
+}
+
+impl CodesysProject {
+    /// The objects at the top, the namespaces as folders.
+    fn write_structure(&self, w: &mut XmlWriter) {
+        let mut folders: BTreeMap<&str, Vec<&RegistryItem>> = BTreeMap::new();
+        w.open("ProjectStructure", &[]);
+        for item in self.exported() {
+            match (item, item.namespace()) {
+                // Configurations are set up on the device, not in the POU pool.
+                (RegistryItem::StConfig(_), _) => {}
+                (_, Some(ns)) => folders.entry(ns).or_default().push(item),
+                (_, None) => write_object(w, item),
+            }
+        }
+        for (folder, items) in folders {
+            w.open("Folder", &[("Name", folder)]);
+            for item in items {
+                write_object(w, item);
+            }
+            w.close("Folder");
+        }
+        w.close("ProjectStructure");
+    }
+
+    fn write_libraries(&self, w: &mut XmlWriter) {
+        w.open("Libraries", &[]);
+        for library in self.libraries.iter() {
+            w.empty(
+                "Library",
+                &[
+                    ("Name", &library.placeholder()),
+                    ("Namespace", &library.namespace),
+                    ("HideWhenReferencedAsDependency", "false"),
+                    ("PublishSymbolsInContainer", "false"),
+                    ("SystemLibrary", "false"),
+                    ("LinkAllContent", "false"),
+                    ("DefaultResolution", &library.default_resolution()),
+                    ("QualifiedOnly", &self.qualified_only.to_string()),
+                ],
+            );
+        }
+        w.close("Libraries");
+    }
+}
+
+fn write_object(w: &mut XmlWriter, item: &RegistryItem) {
+    w.empty(
+        "Object",
+        &[
+            ("Name", item.name()),
+            ("ObjectId", &object_id(item.namespace(), item.name())),
+        ],
+    );
+}
+
+/// The object id, a version 5 UUID of the qualified name so that re-imports update the objects.
+fn object_id(namespace: Option<&str>, name: &str) -> String {
+    let root = Uuid::new_v5(&Uuid::NAMESPACE_URL, b"urn:rust2plc:codesys");
+    let qualified = match namespace {
+        Some(ns) => format!("{}.{}", ns, name),
+        None => name.to_string(),
+    };
+    Uuid::new_v5(&root, qualified.as_bytes()).to_string()
+}
+
+/// Qualifies the references to library items with their namespaces.
+struct Qualifier {
+    names: HashMap<String, String>,
+}
+
+impl Qualifier {
+    fn name(&self, name: &str) -> String {
+        match self.names.get(name) {
+            Some(ns) => format!("{}.{}", ns, name),
+            None => name.to_string(),
+        }
+    }
+
+    fn item(&self, item: &RegistryItem) -> RegistryItem {
+        if self.names.is_empty() {
+            return item.clone();
+        }
+        match item {
+            RegistryItem::StFn(f) => {
+                let locals = locals(f.inputs().iter().chain(f.outputs()).chain(f.locals()));
+                let qualified = Function::new(f.name(), self.value(f.return_value()))
+                    .with_body(self.stmts(f.body(), &locals))
+                    .with_description(f.description().map(String::from))
+                    .with_namespace(f.namespace().map(String::from))
+                    .with_version(f.version().map(String::from))
+                    .with_block(f.block().map(String::from));
+                let qualified = f
+                    .inputs()
+                    .iter()
+                    .fold(qualified, |f, v| f.with_input(self.var(v)));
+                let qualified = f
+                    .outputs()
+                    .iter()
+                    .fold(qualified, |f, v| f.with_output(self.var(v)));
+                RegistryItem::StFn(
+                    f.locals()
+                        .iter()
+                        .fold(qualified, |f, v| f.with_local(self.var(v))),
+                )
+            }
+            RegistryItem::StFb(fb) => {
+                let locals = locals(fb.inputs().iter().chain(fb.outputs()).chain(fb.vars()));
+                let qualified = FunctionBlock::new(fb.name())
+                    .with_body(self.stmts(fb.body(), &locals))
+                    .with_description(fb.description().map(String::from))
+                    .with_namespace(fb.namespace().map(String::from))
+                    .with_version(fb.version().map(String::from))
+                    .with_block(fb.block().map(String::from));
+                let qualified = fb
+                    .inputs()
+                    .iter()
+                    .fold(qualified, |fb, v| fb.with_input(self.var(v)));
+                let qualified = fb
+                    .outputs()
+                    .iter()
+                    .fold(qualified, |fb, v| fb.with_output(self.var(v)));
+                RegistryItem::StFb(
+                    fb.vars()
+                        .iter()
+                        .fold(qualified, |fb, v| fb.with_var(self.var(v))),
+                )
+            }
+            RegistryItem::StProgram(p) => {
+                let locals = locals(p.inputs().iter().chain(p.outputs()).chain(p.vars()));
+                let qualified = Program::new(p.name())
+                    .with_body(self.stmts(p.body(), &locals))
+                    .with_description(p.description().map(String::from))
+                    .with_namespace(p.namespace().map(String::from))
+                    .with_version(p.version().map(String::from))
+                    .with_block(p.block().map(String::from));
+                let qualified = p
+                    .inputs()
+                    .iter()
+                    .fold(qualified, |p, v| p.with_input(self.var(v)));
+                let qualified = p
+                    .outputs()
+                    .iter()
+                    .fold(qualified, |p, v| p.with_output(self.var(v)));
+                let qualified = p
+                    .vars()
+                    .iter()
+                    .fold(qualified, |p, v| p.with_var(self.var(v)));
+                RegistryItem::StProgram(match p.chart() {
+                    Some(chart) => qualified.with_chart(self.chart(chart, &locals)),
+                    None => qualified,
+                })
+            }
+            RegistryItem::StType(t) => match t.value() {
+                TypedValue::Struct(name, fields) => RegistryItem::StType(
+                    DataType::new_struct(
+                        name.clone(),
+                        fields
+                            .iter()
+                            .map(|(field, tv)| (field.clone(), self.value(tv)))
+                            .collect(),
+                    )
+                    .with_description(t.description().map(String::from))
+                    .with_namespace(t.namespace().map(String::from))
+                    .with_version(t.version().map(String::from)),
+                ),
+                _ => item.clone(),
+            },
+            RegistryItem::StConfig(c) => {
+                let qualified = Configuration::new(c.name())
+                    .with_description(c.description().map(String::from));
+                let qualified = c.globals().iter().fold(qualified, |c, v| {
+                    c.with_global(v.name().unwrap_or_default(), self.value(v.typed_value()))
+                });
+                RegistryItem::StConfig(
+                    c.resources()
+                        .iter()
+                        .fold(qualified, |c, r| c.with_resource(r.clone())),
+                )
+            }
+        }
+    }
+
+    fn var(&self, var: &Value) -> Value {
+        let tv = self.value(var.typed_value());
+        match var {
+            Value::Input(name, _) => Value::Input(name.clone(), tv),
+            Value::Output(name, _) => Value::Output(name.clone(), tv),
+            Value::InOut(name, _) => Value::InOut(name.clone(), tv),
+            Value::Local(name, _) => Value::Local(name.clone(), tv),
+            Value::Global(name, _) => Value::Global(name.clone(), tv),
+            Value::External(name, _) => Value::External(name.clone(), tv),
+            Value::Temporary(name, _) => Value::Temporary(name.clone(), tv),
+            Value::Constant(name, _) => Value::Constant(name.clone(), tv),
+            Value::Return(_) => Value::Return(tv),
+        }
+    }
+
+    fn value(&self, tv: &TypedValue) -> TypedValue {
+        match tv {
+            TypedValue::UserDefined(name, value) => TypedValue::UserDefined(
+                self.name(name),
+                value.as_ref().map(|v| Box::new(self.value(v))),
+            ),
+            TypedValue::Struct(name, fields) => TypedValue::Struct(
+                self.name(name),
+                fields
+                    .iter()
+                    .map(|(field, tv)| (field.clone(), self.value(tv)))
+                    .collect(),
+            ),
+            TypedValue::Enum(name, variants, value, initial) => TypedValue::Enum(
+                self.name(name),
+                variants.clone(),
+                value.clone(),
+                initial.clone(),
+            ),
+            TypedValue::Array(values, elem, lower) => TypedValue::Array(
+                values.iter().map(|v| Box::new(self.value(v))).collect(),
+                Box::new(self.value(elem)),
+                *lower,
+            ),
+            other => other.clone(),
+        }
+    }
+
+    fn chart(&self, chart: &Sfc, locals: &HashSet<String>) -> Sfc {
+        let qualified = chart
+            .steps()
+            .iter()
+            .cloned()
+            .fold(Sfc::new(), Sfc::with_step);
+        let qualified = chart.transitions().iter().fold(qualified, |sfc, t| {
+            let transition = Transition::new(
+                t.from().to_vec(),
+                t.to().to_vec(),
+                self.expr(t.condition(), locals),
+            );
+            sfc.with_transition(match t.priority() {
+                Some(priority) => transition.with_priority(priority),
+                None => transition,
+            })
+        });
+        chart.actions().iter().fold(qualified, |sfc, a| {
+            sfc.with_action(Action::new(a.name(), self.stmts(a.body(), locals)))
+        })
+    }
+
+    fn stmts(&self, body: &[Stmt], locals: &HashSet<String>) -> Vec<Stmt> {
+        body.iter().map(|s| self.stmt(s, locals)).collect()
+    }
+
+    fn stmt(&self, stmt: &Stmt, locals: &HashSet<String>) -> Stmt {
+        let otherwise =
+            |otherwise: &Option<Vec<Stmt>>| otherwise.as_ref().map(|body| self.stmts(body, locals));
+        match stmt {
+            Stmt::Assign { target, value } => Stmt::Assign {
+                target: self.expr(target, locals),
+                value: self.expr(value, locals),
+            },
+            Stmt::Call(call) => Stmt::Call(self.call(call, locals)),
+            Stmt::If {
+                branches,
+                otherwise: other,
+            } => Stmt::If {
+                branches: branches
+                    .iter()
+                    .map(|b| Branch {
+                        cond: self.expr(&b.cond, locals),
+                        body: self.stmts(&b.body, locals),
+                    })
+                    .collect(),
+                otherwise: otherwise(other),
+            },
+            Stmt::Case {
+                selector,
+                branches,
+                otherwise: other,
+            } => Stmt::Case {
+                selector: self.expr(selector, locals),
+                branches: branches
+                    .iter()
+                    .map(|b| CaseBranch {
+                        labels: b
+                            .labels
+                            .iter()
+                            .map(|label| match label {
+                                CaseLabel::Value(v) => CaseLabel::Value(self.expr(v, locals)),
+                                CaseLabel::Range(from, to) => {
+                                    CaseLabel::Range(self.expr(from, locals), self.expr(to, locals))
+                                }
+                            })
+                            .collect(),
+                        body: self.stmts(&b.body, locals),
+                    })
+                    .collect(),
+                otherwise: otherwise(other),
+            },
+            Stmt::For {
+                var,
+                from,
+                to,
+                by,
+                body,
+            } => Stmt::For {
+                var: var.clone(),
+                from: self.expr(from, locals),
+                to: self.expr(to, locals),
+                by: by.as_ref().map(|by| self.expr(by, locals)),
+                body: self.stmts(body, locals),
+            },
+            Stmt::While { cond, body } => Stmt::While {
+                cond: self.expr(cond, locals),
+                body: self.stmts(body, locals),
+            },
+            Stmt::Repeat { body, until } => Stmt::Repeat {
+                body: self.stmts(body, locals),
+                until: self.expr(until, locals),
+            },
+            Stmt::Return | Stmt::Exit | Stmt::Continue => stmt.clone(),
+        }
+    }
+
+    fn expr(&self, expr: &Expr, locals: &HashSet<String>) -> Expr {
+        let boxed = |e: &Expr| Box::new(self.expr(e, locals));
+        match expr {
+            Expr::Literal(Literal::Typed { ty, value }) => Expr::Literal(Literal::Typed {
+                ty: self.name(ty),
+                value: value.clone(),
+            }),
+            Expr::Literal(_) | Expr::Var(_) => expr.clone(),
+            Expr::Field(base, member) => Expr::Field(boxed(base), member.clone()),
+            Expr::Index(base, indices) => Expr::Index(
+                boxed(base),
+                indices.iter().map(|i| self.expr(i, locals)).collect(),
+            ),
+            Expr::Unary(op, operand) => Expr::Unary(*op, boxed(operand)),
+            Expr::Binary(op, lhs, rhs) => Expr::Binary(*op, boxed(lhs), boxed(rhs)),
+            Expr::Call(call) => Expr::Call(self.call(call, locals)),
+            Expr::Paren(inner) => Expr::Paren(boxed(inner)),
+        }
+    }
+
+    /// Instances are called by their own names, the functions of a library are qualified.
+    fn call(&self, call: &Call, locals: &HashSet<String>) -> Call {
+        let name = if locals.contains(&call.name) {
+            call.name.clone()
+        } else {
+            self.name(&call.name)
+        };
+        let args = call
+            .args
+            .iter()
+            .map(|arg| match arg {
+                Arg::Value(value) => Arg::Value(self.expr(value, locals)),
+                Arg::Input(name, value) => Arg::Input(name.clone(), self.expr(value, locals)),
+                Arg::Output(name, value) => Arg::Output(name.clone(), self.expr(value, locals)),
+            })
+            .collect();
+        Call { name, args }
+    }
+}
+
+fn locals<'a>(vars: impl Iterator<Item = &'a Value>) -> HashSet<String> {
+    vars.filter_map(|v| v.name()).map(String::from).collect()
+}
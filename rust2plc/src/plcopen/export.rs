@@ -0,0 +1,1164 @@
+use std::collections::{HashMap, VecDeque};
+
+use chrono::NaiveDateTime;
+
+use crate::error::Rust2PlcError;
+use crate::fbd::{Connection, Element, Fbd};
+use crate::il::Il;
+use crate::langs::PLCLang;
+use crate::ld::{Circuit, CoilKind, ContactKind, Ladder};
+use crate::plcopen::{XmlWriter, METADATA, TC6_NAMESPACE, XHTML_NAMESPACE};
+use crate::registry::{PLCRegistry, RegistryItem};
+use crate::sfc::{Sfc, Step, Transition};
+use crate::st::ast::{Printer, Stmt, VarBlock, VarSection};
+use crate::st::config::{Configuration, TaskTrigger};
+use crate::st::data_type::DataType;
+use crate::st::function::Function;
+use crate::types::TypedValue;
+use crate::var::Value;
+
+/// A PLCopen TC6 XML 2.01 project document.
+///
+/// Data types go to `<dataTypes>`, functions, function blocks and programs
+/// to `<pous>` with an ST body and configurations to `<instances>`.
+#[derive(Debug, Clone)]
+pub struct PlcOpenProject {
+    name: String,
+    company: String,
+    product: String,
+    product_version: String,
+    created: NaiveDateTime,
+    language: PLCLang,
+    items: Vec<RegistryItem>,
+}
+
+impl PlcOpenProject {
+    /// An empty project created now.
+    pub fn new(name: impl Into<String>) -> Self {
+        PlcOpenProject {
+            name: name.into(),
+            company: String::new(),
+            product: env!("CARGO_PKG_NAME").to_string(),
+            product_version: env!("CARGO_PKG_VERSION").to_string(),
+            created: chrono::Local::now().naive_local(),
+            language: PLCLang::StructuredText,
+            items: vec![],
+        }
+    }
+
+    pub fn with_company(mut self, company: impl Into<String>) -> Self {
+        self.company = company.into();
+        self
+    }
+
+    /// The tool named in the file header, `rust2plc` by default.
+    pub fn with_product(mut self, product: impl Into<String>, version: impl Into<String>) -> Self {
+        self.product = product.into();
+        self.product_version = version.into();
+        self
+    }
+
+    pub fn with_created(mut self, created: NaiveDateTime) -> Self {
+        self.created = created;
+        self
+    }
+
+    /// The language of the POU bodies, Structured Text by default.
+    ///
+    /// With [`PLCLang::LadderDiagram`] the bodies of boolean logic are drawn as LD networks,
+    /// see [`crate::ld`], with [`PLCLang::FunctionBlockDiagram`] the bodies of assignments
+    /// as FBD networks, see [`crate::fbd`], and with [`PLCLang::InstructionList`] the bodies
+    /// are lowered to IL, see [`crate::il`]. The other bodies stay in ST.
+    /// Programs with a [`crate::sfc::Sfc`] chart are always written as SFC.
+    pub fn with_language(mut self, language: PLCLang) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// Adds an item, the items are written in the order they are added.
+    pub fn with_item(mut self, item: RegistryItem) -> Self {
+        self.items.push(item);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn items(&self) -> &[RegistryItem] {
+        &self.items
+    }
+
+    /// Renders the `<project>` document.
+    pub fn to_xml(&self) -> Result<String, Rust2PlcError> {
+        self.to_xml_with(&())
+    }
+
+    /// Renders the document with the `addData` entries of a tool.
+    pub(crate) fn to_xml_with(&self, ext: &dyn Extension) -> Result<String, Rust2PlcError> {
+        if !matches!(
+            self.language,
+            PLCLang::StructuredText
+                | PLCLang::LadderDiagram
+                | PLCLang::FunctionBlockDiagram
+                | PLCLang::InstructionList
+        ) {
+            return Err(Rust2PlcError::Other(format!(
+                "{} bodies cannot be exported to PLCopen XML",
+                self.language
+            )));
+        }
+        let mut w = XmlWriter::new();
+        w.open(
+            "project",
+            &[("xmlns", TC6_NAMESPACE), ("xmlns:xhtml", XHTML_NAMESPACE)],
+        );
+        w.empty(
+            "fileHeader",
+            &[
+                ("companyName", &self.company),
+                ("productName", &self.product),
+                ("productVersion", &self.product_version),
+                (
+                    "creationDateTime",
+                    &self.created.format("%Y-%m-%dT%H:%M:%S").to_string(),
+                ),
+            ],
+        );
+        w.open("contentHeader", &[("name", &self.name)]);
+        w.open("coordinateInfo", &[]);
+        for language in ["fbd", "ld", "sfc"] {
+            w.open(language, &[]);
+            w.empty("scaling", &[("x", "1"), ("y", "1")]);
+            w.close(language);
+        }
+        w.close("coordinateInfo");
+        w.close("contentHeader");
+
+        w.open("types", &[]);
+        w.open("dataTypes", &[]);
+        for item in self.items.iter() {
+            if let RegistryItem::StType(t) = item {
+                write_data_type(&mut w, t, ext.item_data(item))?;
+            }
+        }
+        w.close("dataTypes");
+        w.open("pous", &[]);
+        for item in self.items.iter() {
+            match item {
+                RegistryItem::StFn(f) => write_pou(
+                    &mut w,
+                    Pou {
+                        name: f.name(),
+                        kind: "function",
+                        return_type: Some(f.return_value()),
+                        vars: f.inputs().iter().chain(f.outputs()).chain(f.locals()),
+                        body: f.body(),
+                        description: f.description(),
+                        namespace: f.namespace(),
+                        version: f.version(),
+                        data: ext.item_data(item),
+                        diagram: self.diagram(item),
+                    },
+                )?,
+                RegistryItem::StFb(fb) => write_pou(
+                    &mut w,
+                    Pou {
+                        name: fb.name(),
+                        kind: "functionBlock",
+                        return_type: None,
+                        vars: fb.inputs().iter().chain(fb.outputs()).chain(fb.vars()),
+                        body: fb.body(),
+                        description: fb.description(),
+                        namespace: fb.namespace(),
+                        version: fb.version(),
+                        data: ext.item_data(item),
+                        diagram: self.diagram(item),
+                    },
+                )?,
+                RegistryItem::StProgram(p) => {
+                    if let Some(chart) = p.chart() {
+                        chart.validate()?;
+                    }
+                    write_pou(
+                        &mut w,
+                        Pou {
+                            name: p.name(),
+                            kind: "program",
+                            return_type: None,
+                            vars: p.inputs().iter().chain(p.outputs()).chain(p.vars()),
+                            body: p.body(),
+                            description: p.description(),
+                            namespace: p.namespace(),
+                            version: p.version(),
+                            data: ext.item_data(item),
+                            diagram: self.diagram(item),
+                        },
+                    )?
+                }
+                RegistryItem::StConfig(_) | RegistryItem::StType(_) => {}
+            }
+        }
+        w.close("pous");
+        w.close("types");
+
+        w.open("instances", &[]);
+        w.open("configurations", &[]);
+        for item in self.items.iter() {
+            if let RegistryItem::StConfig(c) = item {
+                write_configuration(&mut w, c)?;
+            }
+        }
+        w.close("configurations");
+        w.close("instances");
+        write_add_data(&mut w, ext.project_data());
+        w.close("project");
+        Ok(w.finish())
+    }
+
+    fn diagram(&self, item: &RegistryItem) -> Option<Diagram> {
+        if let RegistryItem::StProgram(p) = item {
+            if let Some(chart) = p.chart() {
+                return Some(Diagram::Sfc(chart.clone()));
+            }
+        }
+        match self.language {
+            PLCLang::LadderDiagram => item.to_ladder().ok().map(Diagram::Ladder),
+            PLCLang::FunctionBlockDiagram => item.to_fbd().ok().map(Diagram::Fbd),
+            PLCLang::InstructionList => {
+                let body = match item {
+                    RegistryItem::StFn(f) => f.body(),
+                    RegistryItem::StFb(fb) => fb.body(),
+                    RegistryItem::StProgram(p) => p.body(),
+                    RegistryItem::StType(_) | RegistryItem::StConfig(_) => return None,
+                };
+                Il::lower(body).ok().map(Diagram::Il)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl PLCRegistry {
+    /// Renders the latest version of every item as a PLCopen XML project,
+    /// following [`PLCRegistry::declaration_order`].
+    pub fn to_plcopen_xml(&self, project: &str) -> Result<String, Rust2PlcError> {
+        self.declaration_order()
+            .into_iter()
+            .fold(PlcOpenProject::new(project), |p, item| {
+                p.with_item(item.clone())
+            })
+            .to_xml()
+    }
+}
+
+impl Function {
+    /// Renders the function as a PLCopen XML project of its own.
+    pub fn to_plcopen_xml(&self) -> Result<String, Rust2PlcError> {
+        PlcOpenProject::new(self.name())
+            .with_item(RegistryItem::StFn(self.clone()))
+            .to_xml()
+    }
+}
+
+/// A `<data>` entry of `addData`, the content is written by the closure.
+pub(crate) struct DataEntry<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) handle_unknown: &'a str,
+    pub(crate) content: Box<dyn Fn(&mut XmlWriter) + 'a>,
+}
+
+/// The `addData` a tool adds to the items and the project.
+pub(crate) trait Extension {
+    fn item_data(&self, item: &RegistryItem) -> Vec<DataEntry<'_>>;
+
+    fn project_data(&self) -> Vec<DataEntry<'_>>;
+}
+
+impl Extension for () {
+    fn item_data(&self, _item: &RegistryItem) -> Vec<DataEntry<'_>> {
+        vec![]
+    }
+
+    fn project_data(&self) -> Vec<DataEntry<'_>> {
+        vec![]
+    }
+}
+
+struct Pou<'a, V> {
+    name: &'a str,
+    kind: &'a str,
+    return_type: Option<&'a TypedValue>,
+    vars: V,
+    body: &'a [Stmt],
+    description: Option<&'a str>,
+    namespace: Option<&'a str>,
+    version: Option<&'a str>,
+    data: Vec<DataEntry<'a>>,
+    diagram: Option<Diagram>,
+}
+
+/// A body not written in ST.
+enum Diagram {
+    Ladder(Ladder),
+    Fbd(Fbd),
+    Sfc(Sfc),
+    Il(Il),
+}
+
+fn write_pou<'a>(
+    w: &mut XmlWriter,
+    pou: Pou<'a, impl Iterator<Item = &'a Value>>,
+) -> Result<(), Rust2PlcError> {
+    w.open("pou", &[("name", pou.name), ("pouType", pou.kind)]);
+    w.open("interface", &[]);
+    if let Some(return_type) = pou.return_type {
+        w.open("returnType", &[]);
+        write_type(w, return_type);
+        w.close("returnType");
+    }
+    for block in VarBlock::group(pou.vars) {
+        let (list, attrs): (&str, &[(&str, &str)]) = match block.section {
+            VarSection::Input => ("inputVars", &[]),
+            VarSection::InOut => ("inOutVars", &[]),
+            VarSection::Output => ("outputVars", &[]),
+            VarSection::External => ("externalVars", &[]),
+            VarSection::Var => ("localVars", &[]),
+            VarSection::Temp => ("tempVars", &[]),
+            VarSection::Constant => ("localVars", &[("constant", "true")]),
+            VarSection::Global => ("globalVars", &[]),
+        };
+        w.open(list, attrs);
+        write_variables(w, &block.vars)?;
+        w.close(list);
+    }
+    w.close("interface");
+    if let Some(Diagram::Sfc(chart)) = &pou.diagram {
+        write_sfc_actions(w, chart);
+    }
+    w.open("body", &[]);
+    match &pou.diagram {
+        Some(Diagram::Ladder(ladder)) => write_ladder(w, ladder),
+        Some(Diagram::Fbd(fbd)) => write_fbd(w, fbd),
+        Some(Diagram::Sfc(chart)) => write_sfc(w, chart),
+        Some(Diagram::Il(il)) => {
+            w.open("IL", &[]);
+            w.paragraph(il.to_text(&Printer::default(), 0).trim_end());
+            w.close("IL");
+        }
+        None => {
+            w.open("ST", &[]);
+            w.paragraph(Printer::default().stmts(pou.body, 0).trim_end());
+            w.close("ST");
+        }
+    }
+    w.close("body");
+    let mut data: Vec<DataEntry> = metadata(pou.namespace, pou.version).into_iter().collect();
+    data.extend(pou.data);
+    write_add_data(w, data);
+    write_documentation(w, pou.description);
+    w.close("pou");
+    Ok(())
+}
+
+/// The grid of the LD layout, contacts and coils take one cell.
+const LD_CELL_WIDTH: usize = 60;
+const LD_CELL_HEIGHT: usize = 40;
+
+/// Every rung is a network between its own power rails, the coils in the column after
+/// the widest circuit. Parallel branches join at the `connectionPointIn` of the next element.
+fn write_ladder(w: &mut XmlWriter, ladder: &Ladder) {
+    let coil_column = ladder
+        .rungs()
+        .iter()
+        .map(|rung| ld_size(&rung.circuit).0)
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let mut ids = 0;
+    let mut row = 0;
+    w.open("LD", &[]);
+    for rung in ladder.rungs() {
+        ids += 1;
+        let rail = ids;
+        w.open("leftPowerRail", &[("localId", &rail.to_string())]);
+        ld_position(w, 0, row);
+        w.empty("connectionPointOut", &[("formalParameter", "")]);
+        w.close("leftPowerRail");
+        let (outputs, height) = write_circuit(w, &rung.circuit, &[rail], 1, row, &mut ids);
+        let mut coils = vec![];
+        for (i, coil) in rung.coils.iter().enumerate() {
+            ids += 1;
+            let storage = match coil.kind {
+                CoilKind::Normal => "none",
+                CoilKind::Set => "set",
+                CoilKind::Reset => "reset",
+            };
+            w.open(
+                "coil",
+                &[
+                    ("localId", &ids.to_string()),
+                    ("negated", "false"),
+                    ("storage", storage),
+                ],
+            );
+            ld_position(w, coil_column, row + i);
+            ld_connections(w, &outputs);
+            w.empty("connectionPointOut", &[]);
+            w.text("variable", &coil.var);
+            w.close("coil");
+            coils.push(ids);
+        }
+        ids += 1;
+        w.open("rightPowerRail", &[("localId", &ids.to_string())]);
+        ld_position(w, coil_column + 1, row);
+        for coil in coils {
+            ld_connections(w, &[coil]);
+        }
+        w.close("rightPowerRail");
+        row += height.max(rung.coils.len()) + 1;
+    }
+    w.close("LD");
+}
+
+/// Writes the contacts fed by `inputs` from the cell at `column` and `row`,
+/// gives the ids feeding the next element and the rows taken.
+fn write_circuit(
+    w: &mut XmlWriter,
+    circuit: &Circuit,
+    inputs: &[usize],
+    column: usize,
+    row: usize,
+    ids: &mut usize,
+) -> (Vec<usize>, usize) {
+    match circuit {
+        Circuit::Contact(kind, var) => {
+            *ids += 1;
+            let (negated, edge) = match kind {
+                ContactKind::NormallyOpen => ("false", "none"),
+                ContactKind::NormallyClosed => ("true", "none"),
+                ContactKind::Rising => ("false", "rising"),
+                ContactKind::Falling => ("false", "falling"),
+            };
+            w.open(
+                "contact",
+                &[
+                    ("localId", &ids.to_string()),
+                    ("negated", negated),
+                    ("edge", edge),
+                ],
+            );
+            ld_position(w, column, row);
+            ld_connections(w, inputs);
+            w.empty("connectionPointOut", &[]);
+            w.text("variable", var);
+            w.close("contact");
+            (vec![*ids], 1)
+        }
+        Circuit::Series(items) => {
+            let (mut outputs, mut column, mut height) = (inputs.to_vec(), column, 1);
+            for item in items {
+                let (out, rows) = write_circuit(w, item, &outputs, column, row, ids);
+                outputs = out;
+                column += ld_size(item).0;
+                height = height.max(rows);
+            }
+            (outputs, height)
+        }
+        Circuit::Parallel(branches) => {
+            let (mut outputs, mut rows) = (vec![], 0);
+            for branch in branches {
+                let (out, height) = write_circuit(w, branch, inputs, column, row + rows, ids);
+                outputs.extend(out);
+                rows += height;
+            }
+            (outputs, rows)
+        }
+    }
+}
+
+/// The columns and rows a circuit takes on the grid.
+fn ld_size(circuit: &Circuit) -> (usize, usize) {
+    match circuit {
+        Circuit::Contact(_, _) => (1, 1),
+        Circuit::Series(items) => items
+            .iter()
+            .map(ld_size)
+            .fold((0, 1), |(w, h), (iw, ih)| (w + iw, h.max(ih))),
+        Circuit::Parallel(branches) => branches
+            .iter()
+            .map(ld_size)
+            .fold((0, 0), |(w, h), (bw, bh)| (w.max(bw), h + bh)),
+    }
+}
+
+fn ld_position(w: &mut XmlWriter, column: usize, row: usize) {
+    w.empty(
+        "position",
+        &[
+            ("x", &(column * LD_CELL_WIDTH).to_string()),
+            ("y", &(row * LD_CELL_HEIGHT).to_string()),
+        ],
+    );
+}
+
+fn ld_connections(w: &mut XmlWriter, refs: &[usize]) {
+    w.open("connectionPointIn", &[]);
+    for id in refs {
+        w.empty("connection", &[("refLocalId", &id.to_string())]);
+    }
+    w.close("connectionPointIn");
+}
+
+/// The nodes with the positions of the [`crate::fbd`] layout, the pins relative to the nodes.
+fn write_fbd(w: &mut XmlWriter, fbd: &Fbd) {
+    w.open("FBD", &[]);
+    for node in fbd.nodes() {
+        let id = node.id.to_string();
+        let (width, height) = node.size();
+        let (width, height) = (width.to_string(), height.to_string());
+        let (x, y) = node.position();
+        let position = [("x", x.to_string()), ("y", y.to_string())];
+        let position: Vec<(&str, &str)> = position.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        match &node.element {
+            Element::InVariable(expr) => {
+                w.open(
+                    "inVariable",
+                    &[("localId", &id), ("height", &height), ("width", &width)],
+                );
+                w.empty("position", &position);
+                w.open("connectionPointOut", &[]);
+                fbd_rel_position(w, node.output_pin(0));
+                w.close("connectionPointOut");
+                w.text("expression", expr);
+                w.close("inVariable");
+            }
+            Element::OutVariable(expr, from) => {
+                w.open(
+                    "outVariable",
+                    &[("localId", &id), ("height", &height), ("width", &width)],
+                );
+                w.empty("position", &position);
+                fbd_connection(w, node.input_pin(0), from);
+                w.text("expression", expr);
+                w.close("outVariable");
+            }
+            Element::Block {
+                type_name,
+                instance,
+                inputs,
+                outputs,
+            } => {
+                let mut attrs = vec![("localId", id.as_str()), ("typeName", type_name)];
+                if let Some(instance) = instance {
+                    attrs.push(("instanceName", instance));
+                }
+                attrs.extend([("height", height.as_str()), ("width", width.as_str())]);
+                w.open("block", &attrs);
+                w.empty("position", &position);
+                w.open("inputVariables", &[]);
+                for (i, (pin, from)) in inputs.iter().enumerate() {
+                    w.open("variable", &[("formalParameter", pin)]);
+                    fbd_connection(w, node.input_pin(i), from);
+                    w.close("variable");
+                }
+                w.close("inputVariables");
+                w.empty("inOutVariables", &[]);
+                w.open("outputVariables", &[]);
+                for (i, pin) in outputs.iter().enumerate() {
+                    w.open("variable", &[("formalParameter", pin)]);
+                    w.open("connectionPointOut", &[]);
+                    fbd_rel_position(w, node.output_pin(i));
+                    w.close("connectionPointOut");
+                    w.close("variable");
+                }
+                w.close("outputVariables");
+                w.close("block");
+            }
+        }
+    }
+    w.close("FBD");
+}
+
+fn fbd_connection(w: &mut XmlWriter, pin: (usize, usize), from: &Connection) {
+    w.open("connectionPointIn", &[]);
+    fbd_rel_position(w, pin);
+    let node = from.node.to_string();
+    let mut attrs = vec![("refLocalId", node.as_str())];
+    if let Some(pin) = &from.pin {
+        attrs.push(("formalParameter", pin));
+    }
+    w.empty("connection", &attrs);
+    w.close("connectionPointIn");
+}
+
+fn fbd_rel_position(w: &mut XmlWriter, (x, y): (usize, usize)) {
+    w.empty(
+        "relPosition",
+        &[("x", &x.to_string()), ("y", &y.to_string())],
+    );
+}
+
+/// The grid of the SFC layout, the action blocks sit right of their steps.
+const SFC_COLUMN_WIDTH: usize = 160;
+const SFC_ROW_HEIGHT: usize = 60;
+const SFC_ACTION_OFFSET: usize = 80;
+const SFC_ACTION_HEIGHT: usize = 20;
+
+/// An element of the SFC network, `inputs` are the ids of the elements before it.
+struct SfcNode<'a> {
+    id: usize,
+    kind: SfcKind<'a>,
+    inputs: Vec<usize>,
+}
+
+enum SfcKind<'a> {
+    Step(&'a Step),
+    Transition(&'a Transition),
+    SelectionDivergence(usize),
+    SelectionConvergence,
+    SimultaneousDivergence(usize),
+    SimultaneousConvergence,
+}
+
+/// Links the steps through their transitions.
+///
+/// A step with several transitions leaving it diverges into a selection, a step entered
+/// by several transitions converges from one. Transitions from or to several steps
+/// converge and diverge simultaneously.
+fn sfc_network(chart: &Sfc) -> Vec<SfcNode<'_>> {
+    let mut nodes: Vec<SfcNode> = chart
+        .steps()
+        .iter()
+        .enumerate()
+        .map(|(i, step)| SfcNode {
+            id: i + 1,
+            kind: SfcKind::Step(step),
+            inputs: vec![],
+        })
+        .collect();
+    let ids: HashMap<&str, usize> = nodes
+        .iter()
+        .map(|n| match n.kind {
+            SfcKind::Step(step) => (step.name(), n.id),
+            _ => unreachable!(),
+        })
+        .collect();
+    let mut next = nodes.len();
+
+    let mut exits = HashMap::new();
+    for step in chart.steps() {
+        let id = ids[step.name()];
+        let alternatives = chart.outgoing(step.name()).count();
+        if alternatives > 1 {
+            next += 1;
+            nodes.push(SfcNode {
+                id: next,
+                kind: SfcKind::SelectionDivergence(alternatives),
+                inputs: vec![id],
+            });
+            exits.insert(step.name(), next);
+        } else {
+            exits.insert(step.name(), id);
+        }
+    }
+
+    let mut entries: HashMap<&str, Vec<usize>> = HashMap::new();
+    for transition in chart.transitions() {
+        let input = match transition.from() {
+            [step] => exits[step.as_str()],
+            steps => {
+                next += 1;
+                nodes.push(SfcNode {
+                    id: next,
+                    kind: SfcKind::SimultaneousConvergence,
+                    inputs: steps.iter().map(|s| exits[s.as_str()]).collect(),
+                });
+                next
+            }
+        };
+        next += 1;
+        nodes.push(SfcNode {
+            id: next,
+            kind: SfcKind::Transition(transition),
+            inputs: vec![input],
+        });
+        let output = match transition.to() {
+            [_] => next,
+            steps => {
+                next += 1;
+                nodes.push(SfcNode {
+                    id: next,
+                    kind: SfcKind::SimultaneousDivergence(steps.len()),
+                    inputs: vec![next - 1],
+                });
+                next
+            }
+        };
+        for step in transition.to() {
+            entries.entry(step.as_str()).or_default().push(output);
+        }
+    }
+
+    for step in chart.steps() {
+        let inputs = entries.remove(step.name()).unwrap_or_default();
+        let inputs = if inputs.len() > 1 {
+            next += 1;
+            nodes.push(SfcNode {
+                id: next,
+                kind: SfcKind::SelectionConvergence,
+                inputs,
+            });
+            vec![next]
+        } else {
+            inputs
+        };
+        nodes[ids[step.name()] - 1].inputs = inputs;
+    }
+    nodes
+}
+
+/// A plain top-to-bottom layout, the row is the distance from the initial step
+/// and the elements of a row are placed side by side.
+fn sfc_layout(nodes: &[SfcNode]) -> HashMap<usize, (usize, usize)> {
+    let mut successors: HashMap<usize, Vec<usize>> = HashMap::new();
+    for node in nodes {
+        for input in node.inputs.iter() {
+            successors.entry(*input).or_default().push(node.id);
+        }
+    }
+    let mut rows = HashMap::new();
+    let mut queue: VecDeque<usize> = nodes
+        .iter()
+        .filter(|n| matches!(n.kind, SfcKind::Step(step) if step.is_initial()))
+        .map(|n| n.id)
+        .collect();
+    for id in queue.iter() {
+        rows.insert(*id, 0);
+    }
+    while let Some(id) = queue.pop_front() {
+        let row = rows[&id] + 1;
+        for next in successors.get(&id).into_iter().flatten() {
+            if !rows.contains_key(next) {
+                rows.insert(*next, row);
+                queue.push_back(*next);
+            }
+        }
+    }
+    let mut last = rows.values().max().copied().unwrap_or(0);
+    let mut columns: HashMap<usize, usize> = HashMap::new();
+    let mut positions = HashMap::new();
+    for node in nodes {
+        let row = *rows.entry(node.id).or_insert_with(|| {
+            last += 1;
+            last
+        });
+        let column = columns.entry(row).or_default();
+        positions.insert(node.id, (*column * SFC_COLUMN_WIDTH, row * SFC_ROW_HEIGHT));
+        *column += 1;
+    }
+    positions
+}
+
+/// The actions of the chart, written to the `actions` of the POU.
+fn write_sfc_actions(w: &mut XmlWriter, chart: &Sfc) {
+    if chart.actions().is_empty() {
+        return;
+    }
+    w.open("actions", &[]);
+    for action in chart.actions() {
+        w.open("action", &[("name", action.name())]);
+        w.open("body", &[]);
+        w.open("ST", &[]);
+        w.paragraph(Printer::default().stmts(action.body(), 0).trim_end());
+        w.close("ST");
+        w.close("body");
+        w.close("action");
+    }
+    w.close("actions");
+}
+
+/// The steps, transitions and branches of [`sfc_network`], the qualifiers of
+/// the associations in the action blocks next to the steps.
+fn write_sfc(w: &mut XmlWriter, chart: &Sfc) {
+    let nodes = sfc_network(chart);
+    let positions = sfc_layout(&nodes);
+    let mut next = nodes.len();
+    w.open("SFC", &[]);
+    for node in nodes.iter() {
+        let id = node.id.to_string();
+        let (x, y) = positions[&node.id];
+        let position = |w: &mut XmlWriter, dx: usize| {
+            w.empty(
+                "position",
+                &[("x", &(x + dx).to_string()), ("y", &y.to_string())],
+            )
+        };
+        match node.kind {
+            SfcKind::Step(step) => {
+                let initial = if step.is_initial() { "true" } else { "false" };
+                w.open(
+                    "step",
+                    &[
+                        ("localId", &id),
+                        ("name", step.name()),
+                        ("initialStep", initial),
+                    ],
+                );
+                position(w, 0);
+                sfc_connections(w, &node.inputs);
+                w.empty("connectionPointOut", &[]);
+                if !step.actions().is_empty() {
+                    w.empty("connectionPointOutAction", &[]);
+                }
+                w.close("step");
+                if step.actions().is_empty() {
+                    continue;
+                }
+                next += 1;
+                w.open("actionBlock", &[("localId", &next.to_string())]);
+                position(w, SFC_ACTION_OFFSET);
+                sfc_connections(w, &[node.id]);
+                for (i, association) in step.actions().iter().enumerate() {
+                    let mut attrs = vec![
+                        ("localId", "0"),
+                        ("qualifier", association.qualifier.name()),
+                    ];
+                    let duration = association.qualifier.duration_literal();
+                    if let Some(duration) = &duration {
+                        attrs.push(("duration", duration));
+                    }
+                    w.open("action", &attrs);
+                    fbd_rel_position(w, (0, i * SFC_ACTION_HEIGHT));
+                    w.empty("reference", &[("name", &association.action)]);
+                    w.close("action");
+                }
+                w.close("actionBlock");
+            }
+            SfcKind::Transition(transition) => {
+                let priority = transition.priority().map(|p| p.to_string());
+                let mut attrs = vec![("localId", id.as_str())];
+                if let Some(priority) = &priority {
+                    attrs.push(("priority", priority));
+                }
+                w.open("transition", &attrs);
+                position(w, 0);
+                sfc_connections(w, &node.inputs);
+                w.empty("connectionPointOut", &[]);
+                w.open("condition", &[]);
+                w.open("inline", &[("name", "")]);
+                w.open("ST", &[]);
+                w.paragraph(&Printer::default().expr(transition.condition()));
+                w.close("ST");
+                w.close("inline");
+                w.close("condition");
+                w.close("transition");
+            }
+            SfcKind::SelectionDivergence(outputs) | SfcKind::SimultaneousDivergence(outputs) => {
+                let tag = match node.kind {
+                    SfcKind::SelectionDivergence(_) => "selectionDivergence",
+                    _ => "simultaneousDivergence",
+                };
+                w.open(tag, &[("localId", &id)]);
+                position(w, 0);
+                sfc_connections(w, &node.inputs);
+                for i in 0..outputs {
+                    w.open("connectionPointOut", &[("formalParameter", "")]);
+                    fbd_rel_position(w, (i * SFC_COLUMN_WIDTH, 0));
+                    w.close("connectionPointOut");
+                }
+                w.close(tag);
+            }
+            SfcKind::SelectionConvergence | SfcKind::SimultaneousConvergence => {
+                let tag = match node.kind {
+                    SfcKind::SelectionConvergence => "selectionConvergence",
+                    _ => "simultaneousConvergence",
+                };
+                w.open(tag, &[("localId", &id)]);
+                position(w, 0);
+                for input in node.inputs.iter() {
+                    sfc_connections(w, &[*input]);
+                }
+                w.empty("connectionPointOut", &[]);
+                w.close(tag);
+            }
+        }
+    }
+    w.close("SFC");
+}
+
+fn sfc_connections(w: &mut XmlWriter, refs: &[usize]) {
+    if !refs.is_empty() {
+        ld_connections(w, refs);
+    }
+}
+
+fn write_data_type(
+    w: &mut XmlWriter,
+    t: &DataType,
+    extra: Vec<DataEntry>,
+) -> Result<(), Rust2PlcError> {
+    w.open("dataType", &[("name", t.name())]);
+    w.open("baseType", &[]);
+    match t.value() {
+        TypedValue::Struct(_, fields) => {
+            w.open("struct", &[]);
+            for (field, value) in fields.iter() {
+                write_variable(w, field, value)?;
+            }
+            w.close("struct");
+        }
+        TypedValue::Enum(name, variants, ..) => {
+            if variants.is_empty() {
+                return Err(Rust2PlcError::Other(format!(
+                    "enumerated type {} has no variants",
+                    name
+                )));
+            }
+            w.open("enum", &[]);
+            w.open("values", &[]);
+            let values = t.values().iter().chain(std::iter::repeat(&None));
+            for (variant, value) in variants.iter().zip(values) {
+                match value {
+                    Some(value) => {
+                        w.empty("value", &[("name", variant), ("value", &value.to_string())])
+                    }
+                    None => w.empty("value", &[("name", variant)]),
+                }
+            }
+            w.close("values");
+            if let Some(base) = t.base() {
+                w.open("baseType", &[]);
+                write_type(w, base);
+                w.close("baseType");
+            }
+            w.close("enum");
+        }
+        other => {
+            return Err(Rust2PlcError::Other(format!(
+                "{} cannot be declared as a data type",
+                other.to_plc_type()
+            )))
+        }
+    }
+    w.close("baseType");
+    if let TypedValue::Enum(..) = t.value() {
+        w.open("initialValue", &[]);
+        write_value(w, t.value())?;
+        w.close("initialValue");
+    }
+    let mut data: Vec<DataEntry> = metadata(t.namespace(), t.version()).into_iter().collect();
+    data.extend(extra);
+    write_add_data(w, data);
+    write_documentation(w, t.description());
+    w.close("dataType");
+    Ok(())
+}
+
+fn write_configuration(w: &mut XmlWriter, c: &Configuration) -> Result<(), Rust2PlcError> {
+    c.validate()?;
+    w.open("configuration", &[("name", c.name())]);
+    for resource in c.resources() {
+        w.open("resource", &[("name", resource.name())]);
+        for task in resource.tasks() {
+            let priority = task.priority().to_string();
+            let trigger = match task.trigger() {
+                TaskTrigger::Cyclic(interval) => {
+                    ("interval", TypedValue::Time(*interval).to_plc_literal()?)
+                }
+                TaskTrigger::Event(var) => ("single", var.clone()),
+            };
+            let attrs = [
+                ("name", task.name()),
+                (trigger.0, trigger.1.as_str()),
+                ("priority", priority.as_str()),
+            ];
+            let programs: Vec<_> = resource
+                .programs()
+                .iter()
+                .filter(|p| p.task() == Some(task.name()))
+                .collect();
+            if programs.is_empty() {
+                w.empty("task", &attrs);
+            } else {
+                w.open("task", &attrs);
+                for program in programs {
+                    w.empty(
+                        "pouInstance",
+                        &[("name", program.name()), ("typeName", program.program())],
+                    );
+                }
+                w.close("task");
+            }
+        }
+        for program in resource.programs().iter().filter(|p| p.task().is_none()) {
+            w.empty(
+                "pouInstance",
+                &[("name", program.name()), ("typeName", program.program())],
+            );
+        }
+        w.close("resource");
+    }
+    if !c.globals().is_empty() {
+        w.open("globalVars", &[]);
+        write_variables(w, c.globals())?;
+        w.close("globalVars");
+    }
+    write_documentation(w, c.description());
+    w.close("configuration");
+    Ok(())
+}
+
+fn write_variables(w: &mut XmlWriter, vars: &[Value]) -> Result<(), Rust2PlcError> {
+    for var in vars {
+        write_variable(w, var.name().unwrap_or_default(), var.typed_value())?;
+    }
+    Ok(())
+}
+
+/// A variable, the initial value is written only where it differs from the default of the type.
+fn write_variable(w: &mut XmlWriter, name: &str, tv: &TypedValue) -> Result<(), Rust2PlcError> {
+    w.open("variable", &[("name", name)]);
+    w.open("type", &[]);
+    write_type(w, tv);
+    w.close("type");
+    if *tv != tv.default_value() {
+        w.open("initialValue", &[]);
+        write_value(w, tv)?;
+        w.close("initialValue");
+    }
+    w.close("variable");
+    Ok(())
+}
+
+fn write_type(w: &mut XmlWriter, tv: &TypedValue) {
+    match tv {
+        TypedValue::Bool(_) => w.empty("BOOL", &[]),
+        TypedValue::SInt(_) => w.empty("SINT", &[]),
+        TypedValue::Int(_) => w.empty("INT", &[]),
+        TypedValue::DInt(_) => w.empty("DINT", &[]),
+        TypedValue::LInt(_) => w.empty("LINT", &[]),
+        TypedValue::USInt(_) => w.empty("USINT", &[]),
+        TypedValue::UInt(_) => w.empty("UINT", &[]),
+        TypedValue::UDInt(_) => w.empty("UDINT", &[]),
+        TypedValue::ULInt(_) => w.empty("ULINT", &[]),
+        TypedValue::Real(_) => w.empty("REAL", &[]),
+        TypedValue::LReal(_) => w.empty("LREAL", &[]),
+        TypedValue::Time(_) => w.empty("TIME", &[]),
+        TypedValue::Date(_) => w.empty("DATE", &[]),
+        TypedValue::TimeOfDay(_) => w.empty("TOD", &[]),
+        TypedValue::DateTime(_) => w.empty("DT", &[]),
+        TypedValue::Byte(_) => w.empty("BYTE", &[]),
+        TypedValue::Word(_) => w.empty("WORD", &[]),
+        TypedValue::DWord(_) => w.empty("DWORD", &[]),
+        TypedValue::LWord(_) => w.empty("LWORD", &[]),
+        TypedValue::String(_, Some(len)) => w.empty("string", &[("length", &len.to_string())]),
+        TypedValue::String(_, None) => w.empty("string", &[]),
+        TypedValue::WString(_, Some(len)) => w.empty("wstring", &[("length", &len.to_string())]),
+        TypedValue::WString(_, None) => w.empty("wstring", &[]),
+        // TC6 has no elements for the character types, tools know them by name.
+        TypedValue::Char(_) | TypedValue::WChar(_) => {
+            w.empty("derived", &[("name", &tv.to_plc_type())])
+        }
+        TypedValue::UserDefined(name, _)
+        | TypedValue::Struct(name, _)
+        | TypedValue::Enum(name, ..) => w.empty("derived", &[("name", name)]),
+        TypedValue::Array(values, elem, lower) => {
+            w.open("array", &[]);
+            w.empty(
+                "dimension",
+                &[
+                    ("lower", &lower.to_string()),
+                    ("upper", &(lower + values.len() as i64 - 1).to_string()),
+                ],
+            );
+            w.open("baseType", &[]);
+            write_type(w, elem);
+            w.close("baseType");
+            w.close("array");
+        }
+    }
+}
+
+fn write_value(w: &mut XmlWriter, tv: &TypedValue) -> Result<(), Rust2PlcError> {
+    match tv {
+        TypedValue::Struct(_, fields) => {
+            w.open("structValue", &[]);
+            for (field, value) in fields.iter() {
+                w.open("value", &[("member", field)]);
+                write_value(w, value)?;
+                w.close("value");
+            }
+            w.close("structValue");
+        }
+        TypedValue::Array(values, ..) => {
+            w.open("arrayValue", &[]);
+            for value in values.iter() {
+                w.open("value", &[]);
+                write_value(w, value)?;
+                w.close("value");
+            }
+            w.close("arrayValue");
+        }
+        TypedValue::Enum(name, variants, value, _) => {
+            if !variants.contains(value) {
+                return Err(Rust2PlcError::Other(format!(
+                    "{} is not a variant of {}",
+                    value, name
+                )));
+            }
+            w.empty("simpleValue", &[("value", value)]);
+        }
+        TypedValue::UserDefined(_, Some(value)) => write_value(w, value)?,
+        other => w.empty("simpleValue", &[("value", &other.to_plc_literal()?)]),
+    }
+    Ok(())
+}
+
+/// The namespace and the version of an item, `None` when it has neither.
+fn metadata<'a>(namespace: Option<&'a str>, version: Option<&'a str>) -> Option<DataEntry<'a>> {
+    if namespace.is_none() && version.is_none() {
+        return None;
+    }
+    Some(DataEntry {
+        name: METADATA,
+        handle_unknown: "preserve",
+        content: Box::new(move |w| {
+            let mut attrs = vec![];
+            if let Some(namespace) = namespace {
+                attrs.push(("namespace", namespace));
+            }
+            if let Some(version) = version {
+                attrs.push(("version", version));
+            }
+            w.empty("metadata", &attrs);
+        }),
+    })
+}
+
+fn write_add_data(w: &mut XmlWriter, data: Vec<DataEntry>) {
+    if data.is_empty() {
+        return;
+    }
+    w.open("addData", &[]);
+    for entry in data {
+        w.open(
+            "data",
+            &[
+                ("name", entry.name),
+                ("handleUnknown", entry.handle_unknown),
+            ],
+        );
+        (entry.content)(w);
+        w.close("data");
+    }
+    w.close("addData");
+}
+
+fn write_documentation(w: &mut XmlWriter, description: Option<&str>) {
+    if let Some(description) = description {
+        w.open("documentation", &[]);
+        w.paragraph(description);
+        w.close("documentation");
+    }
+}
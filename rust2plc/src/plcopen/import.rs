@@ -0,0 +1,505 @@
+//! Reads PLCopen TC6 XML projects into the model the macros produce.
+//!
+//! Data types, functions, function blocks and programs with ST bodies and
+//! configurations are imported, `<documentation>` becomes the description
+//! and initial values are parsed into [`TypedValue`]s. Bodies in the graphical
+//! languages or IL are rejected. Failures are reported as
+//! [`Rust2PlcError::ParseError`] with the line and column in the XML document.
+//!
+//! ```
+//! use rust2plc::plcopen::import;
+//! use rust2plc::registry::RegistryItem;
+//!
+//! let items = import::parse(
+//!     r#"<project xmlns="http://www.plcopen.org/xml/tc6_0201">
+//!   <types>
+//!     <dataTypes/>
+//!     <pous>
+//!       <pou name="twice" pouType="function">
+//!         <interface>
+//!           <returnType><INT/></returnType>
+//!           <inputVars>
+//!             <variable name="x"><type><INT/></type></variable>
+//!           </inputVars>
+//!         </interface>
+//!         <body><ST><xhtml:p xmlns:xhtml="http://www.w3.org/1999/xhtml">twice := x * 2;</xhtml:p></ST></body>
+//!       </pou>
+//!     </pous>
+//!   </types>
+//! </project>"#,
+//! )
+//! .unwrap();
+//! let RegistryItem::StFn(twice) = &items[0] else { unreachable!() };
+//! assert_eq!(twice.to_st(), "FUNCTION twice : INT\nVAR_INPUT\n    x : INT;\nEND_VAR\n    twice := x * 2;\nEND_FUNCTION\n");
+//! ```
+//!
+//! Resources carry no processing unit in TC6, imported resources run on `PLC`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::error::{Position, Rust2PlcError};
+use crate::plcopen::{read, Element, METADATA};
+use crate::registry::{PLCRegistry, RegistryItem};
+use crate::st::ast::Stmt;
+use crate::st::config::{Configuration, Resource, Task};
+use crate::st::data_type::DataType;
+use crate::st::function::Function;
+use crate::st::function_block::FunctionBlock;
+use crate::st::parser;
+use crate::st::program::Program;
+use crate::types::TypedValue;
+use crate::var::Value;
+
+/// Parses the data types, POUs and configurations of a project, in the order they are declared.
+pub fn parse(xml: &str) -> Result<Vec<RegistryItem>, Rust2PlcError> {
+    let project = read(xml)?;
+    if project.name != "project" {
+        return Err(project.error(format!("expected <project>, found <{}>", project.name)));
+    }
+    let mut importer = Importer {
+        types: HashMap::new(),
+    };
+    let mut items = vec![];
+    if let Some(types) = project.child("types") {
+        for data_type in types
+            .children("dataTypes")
+            .flat_map(|d| d.children("dataType"))
+        {
+            let data_type = importer.data_type(data_type)?;
+            importer
+                .types
+                .insert(data_type.name().to_string(), data_type.value().clone());
+            items.push(RegistryItem::StType(data_type));
+        }
+        for pou in types.children("pous").flat_map(|p| p.children("pou")) {
+            items.push(importer.pou(pou)?);
+        }
+    }
+    if let Some(instances) = project.child("instances") {
+        for configuration in instances
+            .children("configurations")
+            .flat_map(|c| c.children("configuration"))
+        {
+            items.push(RegistryItem::StConfig(
+                importer.configuration(configuration)?,
+            ));
+        }
+    }
+    Ok(items)
+}
+
+impl PLCRegistry {
+    /// Registers everything declared in the PLCopen XML project file.
+    pub fn import_plcopen(&mut self, path: impl AsRef<Path>) -> Result<(), Rust2PlcError> {
+        for item in parse(&fs::read_to_string(path)?)? {
+            self.register(item);
+        }
+        Ok(())
+    }
+}
+
+struct Importer {
+    /// The data types declared earlier in the document.
+    types: HashMap<String, TypedValue>,
+}
+
+impl Importer {
+    fn data_type(&self, e: &Element) -> Result<DataType, Rust2PlcError> {
+        let name = required(e, "name")?;
+        let base = child(e, "baseType")?;
+        let Some(kind) = base.elements().next() else {
+            return Err(base.error(format!("the data type {} has no base type", name)));
+        };
+        let data_type = match kind.name.as_str() {
+            "struct" => {
+                let fields = kind
+                    .children("variable")
+                    .map(|v| self.variable(v))
+                    .collect::<Result<Vec<_>, _>>()?;
+                DataType::new_struct(name, fields)
+            }
+            "enum" => {
+                let variants = child(kind, "values")?
+                    .children("value")
+                    .map(|v| {
+                        let value = match v.attr("value") {
+                            Some(value) => Some(value.trim().parse::<i64>().map_err(|_| {
+                                v.error(format!("{} is not a valid enumerated value", value))
+                            })?),
+                            None => None,
+                        };
+                        Ok((required(v, "name")?.to_string(), value))
+                    })
+                    .collect::<Result<Vec<_>, Rust2PlcError>>()?;
+                let base = match kind.child("baseType") {
+                    Some(base) => Some(self.type_of(base)?),
+                    None => None,
+                };
+                let mut data_type = DataType::new_enum(name, variants).with_base(base);
+                if let Some(initial) = e.child("initialValue") {
+                    match self.value(data_type.value(), initial)? {
+                        TypedValue::Enum(_, _, variant, _) => {
+                            data_type = data_type.with_initial(variant)
+                        }
+                        _ => unreachable!("an enumerated value is parsed into an enum"),
+                    }
+                }
+                data_type
+            }
+            other => return Err(kind.error(format!(
+                "the data type {} is declared as {}, only structures and enumerations are imported",
+                name, other
+            ))),
+        };
+        let (namespace, version) = metadata(e);
+        Ok(data_type
+            .with_description(documentation(e))
+            .with_namespace(namespace)
+            .with_version(version))
+    }
+
+    fn pou(&self, e: &Element) -> Result<RegistryItem, Rust2PlcError> {
+        let name = required(e, "name")?;
+        let interface = e.child("interface");
+        let mut vars = vec![];
+        for list in interface.iter().flat_map(|i| i.elements()) {
+            let var: fn(String, TypedValue) -> Value = match list.name.as_str() {
+                "inputVars" => Value::Input,
+                "outputVars" => Value::Output,
+                "inOutVars" => Value::InOut,
+                "localVars" if list.attr("constant") == Some("true") => Value::Constant,
+                "localVars" => Value::Local,
+                "tempVars" => Value::Temporary,
+                "externalVars" => Value::External,
+                "globalVars" => Value::Global,
+                _ => continue,
+            };
+            for v in list.children("variable") {
+                let (name, value) = self.variable(v)?;
+                vars.push(var(name, value));
+            }
+        }
+        let body = self.body(name, e)?;
+        let (namespace, version) = metadata(e);
+        let description = documentation(e);
+
+        match required(e, "pouType")? {
+            "function" => {
+                let Some(return_type) = interface.and_then(|i| i.child("returnType")) else {
+                    return Err(e.error(format!("the function {} has no return type", name)));
+                };
+                let mut function = Function::new(name, self.type_of(return_type)?);
+                for var in vars {
+                    function = match var {
+                        Value::Input(_, _) | Value::InOut(_, _) => function.with_input(var),
+                        Value::Output(_, _) => function.with_output(var),
+                        _ => function.with_local(var),
+                    };
+                }
+                Ok(RegistryItem::StFn(
+                    function
+                        .with_body(body)
+                        .with_description(description)
+                        .with_namespace(namespace)
+                        .with_version(version),
+                ))
+            }
+            "functionBlock" => {
+                let mut fb = FunctionBlock::new(name);
+                for var in vars {
+                    fb = match var {
+                        Value::Input(_, _) | Value::InOut(_, _) => fb.with_input(var),
+                        Value::Output(_, _) => fb.with_output(var),
+                        _ => fb.with_var(var),
+                    };
+                }
+                Ok(RegistryItem::StFb(
+                    fb.with_body(body)
+                        .with_description(description)
+                        .with_namespace(namespace)
+                        .with_version(version),
+                ))
+            }
+            "program" => {
+                let mut program = Program::new(name);
+                for var in vars {
+                    program = match var {
+                        Value::Input(_, _) | Value::InOut(_, _) => program.with_input(var),
+                        Value::Output(_, _) => program.with_output(var),
+                        _ => program.with_var(var),
+                    };
+                }
+                Ok(RegistryItem::StProgram(
+                    program
+                        .with_body(body)
+                        .with_description(description)
+                        .with_namespace(namespace)
+                        .with_version(version),
+                ))
+            }
+            other => Err(e.error(format!("{} is not a supported POU type", other))),
+        }
+    }
+
+    /// The ST body, error positions are translated into positions in the document.
+    fn body(&self, pou: &str, e: &Element) -> Result<Vec<Stmt>, Rust2PlcError> {
+        let Some(body) = e.child("body") else {
+            return Ok(vec![]);
+        };
+        let Some(language) = body.elements().find(|l| l.name != "documentation") else {
+            return Ok(vec![]);
+        };
+        if language.name != "ST" {
+            return Err(language.error(format!(
+                "the body of {} is written in {}, only ST bodies are imported",
+                pou, language.name
+            )));
+        }
+        let start = language.text_position();
+        parser::parse_stmts(&language.text()).map_err(|err| match err {
+            Rust2PlcError::ParseError(msg, Some(Position { line, column })) => {
+                let column = if line == 1 {
+                    start.column + column - 1
+                } else {
+                    column
+                };
+                Rust2PlcError::parse_at(msg, start.line + line - 1, column)
+            }
+            other => other,
+        })
+    }
+
+    fn configuration(&self, e: &Element) -> Result<Configuration, Rust2PlcError> {
+        let mut configuration = Configuration::new(required(e, "name")?);
+        let mut globals = vec![];
+        for resource in e.children("resource") {
+            let mut imported = Resource::new(required(resource, "name")?, "PLC");
+            for task in resource.children("task") {
+                let name = required(task, "name")?;
+                let priority = required(task, "priority")?;
+                let priority = priority
+                    .trim()
+                    .parse()
+                    .map_err(|_| task.error(format!("{} is not a valid priority", priority)))?;
+                imported = imported.with_task(match (task.attr("interval"), task.attr("single")) {
+                    (Some(interval), _) => {
+                        Task::cyclic(name, interval_of(task, interval)?, priority)
+                    }
+                    (None, Some(single)) => Task::event(name, single, priority),
+                    (None, None) => {
+                        return Err(task.error(format!("the task {} has no trigger", name)))
+                    }
+                });
+                for instance in task.children("pouInstance") {
+                    imported = imported.with_program(
+                        required(instance, "name")?,
+                        required(instance, "typeName")?,
+                        Some(name),
+                    );
+                }
+            }
+            for instance in resource.children("pouInstance") {
+                imported = imported.with_program(
+                    required(instance, "name")?,
+                    required(instance, "typeName")?,
+                    None,
+                );
+            }
+            configuration = configuration.with_resource(imported);
+            globals.extend(resource.children("globalVars"));
+        }
+        // The model keeps globals per configuration, resource globals are merged into them.
+        globals.extend(e.children("globalVars"));
+        for v in globals.into_iter().flat_map(|g| g.children("variable")) {
+            let (name, value) = self.variable(v)?;
+            configuration = configuration.with_global(name, value);
+        }
+        let configuration = configuration.with_description(documentation(e));
+        configuration
+            .validate()
+            .map_err(|err| e.error(err.to_string()))?;
+        Ok(configuration)
+    }
+
+    fn variable(&self, e: &Element) -> Result<(String, TypedValue), Rust2PlcError> {
+        let name = required(e, "name")?;
+        let ty = self.type_of(child(e, "type")?)?;
+        let value = match e.child("initialValue") {
+            Some(initial) => self.value(&ty, initial)?,
+            None => ty,
+        };
+        Ok((name.to_string(), value))
+    }
+
+    /// The type in a `<type>`, `<baseType>` or `<returnType>` element.
+    fn type_of(&self, e: &Element) -> Result<TypedValue, Rust2PlcError> {
+        let Some(ty) = e.elements().next() else {
+            return Err(e.error(format!("<{}> has no type", e.name)));
+        };
+        let length = match ty.attr("length") {
+            Some(length) => Some(
+                length
+                    .trim()
+                    .parse()
+                    .map_err(|_| ty.error(format!("{} is not a valid length", length)))?,
+            ),
+            None => None,
+        };
+        match ty.name.as_str() {
+            "string" => Ok(TypedValue::new_string(length)),
+            "wstring" => Ok(TypedValue::new_wstring(length)),
+            "derived" => {
+                let name = required(ty, "name")?;
+                match self.types.get(name) {
+                    Some(tv) => Ok(tv.clone()),
+                    None => TypedValue::from_plc_type(name)
+                        .ok_or_else(|| ty.error(format!("unknown type {}", name))),
+                }
+            }
+            "array" => {
+                let dimensions = ty
+                    .children("dimension")
+                    .map(|d| {
+                        let bound = |attr| {
+                            let bound = required(d, attr)?;
+                            bound.trim().parse::<i64>().map_err(|_| {
+                                d.error(format!("{} is not a valid array bound", bound))
+                            })
+                        };
+                        let (lower, upper) = (bound("lower")?, bound("upper")?);
+                        if upper < lower {
+                            return Err(d.error(format!("{}..{} is an empty range", lower, upper)));
+                        }
+                        Ok((lower, (upper - lower + 1) as usize))
+                    })
+                    .collect::<Result<Vec<_>, Rust2PlcError>>()?;
+                if dimensions.is_empty() {
+                    return Err(ty.error("the array has no dimension"));
+                }
+                let elem = self.type_of(child(ty, "baseType")?)?;
+                Ok(dimensions
+                    .into_iter()
+                    .rev()
+                    .fold(elem, |elem, (lower, size)| {
+                        TypedValue::new_array(elem, size).with_lower_bound(lower)
+                    }))
+            }
+            name => match TypedValue::from_plc_type(name) {
+                Some(TypedValue::UserDefined(_, _)) | None => {
+                    Err(ty.error(format!("{} is not a supported type", name)))
+                }
+                Some(tv) => Ok(tv),
+            },
+        }
+    }
+
+    /// The value in an `<initialValue>` element, read through the ST initialiser syntax.
+    fn value(&self, ty: &TypedValue, e: &Element) -> Result<TypedValue, Rust2PlcError> {
+        let Some(value) = e.elements().next() else {
+            return Err(e.error("the initial value is empty"));
+        };
+        let source = initializer(value)?;
+        parser::parse_value(ty, &source).map_err(|err| match err {
+            Rust2PlcError::ParseError(msg, _) => value.error(msg),
+            other => other,
+        })
+    }
+}
+
+/// Renders a `simpleValue`, `arrayValue` or `structValue` as an ST initialiser.
+fn initializer(e: &Element) -> Result<String, Rust2PlcError> {
+    let inner = |v: &Element| match v.elements().next() {
+        Some(value) => initializer(value),
+        None => Err(v.error("the value is empty")),
+    };
+    match e.name.as_str() {
+        "simpleValue" => Ok(required(e, "value")?.to_string()),
+        "arrayValue" => {
+            let values = e
+                .children("value")
+                .map(|v| match v.attr("repetitionValue") {
+                    Some(count) => Ok(format!("{}({})", count.trim(), inner(v)?)),
+                    None => inner(v),
+                })
+                .collect::<Result<Vec<_>, Rust2PlcError>>()?;
+            Ok(format!("[{}]", values.join(", ")))
+        }
+        "structValue" => {
+            let values = e
+                .children("value")
+                .map(|v| Ok(format!("{} := {}", required(v, "member")?, inner(v)?)))
+                .collect::<Result<Vec<_>, Rust2PlcError>>()?;
+            Ok(format!("({})", values.join(", ")))
+        }
+        other => Err(e.error(format!("{} is not a supported value", other))),
+    }
+}
+
+/// A task interval, either an ST literal `T#10ms` or an XML duration `PT0.01S`.
+fn interval_of(e: &Element, interval: &str) -> Result<Duration, Rust2PlcError> {
+    let invalid = || e.error(format!("{} is not a valid task interval", interval));
+    let Some(iso) = interval.trim().strip_prefix("PT") else {
+        return match parser::parse_value(&TypedValue::new_time(), interval) {
+            Ok(TypedValue::Time(duration)) => Ok(duration),
+            _ => Err(invalid()),
+        };
+    };
+    let mut secs = 0.0;
+    let mut number = String::new();
+    for c in iso.chars() {
+        let unit = match c {
+            'H' => 3600.0,
+            'M' => 60.0,
+            'S' => 1.0,
+            c => {
+                number.push(c);
+                continue;
+            }
+        };
+        secs += number.parse::<f64>().map_err(|_| invalid())? * unit;
+        number.clear();
+    }
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs_f64(secs))
+}
+
+fn required<'a>(e: &'a Element, attr: &str) -> Result<&'a str, Rust2PlcError> {
+    e.attr(attr)
+        .ok_or_else(|| e.error(format!("<{}> has no {} attribute", e.name, attr)))
+}
+
+fn child<'a>(e: &'a Element, name: &str) -> Result<&'a Element, Rust2PlcError> {
+    e.child(name)
+        .ok_or_else(|| e.error(format!("<{}> has no <{}>", e.name, name)))
+}
+
+/// The text of the `<documentation>`, paragraphs are joined by line breaks.
+fn documentation(e: &Element) -> Option<String> {
+    let doc = e.child("documentation")?;
+    let text = if doc.elements().next().is_some() {
+        doc.elements()
+            .map(|p| p.text().trim().to_string())
+            .collect::<Vec<_>>()
+            .join("\n")
+    } else {
+        doc.text().trim().to_string()
+    };
+    (!text.is_empty()).then_some(text)
+}
+
+/// The namespace and version written by the export into `addData`.
+fn metadata(e: &Element) -> (Option<String>, Option<String>) {
+    let metadata = e
+        .child("addData")
+        .into_iter()
+        .flat_map(|a| a.children("data"))
+        .find(|d| d.attr("name") == Some(METADATA))
+        .and_then(|d| d.child("metadata"));
+    let attr = |name| metadata.and_then(|m| m.attr(name)).map(|v| v.to_string());
+    (attr("namespace"), attr("version"))
+}
@@ -1,10 +1,306 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+use crate::error::Rust2PlcError;
+use crate::st::config::Configuration;
+use crate::st::data_type::DataType;
 use crate::st::function::Function;
+use crate::st::function_block::FunctionBlock;
+use crate::st::program::Program;
+use crate::types::TypedValue;
+
+/// A POU collected into the registry.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegistryItem {
+    StFn(Function),
+    StFb(FunctionBlock),
+    StProgram(Program),
+    StConfig(Configuration),
+    StType(DataType),
+}
+
+impl RegistryItem {
+    pub fn name(&self) -> &str {
+        match self {
+            RegistryItem::StFn(f) => f.name(),
+            RegistryItem::StFb(fb) => fb.name(),
+            RegistryItem::StProgram(p) => p.name(),
+            RegistryItem::StConfig(c) => c.name(),
+            RegistryItem::StType(t) => t.name(),
+        }
+    }
+
+    pub fn namespace(&self) -> Option<&str> {
+        match self {
+            RegistryItem::StFn(f) => f.namespace(),
+            RegistryItem::StFb(fb) => fb.namespace(),
+            RegistryItem::StProgram(p) => p.namespace(),
+            RegistryItem::StConfig(_) => None,
+            RegistryItem::StType(t) => t.namespace(),
+        }
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        match self {
+            RegistryItem::StFn(f) => f.version(),
+            RegistryItem::StFb(fb) => fb.version(),
+            RegistryItem::StProgram(p) => p.version(),
+            RegistryItem::StConfig(_) => None,
+            RegistryItem::StType(t) => t.version(),
+        }
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        match self {
+            RegistryItem::StFn(f) => f.description(),
+            RegistryItem::StFb(fb) => fb.description(),
+            RegistryItem::StProgram(p) => p.description(),
+            RegistryItem::StConfig(c) => c.description(),
+            RegistryItem::StType(t) => t.description(),
+        }
+    }
+
+    /// The name qualified by the namespace, e.g. `Math.add`.
+    pub fn qualified_name(&self) -> String {
+        match self.namespace() {
+            Some(ns) => format!("{}.{}", ns, self.name()),
+            None => self.name().to_string(),
+        }
+    }
+
+    pub fn to_st(&self) -> Result<String, Rust2PlcError> {
+        match self {
+            RegistryItem::StFn(f) => Ok(f.to_st()),
+            RegistryItem::StFb(fb) => Ok(fb.to_st()),
+            RegistryItem::StProgram(p) => Ok(p.to_st()),
+            RegistryItem::StConfig(c) => c.to_st(),
+            RegistryItem::StType(t) => t.to_st(),
+        }
+    }
+
+    /// Names of the user-defined types and POUs the item refers to in its declarations.
+    pub fn dependencies(&self) -> Vec<String> {
+        let mut deps = vec![];
+        match self {
+            RegistryItem::StFn(f) => {
+                let vars = f.inputs().iter().chain(f.outputs()).chain(f.locals());
+                for tv in vars.map(|v| v.typed_value()).chain([f.return_value()]) {
+                    collect_user_types(tv, &mut deps);
+                }
+            }
+            RegistryItem::StFb(fb) => {
+                let vars = fb.inputs().iter().chain(fb.outputs()).chain(fb.vars());
+                for tv in vars.map(|v| v.typed_value()) {
+                    collect_user_types(tv, &mut deps);
+                }
+            }
+            RegistryItem::StProgram(p) => {
+                let vars = p.inputs().iter().chain(p.outputs()).chain(p.vars());
+                for tv in vars.map(|v| v.typed_value()) {
+                    collect_user_types(tv, &mut deps);
+                }
+            }
+            RegistryItem::StConfig(c) => {
+                deps.extend(c.program_types().into_iter().map(|p| p.to_string()));
+                for tv in c.globals().iter().map(|v| v.typed_value()) {
+                    collect_user_types(tv, &mut deps);
+                }
+            }
+            RegistryItem::StType(t) => {
+                if let TypedValue::Struct(_, fields) = t.value() {
+                    for (_, tv) in fields.iter() {
+                        collect_user_types(tv, &mut deps);
+                    }
+                }
+            }
+        }
+        deps.sort();
+        deps.dedup();
+        deps
+    }
+}
+
+fn collect_user_types(tv: &TypedValue, deps: &mut Vec<String>) {
+    match tv {
+        TypedValue::UserDefined(name, _) => deps.push(name.clone()),
+        TypedValue::Struct(name, _) | TypedValue::Enum(name, ..) => deps.push(name.clone()),
+        TypedValue::Array(_, elem, _) => collect_user_types(elem, deps),
+        _ => {}
+    }
+}
+
+/// A link-time registration of a POU.
+///
+/// The `#[plc_*]` macros submit one entry per annotated item,
+/// [`PLCRegistry::all`] gathers them for the whole binary.
+pub struct RegistryEntry {
+    build: fn() -> RegistryItem,
+}
+
+impl RegistryEntry {
+    pub const fn new(build: fn() -> RegistryItem) -> Self {
+        RegistryEntry { build }
+    }
+}
+
+inventory::collect!(RegistryEntry);
+
+/// A link-time registration of a data type, submitted by `#[derive(PlcType)]`.
+///
+/// Types are kept apart from the other POUs, so that [`TypedValue::from_rust_type`]
+/// can resolve them by name while the POUs are being built.
+pub struct TypeEntry {
+    name: &'static str,
+    build: fn() -> DataType,
+}
+
+impl TypeEntry {
+    pub const fn new(name: &'static str, build: fn() -> DataType) -> Self {
+        TypeEntry { name, build }
+    }
+}
+
+inventory::collect!(TypeEntry);
+
+/// Finds a data type registered by `#[derive(PlcType)]` by its name.
+pub fn find_data_type(name: &str) -> Option<DataType> {
+    inventory::iter::<TypeEntry>
+        .into_iter()
+        .find(|entry| entry.name == name)
+        .map(|entry| (entry.build)())
+}
 
+#[derive(Debug, Clone, Default)]
 pub struct PLCRegistry {
-    plc_types: std::collections::HashMap<String, String>,
+    items: Vec<RegistryItem>,
 }
 
-#[derive(Debug, Clone)]
-enum RegistryItem {
-    StFn(Function),
+impl PLCRegistry {
+    pub fn new() -> Self {
+        PLCRegistry::default()
+    }
+
+    /// The process-wide registry holding every POU registered by the macros.
+    pub fn all() -> &'static PLCRegistry {
+        static REGISTRY: OnceLock<PLCRegistry> = OnceLock::new();
+        REGISTRY.get_or_init(|| {
+            let mut registry = PLCRegistry::new();
+            for entry in inventory::iter::<TypeEntry> {
+                registry.register(RegistryItem::StType((entry.build)()));
+            }
+            for entry in inventory::iter::<RegistryEntry> {
+                registry.register((entry.build)());
+            }
+            registry
+        })
+    }
+
+    /// Adds an item keeping the registry ordered by namespace, name and version.
+    pub fn register(&mut self, item: RegistryItem) {
+        let idx = self
+            .items
+            .partition_point(|i| compare_items(i, &item) != Ordering::Greater);
+        self.items.insert(idx, item);
+    }
+
+    pub fn items(&self) -> &[RegistryItem] {
+        &self.items
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Looks up an item by its namespace and name.
+    /// Without an explicit version the latest registered version is returned.
+    pub fn find(
+        &self,
+        namespace: Option<&str>,
+        name: &str,
+        version: Option<&str>,
+    ) -> Option<&RegistryItem> {
+        self.items
+            .iter()
+            .filter(|i| i.namespace() == namespace && i.name() == name)
+            .filter(|i| version.is_none() || i.version() == version)
+            .max_by(|a, b| compare_versions(a.version(), b.version()))
+    }
+
+    /// The latest version of every item ordered so that an item comes after everything it depends on.
+    pub fn declaration_order(&self) -> Vec<&RegistryItem> {
+        let mut latest: Vec<&RegistryItem> = vec![];
+        for item in self.items.iter() {
+            match latest.last() {
+                Some(last)
+                    if last.namespace() == item.namespace() && last.name() == item.name() =>
+                {
+                    latest.pop();
+                }
+                _ => {}
+            }
+            latest.push(item);
+        }
+
+        let mut ordered: Vec<&RegistryItem> = Vec::with_capacity(latest.len());
+        while !latest.is_empty() {
+            let ready = latest.iter().position(|item| {
+                item.dependencies().iter().all(|dep| {
+                    !latest
+                        .iter()
+                        .any(|other| other.name() == dep || other.qualified_name() == *dep)
+                })
+            });
+            // A dependency cycle cannot be ordered, the rest is emitted as is.
+            ordered.push(latest.remove(ready.unwrap_or(0)));
+        }
+        ordered
+    }
+
+    /// Items grouped by namespace, items without a namespace come first under `None`.
+    pub fn by_namespace(&self) -> BTreeMap<Option<&str>, Vec<&RegistryItem>> {
+        let mut groups: BTreeMap<Option<&str>, Vec<&RegistryItem>> = BTreeMap::new();
+        for item in self.items.iter() {
+            groups.entry(item.namespace()).or_default().push(item);
+        }
+        groups
+    }
+}
+
+fn compare_items(lhs: &RegistryItem, rhs: &RegistryItem) -> Ordering {
+    lhs.namespace()
+        .cmp(&rhs.namespace())
+        .then_with(|| lhs.name().cmp(rhs.name()))
+        .then_with(|| compare_versions(lhs.version(), rhs.version()))
+}
+
+/// Compares dotted versions numerically where possible, so that `1.10` is newer than `1.9`.
+fn compare_versions(lhs: Option<&str>, rhs: Option<&str>) -> Ordering {
+    match (lhs, rhs) {
+        (Some(lhs), Some(rhs)) => {
+            let mut lhs_parts = lhs.split('.');
+            let mut rhs_parts = rhs.split('.');
+            loop {
+                match (lhs_parts.next(), rhs_parts.next()) {
+                    (Some(l), Some(r)) => {
+                        let ord = match (l.parse::<u64>(), r.parse::<u64>()) {
+                            (Ok(l), Ok(r)) => l.cmp(&r),
+                            _ => l.cmp(r),
+                        };
+                        if ord != Ordering::Equal {
+                            return ord;
+                        }
+                    }
+                    (Some(_), None) => return Ordering::Greater,
+                    (None, Some(_)) => return Ordering::Less,
+                    (None, None) => return Ordering::Equal,
+                }
+            }
+        }
+        (lhs, rhs) => lhs.is_some().cmp(&rhs.is_some()),
+    }
 }
@@ -0,0 +1,8 @@
+//! Siemens S7-300/400 binaries, the blocks a CPU keeps in its load memory.
+//!
+//! The STL sources of [`awl`](crate::awl) are the input, `mc7` assembles them into
+//! block files. The encoding has not been checked against STEP 7 or a CPU, the module
+//! is built with the `unverified-mc7` feature only.
+
+#[cfg(feature = "unverified-mc7")]
+pub mod mc7;
@@ -0,0 +1,1977 @@
+//! Unverified MC7, the machine code of S7-300/400 blocks, assembled from the STL sources
+//! of [`AwlProject`]. The module is built with the `unverified-mc7` feature only.
+//!
+//! A block file holds the block as the CPU uploads it: a header of 36 bytes, the MC7
+//! code, the interface segment and a footer of 36 bytes, all numbers big-endian.
+//!
+//! | Offset | Bytes | Header field                                                     |
+//! |--------|-------|------------------------------------------------------------------|
+//! | 0      | 2     | `70 70`, the block marker                                        |
+//! | 2      | 1     | block format, `01`                                               |
+//! | 3      | 1     | attributes, `01` for a linked block                              |
+//! | 4      | 1     | language, see [`Language`]                                      |
+//! | 5      | 1     | block type: OB `08`, DB `0A`, FC `0C`, FB `0E`                   |
+//! | 6      | 2     | block number                                                     |
+//! | 8      | 4     | length of the block file                                         |
+//! | 12     | 4     | know-how protection, zero                                        |
+//! | 16     | 6     | code timestamp, milliseconds since midnight and days since 1984 |
+//! | 22     | 6     | interface timestamp                                              |
+//! | 28     | 2     | length of the interface segment                                  |
+//! | 30     | 2     | length of the additional segment, zero                           |
+//! | 32     | 2     | bytes of local data                                              |
+//! | 34     | 2     | length of the MC7 code                                           |
+//!
+//! The footer holds the author, the family and the name in 8 bytes each, the version as
+//! major and minor nibble, a reserved byte, the checksum and 8 reserved bytes. The checksum
+//! is left zero, how STEP 7 computes it is not known.
+//!
+//! The interface segment lists the declarations in source order, each as type code and
+//! section code (`01` input, `02` output, `03` in-out, `04` static, `05` temporary,
+//! `06` the return value), with `08` added when the declaration has a start value.
+//! Strings add their length, arrays their dimensions and the element, structures their
+//! member count and members, multi-instances the block type and number. A length prefixes
+//! the declarations and the start values that follow them.
+//!
+//! Variables are laid out as STEP 7 does: `BOOL` values share bytes, `BYTE` and `CHAR`
+//! take a byte and everything else starts on an even address. The parameters and statics
+//! of a function block live in its instance data (`DI`), temporaries in the local data
+//! (`L`) and the parameters of a function are reached by their index. A data block holds
+//! the instance data of its function block with the start values in place, `UDT` blocks
+//! exist offline only and are expanded into the interfaces using them.
+//!
+//! ```
+//! use rust2plc::awl::{BlockKind, BlockNumber};
+//! use rust2plc::s7::mc7;
+//!
+//! let blocks = mc7::assemble(
+//!     "FUNCTION FC 1 : INT\n\
+//!      VERSION : 0.1\n\n\
+//!      VAR_INPUT\n  x : INT;\nEND_VAR\n\
+//!      BEGIN\n\
+//!      NETWORK\n\
+//!      TITLE = answer := x;\n\
+//!      \x20     L     #x;\n\
+//!      \x20     T     #RET_VAL;\n\
+//!      END_FUNCTION\n",
+//! )
+//! .unwrap();
+//! assert_eq!(blocks[0].number(), BlockNumber::new(BlockKind::Fc, 1));
+//! assert_eq!(
+//!     blocks[0].code(),
+//!     [0xE1, 0x80, 0, 0, 0, 0, 0xE5, 0x80, 0, 1, 0, 0, 0x65, 0x00]
+//! );
+//! assert_eq!(blocks[0].interface(), [0, 4, 0x05, 0x01, 0x05, 0x06, 0, 0]);
+//! ```
+//!
+//! Siemens does not publish MC7. The container follows the block layout known from the
+//! open-source S7 communication libraries and the opcodes are the table of this module,
+//! no block has been compared with a STEP 7 upload or loaded into a CPU. Symbols, which
+//! the source leaves to the symbol table, have no address and are rejected.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+use crate::awl::{AwlProject, BlockKind, BlockNumber};
+use crate::error::Rust2PlcError;
+use crate::st::parser::duration;
+
+/// The length of the block header.
+pub const HEADER_LEN: usize = 36;
+
+/// The length of the block footer.
+pub const FOOTER_LEN: usize = 36;
+
+/// The format of the block header.
+const FORMAT: u8 = 0x01;
+
+/// The attribute of a linked block, the blocks are assembled ready for download.
+const LINKED: u8 = 0x01;
+
+/// The pointer areas: instance data, local data and the local data of the caller.
+const DI: u8 = 0x85;
+const LOCAL: u8 = 0x86;
+const V: u8 = 0x87;
+
+/// The block types of multi-instances.
+const FB: u8 = 0x0E;
+const SFB: u8 = 0x0F;
+
+/// The section code of the return value and the flag of declarations with a start value.
+const RETURN: u8 = 0x06;
+const START_VALUE: u8 = 0x08;
+
+/// The instructions without operand.
+const PLAIN: [(&str, [u8; 2]); 41] = [
+    ("NOT", [0xFF, 0x00]),
+    ("SET", [0xFF, 0x01]),
+    ("CLR", [0xFF, 0x02]),
+    ("A(", [0xBA, 0x00]),
+    ("AN(", [0xBA, 0x01]),
+    ("X(", [0xBA, 0x02]),
+    ("XN(", [0xBA, 0x03]),
+    ("O(", [0xBB, 0x00]),
+    ("ON(", [0xBB, 0x01]),
+    (")", [0xBF, 0x00]),
+    ("BEU", [0x65, 0x01]),
+    ("BEC", [0x05, 0x00]),
+    ("INVI", [0x01, 0x00]),
+    ("NEGI", [0x09, 0x00]),
+    ("+I", [0x79, 0x00]),
+    ("-I", [0x59, 0x00]),
+    ("/I", [0x60, 0x00]),
+    ("MOD", [0x60, 0x01]),
+    ("ABS", [0x60, 0x02]),
+    ("/R", [0x60, 0x03]),
+    ("*I", [0x60, 0x04]),
+    ("*R", [0x60, 0x05]),
+    ("/D", [0x60, 0x06]),
+    ("*D", [0x60, 0x07]),
+    ("+D", [0x60, 0x08]),
+    ("-D", [0x60, 0x09]),
+    ("+R", [0x60, 0x0A]),
+    ("-R", [0x60, 0x0B]),
+    ("SQR", [0x60, 0x10]),
+    ("SQRT", [0x60, 0x11]),
+    ("EXP", [0x60, 0x12]),
+    ("LN", [0x60, 0x13]),
+    ("SIN", [0x60, 0x14]),
+    ("COS", [0x60, 0x15]),
+    ("TAN", [0x60, 0x16]),
+    ("ASIN", [0x60, 0x17]),
+    ("ACOS", [0x60, 0x18]),
+    ("ATAN", [0x60, 0x19]),
+    ("AW", [0x41, 0x00]),
+    ("OW", [0x49, 0x00]),
+    ("XOW", [0x51, 0x00]),
+];
+
+/// The instructions without operand working on 32 bits.
+const DOUBLE: [(&str, [u8; 2]); 13] = [
+    ("AD", [0x41, 0x01]),
+    ("OD", [0x49, 0x01]),
+    ("XOD", [0x51, 0x01]),
+    ("DTR", [0x68, 0x06]),
+    ("NEGD", [0x68, 0x07]),
+    ("INVD", [0x68, 0x0D]),
+    ("ITD", [0x68, 0x12]),
+    ("NEGR", [0x68, 0x18]),
+    ("TRUNC", [0x68, 0x1A]),
+    ("RND", [0x68, 0x1C]),
+    ("RND+", [0x68, 0x1D]),
+    ("RND-", [0x68, 0x1E]),
+    ("TAK", [0x68, 0x2C]),
+];
+
+/// The bit instructions, followed by the operand.
+const BITS: [(&str, u8); 9] = [
+    ("A", 0xC0),
+    ("AN", 0xC1),
+    ("O", 0xC2),
+    ("ON", 0xC3),
+    ("X", 0xC4),
+    ("XN", 0xC5),
+    ("=", 0xC6),
+    ("S", 0xC7),
+    ("R", 0xC8),
+];
+
+/// `L` and `T` of a byte, a word and a double word, followed by the operand.
+const LOAD: [u8; 3] = [0xE0, 0xE1, 0xE2];
+const TRANSFER: [u8; 3] = [0xE4, 0xE5, 0xE6];
+
+/// The jumps, followed by the distance to the label in words.
+const JUMPS: [(&str, [u8; 2]); 3] = [
+    ("JU", [0x70, 0x0B]),
+    ("JC", [0x70, 0x08]),
+    ("JCN", [0x70, 0x09]),
+];
+
+/// The calls of a function, of a function block with its data block and of a multi-instance.
+const CALL_FC: [u8; 2] = [0xFB, 0x70];
+const CALL_FB: [u8; 2] = [0xFB, 0x72];
+const CALL_MULTI_FB: [u8; 2] = [0xFB, 0x74];
+const CALL_MULTI_SFB: [u8; 2] = [0xFB, 0x76];
+
+/// `BE`, closing every block.
+const BE: [u8; 2] = [0x65, 0x00];
+
+/// The language a block was programmed in, byte 4 of the header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    /// Statement list.
+    Awl,
+    /// Ladder logic.
+    Kop,
+    /// Function block diagram.
+    Fup,
+    Scl,
+    /// The data of a data block.
+    Db,
+    Graph,
+}
+
+impl Language {
+    pub fn code(&self) -> u8 {
+        match self {
+            Language::Awl => 0x01,
+            Language::Kop => 0x02,
+            Language::Fup => 0x03,
+            Language::Scl => 0x04,
+            Language::Db => 0x05,
+            Language::Graph => 0x06,
+        }
+    }
+}
+
+/// An assembled block, written as block file by [`Block::to_bytes`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    number: BlockNumber,
+    language: Language,
+    code: Vec<u8>,
+    interface: Vec<u8>,
+    local_data: u16,
+    version: u8,
+    modified: NaiveDateTime,
+    author: String,
+    family: String,
+    name: String,
+}
+
+impl Block {
+    /// A block with the MC7 code, or the data of a data block, and the interface segment.
+    pub fn new(number: BlockNumber, language: Language, code: Vec<u8>, interface: Vec<u8>) -> Self {
+        Block {
+            number,
+            language,
+            code,
+            interface,
+            local_data: 0,
+            version: 0,
+            modified: NaiveDate::from_ymd_opt(1984, 1, 1)
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+                .unwrap_or_default(),
+            author: String::new(),
+            family: String::new(),
+            name: String::new(),
+        }
+    }
+
+    pub fn with_local_data(mut self, bytes: u16) -> Self {
+        self.local_data = bytes;
+        self
+    }
+
+    /// The version, major and minor are kept as nibbles.
+    pub fn with_version(mut self, major: u8, minor: u8) -> Self {
+        self.version = (major.min(15) << 4) | minor.min(15);
+        self
+    }
+
+    /// The time stamp of the code and the interface, by default 1984-01-01.
+    pub fn with_modified(mut self, modified: NaiveDateTime) -> Self {
+        self.modified = modified;
+        self
+    }
+
+    pub fn with_author(mut self, author: impl Into<String>) -> Self {
+        self.author = author.into();
+        self
+    }
+
+    pub fn with_family(mut self, family: impl Into<String>) -> Self {
+        self.family = family.into();
+        self
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn number(&self) -> BlockNumber {
+        self.number
+    }
+
+    pub fn language(&self) -> Language {
+        self.language
+    }
+
+    pub fn code(&self) -> &[u8] {
+        &self.code
+    }
+
+    pub fn interface(&self) -> &[u8] {
+        &self.interface
+    }
+
+    pub fn local_data(&self) -> u16 {
+        self.local_data
+    }
+
+    /// The name of the block file, e.g. `FC10.mc7`.
+    pub fn file_name(&self) -> String {
+        format!("{}{}.mc7", self.number.kind.prefix(), self.number.number)
+    }
+
+    /// The block file, header, code, interface segment and footer.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Rust2PlcError> {
+        let block_type = match self.number.kind {
+            BlockKind::Ob => 0x08,
+            BlockKind::Db => 0x0A,
+            BlockKind::Fc => 0x0C,
+            BlockKind::Fb => 0x0E,
+            BlockKind::Udt => {
+                return Err(Rust2PlcError::Other(format!(
+                    "{} exists in the offline project only, the CPU has no block for it",
+                    self.number
+                )))
+            }
+        };
+        let length = |what: &str, bytes: &[u8]| {
+            u16::try_from(bytes.len()).map_err(|_| {
+                Rust2PlcError::Other(format!(
+                    "the {} of {} has {} bytes, a block holds at most 65535",
+                    what,
+                    self.number,
+                    bytes.len()
+                ))
+            })
+        };
+        let code_len = length("MC7 code", &self.code)?;
+        let interface_len = length("interface", &self.interface)?;
+        let total = HEADER_LEN + self.code.len() + self.interface.len() + FOOTER_LEN;
+        let stamp = timestamp(self.modified);
+
+        let mut bytes = Vec::with_capacity(total);
+        bytes.extend([0x70, 0x70, FORMAT, LINKED, self.language.code(), block_type]);
+        bytes.extend(self.number.number.to_be_bytes());
+        bytes.extend((total as u32).to_be_bytes());
+        bytes.extend([0; 4]);
+        bytes.extend(stamp);
+        bytes.extend(stamp);
+        bytes.extend(interface_len.to_be_bytes());
+        bytes.extend(0u16.to_be_bytes());
+        bytes.extend(self.local_data.to_be_bytes());
+        bytes.extend(code_len.to_be_bytes());
+        bytes.extend(&self.code);
+        bytes.extend(&self.interface);
+        for (field, text) in [
+            ("author", &self.author),
+            ("family", &self.family),
+            ("name", &self.name),
+        ] {
+            if !text.is_ascii() || text.len() > 8 {
+                return Err(Rust2PlcError::Other(format!(
+                    "the {} {} of {} is longer than 8 ASCII characters",
+                    field, text, self.number
+                )));
+            }
+            let mut padded = [0u8; 8];
+            padded[..text.len()].copy_from_slice(text.as_bytes());
+            bytes.extend(padded);
+        }
+        // the checksum, its algorithm is not known
+        bytes.extend([self.version, 0, 0, 0]);
+        bytes.extend([0; 8]);
+        Ok(bytes)
+    }
+}
+
+impl AwlProject {
+    /// The blocks of the project for the CPU, see [`assemble`].
+    pub fn to_mc7(&self) -> Result<Vec<Block>, Rust2PlcError> {
+        assemble(&self.to_awl()?)
+    }
+
+    /// Writes a block file per block into `dir`, e.g. `FC10.mc7`.
+    ///
+    /// Existing files are overwritten.
+    pub fn export_mc7(&self, dir: impl AsRef<Path>) -> Result<Vec<Block>, Rust2PlcError> {
+        let blocks = self.to_mc7()?;
+        fs::create_dir_all(dir.as_ref())?;
+        for block in blocks.iter() {
+            fs::write(dir.as_ref().join(block.file_name()), block.to_bytes()?)?;
+        }
+        Ok(blocks)
+    }
+}
+
+/// Assembles an STL source into blocks, in source order.
+///
+/// The blocks are declared before they are used, as [`AwlProject::to_awl`] orders them.
+pub fn assemble(awl: &str) -> Result<Vec<Block>, Rust2PlcError> {
+    let mut source = Source::default();
+    let mut blocks = vec![];
+    let mut lines = awl.lines().enumerate().map(|(i, line)| (i + 1, line));
+    while let Some((n, line)) = lines.next() {
+        let text = line.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let (keyword, head) = text.split_once(' ').unwrap_or((text, ""));
+        let (kind, end) = match keyword {
+            "TYPE" => (BlockKind::Udt, "END_TYPE"),
+            "FUNCTION" => (BlockKind::Fc, "END_FUNCTION"),
+            "FUNCTION_BLOCK" => (BlockKind::Fb, "END_FUNCTION_BLOCK"),
+            "ORGANIZATION_BLOCK" => (BlockKind::Ob, "END_ORGANIZATION_BLOCK"),
+            "DATA_BLOCK" => (BlockKind::Db, "END_DATA_BLOCK"),
+            _ => {
+                return Err(Rust2PlcError::parse_at(
+                    format!("expected a block, found {}", text),
+                    n,
+                    column(line),
+                ))
+            }
+        };
+        let mut body = vec![];
+        loop {
+            match lines.next() {
+                Some((_, line)) if line.trim() == end => break,
+                Some(line) => body.push(line),
+                None => {
+                    return Err(Rust2PlcError::parse_at(
+                        format!("{} has no {}", text, end),
+                        n,
+                        column(line),
+                    ))
+                }
+            }
+        }
+        let at = |msg: String| Rust2PlcError::parse_at(msg, n, column(line));
+        let (number, result) = match head.split_once(':') {
+            Some((number, result)) => (number, Some(result.trim())),
+            None => (head, None),
+        };
+        let number: BlockNumber = number
+            .parse()
+            .map_err(|e: Rust2PlcError| at(e.to_string()))?;
+        if number.kind != kind {
+            return Err(at(format!(
+                "{} declares {} blocks, not {}",
+                keyword,
+                kind.prefix(),
+                number
+            )));
+        }
+        let decls = source.decls(&body, n)?;
+        match kind {
+            BlockKind::Udt => {
+                source.types.insert(number.number, decls.members);
+            }
+            BlockKind::Db => blocks.push(source.data_block(number, decls).map_err(at)?),
+            _ => {
+                let result = match result {
+                    Some(ty) if !ty.eq_ignore_ascii_case("VOID") => {
+                        Some(source.parse_type(ty).map_err(at)?)
+                    }
+                    _ => None,
+                };
+                blocks.push(source.code_block(number, result, decls, n)?);
+            }
+        }
+    }
+    Ok(blocks)
+}
+
+/// The 1-based column of the first character of a line.
+fn column(line: &str) -> usize {
+    line.len() - line.trim_start().len() + 1
+}
+
+/// Milliseconds since midnight and days since 1984-01-01.
+fn timestamp(at: NaiveDateTime) -> [u8; 6] {
+    let epoch = NaiveDate::from_ymd_opt(1984, 1, 1).unwrap_or_default();
+    let days = (at.date() - epoch).num_days().clamp(0, u16::MAX as i64) as u16;
+    let millis = millis(at.time());
+    let mut stamp = [0u8; 6];
+    stamp[..4].copy_from_slice(&millis.to_be_bytes());
+    stamp[4..].copy_from_slice(&days.to_be_bytes());
+    stamp
+}
+
+fn millis(time: NaiveTime) -> u32 {
+    time.num_seconds_from_midnight() * 1000 + time.nanosecond() / 1_000_000
+}
+
+/// Rounds a bit address up to the next even byte.
+fn even(bits: usize) -> usize {
+    bits.div_ceil(16) * 16
+}
+
+fn is_label(text: &str) -> bool {
+    text.starts_with(|c: char| c.is_ascii_alphabetic())
+        && text.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Splits a list at the commas outside of quotes and parentheses.
+fn split_list(text: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let (mut depth, mut quoted, mut start) = (0, false, 0);
+    for (i, c) in text.char_indices() {
+        match c {
+            '\'' => quoted = !quoted,
+            '(' | '[' if !quoted => depth += 1,
+            ')' | ']' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                parts.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(text[start..].trim());
+    parts.retain(|p| !p.is_empty());
+    parts
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    Input,
+    Output,
+    InOut,
+    Static,
+    Temp,
+}
+
+impl Section {
+    fn from_keyword(text: &str) -> Option<Section> {
+        match text {
+            "VAR_INPUT" => Some(Section::Input),
+            "VAR_OUTPUT" => Some(Section::Output),
+            "VAR_IN_OUT" => Some(Section::InOut),
+            "VAR" => Some(Section::Static),
+            "VAR_TEMP" => Some(Section::Temp),
+            _ => None,
+        }
+    }
+
+    fn code(&self) -> u8 {
+        match self {
+            Section::Input => 0x01,
+            Section::Output => 0x02,
+            Section::InOut => 0x03,
+            Section::Static => 0x04,
+            Section::Temp => 0x05,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Type {
+    Bool,
+    Byte,
+    Char,
+    Word,
+    Int,
+    DWord,
+    DInt,
+    Real,
+    Date,
+    TimeOfDay,
+    Time,
+    DateAndTime,
+    String(u8),
+    Array(Vec<(i16, i16)>, Box<Type>),
+    Struct(Vec<Member>),
+    Instance(Box<Instance>),
+    /// An in-out parameter of a function block that is not elementary, passed as pointer.
+    Pointer,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Member {
+    name: String,
+    ty: Type,
+    init: Option<String>,
+}
+
+/// A function block instance in the statics of another.
+#[derive(Debug, Clone, PartialEq)]
+struct Instance {
+    block_type: u8,
+    number: u16,
+    interface: Interface,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Interface {
+    sections: Vec<(Section, Vec<Member>)>,
+    result: Option<Type>,
+}
+
+/// A variable in the instance data.
+struct Slot {
+    name: String,
+    section: Section,
+    ty: Type,
+    /// The bit address.
+    at: usize,
+    init: Option<String>,
+}
+
+impl Type {
+    fn code(&self) -> u8 {
+        match self {
+            Type::Bool => 0x01,
+            Type::Byte => 0x02,
+            Type::Char => 0x03,
+            Type::Word => 0x04,
+            Type::Int => 0x05,
+            Type::DWord => 0x06,
+            Type::DInt => 0x07,
+            Type::Real => 0x08,
+            Type::Date => 0x09,
+            Type::TimeOfDay => 0x0A,
+            Type::Time => 0x0B,
+            Type::DateAndTime => 0x0E,
+            Type::Array(_, _) => 0x10,
+            Type::Struct(_) => 0x11,
+            Type::String(_) => 0x13,
+            Type::Pointer => 0x14,
+            Type::Instance(_) => 0x17,
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Type::Bool => "BOOL".to_string(),
+            Type::Byte => "BYTE".to_string(),
+            Type::Char => "CHAR".to_string(),
+            Type::Word => "WORD".to_string(),
+            Type::Int => "INT".to_string(),
+            Type::DWord => "DWORD".to_string(),
+            Type::DInt => "DINT".to_string(),
+            Type::Real => "REAL".to_string(),
+            Type::Date => "DATE".to_string(),
+            Type::TimeOfDay => "TIME_OF_DAY".to_string(),
+            Type::Time => "TIME".to_string(),
+            Type::DateAndTime => "DATE_AND_TIME".to_string(),
+            Type::String(len) => format!("STRING [{}]", len),
+            Type::Array(_, _) => "ARRAY".to_string(),
+            Type::Struct(_) => "STRUCT".to_string(),
+            Type::Instance(i) if i.block_type == SFB => format!("SFB {}", i.number),
+            Type::Instance(i) => format!("FB {}", i.number),
+            Type::Pointer => "POINTER".to_string(),
+        }
+    }
+
+    /// The values of 32 bits or less, all others are passed to function blocks by pointer.
+    fn is_elementary(&self) -> bool {
+        self.bits() <= 32 && !matches!(self, Type::Array(_, _) | Type::Struct(_))
+    }
+
+    /// The size in bits.
+    fn bits(&self) -> usize {
+        match self {
+            Type::Bool => 1,
+            Type::Byte | Type::Char => 8,
+            Type::Word | Type::Int | Type::Date => 16,
+            Type::DWord | Type::DInt | Type::Real | Type::TimeOfDay | Type::Time => 32,
+            Type::Pointer => 48,
+            Type::DateAndTime => 64,
+            Type::String(len) => (*len as usize + 2) * 8,
+            Type::Array(dims, elem) => even(count(dims) * elem.stride()),
+            Type::Struct(members) => even(layout(members).1),
+            Type::Instance(i) => i.interface.slots().1,
+        }
+    }
+
+    /// The distance of array elements, `BOOL` values are packed.
+    fn stride(&self) -> usize {
+        match self {
+            Type::Bool | Type::Byte | Type::Char => self.bits(),
+            _ => even(self.bits()),
+        }
+    }
+
+    /// The address of a value of the type at or after `cursor`.
+    fn align(&self, cursor: usize) -> usize {
+        match self {
+            Type::Bool => cursor,
+            Type::Byte | Type::Char => cursor.div_ceil(8) * 8,
+            _ => even(cursor),
+        }
+    }
+
+    /// The type and the bit offset of a member of a structure or an instance.
+    fn member(&self, name: &str) -> Option<(Type, usize)> {
+        match self {
+            Type::Struct(members) => {
+                let (offsets, _) = layout(members);
+                members
+                    .iter()
+                    .zip(offsets)
+                    .find(|(m, _)| m.name.eq_ignore_ascii_case(name))
+                    .map(|(m, at)| (m.ty.clone(), at))
+            }
+            Type::Instance(i) => i
+                .interface
+                .slots()
+                .0
+                .into_iter()
+                .find(|s| s.name.eq_ignore_ascii_case(name))
+                .map(|s| (s.ty, s.at)),
+            _ => None,
+        }
+    }
+
+    /// The type and the bit offset of an array element.
+    fn element(&self, indices: &[i64]) -> Result<(Type, usize), String> {
+        let Type::Array(dims, elem) = self else {
+            return Err(format!("{} is not an array", self.name()));
+        };
+        if indices.len() != dims.len() {
+            return Err(format!(
+                "the array has {} dimensions, not {}",
+                dims.len(),
+                indices.len()
+            ));
+        }
+        let mut linear = 0;
+        for (index, (lo, hi)) in indices.iter().zip(dims.iter()) {
+            if *index < *lo as i64 || *index > *hi as i64 {
+                return Err(format!("the index {} is outside {}..{}", index, lo, hi));
+            }
+            linear =
+                linear * (*hi as i64 - *lo as i64 + 1) as usize + (*index - *lo as i64) as usize;
+        }
+        Ok(((**elem).clone(), linear * elem.stride()))
+    }
+}
+
+fn count(dims: &[(i16, i16)]) -> usize {
+    dims.iter()
+        .map(|(lo, hi)| (*hi as i64 - *lo as i64 + 1).max(0) as usize)
+        .product()
+}
+
+/// The bit offsets of the members and the end of the last one.
+fn layout(members: &[Member]) -> (Vec<usize>, usize) {
+    let mut cursor = 0;
+    let offsets = members
+        .iter()
+        .map(|m| {
+            let at = m.ty.align(cursor);
+            cursor = at + m.ty.bits();
+            at
+        })
+        .collect();
+    (offsets, cursor)
+}
+
+impl Interface {
+    fn members(&self, section: Section) -> impl Iterator<Item = &Member> {
+        self.sections
+            .iter()
+            .filter(move |(s, _)| *s == section)
+            .flat_map(|(_, members)| members.iter())
+    }
+
+    /// The parameters and statics in the instance data, each section starting on an even
+    /// address, and the size of the instance data in bits.
+    fn slots(&self) -> (Vec<Slot>, usize) {
+        let mut slots = vec![];
+        let mut cursor = 0;
+        for section in [
+            Section::Input,
+            Section::Output,
+            Section::InOut,
+            Section::Static,
+        ] {
+            cursor = even(cursor);
+            for m in self.members(section) {
+                let ty = if section == Section::InOut && !m.ty.is_elementary() {
+                    Type::Pointer
+                } else {
+                    m.ty.clone()
+                };
+                let at = ty.align(cursor);
+                cursor = at + ty.bits();
+                slots.push(Slot {
+                    name: m.name.clone(),
+                    section,
+                    ty,
+                    at,
+                    init: m.init.clone(),
+                });
+            }
+        }
+        (slots, even(cursor))
+    }
+
+    /// The parameters of a function by index, `RET_VAL` last.
+    fn parameters(&self) -> Vec<(Section, String, Type)> {
+        let mut params: Vec<(Section, String, Type)> =
+            [Section::Input, Section::Output, Section::InOut]
+                .into_iter()
+                .flat_map(|s| {
+                    self.members(s)
+                        .map(move |m| (s, m.name.clone(), m.ty.clone()))
+                })
+                .collect();
+        if let Some(result) = &self.result {
+            params.push((Section::Output, "RET_VAL".to_string(), result.clone()));
+        }
+        params
+    }
+
+    /// The interface segment.
+    fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let (mut entries, mut values) = (vec![], vec![]);
+        for (section, members) in self.sections.iter() {
+            for m in members {
+                entry(
+                    &mut entries,
+                    &mut values,
+                    &m.ty,
+                    section.code(),
+                    m.init.as_deref(),
+                )?;
+            }
+        }
+        if let Some(result) = &self.result {
+            entry(&mut entries, &mut values, result, RETURN, None)?;
+        }
+        let length = |bytes: &[u8]| {
+            u16::try_from(bytes.len())
+                .map(u16::to_be_bytes)
+                .map_err(|_| "the interface is longer than 65535 bytes".to_string())
+        };
+        let mut bytes = length(&entries)?.to_vec();
+        bytes.extend(entries);
+        bytes.extend(length(&values)?);
+        bytes.extend(values);
+        Ok(bytes)
+    }
+}
+
+/// Adds a declaration to the interface segment.
+fn entry(
+    entries: &mut Vec<u8>,
+    values: &mut Vec<u8>,
+    ty: &Type,
+    section: u8,
+    init: Option<&str>,
+) -> Result<(), String> {
+    let flag = if init.is_some() { START_VALUE } else { 0 };
+    entries.extend([ty.code(), section | flag]);
+    match ty {
+        Type::String(len) => entries.push(*len),
+        Type::Array(dims, elem) => {
+            entries.push(dims.len() as u8);
+            for (lo, hi) in dims {
+                entries.extend(lo.to_be_bytes());
+                entries.extend(hi.to_be_bytes());
+            }
+            entry(entries, &mut vec![], elem, section, None)?;
+        }
+        Type::Struct(members) => {
+            entries.push(
+                u8::try_from(members.len())
+                    .map_err(|_| "a structure has more than 255 members".to_string())?,
+            );
+            for m in members {
+                entry(entries, values, &m.ty, section, m.init.as_deref())?;
+            }
+        }
+        Type::Instance(i) => {
+            entries.push(i.block_type);
+            entries.extend(i.number.to_be_bytes());
+        }
+        _ => {}
+    }
+    if let Some(init) = init {
+        values.extend(value(ty, init)?);
+    }
+    Ok(())
+}
+
+/// An integer constant, decimal or with radix, e.g. `L#-5`, `16#0F` or `W#16#FFFF`.
+fn integer(text: &str) -> Option<i64> {
+    let text = text.replace('_', "").to_uppercase();
+    let text = ["L#", "DW#", "W#", "B#"]
+        .iter()
+        .find_map(|p| text.strip_prefix(p))
+        .unwrap_or(&text);
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let value = match digits.split_once('#') {
+        Some((radix, digits)) => i64::from_str_radix(digits, radix.parse().ok()?).ok()?,
+        None => digits.parse().ok()?,
+    };
+    Some(if negative { -value } else { value })
+}
+
+fn strip_prefixes<'t>(text: &'t str, prefixes: &[&str]) -> &'t str {
+    prefixes
+        .iter()
+        .find_map(|p| {
+            text.get(..p.len())
+                .filter(|head| head.eq_ignore_ascii_case(p))
+                .map(|_| &text[p.len()..])
+        })
+        .unwrap_or(text)
+}
+
+fn bcd(value: u32) -> u8 {
+    ((((value / 10) % 10) << 4) | (value % 10)) as u8
+}
+
+/// The bytes of a constant of the type.
+fn value(ty: &Type, text: &str) -> Result<Vec<u8>, String> {
+    let invalid = || format!("{} is not a {} value", text, ty.name());
+    let quoted = || {
+        text.strip_prefix('\'')
+            .and_then(|t| t.strip_suffix('\''))
+            .filter(|t| t.is_ascii())
+            .ok_or_else(invalid)
+    };
+    let ranged = |min: i64, max: i64, width: usize| -> Result<Vec<u8>, String> {
+        let value = integer(text)
+            .filter(|v| (min..=max).contains(v))
+            .ok_or_else(invalid)?;
+        Ok(value.to_be_bytes()[8 - width..].to_vec())
+    };
+    Ok(match ty {
+        Type::Bool => match text.to_uppercase().as_str() {
+            "TRUE" | "1" => vec![1],
+            "FALSE" | "0" => vec![0],
+            _ => return Err(invalid()),
+        },
+        Type::Char => vec![*quoted()?.as_bytes().first().ok_or_else(invalid)?],
+        Type::Byte => ranged(0, 0xFF, 1)?,
+        Type::Word => ranged(i16::MIN as i64, 0xFFFF, 2)?,
+        Type::Int => ranged(i16::MIN as i64, i16::MAX as i64, 2)?,
+        Type::DWord => ranged(i32::MIN as i64, 0xFFFF_FFFF, 4)?,
+        Type::DInt => ranged(i32::MIN as i64, i32::MAX as i64, 4)?,
+        Type::Real => {
+            let value: f64 = text.parse().map_err(|_| invalid())?;
+            (value as f32).to_be_bytes().to_vec()
+        }
+        Type::Time => {
+            let text = strip_prefixes(text, &["T#", "TIME#"]);
+            let (negative, text) = match text.strip_prefix('-') {
+                Some(text) => (true, text),
+                None => (false, text),
+            };
+            let millis = duration(text)
+                .and_then(|d| i32::try_from(d.as_millis()).ok())
+                .ok_or_else(invalid)?;
+            (if negative { -millis } else { millis })
+                .to_be_bytes()
+                .to_vec()
+        }
+        Type::Date => {
+            let date =
+                NaiveDate::parse_from_str(strip_prefixes(text, &["D#", "DATE#"]), "%Y-%m-%d")
+                    .map_err(|_| invalid())?;
+            let epoch = NaiveDate::from_ymd_opt(1990, 1, 1).unwrap_or_default();
+            u16::try_from((date - epoch).num_days())
+                .map_err(|_| invalid())?
+                .to_be_bytes()
+                .to_vec()
+        }
+        Type::TimeOfDay => {
+            let time = NaiveTime::parse_from_str(
+                strip_prefixes(text, &["TOD#", "TIME_OF_DAY#"]),
+                "%H:%M:%S%.f",
+            )
+            .map_err(|_| invalid())?;
+            millis(time).to_be_bytes().to_vec()
+        }
+        Type::DateAndTime => {
+            let at = NaiveDateTime::parse_from_str(
+                strip_prefixes(text, &["DT#", "DATE_AND_TIME#"]),
+                "%Y-%m-%d-%H:%M:%S%.f",
+            )
+            .ok()
+            .filter(|at| (1990..2090).contains(&at.year()))
+            .ok_or_else(invalid)?;
+            let ms = at.nanosecond() / 1_000_000;
+            vec![
+                bcd(at.year() as u32 % 100),
+                bcd(at.month()),
+                bcd(at.day()),
+                bcd(at.hour()),
+                bcd(at.minute()),
+                bcd(at.second()),
+                bcd(ms / 10),
+                ((ms % 10) << 4) as u8 | (at.weekday().number_from_sunday() as u8),
+            ]
+        }
+        Type::String(len) => {
+            let text = quoted()?;
+            if text.len() > *len as usize {
+                return Err(format!("{} is longer than {} characters", text, len));
+            }
+            let mut bytes = vec![*len, text.len() as u8];
+            bytes.extend(text.as_bytes());
+            bytes
+        }
+        Type::Array(dims, elem) => {
+            let elements = split_list(text);
+            if elements.len() > count(dims) {
+                return Err(format!(
+                    "{} has {} values for {} elements",
+                    text,
+                    elements.len(),
+                    count(dims)
+                ));
+            }
+            let mut bytes = vec![];
+            for element in elements {
+                bytes.extend(value(elem, element)?);
+            }
+            bytes
+        }
+        Type::Struct(_) | Type::Instance(_) | Type::Pointer => {
+            return Err(format!("{} takes no start value in STL", ty.name()))
+        }
+    })
+}
+
+/// Writes the start value into the data of a data block, strings get their length.
+fn store(data: &mut [u8], ty: &Type, at: usize, init: Option<&str>) -> Result<(), String> {
+    let byte = at / 8;
+    match ty {
+        Type::Bool => {
+            if init.map(|init| value(ty, init)).transpose()? == Some(vec![1]) {
+                data[byte] |= 1 << (at % 8);
+            }
+        }
+        Type::String(len) => {
+            let bytes = match init {
+                Some(init) => value(ty, init)?,
+                None => vec![*len, 0],
+            };
+            data[byte..byte + bytes.len()].copy_from_slice(&bytes);
+        }
+        Type::Array(dims, elem) => {
+            let inits = init.map(split_list).unwrap_or_default();
+            if inits.len() > count(dims) {
+                return Err(format!(
+                    "{} values for {} elements",
+                    inits.len(),
+                    count(dims)
+                ));
+            }
+            for i in 0..count(dims) {
+                store(data, elem, at + i * elem.stride(), inits.get(i).copied())?;
+            }
+        }
+        Type::Struct(members) => {
+            if init.is_some() {
+                return Err("STRUCT takes no start value in STL".to_string());
+            }
+            let (offsets, _) = layout(members);
+            for (m, offset) in members.iter().zip(offsets) {
+                store(data, &m.ty, at + offset, m.init.as_deref())?;
+            }
+        }
+        Type::Instance(i) => {
+            if init.is_some() {
+                return Err(format!("{} takes no start value in STL", ty.name()));
+            }
+            for slot in i.interface.slots().0 {
+                store(data, &slot.ty, at + slot.at, slot.init.as_deref())?;
+            }
+        }
+        Type::Pointer => {}
+        _ => {
+            if let Some(init) = init {
+                let bytes = value(ty, init)?;
+                data[byte..byte + bytes.len()].copy_from_slice(&bytes);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The IEC timers and counters by their SFB number, with the statics of their instances.
+fn system_block(number: u16) -> Option<Type> {
+    let members = |vars: &[(&str, Type)]| {
+        vars.iter()
+            .map(|(name, ty)| Member {
+                name: name.to_string(),
+                ty: ty.clone(),
+                init: None,
+            })
+            .collect::<Vec<_>>()
+    };
+    let (inputs, outputs, statics) = match number {
+        0 => (
+            members(&[("CU", Type::Bool), ("R", Type::Bool), ("PV", Type::Int)]),
+            members(&[("Q", Type::Bool), ("CV", Type::Int)]),
+            members(&[("CUO", Type::Bool)]),
+        ),
+        1 => (
+            members(&[("CD", Type::Bool), ("LOAD", Type::Bool), ("PV", Type::Int)]),
+            members(&[("Q", Type::Bool), ("CV", Type::Int)]),
+            members(&[("CDO", Type::Bool)]),
+        ),
+        2 => (
+            members(&[
+                ("CU", Type::Bool),
+                ("CD", Type::Bool),
+                ("R", Type::Bool),
+                ("LOAD", Type::Bool),
+                ("PV", Type::Int),
+            ]),
+            members(&[("QU", Type::Bool), ("QD", Type::Bool), ("CV", Type::Int)]),
+            members(&[("CUO", Type::Bool), ("CDO", Type::Bool)]),
+        ),
+        3..=5 => (
+            members(&[("IN", Type::Bool), ("PT", Type::Time)]),
+            members(&[("Q", Type::Bool), ("ET", Type::Time)]),
+            members(&[
+                ("STATE", Type::Byte),
+                ("STIME", Type::Time),
+                ("ATIME", Type::Time),
+            ]),
+        ),
+        _ => return None,
+    };
+    Some(Type::Instance(Box::new(Instance {
+        block_type: SFB,
+        number,
+        interface: Interface {
+            sections: vec![
+                (Section::Input, inputs),
+                (Section::Output, outputs),
+                (Section::Static, statics),
+            ],
+            result: None,
+        },
+    })))
+}
+
+/// The declarations of a block and the lines of its body.
+struct Decls<'s> {
+    version: (u8, u8),
+    author: String,
+    family: String,
+    name: String,
+    sections: Vec<(Section, Vec<Member>)>,
+    /// The members of a `TYPE`.
+    members: Vec<Member>,
+    /// The function block of an instance data block.
+    instance: Option<BlockNumber>,
+    body: Vec<(usize, &'s str)>,
+}
+
+/// The types and interfaces declared so far.
+#[derive(Default)]
+struct Source {
+    types: HashMap<u16, Vec<Member>>,
+    interfaces: HashMap<BlockNumber, Interface>,
+}
+
+impl Source {
+    fn decls<'s>(
+        &self,
+        lines: &[(usize, &'s str)],
+        start: usize,
+    ) -> Result<Decls<'s>, Rust2PlcError> {
+        let mut decls = Decls {
+            version: (0, 0),
+            author: String::new(),
+            family: String::new(),
+            name: String::new(),
+            sections: vec![],
+            members: vec![],
+            instance: None,
+            body: vec![],
+        };
+        let mut i = 0;
+        while i < lines.len() {
+            let (n, line) = lines[i];
+            i += 1;
+            let text = line.trim();
+            let at = |msg: String| Rust2PlcError::parse_at(msg, n, column(line));
+            let keyword = text.split([' ', ':']).next().unwrap_or_default();
+            let setting = text
+                .split_once(':')
+                .map(|(_, value)| value.trim().to_string())
+                .unwrap_or_default();
+            match keyword {
+                "" | "TITLE" => {}
+                "BEGIN" => {
+                    decls.body = lines[i..].to_vec();
+                    break;
+                }
+                "VERSION" => {
+                    let version = setting
+                        .split_once('.')
+                        .and_then(|(major, minor)| Some((major.parse().ok()?, minor.parse().ok()?)))
+                        .filter(|(major, minor)| *major < 16 && *minor < 16)
+                        .ok_or_else(|| at(format!("{} is not a block version", setting)))?;
+                    decls.version = version;
+                }
+                "AUTHOR" => decls.author = setting,
+                "FAMILY" => decls.family = setting,
+                "NAME" => decls.name = setting,
+                "STRUCT" | "VAR_INPUT" | "VAR_OUTPUT" | "VAR_IN_OUT" | "VAR" | "VAR_TEMP" => {
+                    let end = if keyword == "STRUCT" {
+                        "END_STRUCT"
+                    } else {
+                        "END_VAR"
+                    };
+                    let mut members = vec![];
+                    loop {
+                        let Some((n, line)) = lines.get(i).copied() else {
+                            return Err(Rust2PlcError::parse_at(
+                                format!("{} has no {}", keyword, end),
+                                start,
+                                1,
+                            ));
+                        };
+                        i += 1;
+                        if line.trim().trim_end_matches([' ', ';']) == end {
+                            break;
+                        }
+                        members.push(
+                            self.member(line.trim())
+                                .map_err(|msg| Rust2PlcError::parse_at(msg, n, column(line)))?,
+                        );
+                    }
+                    match Section::from_keyword(keyword) {
+                        Some(section) => decls.sections.push((section, members)),
+                        None => decls.members = members,
+                    }
+                }
+                _ => match text.parse::<BlockNumber>() {
+                    Ok(fb) if fb.kind == BlockKind::Fb && decls.instance.is_none() => {
+                        decls.instance = Some(fb)
+                    }
+                    _ => return Err(at(format!("{} is not a declaration", text))),
+                },
+            }
+        }
+        Ok(decls)
+    }
+
+    /// `name : TYPE := value;`
+    fn member(&self, text: &str) -> Result<Member, String> {
+        let text = text.trim_end_matches(';').trim();
+        let (name, rest) = text
+            .split_once(':')
+            .ok_or_else(|| format!("{} is not a declaration", text))?;
+        let (ty, init) = match rest.split_once(":=") {
+            Some((ty, init)) => (ty, Some(init.trim().to_string())),
+            None => (rest, None),
+        };
+        Ok(Member {
+            name: name.trim().to_string(),
+            ty: self.parse_type(ty.trim())?,
+            init,
+        })
+    }
+
+    /// An elementary type, `STRING [n]`, `ARRAY [a .. b] OF t`, `UDT n`, `FB n` or `SFB n`.
+    fn parse_type(&self, text: &str) -> Result<Type, String> {
+        let upper = text.to_uppercase();
+        let invalid = || format!("{} is not an S7-300/400 type", text);
+        Ok(match upper.as_str() {
+            "BOOL" => Type::Bool,
+            "BYTE" => Type::Byte,
+            "CHAR" => Type::Char,
+            "WORD" => Type::Word,
+            "INT" => Type::Int,
+            "DWORD" => Type::DWord,
+            "DINT" => Type::DInt,
+            "REAL" => Type::Real,
+            "DATE" => Type::Date,
+            "TIME_OF_DAY" | "TOD" => Type::TimeOfDay,
+            "TIME" => Type::Time,
+            "DATE_AND_TIME" | "DT" => Type::DateAndTime,
+            _ if upper.starts_with("STRING") => {
+                let len = upper["STRING".len()..].trim();
+                if len.is_empty() {
+                    Type::String(254)
+                } else {
+                    len.strip_prefix('[')
+                        .and_then(|len| len.strip_suffix(']'))
+                        .and_then(|len| len.trim().parse().ok())
+                        .map(Type::String)
+                        .ok_or_else(invalid)?
+                }
+            }
+            _ if upper.starts_with("ARRAY") => {
+                let (open, close) = upper.find('[').zip(upper.find(']')).ok_or_else(invalid)?;
+                let dims = upper[open + 1..close]
+                    .split(',')
+                    .map(|dim| {
+                        let (lo, hi) = dim.split_once("..")?;
+                        let (lo, hi) = (lo.trim().parse().ok()?, hi.trim().parse().ok()?);
+                        (lo <= hi).then_some((lo, hi))
+                    })
+                    .collect::<Option<Vec<(i16, i16)>>>()
+                    .ok_or_else(invalid)?;
+                let elem = upper[close + 1..]
+                    .trim()
+                    .strip_prefix("OF ")
+                    .ok_or_else(invalid)?;
+                Type::Array(
+                    dims,
+                    Box::new(self.parse_type(&text[text.len() - elem.len()..])?),
+                )
+            }
+            _ => {
+                let digits = upper
+                    .find(|c: char| c.is_ascii_digit())
+                    .ok_or_else(invalid)?;
+                let number: u16 = upper[digits..].trim().parse().map_err(|_| invalid())?;
+                match upper[..digits].trim() {
+                    "UDT" => {
+                        Type::Struct(self.types.get(&number).cloned().ok_or_else(|| {
+                            format!("UDT {} is used before it is declared", number)
+                        })?)
+                    }
+                    "FB" => Type::Instance(Box::new(Instance {
+                        block_type: FB,
+                        number,
+                        interface: self
+                            .interfaces
+                            .get(&BlockNumber::new(BlockKind::Fb, number))
+                            .cloned()
+                            .ok_or_else(|| {
+                                format!("FB {} is used before it is declared", number)
+                            })?,
+                    })),
+                    "SFB" => system_block(number).ok_or_else(|| {
+                        format!("SFB {} is not one of the IEC timers and counters", number)
+                    })?,
+                    _ => return Err(invalid()),
+                }
+            }
+        })
+    }
+
+    /// A function, function block or organization block.
+    fn code_block(
+        &mut self,
+        number: BlockNumber,
+        result: Option<Type>,
+        decls: Decls,
+        line: usize,
+    ) -> Result<Block, Rust2PlcError> {
+        let at = |msg: String| Rust2PlcError::parse_at(msg, line, 1);
+        let interface = Interface {
+            sections: decls.sections,
+            result,
+        };
+        let mut vars = HashMap::new();
+        let temps: Vec<&Member> = interface.members(Section::Temp).collect();
+        let mut cursor = 0;
+        for m in temps {
+            let address = m.ty.align(cursor);
+            cursor = address + m.ty.bits();
+            vars.insert(
+                m.name.clone(),
+                Var {
+                    ty: m.ty.clone(),
+                    place: Place::Memory(LOCAL, address),
+                },
+            );
+        }
+        let has = |section| interface.members(section).next().is_some();
+        match number.kind {
+            BlockKind::Fc if has(Section::Static) => {
+                return Err(at(format!(
+                    "{} declares static variables, functions keep no state",
+                    number
+                )))
+            }
+            BlockKind::Fc => {
+                for (index, (_, name, ty)) in interface.parameters().into_iter().enumerate() {
+                    vars.insert(
+                        name,
+                        Var {
+                            ty,
+                            place: Place::Param(index as u16, 0),
+                        },
+                    );
+                }
+            }
+            BlockKind::Fb => {
+                for slot in interface.slots().0 {
+                    vars.insert(
+                        slot.name,
+                        Var {
+                            ty: slot.ty,
+                            place: Place::Memory(DI, slot.at),
+                        },
+                    );
+                }
+            }
+            _ if [
+                Section::Input,
+                Section::Output,
+                Section::InOut,
+                Section::Static,
+            ]
+            .into_iter()
+            .any(has) =>
+            {
+                return Err(at(format!(
+                    "{} declares parameters, the operating system calls it without",
+                    number
+                )))
+            }
+            _ => {}
+        }
+
+        let mut code = Code {
+            source: self,
+            vars,
+            bytes: vec![],
+            labels: HashMap::new(),
+            jumps: vec![],
+            local: even(cursor),
+        };
+        for (n, line) in decls.body.iter() {
+            code.line(line)
+                .map_err(|msg| Rust2PlcError::parse_at(msg, *n, column(line)))?;
+        }
+        let local = code.local;
+        let bytes = code.finish()?;
+        let local_data = u16::try_from(even(local) / 8).map_err(|_| {
+            at(format!(
+                "{} needs more than 65535 bytes of local data",
+                number
+            ))
+        })?;
+        let segment = interface.to_bytes().map_err(at)?;
+        if number.kind != BlockKind::Ob {
+            self.interfaces.insert(number, interface);
+        }
+        Ok(Block::new(number, Language::Awl, bytes, segment)
+            .with_local_data(local_data)
+            .with_version(decls.version.0, decls.version.1)
+            .with_author(decls.author)
+            .with_family(decls.family)
+            .with_name(decls.name))
+    }
+
+    /// An instance data block, the data of its function block with the start values.
+    fn data_block(&self, number: BlockNumber, decls: Decls) -> Result<Block, String> {
+        let Some(fb) = decls.instance else {
+            return Err(format!(
+                "{} declares its own structure, only instance data blocks are assembled",
+                number
+            ));
+        };
+        let interface = self.interfaces.get(&fb).ok_or_else(|| {
+            format!(
+                "{} is the instance of {}, which is not declared before it",
+                number, fb
+            )
+        })?;
+        let interface = Interface {
+            sections: interface
+                .sections
+                .iter()
+                .filter(|(section, _)| *section != Section::Temp)
+                .cloned()
+                .collect(),
+            result: None,
+        };
+        let (slots, size) = interface.slots();
+        let mut data = vec![0u8; size / 8];
+        for slot in slots {
+            store(&mut data, &slot.ty, slot.at, slot.init.as_deref())?;
+        }
+        Ok(
+            Block::new(number, Language::Db, data, interface.to_bytes()?)
+                .with_version(decls.version.0, decls.version.1)
+                .with_author(decls.author)
+                .with_family(decls.family)
+                .with_name(decls.name),
+        )
+    }
+}
+
+/// Where a variable is held: in a memory area at a bit address, or in the parameter of
+/// a function with the bit offset into its value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Place {
+    Memory(u8, usize),
+    Param(u16, usize),
+}
+
+impl Place {
+    fn offset(self, bits: usize) -> Place {
+        match self {
+            Place::Memory(area, at) => Place::Memory(area, at + bits),
+            Place::Param(index, at) => Place::Param(index, at + bits),
+        }
+    }
+}
+
+struct Var {
+    ty: Type,
+    place: Place,
+}
+
+/// The assembly of a block body.
+struct Code<'s> {
+    source: &'s Source,
+    vars: HashMap<String, Var>,
+    bytes: Vec<u8>,
+    labels: HashMap<String, usize>,
+    /// The jumps to resolve, the position of the instruction and the label.
+    jumps: Vec<(usize, String)>,
+    /// The end of the local data in bits, constants passed to calls follow the temporaries.
+    local: usize,
+}
+
+impl Code<'_> {
+    fn line(&mut self, line: &str) -> Result<(), String> {
+        let mut text = line.trim();
+        if text.is_empty() || text == "NETWORK" || text.starts_with("TITLE") {
+            return Ok(());
+        }
+        if let Some((label, rest)) = text.split_once(':') {
+            if !rest.starts_with('=') && is_label(label) {
+                if self
+                    .labels
+                    .insert(label.to_string(), self.bytes.len())
+                    .is_some()
+                {
+                    return Err(format!("the label {} is defined twice", label));
+                }
+                text = rest.trim();
+            }
+        }
+        let text = text
+            .strip_suffix(';')
+            .ok_or_else(|| format!("{} is not terminated by ;", text))?
+            .trim();
+        let (op, operand) = match text.split_once(char::is_whitespace) {
+            Some((op, operand)) => (op, operand.trim()),
+            None => (text, ""),
+        };
+        self.instruction(op, operand)
+    }
+
+    fn instruction(&mut self, op: &str, operand: &str) -> Result<(), String> {
+        match op {
+            "NOP" => match operand {
+                "0" => self.bytes.extend([0x00, 0x00]),
+                "1" => self.bytes.extend([0xFF, 0xFF]),
+                _ => return Err(format!("NOP takes 0 or 1, not {}", operand)),
+            },
+            "L" if operand.starts_with(['#', '"']) => {
+                let (ty, place) = self.resolve(operand)?;
+                self.access(&LOAD, &ty, &place, operand)?;
+            }
+            "L" => self.constant(operand)?,
+            "T" => {
+                let (ty, place) = self.resolve(operand)?;
+                self.access(&TRANSFER, &ty, &place, operand)?;
+            }
+            "CALL" => self.call(operand)?,
+            _ => {
+                if let Some((_, code)) = BITS.iter().find(|(bit, _)| *bit == op) {
+                    let (ty, place) = self.resolve(operand)?;
+                    if ty != Type::Bool {
+                        return Err(format!("{} is {}, {} takes a BOOL", operand, ty.name(), op));
+                    }
+                    self.bit(*code, &place)?;
+                } else if let Some((_, code)) = JUMPS.iter().find(|(jump, _)| *jump == op) {
+                    if !is_label(operand) {
+                        return Err(format!("{} is not a label", operand));
+                    }
+                    self.jumps.push((self.bytes.len(), operand.to_string()));
+                    self.bytes.extend(code);
+                    self.bytes.extend([0, 0]);
+                } else if let Some(code) = plain(op) {
+                    if !operand.is_empty() {
+                        return Err(format!("{} takes no operand", op));
+                    }
+                    self.bytes.extend(code);
+                } else {
+                    return Err(format!("{} has no MC7 encoding", op));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolves the jumps and closes the block with `BE`.
+    fn finish(mut self) -> Result<Vec<u8>, Rust2PlcError> {
+        for (position, label) in self.jumps.iter() {
+            let target = self.labels.get(label).ok_or_else(|| {
+                Rust2PlcError::Other(format!("the label {} is not defined", label))
+            })?;
+            let words = i16::try_from((*target as i64 - *position as i64) / 2).map_err(|_| {
+                Rust2PlcError::Other(format!("the label {} is more than 32767 words away", label))
+            })?;
+            self.bytes[position + 2..position + 4].copy_from_slice(&words.to_be_bytes());
+        }
+        self.bytes.extend(BE);
+        Ok(self.bytes)
+    }
+
+    /// The type and the place of `#name`, `#name.member` or `#name[1, 2]`.
+    fn resolve(&self, operand: &str) -> Result<(Type, Place), String> {
+        if operand.starts_with('"') {
+            return Err(format!(
+                "{} is a symbol, MC7 needs its absolute address",
+                operand
+            ));
+        }
+        let path = operand
+            .strip_prefix('#')
+            .ok_or_else(|| format!("{} is not a variable of the block", operand))?;
+        let end = path.find(['.', '[']).unwrap_or(path.len());
+        let (name, mut rest) = path.split_at(end);
+        let var = self
+            .vars
+            .get(name)
+            .ok_or_else(|| format!("#{} is not declared in the block", name))?;
+        let (mut ty, mut place) = (var.ty.clone(), var.place);
+        while !rest.is_empty() {
+            if let Some(tail) = rest.strip_prefix('.') {
+                let end = tail.find(['.', '[']).unwrap_or(tail.len());
+                let (member, tail) = tail.split_at(end);
+                if ty == Type::Pointer {
+                    return Err(format!(
+                        "#{} is passed by pointer, its members cannot be addressed",
+                        name
+                    ));
+                }
+                let (member_ty, offset) = ty
+                    .member(member)
+                    .ok_or_else(|| format!("{} has no member {}", ty.name(), member))?;
+                ty = member_ty;
+                place = place.offset(offset);
+                rest = tail;
+            } else if let Some(tail) = rest.strip_prefix('[') {
+                let close = tail
+                    .find(']')
+                    .ok_or_else(|| format!("{} has no closing ]", operand))?;
+                let indices = tail[..close]
+                    .split(',')
+                    .map(|i| i.trim().parse::<i64>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| format!("{} is not indexed by constants", operand))?;
+                let (element, offset) = ty.element(&indices)?;
+                ty = element;
+                place = place.offset(offset);
+                rest = &tail[close + 1..];
+            } else {
+                return Err(format!("{} is not a variable of the block", operand));
+            }
+        }
+        Ok((ty, place))
+    }
+
+    fn operand(&mut self, place: &Place) -> Result<(), String> {
+        let address = |bits: usize| {
+            u16::try_from(bits / 8)
+                .map(u16::to_be_bytes)
+                .map_err(|_| format!("the address {} is beyond 65535", bits / 8))
+        };
+        match *place {
+            Place::Memory(area, at) => {
+                self.bytes.push(((area & 0x0F) << 4) | (at % 8) as u8);
+                self.bytes.extend(address(at)?);
+            }
+            Place::Param(index, at) => {
+                self.bytes.push(0x80 | (at % 8) as u8);
+                self.bytes.extend(index.to_be_bytes());
+                self.bytes.extend(address(at)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn bit(&mut self, code: u8, place: &Place) -> Result<(), String> {
+        self.bytes.push(code);
+        self.operand(place)
+    }
+
+    /// `L` or `T` of a value of 8, 16 or 32 bits.
+    fn access(
+        &mut self,
+        codes: &[u8; 3],
+        ty: &Type,
+        place: &Place,
+        operand: &str,
+    ) -> Result<(), String> {
+        let size = match ty {
+            Type::Byte | Type::Char => 0,
+            Type::Word | Type::Int | Type::Date => 1,
+            Type::DWord | Type::DInt | Type::Real | Type::Time | Type::TimeOfDay => 2,
+            _ => {
+                return Err(format!(
+                    "{} is {}, L and T move values of 8, 16 or 32 bits",
+                    operand,
+                    ty.name()
+                ))
+            }
+        };
+        self.bytes.push(codes[size]);
+        self.operand(place)
+    }
+
+    /// `L` of a constant, 16-bit values take a word and 32-bit values two.
+    fn constant(&mut self, text: &str) -> Result<(), String> {
+        let upper = text.to_uppercase();
+        let starts = |prefixes: &[&str]| prefixes.iter().any(|p| upper.starts_with(p));
+        let (ty, code) = if starts(&["T#", "TIME#"]) {
+            (Type::Time, [0x38, 0x09])
+        } else if starts(&["D#", "DATE#"]) {
+            (Type::Date, [0x30, 0x0C])
+        } else if starts(&["TOD#", "TIME_OF_DAY#"]) {
+            (Type::TimeOfDay, [0x38, 0x0B])
+        } else if starts(&["L#"]) {
+            (Type::DInt, [0x38, 0x03])
+        } else if starts(&["DW#"]) {
+            (Type::DWord, [0x38, 0x07])
+        } else if upper.contains('#') {
+            (Type::Word, [0x30, 0x07])
+        } else if upper.contains(['.', 'E']) {
+            (Type::Real, [0x38, 0x01])
+        } else {
+            (Type::Int, [0x30, 0x03])
+        };
+        let bytes = value(&ty, text).map_err(|e| match ty {
+            Type::Int if integer(text).is_some() => {
+                format!("{} does not fit 16 bits, write L#{}", text, text)
+            }
+            _ => e,
+        })?;
+        self.bytes.extend(code);
+        self.bytes.extend(bytes);
+        Ok(())
+    }
+
+    /// A new variable in the local data, behind the temporaries.
+    fn alloc(&mut self, ty: &Type) -> Place {
+        let at = ty.align(self.local);
+        self.local = at + ty.bits();
+        Place::Memory(LOCAL, at)
+    }
+
+    /// Moves a variable or a constant into `to`.
+    fn copy_in(&mut self, ty: &Type, to: &Place, actual: &str) -> Result<(), String> {
+        let variable = actual.starts_with(['#', '"']);
+        if *ty == Type::Bool {
+            match actual.to_uppercase().as_str() {
+                "TRUE" => self.bytes.extend(plain("SET").unwrap_or_default()),
+                "FALSE" => self.bytes.extend(plain("CLR").unwrap_or_default()),
+                _ => self.instruction("A", actual)?,
+            }
+            self.bit(BITS[6].1, to)
+        } else {
+            if variable {
+                self.instruction("L", actual)?;
+            } else {
+                self.constant(actual)?;
+            }
+            self.access(&TRANSFER, ty, to, actual)
+        }
+    }
+
+    /// Moves `from` into the variable `target`.
+    fn copy_out(&mut self, ty: &Type, from: &Place, target: &str) -> Result<(), String> {
+        if *ty == Type::Bool {
+            self.bit(BITS[0].1, from)?;
+            self.instruction("=", target)
+        } else {
+            self.access(&LOAD, ty, from, target)?;
+            self.instruction("T", target)
+        }
+    }
+
+    /// `CALL FC 10 (x := #a, RET_VAL := #b)`, `CALL FB 5 , DB 5` or `CALL #timer (IN := #a)`.
+    fn call(&mut self, operand: &str) -> Result<(), String> {
+        let (callee, params) = match operand.split_once('(') {
+            Some((callee, params)) => (
+                callee.trim(),
+                params
+                    .trim_end()
+                    .strip_suffix(')')
+                    .ok_or_else(|| format!("{} has no closing )", operand))?,
+            ),
+            None => (operand.trim(), ""),
+        };
+        let params = split_list(params)
+            .into_iter()
+            .map(|param| {
+                param
+                    .split_once(":=")
+                    .map(|(formal, actual)| (formal.trim(), actual.trim()))
+                    .ok_or_else(|| format!("{} is not a formal parameter", param))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if callee.starts_with('#') {
+            return self.multi_instance(callee, &params);
+        }
+        if let Some((fb, db)) = callee.split_once(',') {
+            let (fb, db): (BlockNumber, BlockNumber) = (
+                fb.parse().map_err(|e: Rust2PlcError| e.to_string())?,
+                db.parse().map_err(|e: Rust2PlcError| e.to_string())?,
+            );
+            if fb.kind != BlockKind::Fb || db.kind != BlockKind::Db {
+                return Err(format!(
+                    "{} is not a function block with its data block",
+                    callee
+                ));
+            }
+            if !params.is_empty() {
+                return Err(format!(
+                    "the parameters of {} are set in {}, call it without",
+                    fb, db
+                ));
+            }
+            self.bytes.extend(CALL_FB);
+            self.bytes.extend(fb.number.to_be_bytes());
+            self.bytes.extend(db.number.to_be_bytes());
+            return Ok(());
+        }
+
+        let fc: BlockNumber = callee.parse().map_err(|e: Rust2PlcError| e.to_string())?;
+        if fc.kind != BlockKind::Fc {
+            return Err(format!("{} is called without an instance data block", fc));
+        }
+        let interface = self
+            .source
+            .interfaces
+            .get(&fc)
+            .ok_or_else(|| format!("{} is called before it is declared", fc))?;
+        let mut pointers = vec![];
+        for (section, formal, ty) in interface.parameters() {
+            let (_, actual) = params
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(&formal))
+                .ok_or_else(|| {
+                    format!("the call of {} has no actual parameter for {}", fc, formal)
+                })?;
+            let place = match self.resolve(actual) {
+                Ok((_, place @ Place::Memory(_, _))) => place,
+                Ok((_, Place::Param(_, _))) | Err(_) if section == Section::Input => {
+                    let hidden = self.alloc(&ty);
+                    self.copy_in(&ty, &hidden, actual)?;
+                    hidden
+                }
+                Ok(_) => {
+                    return Err(format!(
+                        "{} is a parameter of the function and cannot be passed on as {}",
+                        actual, formal
+                    ))
+                }
+                Err(e) => return Err(e),
+            };
+            let Place::Memory(area, at) = place else {
+                unreachable!("parameters are copied into the local data")
+            };
+            let at = u32::try_from(at)
+                .ok()
+                .filter(|at| *at < 1 << 24)
+                .ok_or_else(|| format!("the address of {} is out of reach", actual))?;
+            pointers.push([
+                if area == LOCAL { V } else { area },
+                (at >> 16) as u8,
+                (at >> 8) as u8,
+                at as u8,
+            ]);
+        }
+        self.bytes.extend(CALL_FC);
+        self.bytes.extend(fc.number.to_be_bytes());
+        self.bytes.extend((pointers.len() as u16).to_be_bytes());
+        self.bytes.extend(pointers.concat());
+        Ok(())
+    }
+
+    /// A multi-instance, the parameters are copied into its instance data around the call.
+    fn multi_instance(&mut self, callee: &str, params: &[(&str, &str)]) -> Result<(), String> {
+        let (Type::Instance(instance), Place::Memory(DI, base)) = self.resolve(callee)? else {
+            return Err(format!(
+                "{} is not an instance in the statics of the block",
+                callee
+            ));
+        };
+        let slots = instance.interface.slots().0;
+        let mut outputs = vec![];
+        for (formal, actual) in params {
+            let slot = slots
+                .iter()
+                .find(|s| s.name.eq_ignore_ascii_case(formal))
+                .ok_or_else(|| {
+                    format!("{} has no parameter {}", instance_name(&instance), formal)
+                })?;
+            let place = Place::Memory(DI, base + slot.at);
+            match slot.section {
+                Section::Input => self.copy_in(&slot.ty, &place, actual)?,
+                Section::Output => outputs.push((&slot.ty, place, *actual)),
+                Section::InOut => {
+                    self.copy_in(&slot.ty, &place, actual)?;
+                    outputs.push((&slot.ty, place, *actual));
+                }
+                _ => {
+                    return Err(format!(
+                        "{} is a static of {}, not a parameter",
+                        formal,
+                        instance_name(&instance)
+                    ))
+                }
+            }
+        }
+        self.bytes.extend(if instance.block_type == SFB {
+            CALL_MULTI_SFB
+        } else {
+            CALL_MULTI_FB
+        });
+        self.bytes.extend(instance.number.to_be_bytes());
+        self.bytes.extend(
+            u16::try_from(base / 8)
+                .map_err(|_| format!("{} is beyond byte 65535", callee))?
+                .to_be_bytes(),
+        );
+        for (ty, from, target) in outputs {
+            self.copy_out(ty, &from, target)?;
+        }
+        Ok(())
+    }
+}
+
+fn instance_name(instance: &Instance) -> String {
+    Type::Instance(Box::new(instance.clone())).name()
+}
+
+/// The code of an instruction without operand, comparisons included.
+fn plain(op: &str) -> Option<[u8; 2]> {
+    if let Some((_, code)) = PLAIN
+        .iter()
+        .chain(DOUBLE.iter())
+        .find(|(name, _)| *name == op)
+    {
+        return Some(*code);
+    }
+    let group = match op.chars().last()? {
+        'I' => 0x21,
+        'D' => 0x3D,
+        'R' => 0x31,
+        _ => return None,
+    };
+    let comparison = match &op[..op.len() - 1] {
+        ">" => 0x20,
+        "<" => 0x40,
+        "<>" => 0x60,
+        "==" => 0x80,
+        ">=" => 0xA0,
+        "<=" => 0xC0,
+        _ => return None,
+    };
+    Some([group, comparison])
+}
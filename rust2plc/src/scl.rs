@@ -0,0 +1,716 @@
+//! Siemens SCL as imported by TIA Portal external sources.
+//!
+//! The POU models render as SCL blocks: names of blocks, types and globals are quoted,
+//! the block's own variables are prefixed with `#` and the body is wrapped in a `REGION`.
+//! SCL has no programs, they become function blocks called from an organization block.
+//!
+//! ```
+//! use rust2plc::st::ast::{BinaryOp, Expr, Stmt};
+//! use rust2plc::st::function::Function;
+//! use rust2plc::types::TypedValue;
+//! use rust2plc::var::Value;
+//!
+//! let twice = Function::new("twice", TypedValue::new_int())
+//!     .with_input(Value::Input("x".to_string(), TypedValue::new_int()))
+//!     .with_body(vec![Stmt::assign(
+//!         Expr::var("twice"),
+//!         Expr::binary(BinaryOp::Mul, Expr::var("x"), Expr::int(2)),
+//!     )]);
+//! assert_eq!(
+//!     twice.to_scl().unwrap(),
+//!     "FUNCTION \"twice\" : Int\n\
+//!      { S7_Optimized_Access := 'TRUE' }\n\
+//!      VERSION : 0.1\n   VAR_INPUT\n      x : Int;\n   END_VAR\n\n\
+//!      BEGIN\n   REGION twice\n      #twice := #x * 2;\n   END_REGION\n\
+//!      END_FUNCTION\n"
+//! );
+//! ```
+//!
+//! Calls of functions with several parameters need formal arguments in TIA Portal,
+//! positional arguments are named after the inputs of the functions made known with
+//! [`SclPrinter::with_function`], e.g. `"scale"(raw := #raw, gain := 0.5)`.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use crate::error::Rust2PlcError;
+use crate::registry::RegistryItem;
+use crate::st::ast::{Arg, Branch, Call, CaseBranch, CaseLabel, Expr, Literal, Printer, Stmt};
+use crate::st::data_type::DataType;
+use crate::st::function::Function;
+use crate::st::function_block::FunctionBlock;
+use crate::st::parser;
+use crate::st::program::Program;
+use crate::types::TypedValue;
+use crate::var::Value;
+
+/// The standard functions called without quotes.
+const STANDARD_FUNCTIONS: [&str; 36] = [
+    "ABS", "SQRT", "SQR", "LN", "LOG", "EXP", "EXPT", "SIN", "COS", "TAN", "ASIN", "ACOS", "ATAN",
+    "MIN", "MAX", "LIMIT", "SEL", "MUX", "TRUNC", "ROUND", "CEIL", "FLOOR", "FRAC", "SHL", "SHR",
+    "ROL", "ROR", "LEN", "LEFT", "RIGHT", "MID", "CONCAT", "INSERT", "DELETE", "REPLACE", "FIND",
+];
+
+/// The S7 data type `TIME` variables are declared with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SclTime {
+    /// 32-bit milliseconds, up to `T#24d20h31m23s647ms`.
+    #[default]
+    Time,
+    /// 64-bit nanoseconds, S7-1500 only.
+    LTime,
+    /// The legacy BCD timer value of the S5 timers, up to `S5T#2h46m30s`
+    /// with three significant digits.
+    S5Time,
+}
+
+/// Renders the models as SCL.
+#[derive(Debug, Clone)]
+pub struct SclPrinter {
+    indent: usize,
+    time: SclTime,
+    optimized_access: bool,
+    /// The input names of the callable functions, keyed by their upper case name.
+    functions: HashMap<String, Vec<String>>,
+}
+
+impl Default for SclPrinter {
+    fn default() -> Self {
+        SclPrinter {
+            indent: 3,
+            time: SclTime::Time,
+            optimized_access: true,
+            functions: HashMap::new(),
+        }
+    }
+}
+
+impl SclPrinter {
+    pub fn new() -> Self {
+        SclPrinter::default()
+    }
+
+    /// The number of spaces per nesting level.
+    pub fn with_indent(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    pub fn with_time(mut self, time: SclTime) -> Self {
+        self.time = time;
+        self
+    }
+
+    /// Whether the blocks use optimized access, on by default.
+    pub fn with_optimized_access(mut self, optimized_access: bool) -> Self {
+        self.optimized_access = optimized_access;
+        self
+    }
+
+    /// Makes a function known to the calls in the printed blocks, their positional
+    /// arguments are written as formal ones with its input names.
+    pub fn with_function(mut self, function: &Function) -> Self {
+        let inputs = function
+            .inputs()
+            .iter()
+            .filter_map(|v| v.name().map(str::to_string))
+            .collect();
+        self.functions
+            .insert(function.name().to_uppercase(), inputs);
+        self
+    }
+
+    pub fn item(&self, item: &RegistryItem) -> Result<String, Rust2PlcError> {
+        match item {
+            RegistryItem::StFn(f) => self.function(f),
+            RegistryItem::StFb(fb) => self.function_block(fb),
+            RegistryItem::StProgram(p) => self.program(p),
+            RegistryItem::StType(t) => self.data_type(t),
+            RegistryItem::StConfig(c) => Err(Rust2PlcError::Other(format!(
+                "the configuration {} has no SCL counterpart, tasks are set up in the device configuration",
+                c.name()
+            ))),
+        }
+    }
+
+    /// A `FUNCTION`, the local variables become `VAR_TEMP` as functions keep no state.
+    pub fn function(&self, f: &Function) -> Result<String, Rust2PlcError> {
+        let vars: Vec<&Value> = f
+            .inputs()
+            .iter()
+            .chain(f.outputs())
+            .chain(f.locals())
+            .collect();
+        let mut scl = format!(
+            "FUNCTION \"{}\" : {}\n",
+            f.name(),
+            self.type_name(f.return_value())?
+        );
+        scl.push_str(&self.header(f.description(), f.version())?);
+        scl.push_str(&self.sections(f.name(), &vars, false)?);
+        scl.push_str(&self.body(f.name(), f.body(), &vars, Some(f.name()))?);
+        scl.push_str("END_FUNCTION\n");
+        Ok(scl)
+    }
+
+    pub fn function_block(&self, fb: &FunctionBlock) -> Result<String, Rust2PlcError> {
+        let vars: Vec<&Value> = fb
+            .inputs()
+            .iter()
+            .chain(fb.outputs())
+            .chain(fb.vars())
+            .collect();
+        self.stateful(fb.name(), &vars, fb.body(), fb.description(), fb.version())
+    }
+
+    /// A program, rendered as a `FUNCTION_BLOCK`.
+    pub fn program(&self, p: &Program) -> Result<String, Rust2PlcError> {
+        if p.chart().is_some() {
+            return Err(Rust2PlcError::Other(format!(
+                "the chart of program {} has no SCL counterpart",
+                p.name()
+            )));
+        }
+        let vars: Vec<&Value> = p
+            .inputs()
+            .iter()
+            .chain(p.outputs())
+            .chain(p.vars())
+            .collect();
+        self.stateful(p.name(), &vars, p.body(), p.description(), p.version())
+    }
+
+    /// A structure as a PLC data type, SCL has no enumerated types.
+    pub fn data_type(&self, t: &DataType) -> Result<String, Rust2PlcError> {
+        let TypedValue::Struct(name, fields) = t.value() else {
+            return Err(Rust2PlcError::Other(format!(
+                "the enumerated type {} has no SCL counterpart",
+                t.name()
+            )));
+        };
+        let mut scl = format!("TYPE \"{}\"\nVERSION : {}\n", name, version(t.version())?);
+        scl.push_str(&format!("{}STRUCT\n", self.pad(1)));
+        for (field, value) in fields.iter() {
+            scl.push_str(&format!(
+                "{}{}\n",
+                self.pad(2),
+                self.decl(field, value, true)?
+            ));
+        }
+        scl.push_str(&format!("{}END_STRUCT;\n\nEND_TYPE\n", self.pad(1)));
+        Ok(scl)
+    }
+
+    /// The SCL name of the type, e.g. `Int`, `DTL` or `"Point"`.
+    pub fn type_name(&self, tv: &TypedValue) -> Result<String, Rust2PlcError> {
+        Ok(match tv {
+            TypedValue::Bool(_) => "Bool".to_string(),
+            TypedValue::SInt(_) => "SInt".to_string(),
+            TypedValue::Int(_) => "Int".to_string(),
+            TypedValue::DInt(_) => "DInt".to_string(),
+            TypedValue::LInt(_) => "LInt".to_string(),
+            TypedValue::USInt(_) => "USInt".to_string(),
+            TypedValue::UInt(_) => "UInt".to_string(),
+            TypedValue::UDInt(_) => "UDInt".to_string(),
+            TypedValue::ULInt(_) => "ULInt".to_string(),
+            TypedValue::Real(_) => "Real".to_string(),
+            TypedValue::LReal(_) => "LReal".to_string(),
+            TypedValue::Time(_) => match self.time {
+                SclTime::Time => "Time".to_string(),
+                SclTime::LTime => "LTime".to_string(),
+                SclTime::S5Time => "S5Time".to_string(),
+            },
+            TypedValue::Date(_) => "Date".to_string(),
+            TypedValue::TimeOfDay(_) => "Time_Of_Day".to_string(),
+            // DATE_AND_TIME exists on S7-300/400 only, the newer CPUs use DTL.
+            TypedValue::DateTime(_) => "DTL".to_string(),
+            TypedValue::Char(_) => "Char".to_string(),
+            TypedValue::WChar(_) => "WChar".to_string(),
+            TypedValue::String(_, Some(len)) => format!("String[{}]", len),
+            TypedValue::String(_, None) => "String".to_string(),
+            TypedValue::WString(_, Some(len)) => format!("WString[{}]", len),
+            TypedValue::WString(_, None) => "WString".to_string(),
+            TypedValue::Byte(_) => "Byte".to_string(),
+            TypedValue::Word(_) => "Word".to_string(),
+            TypedValue::DWord(_) => "DWord".to_string(),
+            TypedValue::LWord(_) => "LWord".to_string(),
+            TypedValue::UserDefined(name, _) | TypedValue::Struct(name, _) => {
+                format!("\"{}\"", name)
+            }
+            TypedValue::Enum(name, ..) => {
+                return Err(Rust2PlcError::Other(format!(
+                    "the enumerated type {} has no SCL counterpart",
+                    name
+                )))
+            }
+            TypedValue::Array(values, elem, lower) => format!(
+                "Array[{}..{}] of {}",
+                lower,
+                lower + values.len() as i64 - 1,
+                self.type_name(elem)?
+            ),
+        })
+    }
+
+    /// The SCL literal of the value, e.g. `T#1s`, `S5T#10s` or `DTL#2024-01-01-08:30:00`.
+    pub fn literal(&self, tv: &TypedValue) -> Result<String, Rust2PlcError> {
+        match tv {
+            TypedValue::Time(duration) => self.time_literal(*duration),
+            TypedValue::DateTime(dt) => Ok(format!("DTL#{}", dt.format("%Y-%m-%d-%H:%M:%S"))),
+            TypedValue::UserDefined(_, Some(value)) => self.literal(value),
+            TypedValue::Array(values, ..) => Ok(format!(
+                "[{}]",
+                values
+                    .iter()
+                    .map(|v| self.literal(v))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .join(", ")
+            )),
+            TypedValue::Struct(name, _) | TypedValue::UserDefined(name, None) => {
+                Err(Rust2PlcError::Other(format!(
+                    "{} values are set in the data block, SCL declarations take no structure values",
+                    name
+                )))
+            }
+            TypedValue::Enum(name, ..) => Err(Rust2PlcError::Other(format!(
+                "the enumerated type {} has no SCL counterpart",
+                name
+            ))),
+            other => other.to_plc_literal(),
+        }
+    }
+
+    fn time_literal(&self, duration: Duration) -> Result<String, Rust2PlcError> {
+        let text = TypedValue::Time(duration).to_plc_literal()?;
+        let value = text.trim_start_matches("T#");
+        let millis = duration.as_millis();
+        match self.time {
+            SclTime::Time if millis > i32::MAX as u128 => Err(Rust2PlcError::Other(format!(
+                "{} exceeds the range of Time, use LTime",
+                text
+            ))),
+            SclTime::Time => Ok(text),
+            SclTime::LTime => Ok(format!("LT#{}", value)),
+            SclTime::S5Time => {
+                // Three BCD digits in one of the time bases 10ms, 100ms, 1s and 10s.
+                let fits = [10, 100, 1000, 10000]
+                    .iter()
+                    .any(|base| millis % base == 0 && millis / base <= 999);
+                if fits {
+                    Ok(format!("S5T#{}", value))
+                } else {
+                    Err(Rust2PlcError::Other(format!(
+                        "{} cannot be represented as S5Time",
+                        text
+                    )))
+                }
+            }
+        }
+    }
+
+    fn stateful(
+        &self,
+        name: &str,
+        vars: &[&Value],
+        body: &[Stmt],
+        description: Option<&str>,
+        version: Option<&str>,
+    ) -> Result<String, Rust2PlcError> {
+        let mut scl = format!("FUNCTION_BLOCK \"{}\"\n", name);
+        scl.push_str(&self.header(description, version)?);
+        scl.push_str(&self.sections(name, vars, true)?);
+        scl.push_str(&self.body(name, body, vars, None)?);
+        scl.push_str("END_FUNCTION_BLOCK\n");
+        Ok(scl)
+    }
+
+    fn header(
+        &self,
+        description: Option<&str>,
+        ver: Option<&str>,
+    ) -> Result<String, Rust2PlcError> {
+        let mut scl = String::new();
+        if let Some(description) = description {
+            scl.push_str(&format!("TITLE = {}\n", description));
+        }
+        scl.push_str(&format!(
+            "{{ S7_Optimized_Access := '{}' }}\n",
+            if self.optimized_access {
+                "TRUE"
+            } else {
+                "FALSE"
+            }
+        ));
+        scl.push_str(&format!("VERSION : {}\n", version(ver)?));
+        Ok(scl)
+    }
+
+    /// The declaration sections, initial values are kept where the block holds the data.
+    fn sections(
+        &self,
+        pou: &str,
+        vars: &[&Value],
+        stateful: bool,
+    ) -> Result<String, Rust2PlcError> {
+        let mut sections: Vec<(&str, Vec<String>)> = [
+            "VAR_INPUT",
+            "VAR_OUTPUT",
+            "VAR_IN_OUT",
+            "VAR",
+            "VAR_TEMP",
+            "VAR CONSTANT",
+        ]
+        .iter()
+        .map(|s| (*s, vec![]))
+        .collect();
+        for var in vars {
+            let name = var.name().unwrap_or_default();
+            let tv = var.typed_value();
+            let (section, init) = match var {
+                Value::Input(_, _) => (0, stateful),
+                Value::Output(_, _) => (1, stateful),
+                Value::InOut(_, _) => (2, false),
+                Value::Local(_, _) if stateful => (3, true),
+                Value::Local(_, _) | Value::Temporary(_, _) => (4, false),
+                Value::Constant(_, _) => (5, true),
+                Value::Global(_, _) | Value::External(_, _) | Value::Return(_) => {
+                    return Err(Rust2PlcError::Other(format!(
+                        "{} of {} is declared {}, SCL blocks reach global data by its quoted name",
+                        name,
+                        pou,
+                        var.st_section().unwrap_or("as a return value")
+                    )))
+                }
+            };
+            let decl = match var {
+                Value::Constant(_, _) => format!(
+                    "{} : {} := {};",
+                    name,
+                    self.type_name(tv)?,
+                    self.literal(tv)?
+                ),
+                _ => self.decl(name, tv, init)?,
+            };
+            sections[section].1.push(decl);
+        }
+
+        let mut scl = String::new();
+        for (section, decls) in sections.iter().filter(|(_, d)| !d.is_empty()) {
+            scl.push_str(&format!("{}{}\n", self.pad(1), section));
+            for decl in decls {
+                scl.push_str(&format!("{}{}\n", self.pad(2), decl));
+            }
+            scl.push_str(&format!("{}END_VAR\n", self.pad(1)));
+        }
+        Ok(scl)
+    }
+
+    fn decl(&self, name: &str, tv: &TypedValue, init: bool) -> Result<String, Rust2PlcError> {
+        if init && *tv != tv.default_value() {
+            Ok(format!(
+                "{} : {} := {};",
+                name,
+                self.type_name(tv)?,
+                self.literal(tv)?
+            ))
+        } else {
+            Ok(format!("{} : {};", name, self.type_name(tv)?))
+        }
+    }
+
+    fn body(
+        &self,
+        pou: &str,
+        body: &[Stmt],
+        vars: &[&Value],
+        result: Option<&str>,
+    ) -> Result<String, Rust2PlcError> {
+        let mut locals: HashSet<&str> = vars.iter().filter_map(|v| v.name()).collect();
+        locals.extend(result);
+        let body = body
+            .iter()
+            .map(|s| self.stmt(s, &locals))
+            .collect::<Result<Vec<_>, _>>()?;
+        let printer = Printer::new().with_indent(self.indent);
+        Ok(format!(
+            "\nBEGIN\n{}REGION {}\n{}{}END_REGION\n",
+            self.pad(1),
+            pou,
+            printer.stmts(&body, 2),
+            self.pad(1)
+        ))
+    }
+
+    fn stmt(&self, stmt: &Stmt, locals: &HashSet<&str>) -> Result<Stmt, Rust2PlcError> {
+        let stmts = |body: &[Stmt]| {
+            body.iter()
+                .map(|s| self.stmt(s, locals))
+                .collect::<Result<Vec<_>, Rust2PlcError>>()
+        };
+        let otherwise = |otherwise: &Option<Vec<Stmt>>| match otherwise {
+            Some(body) => stmts(body).map(Some),
+            None => Ok(None),
+        };
+        Ok(match stmt {
+            Stmt::Assign { target, value } => Stmt::Assign {
+                target: self.expr(target, locals)?,
+                value: self.expr(value, locals)?,
+            },
+            Stmt::Call(call) => Stmt::Call(self.call(call, locals)?),
+            Stmt::If {
+                branches,
+                otherwise: other,
+            } => Stmt::If {
+                branches: branches
+                    .iter()
+                    .map(|b| {
+                        Ok(Branch {
+                            cond: self.expr(&b.cond, locals)?,
+                            body: stmts(&b.body)?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, Rust2PlcError>>()?,
+                otherwise: otherwise(other)?,
+            },
+            Stmt::Case {
+                selector,
+                branches,
+                otherwise: other,
+            } => Stmt::Case {
+                selector: self.expr(selector, locals)?,
+                branches: branches
+                    .iter()
+                    .map(|b| {
+                        let labels = b
+                            .labels
+                            .iter()
+                            .map(|label| match label {
+                                CaseLabel::Value(v) => Ok(CaseLabel::Value(self.expr(v, locals)?)),
+                                CaseLabel::Range(from, to) => Ok(CaseLabel::Range(
+                                    self.expr(from, locals)?,
+                                    self.expr(to, locals)?,
+                                )),
+                            })
+                            .collect::<Result<Vec<_>, Rust2PlcError>>()?;
+                        Ok(CaseBranch {
+                            labels,
+                            body: stmts(&b.body)?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, Rust2PlcError>>()?,
+                otherwise: otherwise(other)?,
+            },
+            Stmt::For {
+                var,
+                from,
+                to,
+                by,
+                body,
+            } => Stmt::For {
+                var: self.name(var, locals),
+                from: self.expr(from, locals)?,
+                to: self.expr(to, locals)?,
+                by: match by {
+                    Some(by) => Some(self.expr(by, locals)?),
+                    None => None,
+                },
+                body: stmts(body)?,
+            },
+            Stmt::While { cond, body } => Stmt::While {
+                cond: self.expr(cond, locals)?,
+                body: stmts(body)?,
+            },
+            Stmt::Repeat { body, until } => Stmt::Repeat {
+                body: stmts(body)?,
+                until: self.expr(until, locals)?,
+            },
+            Stmt::Return => Stmt::Return,
+            Stmt::Exit => Stmt::Exit,
+            Stmt::Continue => Stmt::Continue,
+        })
+    }
+
+    fn expr(&self, expr: &Expr, locals: &HashSet<&str>) -> Result<Expr, Rust2PlcError> {
+        let boxed = |e: &Expr| self.expr(e, locals).map(Box::new);
+        Ok(match expr {
+            Expr::Literal(Literal::Typed { ty, value }) => self.typed_literal(ty, value)?,
+            Expr::Literal(literal) => Expr::Literal(literal.clone()),
+            Expr::Var(name) => Expr::Var(self.name(name, locals)),
+            Expr::Field(base, member) => Expr::Field(boxed(base)?, member.clone()),
+            Expr::Index(base, indices) => Expr::Index(
+                boxed(base)?,
+                indices
+                    .iter()
+                    .map(|i| self.expr(i, locals))
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+            Expr::Unary(op, operand) => Expr::Unary(*op, boxed(operand)?),
+            Expr::Binary(op, lhs, rhs) => Expr::Binary(*op, boxed(lhs)?, boxed(rhs)?),
+            Expr::Call(call) => Expr::Call(self.call(call, locals)?),
+            Expr::Paren(inner) => Expr::Paren(boxed(inner)?),
+        })
+    }
+
+    fn call(&self, call: &Call, locals: &HashSet<&str>) -> Result<Call, Rust2PlcError> {
+        let upper = call.name.to_uppercase();
+        let standard = STANDARD_FUNCTIONS.contains(&upper.as_str()) || upper.contains("_TO_");
+        let name = if locals.contains(call.name.as_str()) {
+            format!("#{}", call.name)
+        } else if standard {
+            call.name.clone()
+        } else {
+            format!("\"{}\"", call.name)
+        };
+        let positional = call
+            .args
+            .iter()
+            .filter(|a| matches!(a, Arg::Value(_)))
+            .count();
+        let inputs = match self.functions.get(&upper) {
+            _ if standard || locals.contains(call.name.as_str()) => None,
+            Some(inputs) => Some(inputs),
+            None if positional > 1 => {
+                return Err(Rust2PlcError::Other(format!(
+                    "{} is called with positional arguments, SCL needs formal ones, \
+                     make it known with `SclPrinter::with_function`",
+                    call.name
+                )))
+            }
+            None => None,
+        };
+        let args = call
+            .args
+            .iter()
+            .enumerate()
+            .map(|(i, arg)| {
+                Ok(match arg {
+                    Arg::Value(value) => {
+                        let value = self.expr(value, locals)?;
+                        match inputs {
+                            Some(inputs) => match inputs.get(i) {
+                                Some(input) => Arg::Input(input.clone(), value),
+                                None => {
+                                    return Err(Rust2PlcError::Other(format!(
+                                        "{} takes {} arguments",
+                                        call.name,
+                                        inputs.len()
+                                    )))
+                                }
+                            },
+                            None => Arg::Value(value),
+                        }
+                    }
+                    Arg::Input(name, value) => Arg::Input(name.clone(), self.expr(value, locals)?),
+                    Arg::Output(name, value) => {
+                        Arg::Output(name.clone(), self.expr(value, locals)?)
+                    }
+                })
+            })
+            .collect::<Result<Vec<_>, Rust2PlcError>>()?;
+        Ok(Call { name, args })
+    }
+
+    /// Own variables are written `#name`, everything else is a quoted global name.
+    fn name(&self, name: &str, locals: &HashSet<&str>) -> String {
+        if locals.contains(name) {
+            format!("#{}", name)
+        } else {
+            format!("\"{}\"", name)
+        }
+    }
+
+    fn typed_literal(&self, ty: &str, value: &str) -> Result<Expr, Rust2PlcError> {
+        let typed = |ty: &str| {
+            Expr::Literal(Literal::Typed {
+                ty: ty.to_string(),
+                value: value.to_string(),
+            })
+        };
+        match ty.to_uppercase().as_str() {
+            "T" | "TIME" | "LT" | "LTIME" => {
+                match parser::parse_value(&TypedValue::new_time(), &format!("T#{}", value))? {
+                    TypedValue::Time(duration) => {
+                        let literal = self.time_literal(duration)?;
+                        let (ty, value) = literal.split_once('#').unwrap_or(("T", &literal));
+                        Ok(Expr::Literal(Literal::Typed {
+                            ty: ty.to_string(),
+                            value: value.to_string(),
+                        }))
+                    }
+                    _ => unreachable!("a TIME literal is parsed into a duration"),
+                }
+            }
+            "DT" | "DATE_AND_TIME" => Ok(typed("DTL")),
+            "D" | "DATE" | "TOD" | "TIME_OF_DAY" => Ok(typed(ty)),
+            upper => match TypedValue::from_plc_type(upper) {
+                Some(TypedValue::UserDefined(_, _))
+                | Some(TypedValue::Enum(..))
+                | Some(TypedValue::Struct(_, _))
+                | None => Err(Rust2PlcError::Other(format!(
+                    "the enumerated value {}#{} has no SCL counterpart",
+                    ty, value
+                ))),
+                Some(_) => Ok(typed(ty)),
+            },
+        }
+    }
+
+    fn pad(&self, level: usize) -> String {
+        " ".repeat(self.indent * level)
+    }
+}
+
+/// The `major.minor` block version, `0.1` when the POU has none.
+pub(crate) fn version(version: Option<&str>) -> Result<String, Rust2PlcError> {
+    let Some(version) = version else {
+        return Ok("0.1".to_string());
+    };
+    let mut parts = version.split('.');
+    match (parts.next(), parts.next()) {
+        (Some(major), Some(minor))
+            if [major, minor]
+                .iter()
+                .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit())) =>
+        {
+            Ok(format!("{}.{}", major, minor))
+        }
+        _ => Err(Rust2PlcError::Other(format!(
+            "{} is not a block version, S7 expects major.minor",
+            version
+        ))),
+    }
+}
+
+impl Function {
+    /// Renders the function as an SCL `FUNCTION` block.
+    pub fn to_scl(&self) -> Result<String, Rust2PlcError> {
+        SclPrinter::default().function(self)
+    }
+}
+
+impl FunctionBlock {
+    /// Renders the block as an SCL `FUNCTION_BLOCK`.
+    pub fn to_scl(&self) -> Result<String, Rust2PlcError> {
+        SclPrinter::default().function_block(self)
+    }
+}
+
+impl Program {
+    /// Renders the program as an SCL `FUNCTION_BLOCK`.
+    pub fn to_scl(&self) -> Result<String, Rust2PlcError> {
+        SclPrinter::default().program(self)
+    }
+}
+
+impl DataType {
+    /// Renders the structure as an SCL PLC data type.
+    pub fn to_scl(&self) -> Result<String, Rust2PlcError> {
+        SclPrinter::default().data_type(self)
+    }
+}
+
+impl RegistryItem {
+    pub fn to_scl(&self) -> Result<String, Rust2PlcError> {
+        SclPrinter::default().item(self)
+    }
+}
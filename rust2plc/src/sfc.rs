@@ -0,0 +1,392 @@
+//! Sequential Function Chart, the steps and transitions of a sequence.
+//!
+//! A chart is assembled with [`Sfc::new`] and the `with_*` methods and attached to a
+//! program with [`crate::st::program::Program::with_chart`], where it replaces the body.
+//! Alternative branches are several transitions leaving the same step, simultaneous branches
+//! are transitions from or to several steps. Conditions are ST expressions, `plc_expr!`
+//! translates them from Rust.
+//!
+//! ```
+//! use std::time::Duration;
+//! use rust2plc::sfc::{Action, Qualifier, Sfc, Step, Transition};
+//! use rust2plc::st::ast::{Expr, Stmt};
+//!
+//! let chart = Sfc::new()
+//!     .with_step(Step::initial("Idle"))
+//!     .with_step(
+//!         Step::new("Fill")
+//!             .with_action(Qualifier::N, "fill")
+//!             .with_action(Qualifier::D(Duration::from_secs(30)), "timeout"),
+//!     )
+//!     .with_transition(Transition::new(["Idle"], ["Fill"], Expr::var("start")))
+//!     .with_transition(Transition::new(["Fill"], ["Idle"], Expr::var("full")))
+//!     .with_action(Action::new(
+//!         "fill",
+//!         vec![Stmt::assign(Expr::var("valve"), Expr::bool(true))],
+//!     ));
+//! chart.validate().unwrap();
+//! assert!(chart.to_st().starts_with("INITIAL_STEP Idle:\nEND_STEP\n"));
+//! ```
+
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::error::Rust2PlcError;
+use crate::st::ast::{Expr, Printer, Stmt};
+use crate::types::TypedValue;
+
+/// How an action is run while its step is active, the timed ones carry their time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Qualifier {
+    /// Non-stored, runs while the step is active.
+    N,
+    /// Set, runs from the activation of the step until reset.
+    S,
+    /// Reset, stops a stored action.
+    R,
+    /// Pulse, runs once when the step becomes active.
+    P,
+    /// Time limited, runs while the step is active but at most for the time.
+    L(Duration),
+    /// Time delayed, runs after the step has been active for the time.
+    D(Duration),
+    /// Stored and time delayed.
+    SD(Duration),
+    /// Delayed and stored, stored only if the step is still active after the time.
+    DS(Duration),
+    /// Stored and time limited.
+    SL(Duration),
+}
+
+impl Qualifier {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Qualifier::N => "N",
+            Qualifier::S => "S",
+            Qualifier::R => "R",
+            Qualifier::P => "P",
+            Qualifier::L(_) => "L",
+            Qualifier::D(_) => "D",
+            Qualifier::SD(_) => "SD",
+            Qualifier::DS(_) => "DS",
+            Qualifier::SL(_) => "SL",
+        }
+    }
+
+    pub fn duration(&self) -> Option<Duration> {
+        match self {
+            Qualifier::L(t)
+            | Qualifier::D(t)
+            | Qualifier::SD(t)
+            | Qualifier::DS(t)
+            | Qualifier::SL(t) => Some(*t),
+            _ => None,
+        }
+    }
+
+    /// The time as a `T#` literal.
+    pub fn duration_literal(&self) -> Option<String> {
+        self.duration()
+            .map(|t| TypedValue::Time(t).to_plc_literal().unwrap_or_default())
+    }
+}
+
+/// An action run by a step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionAssociation {
+    pub qualifier: Qualifier,
+    /// An action of the chart or a `BOOL` variable driven like an action.
+    pub action: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    name: String,
+    initial: bool,
+    actions: Vec<ActionAssociation>,
+}
+
+impl Step {
+    pub fn new(name: impl Into<String>) -> Self {
+        Step {
+            name: name.into(),
+            initial: false,
+            actions: vec![],
+        }
+    }
+
+    /// The step active when the chart starts.
+    pub fn initial(name: impl Into<String>) -> Self {
+        Step {
+            initial: true,
+            ..Step::new(name)
+        }
+    }
+
+    pub fn with_action(mut self, qualifier: Qualifier, action: impl Into<String>) -> Self {
+        self.actions.push(ActionAssociation {
+            qualifier,
+            action: action.into(),
+        });
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_initial(&self) -> bool {
+        self.initial
+    }
+
+    pub fn actions(&self) -> &[ActionAssociation] {
+        &self.actions
+    }
+}
+
+/// A transition, several steps on one side make a simultaneous branch.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    from: Vec<String>,
+    to: Vec<String>,
+    condition: Expr,
+    priority: Option<u32>,
+}
+
+impl Transition {
+    pub fn new<F, T>(
+        from: impl IntoIterator<Item = F>,
+        to: impl IntoIterator<Item = T>,
+        condition: Expr,
+    ) -> Self
+    where
+        F: Into<String>,
+        T: Into<String>,
+    {
+        Transition {
+            from: from.into_iter().map(Into::into).collect(),
+            to: to.into_iter().map(Into::into).collect(),
+            condition,
+            priority: None,
+        }
+    }
+
+    /// The order of evaluation among the alternatives leaving a step, lower goes first.
+    pub fn with_priority(mut self, priority: u32) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    pub fn from(&self) -> &[String] {
+        &self.from
+    }
+
+    pub fn to(&self) -> &[String] {
+        &self.to
+    }
+
+    pub fn condition(&self) -> &Expr {
+        &self.condition
+    }
+
+    pub fn priority(&self) -> Option<u32> {
+        self.priority
+    }
+}
+
+/// A named piece of ST run by the steps associated with it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Action {
+    name: String,
+    body: Vec<Stmt>,
+}
+
+impl Action {
+    pub fn new(name: impl Into<String>, body: Vec<Stmt>) -> Self {
+        Action {
+            name: name.into(),
+            body,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn body(&self) -> &[Stmt] {
+        &self.body
+    }
+}
+
+/// A chart, the steps in the order they are drawn.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Sfc {
+    steps: Vec<Step>,
+    transitions: Vec<Transition>,
+    actions: Vec<Action>,
+}
+
+impl Sfc {
+    pub fn new() -> Self {
+        Sfc::default()
+    }
+
+    pub fn with_step(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn with_transition(mut self, transition: Transition) -> Self {
+        self.transitions.push(transition);
+        self
+    }
+
+    pub fn with_action(mut self, action: Action) -> Self {
+        self.actions.push(action);
+        self
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    pub fn transitions(&self) -> &[Transition] {
+        &self.transitions
+    }
+
+    pub fn actions(&self) -> &[Action] {
+        &self.actions
+    }
+
+    /// The transitions leaving a step, the alternatives of a selection.
+    pub fn outgoing(&self, step: &str) -> impl Iterator<Item = &Transition> {
+        let step = step.to_string();
+        self.transitions
+            .iter()
+            .filter(move |t| t.from.contains(&step))
+    }
+
+    /// Checks for exactly one initial step, unique names and transitions between declared steps.
+    ///
+    /// Associations naming no action of the chart are taken for `BOOL` variables.
+    pub fn validate(&self) -> Result<(), Rust2PlcError> {
+        let mut steps = HashSet::new();
+        for step in self.steps.iter() {
+            if !steps.insert(step.name.as_str()) {
+                return Err(Rust2PlcError::Other(format!(
+                    "step {} is declared twice",
+                    step.name
+                )));
+            }
+        }
+        match self.steps.iter().filter(|s| s.initial).count() {
+            1 => {}
+            0 => {
+                return Err(Rust2PlcError::Other(
+                    "the chart has no initial step".to_string(),
+                ))
+            }
+            _ => {
+                return Err(Rust2PlcError::Other(
+                    "the chart has more than one initial step".to_string(),
+                ))
+            }
+        }
+        let mut actions = HashSet::new();
+        for action in self.actions.iter() {
+            if !actions.insert(action.name.as_str()) || steps.contains(action.name.as_str()) {
+                return Err(Rust2PlcError::Other(format!(
+                    "action {} is declared twice",
+                    action.name
+                )));
+            }
+        }
+        for transition in self.transitions.iter() {
+            if transition.from.is_empty() || transition.to.is_empty() {
+                return Err(Rust2PlcError::Other(format!(
+                    "the transition {} needs a step on either side",
+                    Printer::default().expr(&transition.condition)
+                )));
+            }
+            for step in transition.from.iter().chain(&transition.to) {
+                if !steps.contains(step.as_str()) {
+                    return Err(Rust2PlcError::Other(format!(
+                        "a transition refers to the unknown step {}",
+                        step
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Renders the chart in the textual SFC of IEC 61131-3.
+    pub fn to_st(&self) -> String {
+        self.to_st_with(&Printer::default(), 0)
+    }
+
+    /// Renders the chart in the style of the printer, indented by `level`.
+    pub fn to_st_with(&self, printer: &Printer, level: usize) -> String {
+        let pad = printer.pad(level);
+        let kw = |k: &str| printer.keyword(k);
+        let mut parts = vec![];
+        for step in self.steps.iter() {
+            let keyword = if step.initial { "INITIAL_STEP" } else { "STEP" };
+            let mut st = format!("{}{} {}:\n", pad, kw(keyword), step.name);
+            for association in step.actions.iter() {
+                let qualifier = association.qualifier;
+                let args = match qualifier.duration_literal() {
+                    Some(t) => format!("{}, {}", qualifier.name(), t),
+                    None => qualifier.name().to_string(),
+                };
+                st.push_str(&format!(
+                    "{}{}({});\n",
+                    printer.pad(level + 1),
+                    association.action,
+                    args
+                ));
+            }
+            st.push_str(&format!("{}{}\n", pad, kw("END_STEP")));
+            parts.push(st);
+        }
+        for transition in self.transitions.iter() {
+            let priority = transition
+                .priority
+                .map(|p| format!(" ({} := {})", kw("PRIORITY"), p))
+                .unwrap_or_default();
+            parts.push(format!(
+                "{}{}{} {} {} {} {} := {};\n{}{}\n",
+                pad,
+                kw("TRANSITION"),
+                priority,
+                kw("FROM"),
+                steps(&transition.from),
+                kw("TO"),
+                steps(&transition.to),
+                printer.expr(&transition.condition),
+                pad,
+                kw("END_TRANSITION")
+            ));
+        }
+        for action in self.actions.iter() {
+            parts.push(format!(
+                "{}{} {}:\n{}{}{}\n",
+                pad,
+                kw("ACTION"),
+                action.name,
+                printer.stmts(&action.body, level + 1),
+                pad,
+                kw("END_ACTION")
+            ));
+        }
+        parts.join("\n")
+    }
+}
+
+/// `Fill` or `(HeatA, HeatB)` for a simultaneous branch.
+fn steps(steps: &[String]) -> String {
+    match steps {
+        [step] => step.clone(),
+        steps => format!("({})", steps.join(", ")),
+    }
+}
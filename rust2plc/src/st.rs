@@ -1 +1,30 @@
+pub mod ast;
+pub mod config;
+pub mod data_type;
+pub mod export;
 pub mod function;
+pub mod function_block;
+pub mod parser;
+pub mod program;
+pub mod rust;
+
+/// The comment lines preceding a POU declaration.
+pub(crate) fn header(
+    description: Option<&str>,
+    namespace: Option<&str>,
+    version: Option<&str>,
+) -> String {
+    let mut st = String::new();
+    if let Some(description) = description {
+        st.push_str(&format!("(* {} *)\n", description));
+    }
+    match (namespace, version) {
+        (Some(ns), Some(ver)) => {
+            st.push_str(&format!("(* namespace: {}, version: {} *)\n", ns, ver))
+        }
+        (Some(ns), None) => st.push_str(&format!("(* namespace: {} *)\n", ns)),
+        (None, Some(ver)) => st.push_str(&format!("(* version: {} *)\n", ver)),
+        (None, None) => {}
+    }
+    st
+}
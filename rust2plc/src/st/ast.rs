@@ -0,0 +1,597 @@
+//! The syntax tree of ST bodies and its pretty-printer.
+//!
+//! POUs keep their statements as a tree, the text is produced by a [`Printer`]
+//! so the same body can be analysed, transformed and rendered in several styles.
+
+use crate::var::Value;
+
+// ST operator precedence, the higher the tighter the binding.
+pub const PREC_OR: u8 = 1;
+pub const PREC_XOR: u8 = 2;
+pub const PREC_AND: u8 = 3;
+pub const PREC_EQ: u8 = 4;
+pub const PREC_CMP: u8 = 5;
+pub const PREC_ADD: u8 = 6;
+pub const PREC_MUL: u8 = 7;
+pub const PREC_POW: u8 = 8;
+pub const PREC_UNARY: u8 = 9;
+pub const PREC_PRIMARY: u8 = 10;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Bool(bool),
+    Int(i128),
+    Real(f64),
+    Str(String),
+    /// A literal prefixed with its type, e.g. `REAL#1.5`, `T#1s` or `Mode#Running`.
+    Typed {
+        ty: String,
+        value: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Or,
+    Xor,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+}
+
+impl BinaryOp {
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Or => PREC_OR,
+            BinaryOp::Xor => PREC_XOR,
+            BinaryOp::And => PREC_AND,
+            BinaryOp::Eq | BinaryOp::Ne => PREC_EQ,
+            BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => PREC_CMP,
+            BinaryOp::Add | BinaryOp::Sub => PREC_ADD,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => PREC_MUL,
+            BinaryOp::Pow => PREC_POW,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Or => "OR",
+            BinaryOp::Xor => "XOR",
+            BinaryOp::And => "AND",
+            BinaryOp::Eq => "=",
+            BinaryOp::Ne => "<>",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Mod => "MOD",
+            BinaryOp::Pow => "**",
+        }
+    }
+
+    /// Whether the operator is written as a keyword and follows the keyword casing.
+    pub fn is_keyword(&self) -> bool {
+        matches!(
+            self,
+            BinaryOp::Or | BinaryOp::Xor | BinaryOp::And | BinaryOp::Mod
+        )
+    }
+}
+
+/// An argument of a call.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg {
+    Value(Expr),
+    /// `name := value`
+    Input(String, Expr),
+    /// `name => variable`
+    Output(String, Expr),
+}
+
+/// A call of a function or a function block instance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    pub name: String,
+    pub args: Vec<Arg>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Literal),
+    Var(String),
+    Field(Box<Expr>, String),
+    Index(Box<Expr>, Vec<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(Call),
+    /// Parentheses written in the source, kept as they are.
+    Paren(Box<Expr>),
+}
+
+impl Expr {
+    pub fn var(name: impl Into<String>) -> Self {
+        Expr::Var(name.into())
+    }
+
+    pub fn int(value: i128) -> Self {
+        Expr::Literal(Literal::Int(value))
+    }
+
+    pub fn bool(value: bool) -> Self {
+        Expr::Literal(Literal::Bool(value))
+    }
+
+    pub fn binary(op: BinaryOp, lhs: Expr, rhs: Expr) -> Self {
+        Expr::Binary(op, Box::new(lhs), Box::new(rhs))
+    }
+
+    pub fn unary(op: UnaryOp, operand: Expr) -> Self {
+        Expr::Unary(op, Box::new(operand))
+    }
+
+    pub fn call(name: impl Into<String>, args: Vec<Expr>) -> Self {
+        Expr::Call(Call {
+            name: name.into(),
+            args: args.into_iter().map(Arg::Value).collect(),
+        })
+    }
+
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Expr::Unary(_, _) => PREC_UNARY,
+            Expr::Binary(op, _, _) => op.precedence(),
+            Expr::Literal(Literal::Int(v)) if *v < 0 => PREC_UNARY,
+            Expr::Literal(Literal::Real(v)) if v.is_sign_negative() => PREC_UNARY,
+            _ => PREC_PRIMARY,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaseLabel {
+    Value(Expr),
+    /// An inclusive range `from..to`.
+    Range(Expr, Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaseBranch {
+    pub labels: Vec<CaseLabel>,
+    pub body: Vec<Stmt>,
+}
+
+/// A guarded branch of an `IF`, the first one is `IF`, the others `ELSIF`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Branch {
+    pub cond: Expr,
+    pub body: Vec<Stmt>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Assign {
+        target: Expr,
+        value: Expr,
+    },
+    /// A function call or a function block invocation, `timer(IN := start, PT := T#1s);`.
+    Call(Call),
+    If {
+        branches: Vec<Branch>,
+        otherwise: Option<Vec<Stmt>>,
+    },
+    Case {
+        selector: Expr,
+        branches: Vec<CaseBranch>,
+        otherwise: Option<Vec<Stmt>>,
+    },
+    For {
+        var: String,
+        from: Expr,
+        to: Expr,
+        by: Option<Expr>,
+        body: Vec<Stmt>,
+    },
+    While {
+        cond: Expr,
+        body: Vec<Stmt>,
+    },
+    Repeat {
+        body: Vec<Stmt>,
+        until: Expr,
+    },
+    Return,
+    Exit,
+    Continue,
+}
+
+impl Stmt {
+    pub fn assign(target: Expr, value: Expr) -> Self {
+        Stmt::Assign { target, value }
+    }
+}
+
+/// The kind of a `VAR_* ... END_VAR` section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VarSection {
+    Input,
+    InOut,
+    Output,
+    External,
+    Var,
+    Temp,
+    Constant,
+    Global,
+}
+
+impl VarSection {
+    /// The sections of a POU in their canonical order.
+    pub const POU_ORDER: [VarSection; 7] = [
+        VarSection::Input,
+        VarSection::InOut,
+        VarSection::Output,
+        VarSection::External,
+        VarSection::Var,
+        VarSection::Temp,
+        VarSection::Constant,
+    ];
+
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            VarSection::Input => "VAR_INPUT",
+            VarSection::InOut => "VAR_IN_OUT",
+            VarSection::Output => "VAR_OUTPUT",
+            VarSection::External => "VAR_EXTERNAL",
+            VarSection::Var => "VAR",
+            VarSection::Temp => "VAR_TEMP",
+            VarSection::Constant => "VAR CONSTANT",
+            VarSection::Global => "VAR_GLOBAL",
+        }
+    }
+}
+
+/// A declaration section and its variables.
+#[derive(Debug, Clone)]
+pub struct VarBlock {
+    pub section: VarSection,
+    pub vars: Vec<Value>,
+}
+
+impl VarBlock {
+    /// Groups the variables of a POU into non-empty sections in the canonical order.
+    pub fn group<'a>(vars: impl IntoIterator<Item = &'a Value>) -> Vec<VarBlock> {
+        let vars: Vec<&Value> = vars.into_iter().collect();
+        VarSection::POU_ORDER
+            .iter()
+            .map(|section| VarBlock {
+                section: *section,
+                vars: vars
+                    .iter()
+                    .filter(|v| v.st_section() == Some(section.keyword()))
+                    .map(|v| (*v).clone())
+                    .collect(),
+            })
+            .filter(|block| !block.vars.is_empty())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeywordCase {
+    #[default]
+    Upper,
+    Lower,
+}
+
+/// Renders the tree as ST text.
+///
+/// ```
+/// use rust2plc::st::ast::{BinaryOp, Expr, KeywordCase, Printer, Stmt};
+///
+/// let body = vec![Stmt::assign(
+///     Expr::var("q"),
+///     Expr::binary(BinaryOp::And, Expr::var("a"), Expr::var("b")),
+/// )];
+/// let printer = Printer::new().with_indent(2).with_keyword_case(KeywordCase::Lower);
+/// assert_eq!(printer.stmts(&body, 1), "  q := a and b;\n");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Printer {
+    indent: usize,
+    keyword_case: KeywordCase,
+}
+
+impl Default for Printer {
+    fn default() -> Self {
+        Printer {
+            indent: 4,
+            keyword_case: KeywordCase::Upper,
+        }
+    }
+}
+
+impl Printer {
+    pub fn new() -> Self {
+        Printer::default()
+    }
+
+    /// The number of spaces per nesting level.
+    pub fn with_indent(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    pub fn with_keyword_case(mut self, keyword_case: KeywordCase) -> Self {
+        self.keyword_case = keyword_case;
+        self
+    }
+
+    pub fn keyword(&self, keyword: &str) -> String {
+        match self.keyword_case {
+            KeywordCase::Upper => keyword.to_uppercase(),
+            KeywordCase::Lower => keyword.to_lowercase(),
+        }
+    }
+
+    pub(crate) fn pad(&self, level: usize) -> String {
+        " ".repeat(self.indent * level)
+    }
+
+    /// The declaration sections, each line indented by `level`.
+    pub fn var_blocks(&self, blocks: &[VarBlock], level: usize) -> String {
+        let mut st = String::new();
+        for block in blocks {
+            st.push_str(&format!(
+                "{}{}\n",
+                self.pad(level),
+                self.keyword(block.section.keyword())
+            ));
+            for var in block.vars.iter() {
+                st.push_str(&format!("{}{}\n", self.pad(level + 1), var.to_st_decl()));
+            }
+            st.push_str(&format!("{}{}\n", self.pad(level), self.keyword("END_VAR")));
+        }
+        st
+    }
+
+    pub fn expr(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::Literal(literal) => self.literal(literal),
+            Expr::Var(name) => name.clone(),
+            Expr::Field(base, member) => format!("{}.{}", self.wrapped(base, PREC_PRIMARY), member),
+            Expr::Index(base, indices) => format!(
+                "{}[{}]",
+                self.wrapped(base, PREC_PRIMARY),
+                indices
+                    .iter()
+                    .map(|i| self.expr(i))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Expr::Unary(UnaryOp::Neg, operand) => {
+                format!("-{}", self.wrapped(operand, PREC_UNARY + 1))
+            }
+            Expr::Unary(UnaryOp::Not, operand) => format!(
+                "{} {}",
+                self.keyword("NOT"),
+                self.wrapped(operand, PREC_UNARY + 1)
+            ),
+            Expr::Binary(op, lhs, rhs) => {
+                let symbol = if op.is_keyword() {
+                    self.keyword(op.symbol())
+                } else {
+                    op.symbol().to_string()
+                };
+                format!(
+                    "{} {} {}",
+                    self.wrapped(lhs, op.precedence()),
+                    symbol,
+                    self.wrapped(rhs, op.precedence() + 1)
+                )
+            }
+            Expr::Call(call) => self.call(call),
+            Expr::Paren(inner) => format!("({})", self.expr(inner)),
+        }
+    }
+
+    fn wrapped(&self, expr: &Expr, min_prec: u8) -> String {
+        if expr.precedence() < min_prec {
+            format!("({})", self.expr(expr))
+        } else {
+            self.expr(expr)
+        }
+    }
+
+    fn literal(&self, literal: &Literal) -> String {
+        match literal {
+            Literal::Bool(value) => self.keyword(if *value { "TRUE" } else { "FALSE" }),
+            Literal::Int(value) => value.to_string(),
+            Literal::Real(value) => real(*value),
+            Literal::Str(value) => format!("'{}'", escape(value)),
+            Literal::Typed { ty, value } => format!("{}#{}", ty, value),
+        }
+    }
+
+    fn call(&self, call: &Call) -> String {
+        let args: Vec<String> = call
+            .args
+            .iter()
+            .map(|arg| match arg {
+                Arg::Value(value) => self.expr(value),
+                Arg::Input(name, value) => format!("{} := {}", name, self.expr(value)),
+                Arg::Output(name, value) => format!("{} => {}", name, self.expr(value)),
+            })
+            .collect();
+        format!("{}({})", call.name, args.join(", "))
+    }
+
+    /// The statements, one per line and indented by `level`.
+    pub fn stmts(&self, stmts: &[Stmt], level: usize) -> String {
+        stmts.iter().map(|s| self.stmt(s, level)).collect()
+    }
+
+    pub fn stmt(&self, stmt: &Stmt, level: usize) -> String {
+        let pad = self.pad(level);
+        let kw = |k: &str| self.keyword(k);
+        match stmt {
+            Stmt::Assign { target, value } => {
+                format!("{}{} := {};\n", pad, self.expr(target), self.expr(value))
+            }
+            Stmt::Call(call) => format!("{}{};\n", pad, self.call(call)),
+            Stmt::If {
+                branches,
+                otherwise,
+            } => {
+                let mut st = String::new();
+                for (idx, branch) in branches.iter().enumerate() {
+                    let keyword = if idx == 0 { kw("IF") } else { kw("ELSIF") };
+                    st.push_str(&format!(
+                        "{}{} {} {}\n",
+                        pad,
+                        keyword,
+                        self.expr(&branch.cond),
+                        kw("THEN")
+                    ));
+                    st.push_str(&self.stmts(&branch.body, level + 1));
+                }
+                if let Some(otherwise) = otherwise {
+                    st.push_str(&format!("{}{}\n", pad, kw("ELSE")));
+                    st.push_str(&self.stmts(otherwise, level + 1));
+                }
+                st.push_str(&format!("{}{};\n", pad, kw("END_IF")));
+                st
+            }
+            Stmt::Case {
+                selector,
+                branches,
+                otherwise,
+            } => {
+                let mut st = format!(
+                    "{}{} {} {}\n",
+                    pad,
+                    kw("CASE"),
+                    self.expr(selector),
+                    kw("OF")
+                );
+                let label_pad = self.pad(level + 1);
+                for branch in branches.iter() {
+                    let labels: Vec<String> = branch
+                        .labels
+                        .iter()
+                        .map(|label| match label {
+                            CaseLabel::Value(value) => self.expr(value),
+                            CaseLabel::Range(from, to) => {
+                                format!("{}..{}", self.expr(from), self.expr(to))
+                            }
+                        })
+                        .collect();
+                    st.push_str(&format!("{}{}:\n", label_pad, labels.join(", ")));
+                    st.push_str(&self.stmts(&branch.body, level + 2));
+                }
+                if let Some(otherwise) = otherwise {
+                    st.push_str(&format!("{}{}\n", label_pad, kw("ELSE")));
+                    st.push_str(&self.stmts(otherwise, level + 2));
+                }
+                st.push_str(&format!("{}{};\n", pad, kw("END_CASE")));
+                st
+            }
+            Stmt::For {
+                var,
+                from,
+                to,
+                by,
+                body,
+            } => {
+                let by = by
+                    .as_ref()
+                    .map(|by| format!(" {} {}", kw("BY"), self.expr(by)))
+                    .unwrap_or_default();
+                format!(
+                    "{}{} {} := {} {} {}{} {}\n{}{}{};\n",
+                    pad,
+                    kw("FOR"),
+                    var,
+                    self.expr(from),
+                    kw("TO"),
+                    self.expr(to),
+                    by,
+                    kw("DO"),
+                    self.stmts(body, level + 1),
+                    pad,
+                    kw("END_FOR")
+                )
+            }
+            Stmt::While { cond, body } => format!(
+                "{}{} {} {}\n{}{}{};\n",
+                pad,
+                kw("WHILE"),
+                self.expr(cond),
+                kw("DO"),
+                self.stmts(body, level + 1),
+                pad,
+                kw("END_WHILE")
+            ),
+            Stmt::Repeat { body, until } => format!(
+                "{}{}\n{}{}{} {}\n{}{};\n",
+                pad,
+                kw("REPEAT"),
+                self.stmts(body, level + 1),
+                pad,
+                kw("UNTIL"),
+                self.expr(until),
+                pad,
+                kw("END_REPEAT")
+            ),
+            Stmt::Return => format!("{}{};\n", pad, kw("RETURN")),
+            Stmt::Exit => format!("{}{};\n", pad, kw("EXIT")),
+            Stmt::Continue => format!("{}{};\n", pad, kw("CONTINUE")),
+        }
+    }
+}
+
+/// The text of a STRING literal with the `$` escapes.
+fn escape(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '$' => "$$".to_string(),
+            '\'' => "$'".to_string(),
+            '\n' => "$N".to_string(),
+            '\r' => "$R".to_string(),
+            '\t' => "$T".to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// A REAL literal, always with a fractional part as ST requires.
+fn real(value: f64) -> String {
+    let text = format!("{:?}", value);
+    match text.split_once('e') {
+        Some((mantissa, exponent)) if mantissa.contains('.') => {
+            format!("{}E{}", mantissa, exponent)
+        }
+        Some((mantissa, exponent)) => format!("{}.0E{}", mantissa, exponent),
+        None if text.contains('.') => text,
+        None => format!("{}.0", text),
+    }
+}
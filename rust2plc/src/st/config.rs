@@ -0,0 +1,289 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::error::Rust2PlcError;
+use crate::types::TypedValue;
+use crate::var::Value;
+
+/// What makes a task run.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskTrigger {
+    /// Runs every interval.
+    Cyclic(Duration),
+    /// Runs on the rising edge of the named boolean variable.
+    Event(String),
+}
+
+/// An IEC `TASK`, the scheduling unit programs are bound to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Task {
+    name: String,
+    trigger: TaskTrigger,
+    priority: u32,
+}
+
+impl Task {
+    pub fn cyclic(name: impl Into<String>, interval: Duration, priority: u32) -> Self {
+        Task {
+            name: name.into(),
+            trigger: TaskTrigger::Cyclic(interval),
+            priority,
+        }
+    }
+
+    pub fn event(name: impl Into<String>, trigger: impl Into<String>, priority: u32) -> Self {
+        Task {
+            name: name.into(),
+            trigger: TaskTrigger::Event(trigger.into()),
+            priority,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn trigger(&self) -> &TaskTrigger {
+        &self.trigger
+    }
+
+    pub fn priority(&self) -> u32 {
+        self.priority
+    }
+
+    pub fn to_st(&self) -> Result<String, Rust2PlcError> {
+        let trigger = match &self.trigger {
+            TaskTrigger::Cyclic(interval) => format!(
+                "INTERVAL := {}",
+                TypedValue::Time(*interval).to_plc_literal()?
+            ),
+            TaskTrigger::Event(var) => format!("SINGLE := {}", var),
+        };
+        Ok(format!(
+            "TASK {}({}, PRIORITY := {});",
+            self.name, trigger, self.priority
+        ))
+    }
+}
+
+/// An instance of a program type, optionally bound to a task.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgramInstance {
+    name: String,
+    program: String,
+    task: Option<String>,
+}
+
+impl ProgramInstance {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+
+    pub fn task(&self) -> Option<&str> {
+        self.task.as_deref()
+    }
+}
+
+/// An IEC `RESOURCE`, a processing unit running tasks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resource {
+    name: String,
+    target: String,
+    tasks: Vec<Task>,
+    programs: Vec<ProgramInstance>,
+}
+
+impl Resource {
+    /// A resource running on the given processing unit type, e.g. `PLC`.
+    pub fn new(name: impl Into<String>, target: impl Into<String>) -> Self {
+        Resource {
+            name: name.into(),
+            target: target.into(),
+            tasks: vec![],
+            programs: vec![],
+        }
+    }
+
+    pub fn with_task(mut self, task: Task) -> Self {
+        self.tasks.push(task);
+        self
+    }
+
+    /// Instantiates the `program` type as `instance` running in `task`,
+    /// without a task the program runs in the lowest priority background task.
+    pub fn with_program(
+        mut self,
+        instance: impl Into<String>,
+        program: impl Into<String>,
+        task: Option<&str>,
+    ) -> Self {
+        self.programs.push(ProgramInstance {
+            name: instance.into(),
+            program: program.into(),
+            task: task.map(|t| t.to_string()),
+        });
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn tasks(&self) -> &[Task] {
+        &self.tasks
+    }
+
+    pub fn programs(&self) -> &[ProgramInstance] {
+        &self.programs
+    }
+}
+
+/// An IEC `CONFIGURATION`, the deployable unit binding programs to tasks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Configuration {
+    name: String,
+    globals: Vec<Value>,
+    resources: Vec<Resource>,
+    description: Option<String>,
+}
+
+impl Configuration {
+    pub fn new(name: impl Into<String>) -> Self {
+        Configuration {
+            name: name.into(),
+            globals: vec![],
+            resources: vec![],
+            description: None,
+        }
+    }
+
+    /// Adds a `VAR_GLOBAL` variable shared by all resources.
+    pub fn with_global(mut self, name: impl Into<String>, value: TypedValue) -> Self {
+        self.globals.push(Value::Global(name.into(), value));
+        self
+    }
+
+    pub fn with_resource(mut self, resource: Resource) -> Self {
+        self.resources.push(resource);
+        self
+    }
+
+    pub fn with_description(mut self, description: Option<String>) -> Self {
+        self.description = description;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn globals(&self) -> &[Value] {
+        &self.globals
+    }
+
+    pub fn resources(&self) -> &[Resource] {
+        &self.resources
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// The program types instantiated in the configuration.
+    pub fn program_types(&self) -> Vec<&str> {
+        self.resources
+            .iter()
+            .flat_map(|r| r.programs.iter().map(|p| p.program.as_str()))
+            .collect()
+    }
+
+    /// Checks that names are unique and every program runs in a declared task.
+    pub fn validate(&self) -> Result<(), Rust2PlcError> {
+        let mut resources = HashSet::new();
+        for resource in self.resources.iter() {
+            if !resources.insert(resource.name.as_str()) {
+                return Err(Rust2PlcError::Other(format!(
+                    "resource {} is declared twice in configuration {}",
+                    resource.name, self.name
+                )));
+            }
+            let mut tasks = HashSet::new();
+            for task in resource.tasks.iter() {
+                if !tasks.insert(task.name.as_str()) {
+                    return Err(Rust2PlcError::Other(format!(
+                        "task {} is declared twice in resource {}",
+                        task.name, resource.name
+                    )));
+                }
+            }
+            let mut instances = HashSet::new();
+            for program in resource.programs.iter() {
+                if !instances.insert(program.name.as_str()) {
+                    return Err(Rust2PlcError::Other(format!(
+                        "program instance {} is declared twice in resource {}",
+                        program.name, resource.name
+                    )));
+                }
+                if let Some(task) = &program.task {
+                    if !tasks.contains(task.as_str()) {
+                        return Err(Rust2PlcError::Other(format!(
+                            "program instance {} refers to the unknown task {}",
+                            program.name, task
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Renders the `CONFIGURATION ... END_CONFIGURATION` section.
+    pub fn to_st(&self) -> Result<String, Rust2PlcError> {
+        self.validate()?;
+
+        let mut st = String::new();
+        if let Some(description) = &self.description {
+            st.push_str(&format!("(* {} *)\n", description));
+        }
+        st.push_str(&format!("CONFIGURATION {}\n", self.name));
+        if !self.globals.is_empty() {
+            st.push_str("    VAR_GLOBAL\n");
+            for global in self.globals.iter() {
+                st.push_str(&format!("        {}\n", global.to_st_decl()));
+            }
+            st.push_str("    END_VAR\n");
+        }
+        for resource in self.resources.iter() {
+            st.push_str(&format!(
+                "    RESOURCE {} ON {}\n",
+                resource.name, resource.target
+            ));
+            for task in resource.tasks.iter() {
+                st.push_str(&format!("        {}\n", task.to_st()?));
+            }
+            for program in resource.programs.iter() {
+                match &program.task {
+                    Some(task) => st.push_str(&format!(
+                        "        PROGRAM {} WITH {} : {};\n",
+                        program.name, task, program.program
+                    )),
+                    None => st.push_str(&format!(
+                        "        PROGRAM {} : {};\n",
+                        program.name, program.program
+                    )),
+                }
+            }
+            st.push_str("    END_RESOURCE\n");
+        }
+        st.push_str("END_CONFIGURATION\n");
+        Ok(st)
+    }
+}
@@ -0,0 +1,193 @@
+use crate::error::Rust2PlcError;
+use crate::types::TypedValue;
+
+/// A user-defined data type, rendered as `TYPE ... END_TYPE`.
+///
+/// The layout and the initial value are kept in a [`TypedValue::Struct`]
+/// or a [`TypedValue::Enum`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataType {
+    value: TypedValue,
+    base: Option<TypedValue>,
+    values: Vec<Option<i64>>,
+
+    description: Option<String>,
+    namespace: Option<String>,
+    version: Option<String>,
+}
+
+impl DataType {
+    pub fn new_struct(name: impl Into<String>, fields: Vec<(String, TypedValue)>) -> Self {
+        DataType {
+            value: TypedValue::Struct(name.into(), fields),
+            base: None,
+            values: vec![],
+            description: None,
+            namespace: None,
+            version: None,
+        }
+    }
+
+    /// An enumerated type, the variants optionally carry explicit values.
+    /// The first variant is the initial value unless [`DataType::with_initial`] sets another.
+    pub fn new_enum(name: impl Into<String>, variants: Vec<(String, Option<i64>)>) -> Self {
+        let (variants, values) = variants.into_iter().unzip();
+        DataType {
+            value: TypedValue::new_enum(&name.into(), variants, None),
+            base: None,
+            values,
+            description: None,
+            namespace: None,
+            version: None,
+        }
+    }
+
+    /// The integer type the enumerated values are stored in, e.g. `INT`.
+    pub fn with_base(mut self, base: Option<TypedValue>) -> Self {
+        self.base = base;
+        self
+    }
+
+    /// Sets the initial variant of an enumerated type.
+    pub fn with_initial(mut self, variant: impl Into<String>) -> Self {
+        if let TypedValue::Enum(_, _, value, initial) = &mut self.value {
+            *value = variant.into();
+            *initial = value.clone();
+        }
+        self
+    }
+
+    pub fn with_description(mut self, description: Option<String>) -> Self {
+        self.description = description;
+        self
+    }
+
+    pub fn with_namespace(mut self, namespace: Option<String>) -> Self {
+        self.namespace = namespace;
+        self
+    }
+
+    pub fn with_version(mut self, version: Option<String>) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        match &self.value {
+            TypedValue::Struct(name, _) | TypedValue::Enum(name, ..) => name,
+            _ => "",
+        }
+    }
+
+    /// The type holding its initial value.
+    pub fn value(&self) -> &TypedValue {
+        &self.value
+    }
+
+    pub fn base(&self) -> Option<&TypedValue> {
+        self.base.as_ref()
+    }
+
+    /// The explicit values of the variants of an enumerated type.
+    pub fn values(&self) -> &[Option<i64>] {
+        &self.values
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// Renders the `TYPE ... END_TYPE` declaration.
+    /// Initial values are written only where they differ from the defaults of the field type.
+    pub fn to_st(&self) -> Result<String, Rust2PlcError> {
+        let mut st = crate::st::header(self.description(), self.namespace(), self.version());
+        match &self.value {
+            TypedValue::Struct(name, fields) => {
+                st.push_str(&format!("TYPE {} :\nSTRUCT\n", name));
+                for (field, value) in fields.iter() {
+                    if *value == value.default_value() {
+                        st.push_str(&format!("    {} : {};\n", field, value.to_plc_type()));
+                    } else {
+                        st.push_str(&format!(
+                            "    {} : {} := {};\n",
+                            field,
+                            value.to_plc_type(),
+                            value.to_plc_literal()?
+                        ));
+                    }
+                }
+                st.push_str("END_STRUCT\nEND_TYPE\n");
+            }
+            TypedValue::Enum(name, variants, ..) => {
+                if variants.is_empty() {
+                    return Err(Rust2PlcError::Other(format!(
+                        "enumerated type {} has no variants",
+                        name
+                    )));
+                }
+                let variants: Vec<String> = variants
+                    .iter()
+                    .zip(self.values.iter().chain(std::iter::repeat(&None)))
+                    .map(|(variant, value)| match value {
+                        Some(value) => format!("{} := {}", variant, value),
+                        None => variant.clone(),
+                    })
+                    .collect();
+                let base = match &self.base {
+                    Some(base) if is_integer(base) => format!("{} ", base.to_plc_type()),
+                    Some(base) => {
+                        return Err(Rust2PlcError::Other(format!(
+                            "enumerated type {} cannot be based on {}",
+                            name,
+                            base.to_plc_type()
+                        )))
+                    }
+                    None => String::new(),
+                };
+                let initial = self.value.to_plc_literal()?;
+                let initial = initial.rsplit('#').next().unwrap_or(&initial);
+                st.push_str(&format!(
+                    "TYPE {} : {}({}) := {};\nEND_TYPE\n",
+                    name,
+                    base,
+                    variants.join(", "),
+                    initial
+                ));
+            }
+            other => {
+                return Err(Rust2PlcError::Other(format!(
+                    "{} cannot be declared as a data type",
+                    other.to_plc_type()
+                )))
+            }
+        }
+        Ok(st)
+    }
+}
+
+fn is_integer(tv: &TypedValue) -> bool {
+    matches!(
+        tv,
+        TypedValue::SInt(_)
+            | TypedValue::Int(_)
+            | TypedValue::DInt(_)
+            | TypedValue::LInt(_)
+            | TypedValue::USInt(_)
+            | TypedValue::UInt(_)
+            | TypedValue::UDInt(_)
+            | TypedValue::ULInt(_)
+    )
+}
+
+/// Implemented by `#[derive(PlcType)]`, describes the PLC counterpart of a Rust type.
+pub trait PlcType {
+    fn plc_type() -> DataType;
+}
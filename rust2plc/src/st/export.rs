@@ -0,0 +1,85 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::error::Rust2PlcError;
+use crate::registry::{PLCRegistry, RegistryItem};
+
+/// The name of the manifest written next to the exported sources.
+pub const MANIFEST_FILE: &str = "manifest.csv";
+
+/// How the exported ST sources are split into files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StLayout {
+    /// One `<Namespace>.<name>.st` file per POU.
+    PerPou,
+    /// One `<Namespace>.st` file per namespace, POUs without a namespace go to `global.st`.
+    PerNamespace,
+}
+
+impl PLCRegistry {
+    /// Writes one `.st` file per POU and the manifest into `dir`.
+    pub fn export_st(&self, dir: impl AsRef<Path>) -> Result<(), Rust2PlcError> {
+        self.export_st_with(dir, StLayout::PerPou)
+    }
+
+    /// Writes the ST project into `dir` using the given layout.
+    ///
+    /// Only the latest version of every POU is exported and the declarations
+    /// follow [`PLCRegistry::declaration_order`], so types precede their users.
+    pub fn export_st_with(
+        &self,
+        dir: impl AsRef<Path>,
+        layout: StLayout,
+    ) -> Result<(), Rust2PlcError> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        let mut files: BTreeMap<String, Vec<&RegistryItem>> = BTreeMap::new();
+        let mut manifest = vec![];
+        let ordered = self.declaration_order();
+        for item in ordered.iter() {
+            let file = match layout {
+                StLayout::PerPou => format!("{}.st", item.qualified_name()),
+                StLayout::PerNamespace => format!("{}.st", item.namespace().unwrap_or("global")),
+            };
+            files.entry(file.clone()).or_default().push(item);
+            manifest.push((file, *item));
+        }
+
+        for (file, items) in files {
+            let sources = items
+                .iter()
+                .map(|i| i.to_st())
+                .collect::<Result<Vec<_>, _>>()?;
+            fs::write(dir.join(file), sources.join("\n"))?;
+        }
+        fs::write(dir.join(MANIFEST_FILE), manifest_csv(&manifest))?;
+        Ok(())
+    }
+}
+
+fn manifest_csv(entries: &[(String, &RegistryItem)]) -> String {
+    let mut csv = String::from("order,file,namespace,name,version,description\n");
+    for (idx, (file, item)) in entries.iter().enumerate() {
+        let row = [
+            (idx + 1).to_string(),
+            csv_field(file),
+            csv_field(item.namespace().unwrap_or_default()),
+            csv_field(item.name()),
+            csv_field(item.version().unwrap_or_default()),
+            csv_field(item.description().unwrap_or_default()),
+        ];
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
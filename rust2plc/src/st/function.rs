@@ -1,15 +1,144 @@
+use crate::st;
+use crate::st::ast::{Printer, Stmt, VarBlock};
 use crate::types::TypedValue;
 use crate::var::Value;
 
-#[derive(Debug, Clone)]
+/// An ST `FUNCTION` POU.
+///
+/// Functions are produced by `#[plc_fn]`, but can be assembled by hand as well
+/// starting from [`Function::new`] and chaining the `with_*` methods.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Function {
     name: String,
     inputs: Vec<Value>,
     outputs: Vec<Value>,
+    locals: Vec<Value>,
     return_value: TypedValue,
-    body: String,
+    body: Vec<Stmt>,
 
     description: Option<String>,
     namespace: Option<String>,
     version: Option<String>,
+    block: Option<String>,
+}
+
+impl Function {
+    pub fn new(name: impl Into<String>, return_value: TypedValue) -> Self {
+        Function {
+            name: name.into(),
+            inputs: vec![],
+            outputs: vec![],
+            locals: vec![],
+            return_value,
+            body: vec![],
+            description: None,
+            namespace: None,
+            version: None,
+            block: None,
+        }
+    }
+
+    pub fn with_input(mut self, input: Value) -> Self {
+        self.inputs.push(input);
+        self
+    }
+
+    pub fn with_output(mut self, output: Value) -> Self {
+        self.outputs.push(output);
+        self
+    }
+
+    pub fn with_local(mut self, local: Value) -> Self {
+        self.locals.push(local);
+        self
+    }
+
+    pub fn with_body(mut self, body: Vec<Stmt>) -> Self {
+        self.body = body;
+        self
+    }
+
+    pub fn with_description(mut self, description: Option<String>) -> Self {
+        self.description = description;
+        self
+    }
+
+    pub fn with_namespace(mut self, namespace: Option<String>) -> Self {
+        self.namespace = namespace;
+        self
+    }
+
+    pub fn with_version(mut self, version: Option<String>) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// The S7 block the POU is compiled to in STL, e.g. `FC10`, see [`crate::awl`].
+    pub fn with_block(mut self, block: Option<String>) -> Self {
+        self.block = block;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn inputs(&self) -> &[Value] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[Value] {
+        &self.outputs
+    }
+
+    pub fn locals(&self) -> &[Value] {
+        &self.locals
+    }
+
+    pub fn return_value(&self) -> &TypedValue {
+        &self.return_value
+    }
+
+    pub fn body(&self) -> &[Stmt] {
+        &self.body
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    pub fn block(&self) -> Option<&str> {
+        self.block.as_deref()
+    }
+
+    /// Renders the function as an ST `FUNCTION ... END_FUNCTION` block.
+    pub fn to_st(&self) -> String {
+        self.to_st_with(&Printer::default())
+    }
+
+    /// Renders the function in the style of the printer.
+    pub fn to_st_with(&self, printer: &Printer) -> String {
+        let mut st = st::header(self.description(), self.namespace(), self.version());
+        st.push_str(&format!(
+            "{} {} : {}\n",
+            printer.keyword("FUNCTION"),
+            self.name,
+            self.return_value.to_plc_type()
+        ));
+        st.push_str(&printer.var_blocks(
+            &VarBlock::group(self.inputs.iter().chain(&self.outputs).chain(&self.locals)),
+            0,
+        ));
+        st.push_str(&printer.stmts(&self.body, 1));
+        st.push_str(&format!("{}\n", printer.keyword("END_FUNCTION")));
+        st
+    }
 }
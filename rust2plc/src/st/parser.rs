@@ -0,0 +1,1456 @@
+//! A parser of IEC 61131-3 Structured Text.
+//!
+//! [`parse`] reads `FUNCTION`, `FUNCTION_BLOCK`, `PROGRAM` and `TYPE` declarations
+//! into the same model the macros produce, so existing ST can be rendered again,
+//! analysed or migrated. Failures are reported as [`Rust2PlcError::ParseError`]
+//! carrying the line and column of the offending token.
+//!
+//! ```
+//! use rust2plc::registry::RegistryItem;
+//! use rust2plc::st::parser;
+//!
+//! let items = parser::parse(
+//!     "FUNCTION twice : INT\nVAR_INPUT\n    x : INT;\nEND_VAR\n    twice := x * 2;\nEND_FUNCTION\n",
+//! )
+//! .unwrap();
+//! let RegistryItem::StFn(twice) = &items[0] else { unreachable!() };
+//! assert_eq!(twice.inputs().len(), 1);
+//!
+//! let err = parser::parse("PROGRAM main\n    x := ;\nEND_PROGRAM\n").unwrap_err();
+//! assert_eq!(err.position().map(|p| (p.line, p.column)), Some((2, 10)));
+//! ```
+//!
+//! Arrays keep their declared lower bound, e.g. `ARRAY[1..10] OF INT` reads into ten
+//! elements starting at index 1.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+use crate::error::Rust2PlcError;
+use crate::registry::RegistryItem;
+use crate::st::ast::{
+    Arg, BinaryOp, Branch, Call, CaseBranch, CaseLabel, Expr, Literal, Printer, Stmt, UnaryOp,
+    VarSection,
+};
+use crate::st::data_type::DataType;
+use crate::st::function::Function;
+use crate::st::function_block::FunctionBlock;
+use crate::st::program::Program;
+use crate::types::TypedValue;
+use crate::var::Value;
+
+/// Parses the POUs and data types of an ST source, in the order they are declared.
+pub fn parse(source: &str) -> Result<Vec<RegistryItem>, Rust2PlcError> {
+    let mut parser = Parser::new(source)?;
+    let mut items = vec![];
+    while !parser.at_end() {
+        if parser.is_kw("TYPE") {
+            items.extend(parser.data_types()?.into_iter().map(RegistryItem::StType));
+        } else if parser.is_kw("FUNCTION") {
+            items.push(RegistryItem::StFn(parser.function()?));
+        } else if parser.is_kw("FUNCTION_BLOCK") {
+            items.push(RegistryItem::StFb(parser.function_block()?));
+        } else if parser.is_kw("PROGRAM") {
+            items.push(RegistryItem::StProgram(parser.program()?));
+        } else {
+            return Err(parser.unexpected("FUNCTION, FUNCTION_BLOCK, PROGRAM or TYPE"));
+        }
+    }
+    Ok(items)
+}
+
+/// Parses a statement list, e.g. the body of a POU.
+pub fn parse_stmts(source: &str) -> Result<Vec<Stmt>, Rust2PlcError> {
+    let mut parser = Parser::new(source)?;
+    let stmts = parser.stmts(&[])?;
+    parser.finish()?;
+    Ok(stmts)
+}
+
+pub fn parse_expr(source: &str) -> Result<Expr, Rust2PlcError> {
+    let mut parser = Parser::new(source)?;
+    let expr = parser.expr()?;
+    parser.finish()?;
+    Ok(expr)
+}
+
+/// Parses an initial value of the given type, e.g. `T#5s` for a `TIME`
+/// or `(x := 1.0, y := 2.0)` for a structure.
+pub fn parse_value(ty: &TypedValue, source: &str) -> Result<TypedValue, Rust2PlcError> {
+    let mut parser = Parser::new(source)?;
+    let value = parser.initial(ty.clone())?;
+    parser.finish()?;
+    Ok(value)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Tok {
+    Ident(String),
+    Int(i128),
+    Real(f64),
+    Str(String),
+    /// `type#value`, e.g. `T#5s`, `INT#3` or `Mode#Running`.
+    Typed(String, String),
+    Sym(&'static str),
+    Eof,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    tok: Tok,
+    line: usize,
+    column: usize,
+    /// The comments written right before the token.
+    comments: Vec<String>,
+}
+
+const SYMBOLS: [&str; 24] = [
+    ":=", "=>", "..", "<>", "<=", ">=", "**", ":", ";", ",", "(", ")", "[", "]", ".", "+", "-",
+    "*", "/", "=", "<", ">", "&", "^",
+];
+
+struct Lexer {
+    chars: Vec<char>,
+    idx: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Lexer {
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.idx + offset).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek(0)?;
+        self.idx += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn starts_with(&self, text: &str) -> bool {
+        text.chars()
+            .enumerate()
+            .all(|(i, c)| self.peek(i) == Some(c))
+    }
+
+    fn error(&self, msg: impl Into<String>) -> Rust2PlcError {
+        Rust2PlcError::parse_at(msg.into(), self.line, self.column)
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> String {
+        let mut text = String::new();
+        while let Some(c) = self.peek(0).filter(|c| f(*c)) {
+            text.push(c);
+            self.bump();
+        }
+        text
+    }
+
+    fn tokens(mut self) -> Result<Vec<Token>, Rust2PlcError> {
+        let mut tokens = vec![];
+        let mut comments = vec![];
+        loop {
+            self.take_while(char::is_whitespace);
+            let (line, column) = (self.line, self.column);
+            let Some(c) = self.peek(0) else {
+                tokens.push(Token {
+                    tok: Tok::Eof,
+                    line,
+                    column,
+                    comments,
+                });
+                return Ok(tokens);
+            };
+            let tok = if self.starts_with("(*") {
+                comments.push(self.block_comment("(*", "*)")?);
+                continue;
+            } else if self.starts_with("/*") {
+                comments.push(self.block_comment("/*", "*/")?);
+                continue;
+            } else if self.starts_with("//") {
+                let text = self.take_while(|c| c != '\n');
+                comments.push(text[2..].trim().to_string());
+                continue;
+            } else if c == '{' {
+                // pragmas carry no meaning for the model
+                self.block_comment("{", "}")?;
+                continue;
+            } else if c.is_ascii_alphabetic() || c == '_' {
+                let ident = self.take_while(|c| c.is_ascii_alphanumeric() || c == '_');
+                if self.peek(0) == Some('#') {
+                    self.bump();
+                    self.typed(ident)?
+                } else {
+                    Tok::Ident(ident)
+                }
+            } else if c == '%' {
+                // a direct address, e.g. `%IX0.1`
+                self.bump();
+                let address = self.take_while(|c| c.is_ascii_alphanumeric() || c == '.');
+                Tok::Ident(format!("%{}", address))
+            } else if c.is_ascii_digit() {
+                self.number()?
+            } else if c == '\'' || c == '"' {
+                Tok::Str(self.string(c)?)
+            } else if let Some(sym) = SYMBOLS.iter().find(|s| self.starts_with(s)) {
+                for _ in 0..sym.len() {
+                    self.bump();
+                }
+                Tok::Sym(sym)
+            } else {
+                return Err(self.error(format!("unexpected character `{}`", c)));
+            };
+            tokens.push(Token {
+                tok,
+                line,
+                column,
+                comments: std::mem::take(&mut comments),
+            });
+        }
+    }
+
+    /// A comment, `(* ... *)` comments may nest.
+    fn block_comment(&mut self, open: &str, close: &str) -> Result<String, Rust2PlcError> {
+        let (line, column) = (self.line, self.column);
+        let mut depth = 0;
+        let mut text = String::new();
+        loop {
+            if self.starts_with(open) {
+                open.chars().for_each(|_| {
+                    self.bump();
+                });
+                if depth > 0 {
+                    text.push_str(open);
+                }
+                depth += 1;
+            } else if self.starts_with(close) {
+                close.chars().for_each(|_| {
+                    self.bump();
+                });
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(text.trim().to_string());
+                }
+                text.push_str(close);
+            } else if let Some(c) = self.bump() {
+                text.push(c);
+            } else {
+                return Err(Rust2PlcError::parse_at(
+                    "unterminated comment".to_string(),
+                    line,
+                    column,
+                ));
+            }
+        }
+    }
+
+    fn string(&mut self, quote: char) -> Result<String, Rust2PlcError> {
+        let (line, column) = (self.line, self.column);
+        self.bump();
+        let mut text = String::new();
+        loop {
+            match self.bump() {
+                Some(c) if c == quote => return Ok(text),
+                Some('$') => match self.bump() {
+                    Some('L' | 'l' | 'N' | 'n') => text.push('\n'),
+                    Some('R' | 'r') => text.push('\r'),
+                    Some('T' | 't') => text.push('\t'),
+                    Some('P' | 'p') => text.push('\x0C'),
+                    Some(c) if c.is_ascii_hexdigit() => {
+                        let hex: String = [Some(c), self.bump()].into_iter().flatten().collect();
+                        let code = u32::from_str_radix(&hex, 16)
+                            .map_err(|_| self.error(format!("invalid escape `${}`", hex)))?;
+                        text.extend(char::from_u32(code));
+                    }
+                    Some(c) => text.push(c),
+                    None => break,
+                },
+                Some(c) => text.push(c),
+                None => break,
+            }
+        }
+        Err(Rust2PlcError::parse_at(
+            "unterminated string".to_string(),
+            line,
+            column,
+        ))
+    }
+
+    fn number(&mut self) -> Result<Tok, Rust2PlcError> {
+        let (line, column) = (self.line, self.column);
+        let digits = self.take_while(|c| c.is_ascii_digit() || c == '_');
+        if self.peek(0) == Some('#') {
+            self.bump();
+            let value = self.take_while(|c| c.is_ascii_hexdigit() || c == '_');
+            return based(&digits, &value).map(Tok::Int).ok_or_else(|| {
+                Rust2PlcError::parse_at(
+                    format!("invalid number `{}#{}`", digits, value),
+                    line,
+                    column,
+                )
+            });
+        }
+        let mut text = digits.replace('_', "");
+        let mut real = false;
+        if self.peek(0) == Some('.') && self.peek(1).is_some_and(|c| c.is_ascii_digit()) {
+            self.bump();
+            real = true;
+            text.push('.');
+            text.push_str(
+                &self
+                    .take_while(|c| c.is_ascii_digit() || c == '_')
+                    .replace('_', ""),
+            );
+        }
+        if matches!(self.peek(0), Some('e' | 'E'))
+            && (self.peek(1).is_some_and(|c| c.is_ascii_digit())
+                || matches!(self.peek(1), Some('+' | '-'))
+                    && self.peek(2).is_some_and(|c| c.is_ascii_digit()))
+        {
+            self.bump();
+            real = true;
+            text.push('e');
+            if let Some(sign) = self.peek(0).filter(|c| *c == '+' || *c == '-') {
+                self.bump();
+                text.push(sign);
+            }
+            text.push_str(&self.take_while(|c| c.is_ascii_digit()));
+        }
+        let invalid =
+            || Rust2PlcError::parse_at(format!("invalid number `{}`", text), line, column);
+        if real {
+            text.parse().map(Tok::Real).map_err(|_| invalid())
+        } else {
+            text.parse().map(Tok::Int).map_err(|_| invalid())
+        }
+    }
+
+    /// The value of a typed literal, the characters it may contain depend on the type.
+    fn typed(&mut self, ty: String) -> Result<Tok, Rust2PlcError> {
+        let upper = ty.to_uppercase();
+        let mut value = String::new();
+        if let Some(sign) = self.peek(0).filter(|c| *c == '-' || *c == '+') {
+            self.bump();
+            value.push(sign);
+        }
+        let rest = match upper.as_str() {
+            "D" | "DATE" | "LD" | "LDATE" => self.take_while(|c| c.is_ascii_digit() || c == '-'),
+            "TOD" | "TIME_OF_DAY" | "LTOD" => {
+                self.take_while(|c| c.is_ascii_digit() || c == ':' || c == '.')
+            }
+            "DT" | "DATE_AND_TIME" | "LDT" => {
+                self.take_while(|c| c.is_ascii_digit() || matches!(c, '-' | ':' | '.'))
+            }
+            _ => {
+                let mut rest = String::new();
+                loop {
+                    match self.peek(0) {
+                        Some(c) if c.is_ascii_alphanumeric() || c == '_' || c == '#' => {
+                            rest.push(c);
+                            self.bump();
+                        }
+                        Some('.') if self.peek(1).is_some_and(|c| c.is_ascii_digit()) => {
+                            rest.push('.');
+                            self.bump();
+                        }
+                        Some(c @ ('+' | '-'))
+                            if rest.ends_with(['e', 'E'])
+                                && rest[..rest.len() - 1]
+                                    .chars()
+                                    .all(|c| c.is_ascii_digit() || c == '.') =>
+                        {
+                            rest.push(c);
+                            self.bump();
+                        }
+                        _ => break,
+                    }
+                }
+                rest
+            }
+        };
+        if rest.is_empty() {
+            return Err(self.error(format!("missing value after `{}#`", ty)));
+        }
+        value.push_str(&rest);
+        Ok(Tok::Typed(ty, value))
+    }
+}
+
+/// An integer written in base 2, 8 or 16, e.g. `16#FF`.
+fn based(base: &str, digits: &str) -> Option<i128> {
+    let radix = base.replace('_', "").parse::<u32>().ok()?;
+    if !matches!(radix, 2 | 8 | 16) {
+        return None;
+    }
+    i128::from_str_radix(&digits.replace('_', ""), radix).ok()
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// The data types declared so far, the POUs below refer to them by name.
+    types: HashMap<String, DataType>,
+}
+
+impl Parser {
+    fn new(source: &str) -> Result<Self, Rust2PlcError> {
+        let lexer = Lexer {
+            chars: source.chars().collect(),
+            idx: 0,
+            line: 1,
+            column: 1,
+        };
+        Ok(Parser {
+            tokens: lexer.tokens()?,
+            pos: 0,
+            types: HashMap::new(),
+        })
+    }
+
+    fn peek(&self) -> &Token {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> &Token {
+        let last = self.tokens.len() - 1;
+        &self.tokens[(self.pos + offset).min(last)]
+    }
+
+    fn bump(&mut self) -> Token {
+        let token = self.peek().clone();
+        if self.pos < self.tokens.len() - 1 {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn at_end(&self) -> bool {
+        self.peek().tok == Tok::Eof
+    }
+
+    fn finish(&self) -> Result<(), Rust2PlcError> {
+        if self.at_end() {
+            Ok(())
+        } else {
+            Err(self.unexpected("end of input"))
+        }
+    }
+
+    fn error_at(&self, token: &Token, msg: impl Into<String>) -> Rust2PlcError {
+        Rust2PlcError::parse_at(msg.into(), token.line, token.column)
+    }
+
+    fn unexpected(&self, expected: &str) -> Rust2PlcError {
+        let token = self.peek();
+        let found = match &token.tok {
+            Tok::Ident(name) => format!("`{}`", name),
+            Tok::Int(value) => format!("`{}`", value),
+            Tok::Real(value) => format!("`{}`", value),
+            Tok::Str(value) => format!("'{}'", value),
+            Tok::Typed(ty, value) => format!("`{}#{}`", ty, value),
+            Tok::Sym(sym) => format!("`{}`", sym),
+            Tok::Eof => "end of input".to_string(),
+        };
+        self.error_at(token, format!("expected {}, found {}", expected, found))
+    }
+
+    fn is_kw(&self, keyword: &str) -> bool {
+        matches!(&self.peek().tok, Tok::Ident(name) if name.eq_ignore_ascii_case(keyword))
+    }
+
+    fn eat_kw(&mut self, keyword: &str) -> bool {
+        let found = self.is_kw(keyword);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    fn expect_kw(&mut self, keyword: &str) -> Result<(), Rust2PlcError> {
+        if self.eat_kw(keyword) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", keyword)))
+        }
+    }
+
+    fn is_sym(&self, sym: &str) -> bool {
+        self.peek().tok == Tok::Sym(sym_ref(sym))
+    }
+
+    fn eat_sym(&mut self, sym: &str) -> bool {
+        let found = self.is_sym(sym);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    fn expect_sym(&mut self, sym: &str) -> Result<(), Rust2PlcError> {
+        if self.eat_sym(sym) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", sym)))
+        }
+    }
+
+    fn ident(&mut self) -> Result<String, Rust2PlcError> {
+        match &self.peek().tok {
+            Tok::Ident(name) if !is_reserved(name) => {
+                let name = name.clone();
+                self.bump();
+                Ok(name)
+            }
+            _ => Err(self.unexpected("an identifier")),
+        }
+    }
+
+    /// A `END_*` keyword, the trailing semicolon is optional.
+    fn end(&mut self, keyword: &str) -> Result<(), Rust2PlcError> {
+        self.expect_kw(keyword)?;
+        self.eat_sym(";");
+        Ok(())
+    }
+
+    fn function(&mut self) -> Result<Function, Rust2PlcError> {
+        let (description, namespace, version) = header(&self.peek().comments);
+        self.expect_kw("FUNCTION")?;
+        let name = self.ident()?;
+        self.expect_sym(":")?;
+        let return_value = self.type_spec()?;
+        let mut function = Function::new(name, return_value)
+            .with_description(description)
+            .with_namespace(namespace)
+            .with_version(version);
+        for var in self.var_blocks()? {
+            function = match var {
+                Value::Input(_, _) | Value::InOut(_, _) => function.with_input(var),
+                Value::Output(_, _) => function.with_output(var),
+                _ => function.with_local(var),
+            };
+        }
+        let body = self.stmts(&["END_FUNCTION"])?;
+        self.end("END_FUNCTION")?;
+        Ok(function.with_body(body))
+    }
+
+    fn function_block(&mut self) -> Result<FunctionBlock, Rust2PlcError> {
+        let (description, namespace, version) = header(&self.peek().comments);
+        self.expect_kw("FUNCTION_BLOCK")?;
+        let mut fb = FunctionBlock::new(self.ident()?)
+            .with_description(description)
+            .with_namespace(namespace)
+            .with_version(version);
+        for var in self.var_blocks()? {
+            fb = match var {
+                Value::Input(_, _) | Value::InOut(_, _) => fb.with_input(var),
+                Value::Output(_, _) => fb.with_output(var),
+                _ => fb.with_var(var),
+            };
+        }
+        let body = self.stmts(&["END_FUNCTION_BLOCK"])?;
+        self.end("END_FUNCTION_BLOCK")?;
+        Ok(fb.with_body(body))
+    }
+
+    fn program(&mut self) -> Result<Program, Rust2PlcError> {
+        let (description, namespace, version) = header(&self.peek().comments);
+        self.expect_kw("PROGRAM")?;
+        let mut program = Program::new(self.ident()?)
+            .with_description(description)
+            .with_namespace(namespace)
+            .with_version(version);
+        for var in self.var_blocks()? {
+            program = match var {
+                Value::Input(_, _) | Value::InOut(_, _) => program.with_input(var),
+                Value::Output(_, _) => program.with_output(var),
+                _ => program.with_var(var),
+            };
+        }
+        let body = self.stmts(&["END_PROGRAM"])?;
+        self.end("END_PROGRAM")?;
+        Ok(program.with_body(body))
+    }
+
+    /// A `TYPE ... END_TYPE` block, it may declare several types.
+    fn data_types(&mut self) -> Result<Vec<DataType>, Rust2PlcError> {
+        let (description, namespace, version) = header(&self.peek().comments);
+        self.expect_kw("TYPE")?;
+        let mut types = vec![];
+        while !self.is_kw("END_TYPE") {
+            let start = self.peek().clone();
+            let name = self.ident()?;
+            self.expect_sym(":")?;
+            let data_type = if self.eat_kw("STRUCT") {
+                let fields = self.declarations(&["END_STRUCT"])?;
+                self.end("END_STRUCT")?;
+                DataType::new_struct(name, fields)
+            } else {
+                let base = if self.is_sym("(") {
+                    None
+                } else {
+                    let base = self.type_spec()?;
+                    if !self.is_sym("(") {
+                        return Err(
+                            self.error_at(&start, format!("type alias {} is not supported", name))
+                        );
+                    }
+                    Some(base)
+                };
+                self.enumeration(name, base)?
+            };
+            self.types
+                .insert(data_type.name().to_string(), data_type.clone());
+            types.push(data_type);
+        }
+        self.end("END_TYPE")?;
+        if types.is_empty() {
+            return Err(self.error_at(&self.tokens[self.pos - 1], "empty TYPE block"));
+        }
+        types[0] = types[0]
+            .clone()
+            .with_description(description)
+            .with_namespace(namespace)
+            .with_version(version);
+        Ok(types)
+    }
+
+    /// `(Idle, Running := 2) := Idle;`
+    fn enumeration(
+        &mut self,
+        name: String,
+        base: Option<TypedValue>,
+    ) -> Result<DataType, Rust2PlcError> {
+        self.expect_sym("(")?;
+        let mut variants = vec![];
+        loop {
+            let variant = self.ident()?;
+            let value = if self.eat_sym(":=") {
+                let token = self.peek().clone();
+                match int_value(&self.expr()?) {
+                    Some(value) => Some(i64::try_from(value).map_err(|_| {
+                        self.error_at(&token, format!("{} is out of range", value))
+                    })?),
+                    None => return Err(self.error_at(&token, "expected an integer value")),
+                }
+            } else {
+                None
+            };
+            variants.push((variant, value));
+            if !self.eat_sym(",") {
+                break;
+            }
+        }
+        self.expect_sym(")")?;
+        let mut data_type = DataType::new_enum(name.clone(), variants).with_base(base);
+        if self.eat_sym(":=") {
+            let initial = self.initial(data_type.value().clone())?;
+            if let TypedValue::Enum(_, _, variant, _) = initial {
+                data_type = data_type.with_initial(variant);
+            }
+        }
+        self.eat_sym(";");
+        Ok(data_type)
+    }
+
+    /// The `VAR_* ... END_VAR` sections of a POU.
+    fn var_blocks(&mut self) -> Result<Vec<Value>, Rust2PlcError> {
+        let mut vars = vec![];
+        loop {
+            let Some(mut section) = [
+                VarSection::Input,
+                VarSection::Output,
+                VarSection::InOut,
+                VarSection::Temp,
+                VarSection::External,
+                VarSection::Global,
+                VarSection::Var,
+            ]
+            .into_iter()
+            .find(|section| self.is_kw(section.keyword())) else {
+                return Ok(vars);
+            };
+            self.bump();
+            while let Some(qualifier) = ["CONSTANT", "RETAIN", "NON_RETAIN", "PERSISTENT"]
+                .into_iter()
+                .find(|q| self.is_kw(q))
+            {
+                // the model has no retentive variables, reading them as plain ones
+                // would lose their values on a restart
+                if qualifier != "CONSTANT" {
+                    let token = self.peek().clone();
+                    return Err(
+                        self.error_at(&token, format!("{} variables are not supported", qualifier))
+                    );
+                }
+                self.bump();
+                if section == VarSection::Var {
+                    section = VarSection::Constant;
+                }
+            }
+            let make = match section {
+                VarSection::Input => Value::Input,
+                VarSection::Output => Value::Output,
+                VarSection::InOut => Value::InOut,
+                VarSection::Temp => Value::Temporary,
+                VarSection::External => Value::External,
+                VarSection::Global => Value::Global,
+                VarSection::Constant => Value::Constant,
+                VarSection::Var => Value::Local,
+            };
+            for (name, value) in self.declarations(&["END_VAR"])? {
+                vars.push(make(name, value));
+            }
+            self.end("END_VAR")?;
+        }
+    }
+
+    /// `a, b : INT := 1;` lines up to the closing keyword.
+    fn declarations(&mut self, close: &[&str]) -> Result<Vec<(String, TypedValue)>, Rust2PlcError> {
+        let mut decls = vec![];
+        while !close.iter().any(|k| self.is_kw(k)) {
+            let mut names = vec![self.ident()?];
+            while self.eat_sym(",") {
+                names.push(self.ident()?);
+            }
+            let at = self.peek().clone();
+            if self.eat_kw("AT") {
+                let Tok::Ident(address) = &self.peek().tok else {
+                    return Err(self.unexpected("a direct address"));
+                };
+                return Err(self.error_at(
+                    &at,
+                    format!(
+                        "located variables are not supported, found {} AT {}",
+                        names.join(", "),
+                        address
+                    ),
+                ));
+            }
+            self.expect_sym(":")?;
+            let mut value = self.type_spec()?;
+            if self.eat_sym(":=") {
+                value = self.initial(value)?;
+            }
+            self.expect_sym(";")?;
+            decls.extend(names.into_iter().map(|name| (name, value.clone())));
+        }
+        Ok(decls)
+    }
+
+    fn type_spec(&mut self) -> Result<TypedValue, Rust2PlcError> {
+        let token = self.peek().clone();
+        if self.eat_kw("ARRAY") {
+            self.expect_sym("[")?;
+            let mut ranges = vec![];
+            loop {
+                let from = self.bound()?;
+                self.expect_sym("..")?;
+                let to = self.bound()?;
+                if to < from {
+                    return Err(self.error_at(&token, format!("empty range {}..{}", from, to)));
+                }
+                let lower = i64::try_from(from).map_err(|_| {
+                    self.error_at(&token, format!("the array bound {} is out of range", from))
+                })?;
+                ranges.push((lower, (to - from + 1) as usize));
+                if !self.eat_sym(",") {
+                    break;
+                }
+            }
+            self.expect_sym("]")?;
+            self.expect_kw("OF")?;
+            let elem = self.type_spec()?;
+            return Ok(ranges.into_iter().rev().fold(elem, |elem, (lower, size)| {
+                TypedValue::new_array(elem, size).with_lower_bound(lower)
+            }));
+        }
+        if self.is_kw("POINTER") || self.is_kw("REFERENCE") {
+            return Err(self.error_at(&token, "pointers and references are not supported"));
+        }
+        let mut name = self.ident()?;
+        while self.is_sym(".") {
+            self.bump();
+            name = format!("{}.{}", name, self.ident()?);
+        }
+        let upper = name.to_uppercase();
+        if upper == "STRING" || upper == "WSTRING" {
+            let close = if self.eat_sym("[") {
+                Some("]")
+            } else if self.eat_sym("(") {
+                Some(")")
+            } else {
+                None
+            };
+            let len = match close {
+                Some(close) => {
+                    let len = self.bound()?;
+                    self.expect_sym(close)?;
+                    Some(len as usize)
+                }
+                None => None,
+            };
+            return Ok(if upper == "STRING" {
+                TypedValue::new_string(len)
+            } else {
+                TypedValue::new_wstring(len)
+            });
+        }
+        let short = name.rsplit('.').next().unwrap_or(&name);
+        if let Some(data_type) = self.types.get(short) {
+            return Ok(data_type.value().clone());
+        }
+        TypedValue::from_plc_type(&name)
+            .ok_or_else(|| self.error_at(&token, format!("unknown type {}", name)))
+    }
+
+    fn bound(&mut self) -> Result<i128, Rust2PlcError> {
+        let token = self.peek().clone();
+        int_value(&self.unary()?).ok_or_else(|| self.error_at(&token, "expected an integer"))
+    }
+
+    /// The initial value of a declaration, converted to its type.
+    fn initial(&mut self, ty: TypedValue) -> Result<TypedValue, Rust2PlcError> {
+        let token = self.peek().clone();
+        match ty {
+            TypedValue::Struct(name, mut fields) if self.eat_sym("(") => {
+                while !self.is_sym(")") {
+                    let field_token = self.peek().clone();
+                    let field = self.ident()?;
+                    self.expect_sym(":=")?;
+                    let Some(slot) = fields.iter_mut().find(|(f, _)| *f == field) else {
+                        return Err(
+                            self.error_at(&field_token, format!("{} has no field {}", name, field))
+                        );
+                    };
+                    slot.1 = self.initial(slot.1.clone())?;
+                    if !self.eat_sym(",") {
+                        break;
+                    }
+                }
+                self.expect_sym(")")?;
+                Ok(TypedValue::Struct(name, fields))
+            }
+            TypedValue::Array(mut values, elem, lower) if self.eat_sym("[") => {
+                let mut idx = 0;
+                while !self.is_sym("]") {
+                    let repeat = match self.peek().tok {
+                        Tok::Int(count) if self.peek_at(1).tok == Tok::Sym("(") => {
+                            self.bump();
+                            self.bump();
+                            Some(count)
+                        }
+                        _ => None,
+                    };
+                    let value = self.initial((*elem).clone())?;
+                    if repeat.is_some() {
+                        self.expect_sym(")")?;
+                    }
+                    for _ in 0..repeat.unwrap_or(1) {
+                        let Some(slot) = values.get_mut(idx) else {
+                            return Err(self.error_at(&token, "too many array elements"));
+                        };
+                        **slot = value.clone();
+                        idx += 1;
+                    }
+                    if !self.eat_sym(",") {
+                        break;
+                    }
+                }
+                self.expect_sym("]")?;
+                Ok(TypedValue::Array(values, elem, lower))
+            }
+            ty => {
+                let expr = self.expr()?;
+                convert(&ty, &expr).map_err(|msg| self.error_at(&token, msg))
+            }
+        }
+    }
+
+    /// Statements up to one of the closing keywords.
+    fn stmts(&mut self, close: &[&str]) -> Result<Vec<Stmt>, Rust2PlcError> {
+        let mut stmts = vec![];
+        while !self.at_end() && !close.iter().any(|k| self.is_kw(k)) {
+            if self.eat_sym(";") {
+                continue;
+            }
+            stmts.push(self.stmt()?);
+        }
+        Ok(stmts)
+    }
+
+    fn stmt(&mut self) -> Result<Stmt, Rust2PlcError> {
+        if self.eat_kw("IF") {
+            return self.if_stmt();
+        }
+        if self.eat_kw("CASE") {
+            return self.case_stmt();
+        }
+        if self.eat_kw("FOR") {
+            let var = self.ident()?;
+            self.expect_sym(":=")?;
+            let from = self.expr()?;
+            self.expect_kw("TO")?;
+            let to = self.expr()?;
+            let by = if self.eat_kw("BY") {
+                Some(self.expr()?)
+            } else {
+                None
+            };
+            self.expect_kw("DO")?;
+            let body = self.stmts(&["END_FOR"])?;
+            self.end("END_FOR")?;
+            return Ok(Stmt::For {
+                var,
+                from,
+                to,
+                by,
+                body,
+            });
+        }
+        if self.eat_kw("WHILE") {
+            let cond = self.expr()?;
+            self.expect_kw("DO")?;
+            let body = self.stmts(&["END_WHILE"])?;
+            self.end("END_WHILE")?;
+            return Ok(Stmt::While { cond, body });
+        }
+        if self.eat_kw("REPEAT") {
+            let body = self.stmts(&["UNTIL"])?;
+            self.expect_kw("UNTIL")?;
+            let until = self.expr()?;
+            self.end("END_REPEAT")?;
+            return Ok(Stmt::Repeat { body, until });
+        }
+        for (keyword, stmt) in [
+            ("RETURN", Stmt::Return),
+            ("EXIT", Stmt::Exit),
+            ("CONTINUE", Stmt::Continue),
+        ] {
+            if self.eat_kw(keyword) {
+                self.expect_sym(";")?;
+                return Ok(stmt);
+            }
+        }
+
+        if !matches!(&self.peek().tok, Tok::Ident(name) if !is_reserved(name)) {
+            return Err(self.unexpected("a statement"));
+        }
+        let target = self.postfix()?;
+        let stmt = if self.eat_sym(":=") {
+            Stmt::assign(target, self.expr()?)
+        } else if let Expr::Call(call) = target {
+            Stmt::Call(call)
+        } else {
+            return Err(self.unexpected("`:=`"));
+        };
+        self.expect_sym(";")?;
+        Ok(stmt)
+    }
+
+    fn if_stmt(&mut self) -> Result<Stmt, Rust2PlcError> {
+        let mut branches = vec![];
+        let mut otherwise = None;
+        loop {
+            let cond = self.expr()?;
+            self.expect_kw("THEN")?;
+            let body = self.stmts(&["ELSIF", "ELSE", "END_IF"])?;
+            branches.push(Branch { cond, body });
+            if self.eat_kw("ELSIF") {
+                continue;
+            }
+            if self.eat_kw("ELSE") {
+                otherwise = Some(self.stmts(&["END_IF"])?);
+            }
+            self.end("END_IF")?;
+            return Ok(Stmt::If {
+                branches,
+                otherwise,
+            });
+        }
+    }
+
+    fn case_stmt(&mut self) -> Result<Stmt, Rust2PlcError> {
+        let selector = self.expr()?;
+        self.expect_kw("OF")?;
+        let mut branches = vec![];
+        let mut otherwise = None;
+        while !self.is_kw("END_CASE") {
+            if self.eat_kw("ELSE") {
+                otherwise = Some(self.stmts(&["END_CASE"])?);
+                break;
+            }
+            let labels = self.case_labels()?;
+            let mut body = vec![];
+            while !self.at_end()
+                && !self.is_kw("END_CASE")
+                && !self.is_kw("ELSE")
+                && !self.at_case_labels()
+            {
+                if self.eat_sym(";") {
+                    continue;
+                }
+                body.push(self.stmt()?);
+            }
+            branches.push(CaseBranch { labels, body });
+        }
+        self.end("END_CASE")?;
+        Ok(Stmt::Case {
+            selector,
+            branches,
+            otherwise,
+        })
+    }
+
+    /// `1, 3..5, Mode#Idle:`
+    fn case_labels(&mut self) -> Result<Vec<CaseLabel>, Rust2PlcError> {
+        let mut labels = vec![];
+        loop {
+            let from = self.expr()?;
+            if self.eat_sym("..") {
+                labels.push(CaseLabel::Range(from, self.expr()?));
+            } else {
+                labels.push(CaseLabel::Value(from));
+            }
+            if !self.eat_sym(",") {
+                break;
+            }
+        }
+        self.expect_sym(":")?;
+        Ok(labels)
+    }
+
+    /// Whether the next tokens start a new branch of a `CASE`.
+    fn at_case_labels(&mut self) -> bool {
+        let pos = self.pos;
+        let found = self.case_labels().is_ok();
+        self.pos = pos;
+        found
+    }
+
+    fn expr(&mut self) -> Result<Expr, Rust2PlcError> {
+        self.binary(0)
+    }
+
+    fn binary(&mut self, min_prec: u8) -> Result<Expr, Rust2PlcError> {
+        let mut lhs = self.unary()?;
+        while let Some(op) = self.binary_op().filter(|op| op.precedence() >= min_prec) {
+            self.bump();
+            let rhs = self.binary(op.precedence() + 1)?;
+            lhs = Expr::binary(op, lhs, rhs);
+        }
+        Ok(lhs)
+    }
+
+    fn binary_op(&self) -> Option<BinaryOp> {
+        let op = match &self.peek().tok {
+            Tok::Ident(name) => match name.to_uppercase().as_str() {
+                "OR" => BinaryOp::Or,
+                "XOR" => BinaryOp::Xor,
+                "AND" => BinaryOp::And,
+                "MOD" => BinaryOp::Mod,
+                _ => return None,
+            },
+            Tok::Sym(sym) => match *sym {
+                "&" => BinaryOp::And,
+                "=" => BinaryOp::Eq,
+                "<>" => BinaryOp::Ne,
+                "<" => BinaryOp::Lt,
+                "<=" => BinaryOp::Le,
+                ">" => BinaryOp::Gt,
+                ">=" => BinaryOp::Ge,
+                "+" => BinaryOp::Add,
+                "-" => BinaryOp::Sub,
+                "*" => BinaryOp::Mul,
+                "/" => BinaryOp::Div,
+                "**" => BinaryOp::Pow,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    fn unary(&mut self) -> Result<Expr, Rust2PlcError> {
+        if self.eat_sym("-") {
+            return Ok(match self.unary()? {
+                Expr::Literal(Literal::Int(value)) => Expr::int(-value),
+                Expr::Literal(Literal::Real(value)) => Expr::Literal(Literal::Real(-value)),
+                operand => Expr::unary(UnaryOp::Neg, operand),
+            });
+        }
+        if self.eat_sym("+") {
+            return self.unary();
+        }
+        if self.eat_kw("NOT") {
+            return Ok(Expr::unary(UnaryOp::Not, self.unary()?));
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Expr, Rust2PlcError> {
+        let mut expr = self.primary()?;
+        loop {
+            if self.eat_sym(".") {
+                expr = Expr::Field(Box::new(expr), self.ident()?);
+            } else if self.eat_sym("[") {
+                let mut indices = vec![self.expr()?];
+                while self.eat_sym(",") {
+                    indices.push(self.expr()?);
+                }
+                self.expect_sym("]")?;
+                expr = Expr::Index(Box::new(expr), indices);
+            } else if self.is_sym("(") {
+                let Some(name) = path(&expr) else {
+                    return Err(self.unexpected("an operator"));
+                };
+                self.bump();
+                expr = Expr::Call(Call {
+                    name,
+                    args: self.args()?,
+                });
+            } else if self.eat_sym("^") {
+                return Err(self.error_at(
+                    &self.tokens[self.pos - 1],
+                    "pointers and references are not supported",
+                ));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    /// The arguments of a call, after the opening parenthesis.
+    fn args(&mut self) -> Result<Vec<Arg>, Rust2PlcError> {
+        let mut args = vec![];
+        while !self.is_sym(")") {
+            let named = matches!(self.peek().tok, Tok::Ident(_))
+                && matches!(self.peek_at(1).tok, Tok::Sym(":=" | "=>"));
+            let arg = if named {
+                let name = self.ident()?;
+                if self.eat_sym(":=") {
+                    Arg::Input(name, self.expr()?)
+                } else {
+                    self.bump();
+                    Arg::Output(name, self.expr()?)
+                }
+            } else {
+                Arg::Value(self.expr()?)
+            };
+            args.push(arg);
+            if !self.eat_sym(",") {
+                break;
+            }
+        }
+        self.expect_sym(")")?;
+        Ok(args)
+    }
+
+    fn primary(&mut self) -> Result<Expr, Rust2PlcError> {
+        let token = self.peek().clone();
+        let expr = match token.tok {
+            Tok::Int(value) => Expr::int(value),
+            Tok::Real(value) => Expr::Literal(Literal::Real(value)),
+            Tok::Str(value) => Expr::Literal(Literal::Str(value)),
+            Tok::Typed(ty, value) => Expr::Literal(Literal::Typed { ty, value }),
+            Tok::Sym("(") => {
+                self.bump();
+                let inner = self.expr()?;
+                self.expect_sym(")")?;
+                return Ok(Expr::Paren(Box::new(inner)));
+            }
+            Tok::Ident(name) if name.eq_ignore_ascii_case("TRUE") => Expr::bool(true),
+            Tok::Ident(name) if name.eq_ignore_ascii_case("FALSE") => Expr::bool(false),
+            Tok::Ident(name) if !is_reserved(&name) => Expr::Var(name),
+            _ => return Err(self.unexpected("an expression")),
+        };
+        self.bump();
+        Ok(expr)
+    }
+}
+
+/// The `&'static` symbol matching the text.
+fn sym_ref(sym: &str) -> &'static str {
+    SYMBOLS.iter().find(|s| **s == sym).copied().unwrap_or("")
+}
+
+/// Keywords that can not name a variable.
+fn is_reserved(name: &str) -> bool {
+    const RESERVED: [&str; 40] = [
+        "AND",
+        "OR",
+        "XOR",
+        "NOT",
+        "MOD",
+        "IF",
+        "THEN",
+        "ELSIF",
+        "ELSE",
+        "END_IF",
+        "CASE",
+        "OF",
+        "END_CASE",
+        "FOR",
+        "TO",
+        "BY",
+        "DO",
+        "END_FOR",
+        "WHILE",
+        "END_WHILE",
+        "REPEAT",
+        "UNTIL",
+        "END_REPEAT",
+        "RETURN",
+        "EXIT",
+        "CONTINUE",
+        "VAR",
+        "VAR_INPUT",
+        "VAR_OUTPUT",
+        "VAR_IN_OUT",
+        "VAR_TEMP",
+        "END_VAR",
+        "FUNCTION",
+        "END_FUNCTION",
+        "FUNCTION_BLOCK",
+        "END_FUNCTION_BLOCK",
+        "PROGRAM",
+        "END_PROGRAM",
+        "TRUE",
+        "FALSE",
+    ];
+    RESERVED.iter().any(|k| k.eq_ignore_ascii_case(name))
+}
+
+/// The dotted name of a called function or function block instance.
+fn path(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Var(name) => Some(name.clone()),
+        Expr::Field(base, member) => path(base).map(|base| format!("{}.{}", base, member)),
+        _ => None,
+    }
+}
+
+/// The description, namespace and version written in the comments above a declaration,
+/// in the layout the POUs are rendered with.
+fn header(comments: &[String]) -> (Option<String>, Option<String>, Option<String>) {
+    let (mut description, mut namespace, mut version) = (None, None, None);
+    for comment in comments {
+        if comment.starts_with("namespace:") || comment.starts_with("version:") {
+            for part in comment.split(',') {
+                match part.split_once(':') {
+                    Some((key, value)) if key.trim() == "namespace" => {
+                        namespace = Some(value.trim().to_string())
+                    }
+                    Some((key, value)) if key.trim() == "version" => {
+                        version = Some(value.trim().to_string())
+                    }
+                    _ => {}
+                }
+            }
+        } else if description.is_none() {
+            description = Some(comment.clone());
+        }
+    }
+    (description, namespace, version)
+}
+
+pub(crate) fn int_value(expr: &Expr) -> Option<i128> {
+    match expr {
+        Expr::Literal(Literal::Int(value)) => Some(*value),
+        Expr::Unary(UnaryOp::Neg, operand) => int_value(operand).map(|v| -v),
+        Expr::Paren(inner) => int_value(inner),
+        _ => None,
+    }
+}
+
+fn real_value(expr: &Expr) -> Option<f64> {
+    match expr {
+        Expr::Literal(Literal::Real(value)) => Some(*value),
+        Expr::Unary(UnaryOp::Neg, operand) => real_value(operand).map(|v| -v),
+        Expr::Paren(inner) => real_value(inner),
+        _ => int_value(expr).map(|v| v as f64),
+    }
+}
+
+/// Converts a constant expression to a value of the type.
+fn convert(ty: &TypedValue, expr: &Expr) -> Result<TypedValue, String> {
+    let invalid = || {
+        format!(
+            "{} is not a valid {} value",
+            Printer::default().expr(expr),
+            ty.to_plc_type()
+        )
+    };
+    // `INT#5`, `BOOL#1`, `REAL#1.5`: the prefix is checked against the type
+    if let Expr::Literal(Literal::Typed { ty: prefix, value }) = expr {
+        let elementary = TypedValue::from_plc_type(prefix).filter(|t| {
+            !is_time(t)
+                && !matches!(
+                    t,
+                    TypedValue::UserDefined(_, _)
+                        | TypedValue::Struct(_, _)
+                        | TypedValue::Enum(..)
+                        | TypedValue::Array(..)
+                )
+        });
+        if let Some(prefix) = elementary {
+            if prefix.to_plc_type() != ty.to_plc_type() {
+                return Err(invalid());
+            }
+            let inner = parse_expr(value).map_err(|_| invalid())?;
+            return convert(ty, &inner);
+        }
+    }
+    let typed = |types: &[&str]| match expr {
+        Expr::Literal(Literal::Typed { ty, value })
+            if types.iter().any(|t| t.eq_ignore_ascii_case(ty)) =>
+        {
+            Some(value.as_str())
+        }
+        _ => None,
+    };
+    let text = match expr {
+        Expr::Literal(Literal::Str(text)) => Some(text.as_str()),
+        _ => None,
+    };
+    macro_rules! int {
+        ($variant:ident, $ty:ty) => {
+            int_value(expr)
+                .and_then(|v| <$ty>::try_from(v).ok())
+                .map(TypedValue::$variant)
+                .ok_or_else(invalid)
+        };
+    }
+    match ty {
+        TypedValue::Bool(_) => match expr {
+            Expr::Literal(Literal::Bool(value)) => Ok(TypedValue::Bool(*value)),
+            _ => match int_value(expr) {
+                Some(0) => Ok(TypedValue::Bool(false)),
+                Some(1) => Ok(TypedValue::Bool(true)),
+                _ => Err(invalid()),
+            },
+        },
+        TypedValue::SInt(_) => int!(SInt, i8),
+        TypedValue::Int(_) => int!(Int, i16),
+        TypedValue::DInt(_) => int!(DInt, i32),
+        TypedValue::LInt(_) => int!(LInt, i64),
+        TypedValue::USInt(_) => int!(USInt, u8),
+        TypedValue::UInt(_) => int!(UInt, u16),
+        TypedValue::UDInt(_) => int!(UDInt, u32),
+        TypedValue::ULInt(_) => int!(ULInt, u64),
+        TypedValue::Byte(_) => int!(Byte, u8),
+        TypedValue::Word(_) => int!(Word, u16),
+        TypedValue::DWord(_) => int!(DWord, u32),
+        TypedValue::LWord(_) => int!(LWord, u64),
+        TypedValue::Real(_) => real_value(expr)
+            .map(|v| TypedValue::Real(v as f32))
+            .ok_or_else(invalid),
+        TypedValue::LReal(_) => real_value(expr).map(TypedValue::LReal).ok_or_else(invalid),
+        TypedValue::Time(_) => typed(&["T", "TIME", "LT", "LTIME"])
+            .and_then(duration)
+            .map(TypedValue::Time)
+            .ok_or_else(invalid),
+        TypedValue::Date(_) => typed(&["D", "DATE", "LD", "LDATE"])
+            .and_then(|v| NaiveDate::parse_from_str(v, "%Y-%m-%d").ok())
+            .map(TypedValue::Date)
+            .ok_or_else(invalid),
+        TypedValue::TimeOfDay(_) => typed(&["TOD", "TIME_OF_DAY", "LTOD"])
+            .and_then(|v| NaiveTime::parse_from_str(v, "%H:%M:%S%.f").ok())
+            .map(TypedValue::TimeOfDay)
+            .ok_or_else(invalid),
+        TypedValue::DateTime(_) => typed(&["DT", "DATE_AND_TIME", "LDT"])
+            .and_then(|v| NaiveDateTime::parse_from_str(v, "%Y-%m-%d-%H:%M:%S%.f").ok())
+            .map(TypedValue::DateTime)
+            .ok_or_else(invalid),
+        TypedValue::Char(_) | TypedValue::WChar(_) => {
+            let mut chars = text.ok_or_else(invalid)?.chars();
+            match (chars.next(), chars.next(), ty) {
+                (Some(c), None, TypedValue::Char(_)) => Ok(TypedValue::Char(c)),
+                (Some(c), None, _) => Ok(TypedValue::WChar(c)),
+                _ => Err(invalid()),
+            }
+        }
+        TypedValue::String(_, len) | TypedValue::WString(_, len) => {
+            let text = text.ok_or_else(invalid)?;
+            if len.is_some_and(|len| text.chars().count() > len) {
+                return Err(format!("'{}' is longer than {}", text, ty.to_plc_type()));
+            }
+            Ok(match ty {
+                TypedValue::String(_, _) => TypedValue::String(text.to_string(), *len),
+                _ => TypedValue::WString(text.to_string(), *len),
+            })
+        }
+        TypedValue::Enum(name, variants, _, initial) => {
+            let variant = match expr {
+                Expr::Var(variant) => Some(variant),
+                Expr::Literal(Literal::Typed { ty, value }) if ty == name => Some(value),
+                _ => None,
+            };
+            match variant {
+                Some(variant) if variants.contains(variant) => Ok(TypedValue::Enum(
+                    name.clone(),
+                    variants.clone(),
+                    variant.clone(),
+                    initial.clone(),
+                )),
+                _ => Err(invalid()),
+            }
+        }
+        TypedValue::UserDefined(name, _) => {
+            Err(format!("the initial value of {} can not be checked", name))
+        }
+        TypedValue::Struct(_, _) | TypedValue::Array(..) => Err(invalid()),
+    }
+}
+
+fn is_time(ty: &TypedValue) -> bool {
+    matches!(
+        ty,
+        TypedValue::Time(_)
+            | TypedValue::Date(_)
+            | TypedValue::TimeOfDay(_)
+            | TypedValue::DateTime(_)
+    )
+}
+
+/// The value of a duration literal, e.g. `1h30m` or `1.5s`.
+pub(crate) fn duration(text: &str) -> Option<Duration> {
+    let text = text.replace('_', "").to_lowercase();
+    let text = text.strip_prefix('+').unwrap_or(&text);
+    let mut nanos = 0f64;
+    let mut rest = text;
+    if rest.is_empty() {
+        return None;
+    }
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let number: f64 = rest[..number_len].parse().ok()?;
+        rest = &rest[number_len..];
+        let unit_len = rest
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(rest.len());
+        let unit = match &rest[..unit_len] {
+            "d" => 86_400e9,
+            "h" => 3_600e9,
+            "m" => 60e9,
+            "s" => 1e9,
+            "ms" => 1e6,
+            "us" => 1e3,
+            "ns" => 1.0,
+            _ => return None,
+        };
+        rest = &rest[unit_len..];
+        nanos += number * unit;
+    }
+    Some(Duration::from_nanos(nanos.round() as u64))
+}
@@ -0,0 +1,157 @@
+use crate::sfc::Sfc;
+use crate::st;
+use crate::st::ast::{Printer, Stmt, VarBlock};
+use crate::var::Value;
+
+/// An ST `PROGRAM` POU, the top level unit bound to a task in a [`crate::st::config::Configuration`].
+///
+/// Produced by `#[plc_program]` or assembled by hand from [`Program::new`]
+/// and the `with_*` methods. A program can run a [`Sfc`] chart in place of its body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    name: String,
+    inputs: Vec<Value>,
+    outputs: Vec<Value>,
+    vars: Vec<Value>,
+    body: Vec<Stmt>,
+    chart: Option<Sfc>,
+
+    description: Option<String>,
+    namespace: Option<String>,
+    version: Option<String>,
+    block: Option<String>,
+}
+
+impl Program {
+    pub fn new(name: impl Into<String>) -> Self {
+        Program {
+            name: name.into(),
+            inputs: vec![],
+            outputs: vec![],
+            vars: vec![],
+            body: vec![],
+            chart: None,
+            description: None,
+            namespace: None,
+            version: None,
+            block: None,
+        }
+    }
+
+    pub fn with_input(mut self, input: Value) -> Self {
+        self.inputs.push(input);
+        self
+    }
+
+    pub fn with_output(mut self, output: Value) -> Self {
+        self.outputs.push(output);
+        self
+    }
+
+    /// Adds an internal variable, a `Value::Local` keeps its value between scans
+    /// while a `Value::Temporary` lives for one scan only.
+    pub fn with_var(mut self, var: Value) -> Self {
+        self.vars.push(var);
+        self
+    }
+
+    pub fn with_body(mut self, body: Vec<Stmt>) -> Self {
+        self.body = body;
+        self
+    }
+
+    /// Replaces the body with a Sequential Function Chart.
+    pub fn with_chart(mut self, chart: Sfc) -> Self {
+        self.chart = Some(chart);
+        self
+    }
+
+    pub fn with_description(mut self, description: Option<String>) -> Self {
+        self.description = description;
+        self
+    }
+
+    pub fn with_namespace(mut self, namespace: Option<String>) -> Self {
+        self.namespace = namespace;
+        self
+    }
+
+    pub fn with_version(mut self, version: Option<String>) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// The S7 block the POU is compiled to in STL, e.g. `FC10`, see [`crate::awl`].
+    pub fn with_block(mut self, block: Option<String>) -> Self {
+        self.block = block;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn inputs(&self) -> &[Value] {
+        &self.inputs
+    }
+
+    pub fn outputs(&self) -> &[Value] {
+        &self.outputs
+    }
+
+    pub fn vars(&self) -> &[Value] {
+        &self.vars
+    }
+
+    pub fn body(&self) -> &[Stmt] {
+        &self.body
+    }
+
+    pub fn chart(&self) -> Option<&Sfc> {
+        self.chart.as_ref()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    pub fn block(&self) -> Option<&str> {
+        self.block.as_deref()
+    }
+
+    /// Renders the program as an ST `PROGRAM ... END_PROGRAM` block.
+    pub fn to_st(&self) -> String {
+        self.to_st_with(&Printer::default())
+    }
+
+    /// Renders the program in the style of the printer.
+    pub fn to_st_with(&self, printer: &Printer) -> String {
+        let mut st = st::header(self.description(), self.namespace(), self.version());
+        st.push_str(&format!("{} {}\n", printer.keyword("PROGRAM"), self.name));
+        st.push_str(&printer.var_blocks(
+            &VarBlock::group(self.inputs.iter().chain(&self.outputs).chain(&self.vars)),
+            0,
+        ));
+        match &self.chart {
+            Some(chart) => st.push_str(&chart.to_st_with(printer, 1)),
+            None => st.push_str(&printer.stmts(&self.body, 1)),
+        }
+        st.push_str(&format!("{}\n", printer.keyword("END_PROGRAM")));
+        st
+    }
+}
+
+/// The declaration part of a program, implemented by `#[plc_program]` on a struct.
+///
+/// `#[plc_program]` on the impl block completes it with the body of the cycle method.
+pub trait PlcProgram {
+    fn plc_declaration() -> Program;
+}
@@ -0,0 +1,1656 @@
+//! Translation of ST POUs into Rust source, the way back for migrating legacy code.
+//!
+//! Functions become `#[plc_fn]` functions, function blocks and programs a struct with
+//! `#[plc_fb]`/`#[plc_program]` and a `cycle` method, data types `#[derive(PlcType)]`
+//! structs and enums. Names are converted to the Rust conventions, `FillLevel` becomes
+//! `fill_level`. Whatever has no Rust counterpart the macros understand is written
+//! as close as possible and reported in the warnings, code that cannot be written
+//! at all, like a date literal in an expression, fails the translation.
+//!
+//! ```
+//! use rust2plc::st::rust;
+//!
+//! let source = rust::translate(
+//!     "FUNCTION Twice : INT\nVAR_INPUT\n    X : INT;\nEND_VAR\n    Twice := X * 2;\nEND_FUNCTION\n",
+//! )
+//! .unwrap();
+//! assert_eq!(
+//!     source.code(),
+//!     "#[plc_fn(st)]\npub fn twice(x: i16) -> i16 {\n    x * 2\n}\n"
+//! );
+//! assert!(source.warnings().is_empty());
+//! ```
+
+use std::collections::HashMap;
+
+use crate::error::Rust2PlcError;
+use crate::registry::RegistryItem;
+use crate::st::ast::{Arg, BinaryOp, Call, CaseLabel, Expr, Literal, Stmt, UnaryOp};
+use crate::st::data_type::DataType;
+use crate::st::function::Function;
+use crate::st::function_block::FunctionBlock;
+use crate::st::parser;
+use crate::st::program::Program;
+use crate::types::TypedValue;
+use crate::var::Value;
+
+// Rust operator precedence, the higher the tighter the binding.
+const R_IF: u8 = 0;
+const R_OR: u8 = 1;
+const R_AND: u8 = 2;
+const R_CMP: u8 = 3;
+const R_BIT_OR: u8 = 4;
+const R_XOR: u8 = 5;
+const R_BIT_AND: u8 = 6;
+const R_ADD: u8 = 8;
+const R_MUL: u8 = 9;
+const R_AS: u8 = 10;
+const R_UNARY: u8 = 11;
+const R_POSTFIX: u8 = 12;
+const R_ATOM: u8 = 13;
+
+const RUST_KEYWORDS: [&str; 52] = [
+    "as", "break", "const", "continue", "crate", "else", "enum", "extern", "false", "fn", "for",
+    "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+    "self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where",
+    "while", "async", "await", "dyn", "abstract", "become", "box", "do", "final", "macro",
+    "override", "priv", "typeof", "unsized", "virtual", "yield", "try", "gen", "union",
+];
+
+/// Rust source translated from ST.
+#[derive(Debug, Clone)]
+pub struct RustSource {
+    code: String,
+    warnings: Vec<String>,
+}
+
+impl RustSource {
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// The constructs that could not be expressed, one message per finding.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+}
+
+/// Parses an ST source and translates its POUs and data types into Rust.
+pub fn translate(source: &str) -> Result<RustSource, Rust2PlcError> {
+    let items = parser::parse(source)?;
+    let mut translator = RustTranslator::new(&items);
+    let mut code = vec![];
+    for item in items.iter() {
+        code.extend(translator.item(item)?);
+    }
+    Ok(RustSource {
+        code: code.join("\n"),
+        warnings: translator.warnings,
+    })
+}
+
+/// How a variable is reached from the Rust code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Access {
+    /// A field of the function block, `self.name`.
+    Field,
+    /// A `&mut` parameter, `*name`.
+    Deref,
+    Local,
+}
+
+#[derive(Debug, Clone)]
+struct Var {
+    rust: String,
+    ty: TypedValue,
+    access: Access,
+}
+
+/// The variables visible in the POU being translated, keyed by their upper case ST name.
+#[derive(Debug, Default)]
+struct Scope {
+    pou: String,
+    vars: HashMap<String, Var>,
+    /// The ST name of a function and the Rust variable holding its result.
+    result: Option<(String, String)>,
+    loop_vars: Vec<String>,
+    /// The enclosing loops with the statements a `CONTINUE` runs first, so that it
+    /// still reaches the step or the `UNTIL` check after the body.
+    loops: Vec<Option<Vec<String>>>,
+}
+
+/// Translates ST items into Rust, knowing the enumerations and functions
+/// declared next to them.
+#[derive(Debug, Default)]
+pub struct RustTranslator {
+    /// Upper case variant name to the enumeration name and the variant.
+    variants: HashMap<String, (String, String)>,
+    enums: HashMap<String, String>,
+    /// Upper case function name to its inputs and return value.
+    signatures: HashMap<String, (Vec<(String, TypedValue)>, TypedValue)>,
+    scope: Scope,
+    warnings: Vec<String>,
+    /// The constructs of the current POU without any Rust counterpart.
+    unsupported: Vec<String>,
+}
+
+impl RustTranslator {
+    pub fn new(items: &[RegistryItem]) -> Self {
+        let mut translator = RustTranslator::default();
+        for item in items {
+            match item {
+                RegistryItem::StType(data_type) => {
+                    if let TypedValue::Enum(name, variants, ..) = data_type.value() {
+                        translator.enums.insert(name.to_uppercase(), name.clone());
+                        for variant in variants {
+                            translator
+                                .variants
+                                .insert(variant.to_uppercase(), (name.clone(), variant.clone()));
+                        }
+                    }
+                }
+                RegistryItem::StFn(function) => {
+                    let inputs = function
+                        .inputs()
+                        .iter()
+                        .filter_map(|v| Some((v.name()?.to_uppercase(), v.typed_value().clone())))
+                        .collect();
+                    translator.signatures.insert(
+                        function.name().to_uppercase(),
+                        (inputs, function.return_value().clone()),
+                    );
+                }
+                _ => {}
+            }
+        }
+        translator
+    }
+
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// The Rust source of an item, configurations have no counterpart.
+    pub fn item(&mut self, item: &RegistryItem) -> Result<Option<String>, Rust2PlcError> {
+        match item {
+            RegistryItem::StFn(function) => self.function(function).map(Some),
+            RegistryItem::StFb(fb) => self.function_block(fb).map(Some),
+            RegistryItem::StProgram(program) => self.program(program).map(Some),
+            RegistryItem::StType(data_type) => Ok(Some(self.data_type(data_type))),
+            RegistryItem::StConfig(config) => {
+                self.scope.pou = config.name().to_string();
+                self.warn("configurations are not translated, declare them with `Configuration`");
+                Ok(None)
+            }
+        }
+    }
+
+    fn warn(&mut self, msg: impl AsRef<str>) {
+        let msg = format!("{}: {}", self.scope.pou, msg.as_ref());
+        if !self.warnings.contains(&msg) {
+            self.warnings.push(msg);
+        }
+    }
+
+    /// Records a construct that cannot be written in Rust, the POU fails in [`Self::checked`].
+    fn unsupported(&mut self, msg: impl AsRef<str>) {
+        let msg = msg.as_ref().to_string();
+        if !self.unsupported.contains(&msg) {
+            self.unsupported.push(msg);
+        }
+    }
+
+    /// The source of the POU, or the error listing what could not be translated.
+    fn checked(&mut self, rs: String) -> Result<String, Rust2PlcError> {
+        if self.unsupported.is_empty() {
+            return Ok(rs);
+        }
+        let unsupported = std::mem::take(&mut self.unsupported);
+        Err(Rust2PlcError::Other(format!(
+            "{}: {}",
+            self.scope.pou,
+            unsupported.join(", ")
+        )))
+    }
+
+    pub fn data_type(&mut self, data_type: &DataType) -> String {
+        self.scope = Scope {
+            pou: data_type.name().to_string(),
+            ..Scope::default()
+        };
+        let mut rs = String::new();
+        match data_type.value() {
+            TypedValue::Enum(name, variants, initial, _) => {
+                rs.push_str("#[derive(PlcType, Debug, Clone, Copy, Default, PartialEq)]\n");
+                rs.push_str(&plc_attr(
+                    data_type.description(),
+                    data_type.namespace(),
+                    data_type.version(),
+                ));
+                if let Some(base) = data_type.base() {
+                    rs.push_str(&format!("#[repr({})]\n", base.to_rust_type()));
+                }
+                rs.push_str(&format!("pub enum {} {{\n", name));
+                for (idx, variant) in variants.iter().enumerate() {
+                    if variant == initial {
+                        rs.push_str("    #[default]\n");
+                    }
+                    match data_type.values().get(idx).copied().flatten() {
+                        Some(value) => {
+                            rs.push_str(&format!("    {} = {},\n", camel(variant), value))
+                        }
+                        None => rs.push_str(&format!("    {},\n", camel(variant))),
+                    }
+                }
+                rs.push_str("}\n");
+            }
+            TypedValue::Struct(name, fields) => {
+                rs.push_str("#[derive(PlcType, Debug, Clone, Default, PartialEq)]\n");
+                rs.push_str(&plc_attr(
+                    data_type.description(),
+                    data_type.namespace(),
+                    data_type.version(),
+                ));
+                rs.push_str(&format!("pub struct {} {{\n", name));
+                for (field, value) in fields {
+                    if *value != value.default_value() {
+                        if let Some(init) = self.value(value) {
+                            rs.push_str(&format!("    #[plc(init = {})]\n", init));
+                        }
+                    }
+                    rs.push_str(&format!(
+                        "    pub {}: {},\n",
+                        snake(field),
+                        value.to_rust_type()
+                    ));
+                }
+                rs.push_str("}\n");
+            }
+            _ => {}
+        }
+        rs
+    }
+
+    pub fn function(&mut self, function: &Function) -> Result<String, Rust2PlcError> {
+        self.scope = Scope {
+            pou: function.name().to_string(),
+            ..Scope::default()
+        };
+        let mut params = vec![];
+        for var in function.inputs().iter().chain(function.outputs()) {
+            let (Some(name), ty) = (var.name(), var.typed_value()) else {
+                continue;
+            };
+            let rust = snake(name);
+            match var {
+                Value::Input(_, _) => {
+                    if *ty != ty.default_value() {
+                        self.warn(format!("the default value of input {} is dropped", name));
+                    }
+                    params.push(format!("{}: {}", rust, ty.to_rust_type()));
+                    self.declare(name, &rust, ty, Access::Local);
+                }
+                _ => {
+                    if let Value::Output(_, _) = var {
+                        self.warn(format!(
+                            "output {} becomes a `&mut` parameter, an in-out variable",
+                            name
+                        ));
+                    }
+                    params.push(format!("{}: &mut {}", rust, ty.to_rust_type()));
+                    self.declare(name, &rust, ty, Access::Deref);
+                }
+            }
+        }
+        let locals: Vec<Value> = function.locals().to_vec();
+        for var in locals.iter() {
+            if let Some(name) = var.name() {
+                self.declare(name, &snake(name), var.typed_value(), Access::Local);
+            }
+        }
+
+        let upper = function.name().to_uppercase();
+        let inputs = function
+            .inputs()
+            .iter()
+            .filter_map(|v| Some((v.name()?.to_uppercase(), v.typed_value().clone())))
+            .collect();
+        self.signatures
+            .insert(upper.clone(), (inputs, function.return_value().clone()));
+        let body = function.body();
+        let tail = match body.split_last() {
+            Some((
+                Stmt::Assign {
+                    target: Expr::Var(target),
+                    value,
+                },
+                rest,
+            )) if target.to_uppercase() == upper
+                && !rest.iter().any(|s| mentions(s, &upper))
+                && !mentions_expr(value, &upper)
+                && !body.iter().any(returns) =>
+            {
+                Some((rest, value))
+            }
+            _ => None,
+        };
+
+        let ret = function.return_value();
+        let mut rs = plc_macro(
+            "plc_fn",
+            function.description(),
+            function.namespace(),
+            function.version(),
+        );
+        rs.push_str(&format!(
+            "pub fn {}({}) -> {} {{\n",
+            snake(function.name()),
+            params.join(", "),
+            ret.to_rust_type()
+        ));
+        rs.push_str(&self.locals(&locals, body, 1));
+        match tail {
+            Some((rest, value)) => {
+                rs.push_str(&self.stmts(rest, 1));
+                let (value, _) = self.expr(value, Some(ret));
+                rs.push_str(&format!("    {}\n", value));
+            }
+            None => {
+                let result = if self.scope.vars.contains_key("RESULT") {
+                    "ret"
+                } else {
+                    "result"
+                };
+                self.scope.result = Some((upper, result.to_string()));
+                rs.push_str(&format!(
+                    "    let mut {}: {} = {};\n",
+                    result,
+                    ret.to_rust_type(),
+                    self.init(&ret.default_value())
+                ));
+                rs.push_str(&self.stmts(body, 1));
+                rs.push_str(&format!("    {}\n", result));
+            }
+        }
+        rs.push_str("}\n");
+        self.checked(rs)
+    }
+
+    pub fn function_block(&mut self, fb: &FunctionBlock) -> Result<String, Rust2PlcError> {
+        let vars: Vec<Value> = fb
+            .inputs()
+            .iter()
+            .chain(fb.outputs())
+            .chain(fb.vars())
+            .cloned()
+            .collect();
+        let rs = self.stateful(
+            "plc_fb",
+            fb.name(),
+            &vars,
+            fb.body(),
+            (fb.description(), fb.namespace(), fb.version()),
+        );
+        self.checked(rs)
+    }
+
+    pub fn program(&mut self, program: &Program) -> Result<String, Rust2PlcError> {
+        let vars: Vec<Value> = program
+            .inputs()
+            .iter()
+            .chain(program.outputs())
+            .chain(program.vars())
+            .cloned()
+            .collect();
+        let rs = self.stateful(
+            "plc_program",
+            program.name(),
+            &vars,
+            program.body(),
+            (
+                program.description(),
+                program.namespace(),
+                program.version(),
+            ),
+        );
+        self.checked(rs)
+    }
+
+    /// A function block or a program, a struct keeping the variables and its `cycle` method.
+    fn stateful(
+        &mut self,
+        attr: &str,
+        name: &str,
+        vars: &[Value],
+        body: &[Stmt],
+        (description, namespace, version): (Option<&str>, Option<&str>, Option<&str>),
+    ) -> String {
+        self.scope = Scope {
+            pou: name.to_string(),
+            ..Scope::default()
+        };
+        let mut fields = vec![];
+        let mut temps = vec![];
+        for var in vars {
+            let Some(var_name) = var.name() else {
+                continue;
+            };
+            let rust = snake(var_name);
+            let ty = var.typed_value();
+            match var {
+                Value::Temporary(_, _) => {
+                    self.declare(var_name, &rust, ty, Access::Local);
+                    temps.push(var.clone());
+                    continue;
+                }
+                Value::InOut(_, _) => self.warn(format!(
+                    "in-out variable {} becomes an input, function blocks keep no references",
+                    var_name
+                )),
+                Value::Global(_, _) | Value::External(_, _) => self.warn(format!(
+                    "global variable {} becomes a field of {}",
+                    var_name, name
+                )),
+                _ => {}
+            }
+            self.declare(var_name, &rust, ty, Access::Field);
+            fields.push((var, rust));
+        }
+
+        let defaults = fields.iter().all(|(var, _)| {
+            let ty = var.typed_value();
+            *ty == ty.default_value()
+        });
+        let mut rs = plc_macro(attr, description, namespace, version);
+        if defaults {
+            rs.push_str("#[derive(Default)]\n");
+        }
+        rs.push_str(&format!("pub struct {} {{\n", name));
+        for (var, rust) in fields.iter() {
+            let ty = var.typed_value().to_rust_type();
+            match var {
+                Value::Input(_, _) | Value::InOut(_, _) => {
+                    rs.push_str(&format!("    #[input]\n    pub {}: {},\n", rust, ty))
+                }
+                Value::Output(_, _) => {
+                    rs.push_str(&format!("    #[output]\n    pub {}: {},\n", rust, ty))
+                }
+                _ => rs.push_str(&format!("    {}: {},\n", rust, ty)),
+            }
+        }
+        rs.push_str("}\n\n");
+
+        if !defaults {
+            rs.push_str(&format!(
+                "impl Default for {} {{\n    fn default() -> Self {{\n        {} {{\n",
+                name, name
+            ));
+            for (var, rust) in fields.iter() {
+                rs.push_str(&format!(
+                    "            {}: {},\n",
+                    rust,
+                    self.init(var.typed_value())
+                ));
+            }
+            rs.push_str("        }\n    }\n}\n\n");
+        }
+
+        rs.push_str(&format!(
+            "#[{}]\nimpl {} {{\n    pub fn cycle(&mut self) {{\n",
+            attr, name
+        ));
+        rs.push_str(&self.locals(&temps, body, 2));
+        rs.push_str(&self.stmts(body, 2));
+        rs.push_str("    }\n}\n");
+        rs
+    }
+
+    fn declare(&mut self, name: &str, rust: &str, ty: &TypedValue, access: Access) {
+        self.scope.vars.insert(
+            name.to_uppercase(),
+            Var {
+                rust: rust.to_string(),
+                ty: ty.clone(),
+                access,
+            },
+        );
+    }
+
+    /// `let` bindings of the local variables, loop counters are bound by their loops.
+    fn locals(&mut self, locals: &[Value], body: &[Stmt], level: usize) -> String {
+        let pad = "    ".repeat(level);
+        let mut rs = String::new();
+        for var in locals {
+            let Some(name) = var.name() else {
+                continue;
+            };
+            if is_loop_var(body, &name.to_uppercase()) {
+                continue;
+            }
+            let ty = var.typed_value();
+            let binding = if matches!(var, Value::Constant(_, _)) {
+                "let"
+            } else {
+                "let mut"
+            };
+            rs.push_str(&format!(
+                "{}{} {}: {} = {};\n",
+                pad,
+                binding,
+                snake(name),
+                ty.to_rust_type(),
+                self.init(ty)
+            ));
+        }
+        rs
+    }
+
+    /// The Rust expression of an initial value.
+    fn init(&mut self, value: &TypedValue) -> String {
+        self.value(value)
+            .unwrap_or_else(|| "Default::default()".to_string())
+    }
+
+    /// A Rust literal of the value, `None` where `Default::default()` has to do.
+    fn value(&mut self, value: &TypedValue) -> Option<String> {
+        let default = *value == value.default_value();
+        let text = match value {
+            TypedValue::Bool(v) => v.to_string(),
+            TypedValue::SInt(v) => v.to_string(),
+            TypedValue::Int(v) => v.to_string(),
+            TypedValue::DInt(v) => v.to_string(),
+            TypedValue::LInt(v) => v.to_string(),
+            TypedValue::USInt(v) | TypedValue::Byte(v) => v.to_string(),
+            TypedValue::UInt(v) | TypedValue::Word(v) => v.to_string(),
+            TypedValue::UDInt(v) | TypedValue::DWord(v) => v.to_string(),
+            TypedValue::ULInt(v) | TypedValue::LWord(v) => v.to_string(),
+            TypedValue::Real(v) => real(*v as f64),
+            TypedValue::LReal(v) => real(*v),
+            TypedValue::Time(d) => format!("std::time::Duration::from_millis({})", d.as_millis()),
+            TypedValue::Char(c) | TypedValue::WChar(c) => format!("{:?}", c),
+            TypedValue::String(_, _) | TypedValue::WString(_, _) if default => {
+                "String::new()".to_string()
+            }
+            TypedValue::String(s, _) | TypedValue::WString(s, _) => format!("{:?}.to_string()", s),
+            TypedValue::Enum(name, _, variant, _) => format!("{}::{}", name, camel(variant)),
+            TypedValue::Date(_) | TypedValue::TimeOfDay(_) | TypedValue::DateTime(_) => {
+                if !default {
+                    self.warn(format!(
+                        "the date and time value {} is not translated",
+                        value.to_plc_literal().unwrap_or_default()
+                    ));
+                }
+                return None;
+            }
+            TypedValue::Struct(_, _) | TypedValue::Array(..) | TypedValue::UserDefined(_, _)
+                if default =>
+            {
+                return None
+            }
+            TypedValue::Struct(name, fields) => {
+                let fields: Option<Vec<String>> = fields
+                    .iter()
+                    .map(|(f, v)| Some(format!("{}: {}", snake(f), self.value(v)?)))
+                    .collect();
+                format!("{} {{ {} }}", name, fields?.join(", "))
+            }
+            TypedValue::Array(values, ..) => {
+                let values: Option<Vec<String>> = values.iter().map(|v| self.value(v)).collect();
+                format!("[{}]", values?.join(", "))
+            }
+            TypedValue::UserDefined(_, _) => return None,
+        };
+        Some(text)
+    }
+
+    fn stmts(&mut self, stmts: &[Stmt], level: usize) -> String {
+        stmts.iter().map(|s| self.stmt(s, level)).collect()
+    }
+
+    fn stmt(&mut self, stmt: &Stmt, level: usize) -> String {
+        let pad = "    ".repeat(level);
+        match stmt {
+            Stmt::Assign { target, value } => {
+                let ty = self.type_of(target);
+                let (lhs, _) = self.expr(target, None);
+                if let Expr::Binary(op, left, right) = value {
+                    let compound = match op {
+                        BinaryOp::Add => Some("+="),
+                        BinaryOp::Sub => Some("-="),
+                        BinaryOp::Mul => Some("*="),
+                        BinaryOp::Div => Some("/="),
+                        BinaryOp::Mod => Some("%="),
+                        _ => None,
+                    };
+                    if let Some(compound) = compound.filter(|_| strip(left) == strip(target)) {
+                        let (rhs, _) = self.expr(right, ty.as_ref());
+                        return format!("{}{} {} {};\n", pad, lhs, compound, rhs);
+                    }
+                }
+                let (rhs, _) = self.expr(value, ty.as_ref());
+                format!("{}{} = {};\n", pad, lhs, rhs)
+            }
+            Stmt::Call(call) => self.call_stmt(call, level),
+            Stmt::If {
+                branches,
+                otherwise,
+            } => {
+                let mut rs = String::new();
+                for (idx, branch) in branches.iter().enumerate() {
+                    let (cond, _) = self.expr(&branch.cond, Some(&TypedValue::new_bool()));
+                    if idx == 0 {
+                        rs.push_str(&format!("{}if {} {{\n", pad, cond));
+                    } else {
+                        rs.push_str(&format!("{}}} else if {} {{\n", pad, cond));
+                    }
+                    rs.push_str(&self.stmts(&branch.body, level + 1));
+                }
+                if let Some(otherwise) = otherwise {
+                    rs.push_str(&format!("{}}} else {{\n", pad));
+                    rs.push_str(&self.stmts(otherwise, level + 1));
+                }
+                rs.push_str(&format!("{}}}\n", pad));
+                rs
+            }
+            Stmt::Case {
+                selector,
+                branches,
+                otherwise,
+            } => {
+                let ty = self.type_of(selector);
+                let (selector, _) = self.expr(selector, None);
+                let mut rs = format!("{}match {} {{\n", pad, selector);
+                let mut arms = vec![];
+                for branch in branches {
+                    let labels: Vec<String> = branch
+                        .labels
+                        .iter()
+                        .map(|label| match label {
+                            CaseLabel::Value(value) => self.expr(value, ty.as_ref()).0,
+                            CaseLabel::Range(from, to) => format!(
+                                "{}..={}",
+                                self.expr(from, ty.as_ref()).0,
+                                self.expr(to, ty.as_ref()).0
+                            ),
+                        })
+                        .collect();
+                    rs.push_str(&format!("{}    {} => {{\n", pad, labels.join(" | ")));
+                    arms.push(labels);
+                    rs.push_str(&self.stmts(&branch.body, level + 2));
+                    rs.push_str(&format!("{}    }}\n", pad));
+                }
+                // a match covering every variant of an enumeration needs no catch-all
+                let exhaustive = match &ty {
+                    Some(TypedValue::Enum(name, variants, ..)) => variants.iter().all(|v| {
+                        let variant = format!("{}::{}", name, camel(v));
+                        arms.iter().any(|labels| labels.contains(&variant))
+                    }),
+                    _ => false,
+                };
+                match otherwise {
+                    Some(otherwise) => {
+                        rs.push_str(&format!("{}    _ => {{\n", pad));
+                        rs.push_str(&self.stmts(otherwise, level + 2));
+                        rs.push_str(&format!("{}    }}\n", pad));
+                    }
+                    None if exhaustive => {}
+                    None => rs.push_str(&format!("{}    _ => {{}}\n", pad)),
+                }
+                rs.push_str(&format!("{}}}\n", pad));
+                rs
+            }
+            Stmt::For {
+                var,
+                from,
+                to,
+                by,
+                body,
+            } => {
+                let ty = self
+                    .scope
+                    .vars
+                    .get(&var.to_uppercase())
+                    .map(|v| v.ty.clone());
+                let (from, _) = self.expr(from, ty.as_ref());
+                let (to, to_prec) = self.expr(to, ty.as_ref());
+                let step = match by {
+                    None => Ok(1),
+                    Some(by) => parser::int_value(by).ok_or(by),
+                };
+                // a counter changed in the body cannot be bound by a Rust `for`
+                let assigned = assigns(body, &var.to_uppercase());
+                let range = match step {
+                    Ok(1) if !assigned => format!("{}..={}", from, to),
+                    Ok(step) if step > 0 && !assigned => {
+                        format!("({}..={}).step_by({})", from, to, step)
+                    }
+                    Ok(step) if step != 0 => {
+                        let bound = (to.as_str(), to_prec);
+                        return self.counted(var, ty.as_ref(), (&from, bound, step), body, level);
+                    }
+                    by => {
+                        // a step only known at run time, its sign picks the comparison
+                        let by = match by {
+                            Ok(step) => step.to_string(),
+                            Err(by) => self.expr(by, ty.as_ref()).0,
+                        };
+                        return self.stepped(var, (&from, &to, &by), body, level);
+                    }
+                };
+                self.scope.loop_vars.push(var.to_uppercase());
+                let body = self.looped(body, level, None);
+                self.scope.loop_vars.pop();
+                format!(
+                    "{}for {} in {} {{\n{}{}}}\n",
+                    pad,
+                    snake(var),
+                    range,
+                    body,
+                    pad
+                )
+            }
+            Stmt::While { cond, body } => {
+                let (cond, _) = self.expr(cond, Some(&TypedValue::new_bool()));
+                format!(
+                    "{}while {} {{\n{}{}}}\n",
+                    pad,
+                    cond,
+                    self.looped(body, level, None),
+                    pad
+                )
+            }
+            Stmt::Repeat { body, until } => {
+                let (until, _) = self.expr(until, Some(&TypedValue::new_bool()));
+                let check = vec![
+                    format!("if {} {{", until),
+                    "    break;".to_string(),
+                    "}".to_string(),
+                ];
+                let body = self.looped(body, level, Some(check.clone()));
+                format!(
+                    "{}loop {{\n{}{}{}}}\n",
+                    pad,
+                    body,
+                    lines(&check, level + 1),
+                    pad
+                )
+            }
+            Stmt::Return => match &self.scope.result {
+                Some((_, result)) => format!("{}return {};\n", pad, result),
+                None => format!("{}return;\n", pad),
+            },
+            Stmt::Exit => format!("{}break;\n", pad),
+            Stmt::Continue => match self.scope.loops.last() {
+                Some(Some(tail)) => format!("{}{}continue;\n", lines(tail, level), pad),
+                _ => format!("{}continue;\n", pad),
+            },
+        }
+    }
+
+    /// The body of a loop, `tail` are the statements a `CONTINUE` runs before
+    /// the next iteration, e.g. the step of a counter.
+    fn looped(&mut self, body: &[Stmt], level: usize, tail: Option<Vec<String>>) -> String {
+        self.scope.loops.push(tail);
+        let rs = self.stmts(body, level + 1);
+        self.scope.loops.pop();
+        rs
+    }
+
+    /// A `FOR` loop counting down or changing its counter in the body, written as a
+    /// `while` loop over a mutable counter.
+    fn counted(
+        &mut self,
+        var: &str,
+        ty: Option<&TypedValue>,
+        (from, (to, to_prec), step): (&str, (&str, u8), i128),
+        body: &[Stmt],
+        level: usize,
+    ) -> String {
+        let pad = "    ".repeat(level);
+        let name = snake(var);
+        let (cmp, op) = if step > 0 { ("<=", "+=") } else { (">=", "-=") };
+        let step = step.unsigned_abs();
+        let mut next = vec![];
+        // an unsigned counter stops before it would go below the end, e.g. below 0
+        if op == "-=" && ty.is_some_and(is_unsigned) {
+            let end = wrap(to.to_string(), to_prec, R_ADD);
+            next.push(format!("if {} < {} + {} {{", name, end, step));
+            next.push("    break;".to_string());
+            next.push("}".to_string());
+        }
+        next.push(format!("{} {} {};", name, op, step));
+        let typed = ty
+            .map(|ty| format!(": {}", ty.to_rust_type()))
+            .unwrap_or_default();
+        let mut rs = format!("{}{{\n", pad);
+        rs.push_str(&format!(
+            "{}    let mut {}{} = {};\n",
+            pad, name, typed, from
+        ));
+        rs.push_str(&format!("{}    while {} {} {} {{\n", pad, name, cmp, to));
+        self.scope.loop_vars.push(var.to_uppercase());
+        rs.push_str(&self.looped(body, level + 1, Some(next.clone())));
+        self.scope.loop_vars.pop();
+        rs.push_str(&lines(&next, level + 2));
+        rs.push_str(&format!("{}    }}\n{}}}\n", pad, pad));
+        rs
+    }
+
+    /// A `FOR` loop with a step only known at run time, written as a `while` loop
+    /// counting up or down by the sign of the step.
+    fn stepped(
+        &mut self,
+        var: &str,
+        (from, to, by): (&str, &str, &str),
+        body: &[Stmt],
+        level: usize,
+    ) -> String {
+        let pad = "    ".repeat(level);
+        let name = snake(var);
+        let mut rs = format!("{}{{\n", pad);
+        rs.push_str(&format!("{}    let {}_step = {};\n", pad, name, by));
+        rs.push_str(&format!("{}    let {}_end = {};\n", pad, name, to));
+        rs.push_str(&format!("{}    let mut {} = {};\n", pad, name, from));
+        let next = vec![format!("{} += {}_step;", name, name)];
+        self.scope.loop_vars.push(var.to_uppercase());
+        let body = self.looped(body, level + 1, Some(next.clone()));
+        self.scope.loop_vars.pop();
+        rs.push_str(&format!(
+            "{pad}    while ({n}_step > 0 && {n} <= {n}_end) || ({n}_step < 0 && {n} >= {n}_end) {{\n{body}{next}{pad}    }}\n",
+            pad = pad,
+            n = name,
+            body = body,
+            next = lines(&next, level + 2)
+        ));
+        rs.push_str(&format!("{}}}\n", pad));
+        rs
+    }
+
+    /// A call statement, a function block instance runs its `cycle` with the inputs set.
+    fn call_stmt(&mut self, call: &Call, level: usize) -> String {
+        let pad = "    ".repeat(level);
+        let head = call
+            .name
+            .split('.')
+            .next()
+            .unwrap_or_default()
+            .to_uppercase();
+        if !self.scope.vars.contains_key(&head) {
+            let (call, _) = self.call(call);
+            return format!("{}{};\n", pad, call);
+        }
+        self.warn(format!(
+            "the call of instance {} is written as field assignments and `cycle()`, \
+             which the PLC macros do not translate",
+            call.name
+        ));
+        let (instance, _) = self.path(&call.name);
+        let mut rs = String::new();
+        let mut outputs = vec![];
+        for arg in call.args.iter() {
+            match arg {
+                Arg::Input(name, value) => {
+                    let (value, _) = self.expr(value, None);
+                    rs.push_str(&format!(
+                        "{}{}.{} = {};\n",
+                        pad,
+                        instance,
+                        snake(name),
+                        value
+                    ));
+                }
+                Arg::Output(name, target) => {
+                    let (target, _) = self.expr(target, None);
+                    outputs.push(format!(
+                        "{}{} = {}.{};\n",
+                        pad,
+                        target,
+                        instance,
+                        snake(name)
+                    ));
+                }
+                Arg::Value(value) => {
+                    let (value, _) = self.expr(value, None);
+                    self.warn(format!(
+                        "the positional argument {} of instance {} is dropped",
+                        value, call.name
+                    ));
+                }
+            }
+        }
+        rs.push_str(&format!("{}{}.cycle();\n", pad, instance));
+        rs.extend(outputs);
+        rs
+    }
+
+    /// The Rust name of a dotted ST path, e.g. `self.timer.q` for `timer.Q`.
+    fn path(&mut self, path: &str) -> (String, u8) {
+        let mut parts = path.split('.');
+        let head = parts.next().unwrap_or_default();
+        let mut expr = Expr::var(head);
+        for part in parts {
+            expr = Expr::Field(Box::new(expr), part.to_string());
+        }
+        self.expr(&expr, None)
+    }
+
+    /// The ST type of an expression as far as the declarations tell it.
+    fn type_of(&self, expr: &Expr) -> Option<TypedValue> {
+        match expr {
+            Expr::Literal(Literal::Bool(_)) => Some(TypedValue::new_bool()),
+            Expr::Literal(Literal::Real(_)) => Some(TypedValue::new_lreal()),
+            Expr::Literal(Literal::Typed { ty, .. }) => {
+                literal_type(ty).filter(|t| !matches!(t, TypedValue::UserDefined(_, _)))
+            }
+            Expr::Literal(_) => None,
+            Expr::Var(name) => {
+                let upper = name.to_uppercase();
+                if self.scope.loop_vars.contains(&upper) {
+                    return self.scope.vars.get(&upper).map(|v| v.ty.clone());
+                }
+                match &self.scope.result {
+                    Some((result, _)) if *result == upper => {
+                        self.signatures.get(&upper).map(|(_, ret)| ret.clone())
+                    }
+                    _ => self.scope.vars.get(&upper).map(|v| v.ty.clone()),
+                }
+            }
+            Expr::Field(base, member) => match self.type_of(base) {
+                Some(TypedValue::Struct(_, fields)) => fields
+                    .iter()
+                    .find(|(f, _)| f.eq_ignore_ascii_case(member))
+                    .map(|(_, v)| v.clone()),
+                _ => None,
+            },
+            Expr::Index(base, _) => match self.type_of(base) {
+                Some(TypedValue::Array(_, elem, _)) => Some(*elem),
+                _ => None,
+            },
+            Expr::Unary(_, operand) | Expr::Paren(operand) => self.type_of(operand),
+            Expr::Binary(op, lhs, rhs) => match op {
+                BinaryOp::Eq
+                | BinaryOp::Ne
+                | BinaryOp::Lt
+                | BinaryOp::Le
+                | BinaryOp::Gt
+                | BinaryOp::Ge => Some(TypedValue::new_bool()),
+                _ => self.type_of(lhs).or_else(|| self.type_of(rhs)),
+            },
+            Expr::Call(call) => {
+                let upper = call.name.to_uppercase();
+                if let Some((_, ret)) = self.signatures.get(&upper) {
+                    return Some(ret.clone());
+                }
+                if let Some((_, dst)) = conversion(&upper) {
+                    return Some(dst);
+                }
+                match (upper.as_str(), call.args.first()) {
+                    (_, Some(Arg::Value(first))) => self.type_of(first),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// The Rust text of an expression and its precedence. The hint is the type
+    /// the value ends up in, it decides how numeric literals are written.
+    fn expr(&mut self, expr: &Expr, hint: Option<&TypedValue>) -> (String, u8) {
+        match expr {
+            Expr::Literal(literal) => self.literal(literal, hint),
+            Expr::Var(name) => (self.var(name), R_ATOM),
+            Expr::Field(base, member) => {
+                let (base, prec) = self.expr(base, None);
+                (
+                    format!("{}.{}", wrap(base, prec, R_POSTFIX), snake(member)),
+                    R_POSTFIX,
+                )
+            }
+            Expr::Index(base, indices) => {
+                let mut ty = self.type_of(base);
+                let (mut text, prec) = self.expr(base, None);
+                text = wrap(text, prec, R_POSTFIX);
+                for index in indices {
+                    // Rust arrays start at 0, the ST ones at their lower bound.
+                    let lower = ty.as_ref().map_or(0, |ty| ty.array_lower_bound()) as i128;
+                    let index = match index {
+                        Expr::Literal(Literal::Int(i)) if *i >= lower => (i - lower).to_string(),
+                        index => {
+                            let (index, prec) = self.expr(index, None);
+                            let index = match lower {
+                                0 => wrap(index, prec, R_AS),
+                                lower if lower > 0 => {
+                                    format!("({} - {})", wrap(index, prec, R_ADD), lower)
+                                }
+                                lower => format!("({} + {})", wrap(index, prec, R_ADD), -lower),
+                            };
+                            format!("{} as usize", index)
+                        }
+                    };
+                    text = format!("{}[{}]", text, index);
+                    ty = match ty {
+                        Some(TypedValue::Array(_, elem, _)) => Some(*elem),
+                        _ => None,
+                    };
+                }
+                (text, R_POSTFIX)
+            }
+            Expr::Unary(UnaryOp::Neg, operand) => {
+                let (operand, prec) = self.expr(operand, hint);
+                (format!("-{}", wrap(operand, prec, R_UNARY)), R_UNARY)
+            }
+            Expr::Unary(UnaryOp::Not, operand) => {
+                let (operand, prec) = self.expr(operand, hint);
+                (format!("!{}", wrap(operand, prec, R_UNARY)), R_UNARY)
+            }
+            Expr::Paren(inner) => self.expr(inner, hint),
+            Expr::Binary(op, lhs, rhs) => self.binary(*op, lhs, rhs, hint),
+            Expr::Call(call) => self.call(call),
+        }
+    }
+
+    fn binary(
+        &mut self,
+        op: BinaryOp,
+        lhs: &Expr,
+        rhs: &Expr,
+        hint: Option<&TypedValue>,
+    ) -> (String, u8) {
+        let operand_hint = match op {
+            BinaryOp::Eq
+            | BinaryOp::Ne
+            | BinaryOp::Lt
+            | BinaryOp::Le
+            | BinaryOp::Gt
+            | BinaryOp::Ge => self.type_of(lhs).or_else(|| self.type_of(rhs)),
+            _ => hint
+                .cloned()
+                .or_else(|| self.type_of(lhs))
+                .or_else(|| self.type_of(rhs)),
+        };
+        let (l, lp) = self.expr(lhs, operand_hint.as_ref());
+        let (r, rp) = self.expr(rhs, operand_hint.as_ref());
+        if op == BinaryOp::Pow {
+            let method = match &operand_hint {
+                Some(TypedValue::Real(_) | TypedValue::LReal(_)) | None => "powf",
+                Some(_) => "pow",
+            };
+            return (
+                format!("{}.{}({})", wrap(l, lp, R_POSTFIX), method, r),
+                R_POSTFIX,
+            );
+        }
+        let logical = !matches!(
+            operand_hint,
+            Some(
+                TypedValue::SInt(_)
+                    | TypedValue::Int(_)
+                    | TypedValue::DInt(_)
+                    | TypedValue::LInt(_)
+                    | TypedValue::USInt(_)
+                    | TypedValue::UInt(_)
+                    | TypedValue::UDInt(_)
+                    | TypedValue::ULInt(_)
+                    | TypedValue::Byte(_)
+                    | TypedValue::Word(_)
+                    | TypedValue::DWord(_)
+                    | TypedValue::LWord(_)
+            )
+        );
+        let (symbol, prec) = match op {
+            BinaryOp::Or if logical => ("||", R_OR),
+            BinaryOp::Or => ("|", R_BIT_OR),
+            BinaryOp::And if logical => ("&&", R_AND),
+            BinaryOp::And => ("&", R_BIT_AND),
+            BinaryOp::Xor => ("^", R_XOR),
+            BinaryOp::Eq => ("==", R_CMP),
+            BinaryOp::Ne => ("!=", R_CMP),
+            BinaryOp::Lt => ("<", R_CMP),
+            BinaryOp::Le => ("<=", R_CMP),
+            BinaryOp::Gt => (">", R_CMP),
+            BinaryOp::Ge => (">=", R_CMP),
+            BinaryOp::Add => ("+", R_ADD),
+            BinaryOp::Sub => ("-", R_ADD),
+            BinaryOp::Mul => ("*", R_MUL),
+            BinaryOp::Div => ("/", R_MUL),
+            BinaryOp::Mod => ("%", R_MUL),
+            BinaryOp::Pow => unreachable!("handled above"),
+        };
+        // comparisons do not chain in Rust
+        let lhs_min = if prec == R_CMP { prec + 1 } else { prec };
+        (
+            format!(
+                "{} {} {}",
+                wrap(l, lp, lhs_min),
+                symbol,
+                wrap(r, rp, prec + 1)
+            ),
+            prec,
+        )
+    }
+
+    fn var(&mut self, name: &str) -> String {
+        let upper = name.to_uppercase();
+        if self.scope.loop_vars.contains(&upper) {
+            return snake(name);
+        }
+        if let Some(var) = self.scope.vars.get(&upper) {
+            return match var.access {
+                Access::Field => format!("self.{}", var.rust),
+                Access::Deref => format!("*{}", var.rust),
+                Access::Local => var.rust.clone(),
+            };
+        }
+        if let Some((result, rust)) = &self.scope.result {
+            if *result == upper {
+                return rust.clone();
+            }
+        }
+        if let Some((ty, variant)) = self.variants.get(&upper) {
+            return format!("{}::{}", ty, camel(variant));
+        }
+        snake(name)
+    }
+
+    fn literal(&mut self, literal: &Literal, hint: Option<&TypedValue>) -> (String, u8) {
+        let real_hint = matches!(hint, Some(TypedValue::Real(_) | TypedValue::LReal(_)));
+        let prec = |text: &str| {
+            if text.starts_with('-') {
+                R_UNARY
+            } else {
+                R_ATOM
+            }
+        };
+        match literal {
+            Literal::Bool(value) => (value.to_string(), R_ATOM),
+            Literal::Int(value) if real_hint => {
+                let text = real(*value as f64);
+                let p = prec(&text);
+                (text, p)
+            }
+            Literal::Int(value) => (value.to_string(), prec(&value.to_string())),
+            Literal::Real(value) => {
+                let text = real(*value);
+                let p = prec(&text);
+                (text, p)
+            }
+            Literal::Str(value) => match hint {
+                Some(TypedValue::Char(_) | TypedValue::WChar(_)) if value.chars().count() == 1 => (
+                    format!("{:?}", value.chars().next().unwrap_or_default()),
+                    R_ATOM,
+                ),
+                _ => (format!("{:?}", value), R_ATOM),
+            },
+            Literal::Typed { ty, value } => self.typed(ty, value),
+        }
+    }
+
+    /// A typed literal, `T#5s`, `INT#3` or `Mode#Running`.
+    fn typed(&mut self, ty: &str, value: &str) -> (String, u8) {
+        let upper = ty.to_uppercase();
+        if let Some(name) = self.enums.get(&upper) {
+            return (format!("{}::{}", name, camel(value)), R_ATOM);
+        }
+        let Some(tv) = literal_type(ty) else {
+            return (format!("{}::{}", ty, camel(value)), R_ATOM);
+        };
+        let source = format!("{}#{}", ty, value);
+        match parser::parse_value(&tv, &source) {
+            Ok(TypedValue::UserDefined(_, _)) | Err(_) => {
+                if let TypedValue::UserDefined(_, _) = tv {
+                    return (format!("{}::{}", ty, camel(value)), R_ATOM);
+                }
+                self.unsupported(format!("the literal {} has no Rust counterpart", source));
+                (source, R_ATOM)
+            }
+            Ok(parsed) => match parsed {
+                TypedValue::Date(_) | TypedValue::TimeOfDay(_) | TypedValue::DateTime(_) => {
+                    self.unsupported(format!("the literal {} has no Rust counterpart", source));
+                    (source, R_ATOM)
+                }
+                TypedValue::Time(_) | TypedValue::Bool(_) | TypedValue::Enum(..) => {
+                    (self.init(&parsed), R_ATOM)
+                }
+                parsed => {
+                    let text = format!("{}_{}", self.init(&parsed), parsed.to_rust_type());
+                    let prec = if text.starts_with('-') {
+                        R_UNARY
+                    } else {
+                        R_ATOM
+                    };
+                    (text, prec)
+                }
+            },
+        }
+    }
+
+    fn call(&mut self, call: &Call) -> (String, u8) {
+        let upper = call.name.to_uppercase();
+        let mut values = vec![];
+        let mut named = vec![];
+        for arg in call.args.iter() {
+            match arg {
+                Arg::Value(value) => values.push(value.clone()),
+                Arg::Input(name, value) => named.push((name.to_uppercase(), value.clone())),
+                Arg::Output(name, _) => self.warn(format!(
+                    "the output {} of the call of {} is dropped",
+                    name, call.name
+                )),
+            }
+        }
+
+        if let Some((inputs, _)) = self.signatures.get(&upper).cloned() {
+            let mut args = vec![];
+            for (idx, (input, ty)) in inputs.iter().enumerate() {
+                let value = match named.iter().find(|(n, _)| n == input) {
+                    Some((_, value)) => Some(value.clone()),
+                    None => values.get(idx).cloned(),
+                };
+                match value {
+                    Some(value) => args.push(self.expr(&value, Some(ty)).0),
+                    None => {
+                        self.warn(format!(
+                            "the call of {} misses input {}, its default is used",
+                            call.name, input
+                        ));
+                        args.push(self.init(&ty.default_value()));
+                    }
+                }
+            }
+            return (
+                format!("{}({})", snake(&call.name), args.join(", ")),
+                R_POSTFIX,
+            );
+        }
+
+        if !named.is_empty() {
+            self.warn(format!(
+                "the named arguments of {} are passed in the written order",
+                call.name
+            ));
+            values.extend(named.into_iter().map(|(_, v)| v));
+        }
+        let hint = values.first().and_then(|v| self.type_of(v));
+        let args: Vec<(String, u8)> = values.iter().map(|v| self.expr(v, hint.as_ref())).collect();
+        let method = |name: &str, args: &[(String, u8)]| -> (String, u8) {
+            let (receiver, prec) = args[0].clone();
+            let rest: Vec<String> = args[1..].iter().map(|(a, _)| a.clone()).collect();
+            (
+                format!(
+                    "{}.{}({})",
+                    wrap(receiver, prec, R_POSTFIX),
+                    name,
+                    rest.join(", ")
+                ),
+                R_POSTFIX,
+            )
+        };
+        match (upper.as_str(), args.len()) {
+            (
+                "ABS" | "SQRT" | "LN" | "EXP" | "SIN" | "COS" | "TAN" | "ASIN" | "ACOS" | "ATAN",
+                1,
+            ) => return method(&upper.to_lowercase(), &args),
+            ("LOG", 1) => return method("log10", &args),
+            ("MIN" | "MAX", n) if n >= 2 => {
+                let name = upper.to_lowercase();
+                let mut acc = args[0].clone();
+                for arg in args[1..].iter() {
+                    acc = method(&name, &[acc, arg.clone()]);
+                }
+                return acc;
+            }
+            ("EXPT", 2) => return method("powf", &args),
+            ("LIMIT", 3) => {
+                return method(
+                    "clamp",
+                    &[args[1].clone(), args[0].clone(), args[2].clone()],
+                )
+            }
+            ("MOVE", 1) => return args[0].clone(),
+            ("SEL", 3) => {
+                return (
+                    format!(
+                        "if {} {{ {} }} else {{ {} }}",
+                        args[0].0, args[2].0, args[1].0
+                    ),
+                    R_IF,
+                )
+            }
+            _ => {}
+        }
+        if let (Some((src, dst)), [(arg, prec)]) = (conversion(&upper), args.as_slice()) {
+            let integer = !matches!(
+                dst,
+                TypedValue::Real(_) | TypedValue::LReal(_) | TypedValue::Bool(_)
+            );
+            return match (dst, src) {
+                (TypedValue::Bool(_), _) => (
+                    format!("{} != 0", wrap(arg.clone(), *prec, R_CMP + 1)),
+                    R_CMP,
+                ),
+                (dst, Some(TypedValue::Real(_) | TypedValue::LReal(_))) if integer => (
+                    format!(
+                        "{}.round() as {}",
+                        wrap(arg.clone(), *prec, R_POSTFIX),
+                        dst.to_rust_type()
+                    ),
+                    R_AS,
+                ),
+                (dst, _) => (
+                    format!(
+                        "{} as {}",
+                        wrap(arg.clone(), *prec, R_AS),
+                        dst.to_rust_type()
+                    ),
+                    R_AS,
+                ),
+            };
+        }
+        let args: Vec<String> = args.into_iter().map(|(a, _)| a).collect();
+        (
+            format!("{}({})", snake(&call.name), args.join(", ")),
+            R_POSTFIX,
+        )
+    }
+}
+
+/// The type of a typed literal prefix, `T` is a `TIME` and `INT` an `INT`.
+fn literal_type(prefix: &str) -> Option<TypedValue> {
+    match prefix.to_uppercase().as_str() {
+        "T" | "LT" | "LTIME" => Some(TypedValue::new_time()),
+        "D" | "LD" | "LDATE" => Some(TypedValue::new_date()),
+        "TOD" | "LTOD" => Some(TypedValue::new_time_of_day()),
+        "DT" | "LDT" => Some(TypedValue::new_date_time()),
+        _ => TypedValue::from_plc_type(prefix),
+    }
+}
+
+/// The source and destination of a conversion function, `INT_TO_REAL` or `TO_REAL`.
+fn conversion(name: &str) -> Option<(Option<TypedValue>, TypedValue)> {
+    let elementary = |ty: &str| {
+        TypedValue::from_plc_type(ty).filter(|t| {
+            !matches!(
+                t,
+                TypedValue::UserDefined(_, _)
+                    | TypedValue::Struct(_, _)
+                    | TypedValue::Enum(..)
+                    | TypedValue::Array(..)
+                    | TypedValue::Time(_)
+                    | TypedValue::Date(_)
+                    | TypedValue::TimeOfDay(_)
+                    | TypedValue::DateTime(_)
+                    | TypedValue::String(_, _)
+                    | TypedValue::WString(_, _)
+            )
+        })
+    };
+    if let Some(dst) = name.strip_prefix("TO_") {
+        return elementary(dst).map(|dst| (None, dst));
+    }
+    let (src, dst) = name.split_once("_TO_")?;
+    Some((Some(elementary(src)?), elementary(dst)?))
+}
+
+fn wrap(text: String, prec: u8, min: u8) -> String {
+    if prec < min {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+fn strip(expr: &Expr) -> &Expr {
+    match expr {
+        Expr::Paren(inner) => strip(inner),
+        expr => expr,
+    }
+}
+
+/// Statements kept as unindented lines, e.g. the step of a loop, indented to `level`.
+fn lines(stmts: &[String], level: usize) -> String {
+    let pad = "    ".repeat(level);
+    stmts.iter().map(|s| format!("{}{}\n", pad, s)).collect()
+}
+
+fn is_unsigned(ty: &TypedValue) -> bool {
+    matches!(
+        ty,
+        TypedValue::USInt(_)
+            | TypedValue::UInt(_)
+            | TypedValue::UDInt(_)
+            | TypedValue::ULInt(_)
+            | TypedValue::Byte(_)
+            | TypedValue::Word(_)
+            | TypedValue::DWord(_)
+            | TypedValue::LWord(_)
+    )
+}
+
+/// A Rust float literal, always with a fractional part.
+fn real(value: f64) -> String {
+    let text = format!("{:?}", value);
+    if text.contains(['.', 'e', 'E']) || !value.is_finite() {
+        text
+    } else {
+        format!("{}.0", text)
+    }
+}
+
+/// `#[plc_fn(st, description = "...")]` and the like.
+fn plc_macro(
+    attr: &str,
+    description: Option<&str>,
+    namespace: Option<&str>,
+    version: Option<&str>,
+) -> String {
+    let mut args = vec!["st".to_string()];
+    args.extend(meta(description, namespace, version));
+    format!("#[{}({})]\n", attr, args.join(", "))
+}
+
+/// `#[plc(description = "...")]` of a data type, empty without any metadata.
+fn plc_attr(description: Option<&str>, namespace: Option<&str>, version: Option<&str>) -> String {
+    let meta = meta(description, namespace, version);
+    if meta.is_empty() {
+        String::new()
+    } else {
+        format!("#[plc({})]\n", meta.join(", "))
+    }
+}
+
+fn meta(description: Option<&str>, namespace: Option<&str>, version: Option<&str>) -> Vec<String> {
+    [
+        ("description", description),
+        ("namespace", namespace),
+        ("version", version),
+    ]
+    .into_iter()
+    .filter_map(|(key, value)| Some(format!("{} = {:?}", key, value?)))
+    .collect()
+}
+
+/// `fillLevel` and `FillLevel` become `fill_level`, keywords get a trailing underscore.
+pub fn snake(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::new();
+    for (idx, c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() {
+            let prev = idx.checked_sub(1).map(|i| chars[i]);
+            let next = chars.get(idx + 1);
+            let boundary = match prev {
+                Some('_') => false,
+                Some(p) if p.is_ascii_lowercase() || p.is_ascii_digit() => true,
+                Some(p) if p.is_ascii_uppercase() => next.is_some_and(|n| n.is_ascii_lowercase()),
+                _ => false,
+            };
+            if boundary {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+        } else {
+            out.push(*c);
+        }
+    }
+    if RUST_KEYWORDS.contains(&out.as_str()) {
+        out.push('_');
+    }
+    out
+}
+
+/// `RUNNING` and `motor_on` become `Running` and `MotorOn`, `Running` stays as it is.
+pub fn camel(name: &str) -> String {
+    if !name.contains('_') && name.chars().any(|c| c.is_ascii_lowercase()) {
+        let mut chars = name.chars();
+        return chars
+            .next()
+            .map(|c| c.to_ascii_uppercase().to_string() + chars.as_str())
+            .unwrap_or_default();
+    }
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let lower = part.to_ascii_lowercase();
+            let mut chars = lower.chars();
+            chars
+                .next()
+                .map(|c| c.to_ascii_uppercase().to_string() + chars.as_str())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Whether the statements assign the variable, e.g. a `FOR` counter skipping ahead.
+fn assigns(stmts: &[Stmt], upper: &str) -> bool {
+    stmts.iter().any(|stmt| match stmt {
+        Stmt::Assign { target, .. } => {
+            matches!(strip(target), Expr::Var(name) if name.to_uppercase() == upper)
+        }
+        Stmt::If {
+            branches,
+            otherwise,
+        } => {
+            branches.iter().any(|b| assigns(&b.body, upper))
+                || otherwise.as_ref().is_some_and(|o| assigns(o, upper))
+        }
+        Stmt::Case {
+            branches,
+            otherwise,
+            ..
+        } => {
+            branches.iter().any(|b| assigns(&b.body, upper))
+                || otherwise.as_ref().is_some_and(|o| assigns(o, upper))
+        }
+        Stmt::For { body, .. } | Stmt::While { body, .. } | Stmt::Repeat { body, .. } => {
+            assigns(body, upper)
+        }
+        _ => false,
+    })
+}
+
+fn is_loop_var(stmts: &[Stmt], upper: &str) -> bool {
+    stmts.iter().any(|stmt| match stmt {
+        Stmt::For { var, body, .. } => var.to_uppercase() == upper || is_loop_var(body, upper),
+        Stmt::If {
+            branches,
+            otherwise,
+        } => {
+            branches.iter().any(|b| is_loop_var(&b.body, upper))
+                || otherwise.as_ref().is_some_and(|o| is_loop_var(o, upper))
+        }
+        Stmt::Case {
+            branches,
+            otherwise,
+            ..
+        } => {
+            branches.iter().any(|b| is_loop_var(&b.body, upper))
+                || otherwise.as_ref().is_some_and(|o| is_loop_var(o, upper))
+        }
+        Stmt::While { body, .. } | Stmt::Repeat { body, .. } => is_loop_var(body, upper),
+        _ => false,
+    })
+}
+
+fn returns(stmt: &Stmt) -> bool {
+    match stmt {
+        Stmt::Return => true,
+        Stmt::If {
+            branches,
+            otherwise,
+        } => {
+            branches.iter().any(|b| b.body.iter().any(returns))
+                || otherwise.iter().flatten().any(returns)
+        }
+        Stmt::Case {
+            branches,
+            otherwise,
+            ..
+        } => {
+            branches.iter().any(|b| b.body.iter().any(returns))
+                || otherwise.iter().flatten().any(returns)
+        }
+        Stmt::For { body, .. } | Stmt::While { body, .. } | Stmt::Repeat { body, .. } => {
+            body.iter().any(returns)
+        }
+        _ => false,
+    }
+}
+
+/// Whether the statement reads or writes the variable.
+fn mentions(stmt: &Stmt, upper: &str) -> bool {
+    let any = |stmts: &[Stmt]| stmts.iter().any(|s| mentions(s, upper));
+    match stmt {
+        Stmt::Assign { target, value } => {
+            mentions_expr(target, upper) || mentions_expr(value, upper)
+        }
+        Stmt::Call(call) => mentions_expr(&Expr::Call(call.clone()), upper),
+        Stmt::If {
+            branches,
+            otherwise,
+        } => {
+            branches
+                .iter()
+                .any(|b| mentions_expr(&b.cond, upper) || any(&b.body))
+                || otherwise.as_deref().is_some_and(any)
+        }
+        Stmt::Case {
+            selector,
+            branches,
+            otherwise,
+        } => {
+            mentions_expr(selector, upper)
+                || branches.iter().any(|b| any(&b.body))
+                || otherwise.as_deref().is_some_and(any)
+        }
+        Stmt::For {
+            var,
+            from,
+            to,
+            by,
+            body,
+        } => {
+            var.to_uppercase() == upper
+                || mentions_expr(from, upper)
+                || mentions_expr(to, upper)
+                || by.as_ref().is_some_and(|b| mentions_expr(b, upper))
+                || any(body)
+        }
+        Stmt::While { cond, body } => mentions_expr(cond, upper) || any(body),
+        Stmt::Repeat { body, until } => mentions_expr(until, upper) || any(body),
+        Stmt::Return | Stmt::Exit | Stmt::Continue => false,
+    }
+}
+
+fn mentions_expr(expr: &Expr, upper: &str) -> bool {
+    match expr {
+        Expr::Literal(_) => false,
+        Expr::Var(name) => name.to_uppercase() == upper,
+        Expr::Field(base, _) => mentions_expr(base, upper),
+        Expr::Index(base, indices) => {
+            mentions_expr(base, upper) || indices.iter().any(|i| mentions_expr(i, upper))
+        }
+        Expr::Unary(_, operand) | Expr::Paren(operand) => mentions_expr(operand, upper),
+        Expr::Binary(_, lhs, rhs) => mentions_expr(lhs, upper) || mentions_expr(rhs, upper),
+        Expr::Call(call) => call.args.iter().any(|arg| match arg {
+            Arg::Value(e) | Arg::Input(_, e) | Arg::Output(_, e) => mentions_expr(e, upper),
+        }),
+    }
+}
@@ -0,0 +1,221 @@
+//! TwinCAT 3 PLC project files.
+//!
+//! Every POU becomes a `.TcPOU` file with the declaration and the ST implementation
+//! kept in separate CDATA blocks, data types become `.TcDUT` files and the globals of
+//! a configuration a `.TcGVL` file. The object ids are name-based UUIDs of the
+//! namespace and the name, so exporting the same registry twice yields identical files.
+//!
+//! ```
+//! use rust2plc::registry::RegistryItem;
+//! use rust2plc::st::function::Function;
+//! use rust2plc::twincat;
+//! use rust2plc::types::TypedValue;
+//!
+//! let answer = Function::new("answer", TypedValue::new_int()).with_namespace(Some("Math".to_string()));
+//! let file = RegistryItem::StFn(answer).to_twincat().unwrap().unwrap();
+//! assert_eq!(file.path().to_str(), Some("POUs/Math/answer.TcPOU"));
+//! assert_eq!(file.id(), twincat::guid(Some("Math"), "answer"));
+//! ```
+//!
+//! Tasks and the program instances of a configuration live in the system manager
+//! project (`.tsproj`) and are not exported.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use uuid::Uuid;
+
+use crate::error::Rust2PlcError;
+use crate::plcopen::XmlWriter;
+use crate::registry::{PLCRegistry, RegistryItem};
+use crate::st;
+use crate::st::ast::{Printer, Stmt, VarBlock, VarSection};
+use crate::st::data_type::DataType;
+use crate::types::TypedValue;
+
+/// The format version of the `TcPlcObject` root element.
+pub const TC_PLC_OBJECT_VERSION: &str = "1.1.0.1";
+
+/// The TwinCAT build written into the files, 3.1 Build 4024.
+pub const TC_PRODUCT_VERSION: &str = "3.1.4024.12";
+
+/// An exported TwinCAT object and its path relative to the PLC project folder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TcFile {
+    path: PathBuf,
+    id: String,
+    xml: String,
+}
+
+impl TcFile {
+    /// The path, e.g. `POUs/Math/add.TcPOU`, namespaces become folders.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The object id in the `{xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx}` form TwinCAT uses.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn xml(&self) -> &str {
+        &self.xml
+    }
+}
+
+/// The object id of an item, a version 5 UUID of the qualified name.
+pub fn guid(namespace: Option<&str>, name: &str) -> String {
+    let root = Uuid::new_v5(&Uuid::NAMESPACE_URL, b"urn:rust2plc:twincat");
+    let qualified = match namespace {
+        Some(ns) => format!("{}.{}", ns, name),
+        None => name.to_string(),
+    };
+    format!("{{{}}}", Uuid::new_v5(&root, qualified.as_bytes()))
+}
+
+impl RegistryItem {
+    /// Renders the item as a TwinCAT object.
+    ///
+    /// Configurations without globals have no counterpart and give `None`.
+    pub fn to_twincat(&self) -> Result<Option<TcFile>, Rust2PlcError> {
+        let printer = Printer::default();
+        let (folder, extension, tag, declaration, body) = match self {
+            RegistryItem::StFn(f) => {
+                let mut decl = st::header(f.description(), f.namespace(), f.version());
+                decl.push_str(&format!(
+                    "FUNCTION {} : {}\n",
+                    f.name(),
+                    f.return_value().to_plc_type()
+                ));
+                decl.push_str(&printer.var_blocks(
+                    &VarBlock::group(f.inputs().iter().chain(f.outputs()).chain(f.locals())),
+                    0,
+                ));
+                ("POUs", "TcPOU", "POU", decl, Some(f.body()))
+            }
+            RegistryItem::StFb(fb) => {
+                let mut decl = st::header(fb.description(), fb.namespace(), fb.version());
+                decl.push_str(&format!("FUNCTION_BLOCK {}\n", fb.name()));
+                decl.push_str(&printer.var_blocks(
+                    &VarBlock::group(fb.inputs().iter().chain(fb.outputs()).chain(fb.vars())),
+                    0,
+                ));
+                ("POUs", "TcPOU", "POU", decl, Some(fb.body()))
+            }
+            RegistryItem::StProgram(p) => {
+                if p.chart().is_some() {
+                    return Err(Rust2PlcError::Other(format!(
+                        "the chart of program {} is not exported to TwinCAT yet",
+                        p.name()
+                    )));
+                }
+                let mut decl = st::header(p.description(), p.namespace(), p.version());
+                decl.push_str(&format!("PROGRAM {}\n", p.name()));
+                decl.push_str(&printer.var_blocks(
+                    &VarBlock::group(p.inputs().iter().chain(p.outputs()).chain(p.vars())),
+                    0,
+                ));
+                ("POUs", "TcPOU", "POU", decl, Some(p.body()))
+            }
+            RegistryItem::StType(t) => ("DUTs", "TcDUT", "DUT", dut_declaration(t)?, None),
+            RegistryItem::StConfig(c) => {
+                if c.globals().is_empty() {
+                    return Ok(None);
+                }
+                let mut decl = st::header(c.description(), None, None);
+                let globals = VarBlock {
+                    section: VarSection::Global,
+                    vars: c.globals().to_vec(),
+                };
+                decl.push_str(&printer.var_blocks(&[globals], 0));
+                ("GVLs", "TcGVL", "GVL", decl, None)
+            }
+        };
+
+        let id = guid(self.namespace(), self.name());
+        Ok(Some(TcFile {
+            path: self
+                .namespace()
+                .map_or_else(|| PathBuf::from(folder), |ns| Path::new(folder).join(ns))
+                .join(format!("{}.{}", self.name(), extension)),
+            xml: tc_object(tag, self.name(), &id, &declaration, body),
+            id,
+        }))
+    }
+}
+
+impl PLCRegistry {
+    /// Writes the latest version of every item into the PLC project folder `dir`.
+    ///
+    /// Existing files are overwritten, files of items no longer registered are left in place.
+    pub fn export_twincat(&self, dir: impl AsRef<Path>) -> Result<Vec<TcFile>, Rust2PlcError> {
+        let mut files = vec![];
+        for item in self.declaration_order() {
+            if let Some(file) = item.to_twincat()? {
+                let path = dir.as_ref().join(file.path());
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(path, file.xml())?;
+                files.push(file);
+            }
+        }
+        Ok(files)
+    }
+}
+
+fn tc_object(tag: &str, name: &str, id: &str, declaration: &str, body: Option<&[Stmt]>) -> String {
+    let mut w = XmlWriter::new();
+    w.open(
+        "TcPlcObject",
+        &[
+            ("Version", TC_PLC_OBJECT_VERSION),
+            ("ProductVersion", TC_PRODUCT_VERSION),
+        ],
+    );
+    let mut attrs = vec![("Name", name), ("Id", id)];
+    if body.is_some() {
+        attrs.push(("SpecialFunc", "None"));
+    }
+    w.open(tag, &attrs);
+    w.cdata("Declaration", &[], declaration);
+    if let Some(body) = body {
+        w.open("Implementation", &[]);
+        w.cdata("ST", &[], Printer::default().stmts(body, 0).trim_end());
+        w.close("Implementation");
+    }
+    w.close(tag);
+    w.close("TcPlcObject");
+    w.finish()
+}
+
+/// The `TYPE` declaration, TwinCAT writes the base of an enumeration after the values.
+fn dut_declaration(t: &DataType) -> Result<String, Rust2PlcError> {
+    let TypedValue::Enum(name, variants, ..) = t.value() else {
+        return t.to_st();
+    };
+    // The ST declaration checks the variants and the base.
+    t.to_st()?;
+    let values = t.values().iter().chain(std::iter::repeat(&None));
+    let variants: Vec<String> = variants
+        .iter()
+        .zip(values)
+        .map(|(variant, value)| match value {
+            Some(value) => format!("    {} := {}", variant, value),
+            None => format!("    {}", variant),
+        })
+        .collect();
+    let initial = t.value().to_plc_literal()?;
+    let initial = initial.rsplit('#').next().unwrap_or(&initial);
+    let mut st = st::header(t.description(), t.namespace(), t.version());
+    st.push_str(&format!(
+        "TYPE {} :\n(\n{}\n){} := {};\nEND_TYPE\n",
+        name,
+        variants.join(",\n"),
+        t.base()
+            .map(|base| format!(" {}", base.to_plc_type()))
+            .unwrap_or_default(),
+        initial
+    ));
+    Ok(st)
+}
@@ -1,6 +1,7 @@
 use std::time::Duration;
 use chrono::{NaiveDate, NaiveTime, NaiveDateTime};
 use crate::error::Rust2PlcError;
+use crate::registry::find_data_type;
 use std::fmt;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -43,8 +44,14 @@ pub enum TypedValue {
     // User-defined type reference
     UserDefined(String, Option<Box<TypedValue>>), // (type_name, optional contained value)
 
+    // Structured type
+    Struct(String, Vec<(String, TypedValue)>), // (type_name, fields with their values)
+
+    // Enumerated type
+    Enum(String, Vec<String>, String, String), // (type_name, variants, current variant, initial variant of the type)
+
     // Array type
-    Array(Vec<Box<TypedValue>>, Box<TypedValue>), // (values, element_type)
+    Array(Vec<Box<TypedValue>>, Box<TypedValue>, i64), // (values, element_type, lower bound)
 }
 
 impl TypedValue {
@@ -73,8 +80,10 @@ impl TypedValue {
             TypedValue::Word(_) => "Word",
             TypedValue::DWord(_) => "DWord",
             TypedValue::LWord(_) => "LWord",
-            TypedValue::UserDefined(name, _) => "UserDefined",
-            TypedValue::Array(_, _) => "Array",
+            TypedValue::UserDefined(_, _) => "UserDefined",
+            TypedValue::Struct(_, _) => "Struct",
+            TypedValue::Enum(..) => "Enum",
+            TypedValue::Array(..) => "Array",
         }
     }
 
@@ -104,7 +113,9 @@ impl TypedValue {
             TypedValue::DWord(_) => "u32".to_string(),
             TypedValue::LWord(_) => "u64".to_string(),
             TypedValue::UserDefined(name, _) => name.clone(),
-            TypedValue::Array(_, elem_type) => format!("[{}; {}]", elem_type.to_rust_type(), self.array_size()),
+            TypedValue::Struct(name, _) => name.clone(),
+            TypedValue::Enum(name, ..) => name.clone(),
+            TypedValue::Array(_, elem_type, _) => format!("[{}; {}]", elem_type.to_rust_type(), self.array_size()),
         }
     }
 
@@ -136,17 +147,35 @@ impl TypedValue {
             TypedValue::DWord(_) => "DWORD".to_string(),
             TypedValue::LWord(_) => "LWORD".to_string(),
             TypedValue::UserDefined(name, _) => name.clone(),
-            TypedValue::Array(_, elem_type) => format!("ARRAY[0..{}] OF {}", self.array_size()-1, elem_type.to_plc_type()),
+            TypedValue::Struct(name, _) => name.clone(),
+            TypedValue::Enum(name, ..) => name.clone(),
+            TypedValue::Array(_, elem_type, lower) => format!("ARRAY[{}..{}] OF {}", lower, lower + self.array_size() as i64 - 1, elem_type.to_plc_type()),
         }
     }
 
     pub fn array_size(&self) -> usize {
         match self {
-            TypedValue::Array(values, _) => values.len(),
+            TypedValue::Array(values, ..) => values.len(),
+            _ => 0,
+        }
+    }
+
+    /// The index of the first element of an array, 0 unless declared otherwise in ST.
+    pub fn array_lower_bound(&self) -> i64 {
+        match self {
+            TypedValue::Array(_, _, lower) => *lower,
             _ => 0,
         }
     }
 
+    /// The array indexed from `lower`, e.g. `ARRAY[1..10] OF INT`.
+    pub fn with_lower_bound(self, lower: i64) -> Self {
+        match self {
+            TypedValue::Array(values, elem, _) => TypedValue::Array(values, elem, lower),
+            other => other,
+        }
+    }
+
     pub fn to_plc_literal(&self) -> Result<String, Rust2PlcError> {
         match self {
             TypedValue::Bool(value) => Ok(if *value { "TRUE" } else { "FALSE" }.to_string()),
@@ -221,16 +250,31 @@ impl TypedValue {
             TypedValue::WString(value, _) => Ok(format!("\"{}\"", value)),
 
             // Bit string literals
-            TypedValue::Byte(value) => Ok(format!("16#{}", format!("{:02X}", value))),
-            TypedValue::Word(value) => Ok(format!("16#{}", format!("{:04X}", value))),
-            TypedValue::DWord(value) => Ok(format!("16#{}", format!("{:08X}", value))),
-            TypedValue::LWord(value) => Ok(format!("16#{}", format!("{:016X}", value))),
+            TypedValue::Byte(value) => Ok(format!("16#{:02X}", value)),
+            TypedValue::Word(value) => Ok(format!("16#{:04X}", value)),
+            TypedValue::DWord(value) => Ok(format!("16#{:08X}", value)),
+            TypedValue::LWord(value) => Ok(format!("16#{:016X}", value)),
 
             // Complex types
             TypedValue::UserDefined(_, Some(value)) => value.to_plc_literal(),
             TypedValue::UserDefined(name, None) => Err(Rust2PlcError::parse(format!("Cannot convert empty user-defined type {} to PLC literal", name))),
+            TypedValue::Struct(_, fields) => {
+                let fields: Result<Vec<String>, Rust2PlcError> = fields
+                    .iter()
+                    .map(|(name, v)| v.to_plc_literal().map(|v| format!("{} := {}", name, v)))
+                    .collect();
+                Ok(format!("({})", fields?.join(", ")))
+            }
+
+            TypedValue::Enum(name, variants, value, _) => {
+                if variants.contains(value) {
+                    Ok(format!("{}#{}", name, value))
+                } else {
+                    Err(Rust2PlcError::parse(format!("{} is not a variant of {}", value, name)))
+                }
+            }
 
-            TypedValue::Array(values, _) => {
+            TypedValue::Array(values, ..) => {
                 let elements: Result<Vec<String>, Rust2PlcError> = values
                     .iter()
                     .map(|v| v.to_plc_literal())
@@ -283,41 +327,64 @@ impl TypedValue {
         TypedValue::UserDefined(name.to_string(), None)
     }
 
+    pub fn new_struct(name: &str, fields: Vec<(String, TypedValue)>) -> Self {
+        TypedValue::Struct(name.to_string(), fields)
+    }
+
+    /// An enumerated value holding the initial variant of the type, e.g. the `#[default]`
+    /// of a Rust enum. Without one it is the first variant, the IEC default.
+    pub fn new_enum(name: &str, variants: Vec<String>, initial: Option<&str>) -> Self {
+        let value = initial
+            .map(str::to_string)
+            .or_else(|| variants.first().cloned())
+            .unwrap_or_default();
+        TypedValue::Enum(name.to_string(), variants, value.clone(), value)
+    }
+
     pub fn new_array(element_type: TypedValue, size: usize) -> Self {
-        let mut values = Vec::with_capacity(size);
-        let template = match element_type.clone() {
-            TypedValue::Bool(_) => Box::new(TypedValue::new_bool()),
-            TypedValue::SInt(_) => Box::new(TypedValue::new_sint()),
-            TypedValue::Int(_) => Box::new(TypedValue::new_int()),
-            TypedValue::DInt(_) => Box::new(TypedValue::new_dint()),
-            TypedValue::LInt(_) => Box::new(TypedValue::new_lint()),
-            TypedValue::USInt(_) => Box::new(TypedValue::new_usint()),
-            TypedValue::UInt(_) => Box::new(TypedValue::new_uint()),
-            TypedValue::UDInt(_) => Box::new(TypedValue::new_udint()),
-            TypedValue::ULInt(_) => Box::new(TypedValue::new_ulint()),
-            TypedValue::Real(_) => Box::new(TypedValue::new_real()),
-            TypedValue::LReal(_) => Box::new(TypedValue::new_lreal()),
-            TypedValue::Time(_) => Box::new(TypedValue::new_time()),
-            TypedValue::Date(_) => Box::new(TypedValue::new_date()),
-            TypedValue::TimeOfDay(_) => Box::new(TypedValue::new_time_of_day()),
-            TypedValue::DateTime(_) => Box::new(TypedValue::new_date_time()),
-            TypedValue::Char(_) => Box::new(TypedValue::new_char()),
-            TypedValue::WChar(_) => Box::new(TypedValue::new_wchar()),
-            TypedValue::String(_, max_len) => Box::new(TypedValue::new_string(max_len)),
-            TypedValue::WString(_, max_len) => Box::new(TypedValue::new_wstring(max_len)),
-            TypedValue::Byte(_) => Box::new(TypedValue::new_byte()),
-            TypedValue::Word(_) => Box::new(TypedValue::new_word()),
-            TypedValue::DWord(_) => Box::new(TypedValue::new_dword()),
-            TypedValue::LWord(_) => Box::new(TypedValue::new_lword()),
-            TypedValue::UserDefined(name, _) => Box::new(TypedValue::new_user_defined(&name)),
-            el => Box::new(el),
-        };
-
-        for _ in 0..size {
-            values.push(template.clone());
+        let template = Box::new(element_type.default_value());
+        let values = vec![template; size];
+        TypedValue::Array(values, Box::new(element_type), 0)
+    }
+
+    /// The same type holding its default value.
+    pub fn default_value(&self) -> TypedValue {
+        match self.clone() {
+            TypedValue::Bool(_) => TypedValue::new_bool(),
+            TypedValue::SInt(_) => TypedValue::new_sint(),
+            TypedValue::Int(_) => TypedValue::new_int(),
+            TypedValue::DInt(_) => TypedValue::new_dint(),
+            TypedValue::LInt(_) => TypedValue::new_lint(),
+            TypedValue::USInt(_) => TypedValue::new_usint(),
+            TypedValue::UInt(_) => TypedValue::new_uint(),
+            TypedValue::UDInt(_) => TypedValue::new_udint(),
+            TypedValue::ULInt(_) => TypedValue::new_ulint(),
+            TypedValue::Real(_) => TypedValue::new_real(),
+            TypedValue::LReal(_) => TypedValue::new_lreal(),
+            TypedValue::Time(_) => TypedValue::new_time(),
+            TypedValue::Date(_) => TypedValue::new_date(),
+            TypedValue::TimeOfDay(_) => TypedValue::new_time_of_day(),
+            TypedValue::DateTime(_) => TypedValue::new_date_time(),
+            TypedValue::Char(_) => TypedValue::new_char(),
+            TypedValue::WChar(_) => TypedValue::new_wchar(),
+            TypedValue::String(_, max_len) => TypedValue::new_string(max_len),
+            TypedValue::WString(_, max_len) => TypedValue::new_wstring(max_len),
+            TypedValue::Byte(_) => TypedValue::new_byte(),
+            TypedValue::Word(_) => TypedValue::new_word(),
+            TypedValue::DWord(_) => TypedValue::new_dword(),
+            TypedValue::LWord(_) => TypedValue::new_lword(),
+            TypedValue::UserDefined(name, _) => TypedValue::new_user_defined(&name),
+            TypedValue::Struct(name, fields) => TypedValue::Struct(
+                name,
+                fields.into_iter().map(|(f, v)| (f, v.default_value())).collect(),
+            ),
+            TypedValue::Enum(name, variants, _, initial) => {
+                TypedValue::new_enum(&name, variants, Some(&initial))
+            }
+            TypedValue::Array(values, elem, lower) => {
+                TypedValue::new_array(*elem, values.len()).with_lower_bound(lower)
+            }
         }
-
-        TypedValue::Array(values, Box::new(element_type))
     }
 
     pub fn from_rust_type(type_str: &str) -> Option<TypedValue> {
@@ -351,6 +418,10 @@ impl TypedValue {
                         }
                     }
                 }
+                // Types declared with #[derive(PlcType)] resolve to their layout
+                if let Some(data_type) = find_data_type(type_str.rsplit("::").next().unwrap_or(type_str)) {
+                    return Some(data_type.value().clone());
+                }
                 // Fallback to user-defined type
                 Some(TypedValue::new_user_defined(type_str))
             }
@@ -419,6 +490,9 @@ impl TypedValue {
                     }
                 }
 
+                if let Some(data_type) = find_data_type(type_str.rsplit('.').next().unwrap_or(type_str)) {
+                    return Some(data_type.value().clone());
+                }
                 // Default to user-defined type
                 Some(TypedValue::new_user_defined(type_str))
             }
@@ -462,7 +536,16 @@ impl fmt::Display for TypedValue {
             TypedValue::LWord(value) => write!(f, "{:#018x}", value),
             TypedValue::UserDefined(name, Some(value)) => write!(f, "{}({})", name, value),
             TypedValue::UserDefined(name, None) => write!(f, "{}", name),
-            TypedValue::Array(values, _) => {
+            TypedValue::Struct(name, fields) => {
+                write!(f, "{} {{ ", name)?;
+                for (i, (field, v)) in fields.iter().enumerate() {
+                    if i > 0 { write!(f, ", ")?; }
+                    write!(f, "{}: {}", field, v)?;
+                }
+                write!(f, " }}")
+            }
+            TypedValue::Enum(name, _, value, _) => write!(f, "{}::{}", name, value),
+            TypedValue::Array(values, ..) => {
                 write!(f, "[")?;
                 for (i, v) in values.iter().enumerate() {
                     if i > 0 { write!(f, ", ")?; }
@@ -591,7 +674,7 @@ impl From<&str> for TypedValue {
 impl<T: Into<TypedValue> + Clone> From<Vec<T>> for TypedValue {
     fn from(values: Vec<T>) -> Self {
         if values.is_empty() {
-            return TypedValue::Array(vec![], Box::new(TypedValue::new_bool()));
+            return TypedValue::Array(vec![], Box::new(TypedValue::new_bool()), 0);
         }
 
         // Create the element type from the first element
@@ -604,7 +687,7 @@ impl<T: Into<TypedValue> + Clone> From<Vec<T>> for TypedValue {
             .map(|v| Box::new(v.into()))
             .collect();
 
-        TypedValue::Array(typed_values, elem_type)
+        TypedValue::Array(typed_values, elem_type, 0)
     }
 }
 
@@ -612,7 +695,7 @@ impl<T: Into<TypedValue> + Clone> From<Vec<T>> for TypedValue {
 impl<T: Into<TypedValue> + Clone, const N: usize> From<[T; N]> for TypedValue {
     fn from(array: [T; N]) -> Self {
         if N == 0 {
-            return TypedValue::Array(vec![], Box::new(TypedValue::new_bool()));
+            return TypedValue::Array(vec![], Box::new(TypedValue::new_bool()), 0);
         }
 
         // Create the element type from the first element
@@ -625,7 +708,7 @@ impl<T: Into<TypedValue> + Clone, const N: usize> From<[T; N]> for TypedValue {
             .map(|v| Box::new(v.into()))
             .collect();
 
-        TypedValue::Array(typed_values, elem_type)
+        TypedValue::Array(typed_values, elem_type, 0)
     }
 }
 
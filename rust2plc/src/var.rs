@@ -3,7 +3,7 @@ use crate::types::TypedValue;
 
 type ValueName = String;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Input(ValueName, TypedValue),     // Named input parameter
     Output(ValueName, TypedValue),    // Named output parameter
@@ -15,3 +15,57 @@ pub enum Value {
     Return(TypedValue),               // Return value (no name needed as it's the function's return)
     Constant(ValueName, TypedValue),  // Named constant value
 }
+
+impl Value {
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Value::Input(name, _)
+            | Value::Output(name, _)
+            | Value::InOut(name, _)
+            | Value::Local(name, _)
+            | Value::Global(name, _)
+            | Value::External(name, _)
+            | Value::Temporary(name, _)
+            | Value::Constant(name, _) => Some(name),
+            Value::Return(_) => None,
+        }
+    }
+
+    pub fn typed_value(&self) -> &TypedValue {
+        match self {
+            Value::Input(_, tv)
+            | Value::Output(_, tv)
+            | Value::InOut(_, tv)
+            | Value::Local(_, tv)
+            | Value::Global(_, tv)
+            | Value::External(_, tv)
+            | Value::Temporary(_, tv)
+            | Value::Return(tv)
+            | Value::Constant(_, tv) => tv,
+        }
+    }
+
+    /// The ST `VAR_*` section the value is declared in.
+    pub fn st_section(&self) -> Option<&'static str> {
+        match self {
+            Value::Input(_, _) => Some("VAR_INPUT"),
+            Value::Output(_, _) => Some("VAR_OUTPUT"),
+            Value::InOut(_, _) => Some("VAR_IN_OUT"),
+            Value::Local(_, _) => Some("VAR"),
+            Value::Global(_, _) => Some("VAR_GLOBAL"),
+            Value::External(_, _) => Some("VAR_EXTERNAL"),
+            Value::Temporary(_, _) => Some("VAR_TEMP"),
+            Value::Constant(_, _) => Some("VAR CONSTANT"),
+            Value::Return(_) => None,
+        }
+    }
+
+    /// The ST declaration line of the value, e.g. `left : ULINT;`
+    pub fn to_st_decl(&self) -> String {
+        let tv = self.typed_value();
+        match self.name() {
+            Some(name) => format!("{} : {};", name, tv.to_plc_type()),
+            None => tv.to_plc_type(),
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use rust2plc_macro::plc_fn;
+use rust2plc_macro::{plc_fb, plc_fn};
 
 #[plc_fn(
     st,
@@ -15,13 +15,3065 @@ pub fn sub(left: u64, right: u64) -> u64 {
     left - right
 }
 
+#[plc_fb(st, description = "Rising edge detector")]
+#[derive(Default)]
+pub struct RTrig {
+    #[input]
+    pub clk: bool,
+    #[output]
+    pub q: bool,
+    #[internal]
+    mem: bool,
+}
+
+#[plc_fb]
+impl RTrig {
+    pub fn cycle(&mut self) {
+        self.q = self.clk && !self.mem;
+        self.mem = self.clk;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rust2plc::l5x::L5xProject;
+    use rust2plc::plcopen;
+    use rust2plc::plcopen::export::PlcOpenProject;
+    use rust2plc::registry::{PLCRegistry, RegistryItem};
+    use rust2plc::scl::{SclPrinter, SclTime};
+    use rust2plc::st::config::{Configuration, Resource, Task};
+    use rust2plc::st::data_type::PlcType;
+    use rust2plc::st::export::StLayout;
+    use rust2plc::st::function::Function;
+    use rust2plc::twincat;
+    use rust2plc::types::TypedValue;
+    use rust2plc_macro::{plc_program, PlcType};
+    use std::time::Duration;
 
     #[test]
     fn it_works() {
         let result = add(2, 2);
         assert_eq!(result, 4);
     }
+
+    #[plc_fn(st)]
+    fn scale(raw: i16, gain: f32, enabled: bool) -> f32 {
+        let value = raw as f32 * gain;
+        let mut out: f32 = (value - 1.5) * 2.0;
+        out += value.abs().min(100.0);
+        if_enabled(enabled, out)
+    }
+
+    fn if_enabled(enabled: bool, value: f32) -> f32 {
+        if enabled {
+            value
+        } else {
+            0.0
+        }
+    }
+
+    #[plc_fb(st, namespace = "Counters")]
+    #[derive(Default)]
+    struct CountUp {
+        #[input]
+        cu: bool,
+        #[input]
+        pv: u16,
+        #[output]
+        q: bool,
+        #[output]
+        cv: u16,
+        edge: RTrig,
+    }
+
+    #[plc_fb]
+    impl CountUp {
+        #[cycle]
+        fn scan(&mut self) {
+            let next: u16 = self.cv + 1;
+            self.cv = next.min(self.pv);
+            self.q = self.cv >= self.pv;
+        }
+    }
+
+    #[plc_program(st, description = "Main line")]
+    #[derive(Default)]
+    struct Line {
+        #[input]
+        start: bool,
+        #[output]
+        running: bool,
+        count: u32,
+    }
+
+    #[plc_program]
+    impl Line {
+        fn cycle(&mut self) {
+            self.running = self.start;
+            self.count += 1;
+        }
+    }
+
+    #[plc_program(st)]
+    fn housekeeping() {
+        let ticks: u32 = 0;
+        noop(ticks);
+    }
+
+    fn noop(_ticks: u32) {}
+
+    #[test]
+    fn add_st() {
+        assert_eq!(
+            ADD_ST,
+            "(* Adds two numbers *)\n\
+             (* namespace: Math, version: 1.0 *)\n\
+             FUNCTION add : ULINT\n\
+             VAR_INPUT\n    left : ULINT;\n    right : ULINT;\nEND_VAR\n\
+             \x20   add := left + right;\n\
+             END_FUNCTION\n"
+        );
+    }
+
+    #[plc_fn(st)]
+    fn shadow(c: bool) -> i32 {
+        let x = 1;
+        let mut y = 0;
+        if c {
+            let x = 5;
+            y = x;
+        }
+        x + y
+    }
+
+    #[test]
+    fn shadow_st() {
+        assert_eq!(shadow(true), 6);
+        assert_eq!(
+            SHADOW_ST,
+            "FUNCTION shadow : DINT\n\
+             VAR_INPUT\n    c : BOOL;\nEND_VAR\n\
+             VAR\n    x : DINT;\n    y : DINT;\n    x_1 : DINT;\nEND_VAR\n\
+             \x20   x := 1;\n\
+             \x20   y := 0;\n\
+             \x20   IF c THEN\n\
+             \x20       x_1 := 5;\n\
+             \x20       y := x_1;\n\
+             \x20   END_IF;\n\
+             \x20   shadow := x + y;\n\
+             END_FUNCTION\n"
+        );
+    }
+
+    #[plc_fn(st)]
+    fn recount(n: i16) -> i16 {
+        let mut total: i16 = 0;
+        for _ in 0..n {
+            let mut count: i16 = Default::default();
+            count += 1;
+            total += count;
+        }
+        total
+    }
+
+    #[test]
+    fn recount_st() {
+        assert_eq!(recount(3), 3);
+        assert_eq!(
+            RECOUNT_ST,
+            "FUNCTION recount : INT\n\
+             VAR_INPUT\n    n : INT;\nEND_VAR\n\
+             VAR\n    total : INT;\n    _tmp1 : INT;\n    count : INT;\nEND_VAR\n\
+             \x20   total := 0;\n\
+             \x20   FOR _tmp1 := 0 TO n - 1 DO\n\
+             \x20       count := 0;\n\
+             \x20       count := count + 1;\n\
+             \x20       total := total + count;\n\
+             \x20   END_FOR;\n\
+             \x20   recount := total;\n\
+             END_FUNCTION\n"
+        );
+    }
+
+    #[test]
+    fn scale_st() {
+        assert_eq!(
+            SCALE_ST,
+            "FUNCTION scale : REAL\n\
+             VAR_INPUT\n    raw : INT;\n    gain : REAL;\n    enabled : BOOL;\nEND_VAR\n\
+             VAR\n    value : REAL;\n    out : REAL;\nEND_VAR\n\
+             \x20   value := INT_TO_REAL(raw) * gain;\n\
+             \x20   out := (value - 1.5) * 2.0;\n\
+             \x20   out := out + MIN(ABS(value), 100.0);\n\
+             \x20   scale := if_enabled(enabled, out);\n\
+             END_FUNCTION\n"
+        );
+        assert_eq!(scale(4, 0.5, true), 3.0);
+    }
+
+    #[test]
+    fn add_model() {
+        let function = add_plc();
+        assert_eq!(function.name(), "add");
+        assert_eq!(function.description(), Some("Adds two numbers"));
+        assert_eq!(function.namespace(), Some("Math"));
+        assert_eq!(function.version(), Some("1.0"));
+        assert_eq!(function.return_value().to_plc_type(), "ULINT");
+        let inputs: Vec<_> = function.inputs().iter().filter_map(|v| v.name()).collect();
+        assert_eq!(inputs, vec!["left", "right"]);
+        assert_eq!(function.to_st(), ADD_ST);
+        assert_eq!(scale_plc().locals().len(), 2);
+    }
+
+    #[test]
+    fn function_block_st() {
+        assert_eq!(
+            RTrig::plc().to_st(),
+            "(* Rising edge detector *)\n\
+             FUNCTION_BLOCK RTrig\n\
+             VAR_INPUT\n    clk : BOOL;\nEND_VAR\n\
+             VAR_OUTPUT\n    q : BOOL;\nEND_VAR\n\
+             VAR\n    mem : BOOL;\nEND_VAR\n\
+             \x20   q := clk AND NOT mem;\n\
+             \x20   mem := clk;\n\
+             END_FUNCTION_BLOCK\n"
+        );
+        assert_eq!(
+            CountUp::plc().to_st(),
+            "(* namespace: Counters *)\n\
+             FUNCTION_BLOCK CountUp\n\
+             VAR_INPUT\n    cu : BOOL;\n    pv : UINT;\nEND_VAR\n\
+             VAR_OUTPUT\n    q : BOOL;\n    cv : UINT;\nEND_VAR\n\
+             VAR\n    edge : RTrig;\nEND_VAR\n\
+             VAR_TEMP\n    next : UINT;\nEND_VAR\n\
+             \x20   next := cv + 1;\n\
+             \x20   cv := MIN(next, pv);\n\
+             \x20   q := cv >= pv;\n\
+             END_FUNCTION_BLOCK\n"
+        );
+
+        let mut trig = RTrig {
+            clk: true,
+            ..Default::default()
+        };
+        trig.cycle();
+        assert!(trig.q);
+        trig.cycle();
+        assert!(!trig.q);
+        let mut counter = CountUp {
+            pv: 1,
+            ..Default::default()
+        };
+        counter.scan();
+        counter.scan();
+        assert!(counter.q && counter.cv == 1 && !counter.cu && !counter.edge.q);
+    }
+
+    #[test]
+    fn program_and_configuration_st() {
+        assert_eq!(
+            Line::plc().to_st(),
+            "(* Main line *)\n\
+             PROGRAM Line\n\
+             VAR_INPUT\n    start : BOOL;\nEND_VAR\n\
+             VAR_OUTPUT\n    running : BOOL;\nEND_VAR\n\
+             VAR\n    count : UDINT;\nEND_VAR\n\
+             \x20   running := start;\n\
+             \x20   count := count + 1;\n\
+             END_PROGRAM\n"
+        );
+        assert_eq!(
+            housekeeping_plc().to_st(),
+            "PROGRAM housekeeping\n\
+             VAR_TEMP\n    ticks : UDINT;\nEND_VAR\n\
+             \x20   ticks := 0;\n\
+             \x20   noop(ticks);\n\
+             END_PROGRAM\n"
+        );
+
+        let mut line = Line {
+            start: true,
+            ..Default::default()
+        };
+        line.cycle();
+        housekeeping();
+        assert!(line.running && line.count == 1);
+
+        let config = Configuration::new("Plant")
+            .with_global("alarm", TypedValue::new_bool())
+            .with_resource(
+                Resource::new("Cpu", "PLC")
+                    .with_task(Task::cyclic("Fast", Duration::from_millis(10), 1))
+                    .with_task(Task::event("OnAlarm", "alarm", 0))
+                    .with_program("main", "Line", Some("Fast"))
+                    .with_program("house", "housekeeping", Some("OnAlarm")),
+            );
+        assert_eq!(
+            config.to_st().unwrap(),
+            "CONFIGURATION Plant\n\
+             \x20   VAR_GLOBAL\n        alarm : BOOL;\n    END_VAR\n\
+             \x20   RESOURCE Cpu ON PLC\n\
+             \x20       TASK Fast(INTERVAL := T#0s10ms, PRIORITY := 1);\n\
+             \x20       TASK OnAlarm(SINGLE := alarm, PRIORITY := 0);\n\
+             \x20       PROGRAM main WITH Fast : Line;\n\
+             \x20       PROGRAM house WITH OnAlarm : housekeeping;\n\
+             \x20   END_RESOURCE\n\
+             END_CONFIGURATION\n"
+        );
+
+        let broken = Configuration::new("Plant")
+            .with_resource(Resource::new("Cpu", "PLC").with_program("main", "Line", Some("Slow")));
+        assert!(broken.to_st().is_err());
+
+        let mut registry = PLCRegistry::new();
+        registry.register(RegistryItem::StConfig(config));
+        registry.register(RegistryItem::StProgram(Line::plc()));
+        let order: Vec<_> = registry
+            .declaration_order()
+            .iter()
+            .map(|i| i.name())
+            .collect();
+        assert_eq!(order, vec!["Line", "Plant"]);
+    }
+
+    #[test]
+    fn registry() {
+        let registry = PLCRegistry::all();
+        assert!(registry.find(Some("Math"), "add", Some("1.0")).is_some());
+        assert!(registry.find(Some("Math"), "sub", None).is_some());
+        assert!(registry.find(None, "add", None).is_none());
+        assert!(registry.find(None, "scale", None).is_some());
+
+        let groups = registry.by_namespace();
+        let math: Vec<_> = groups[&Some("Math")].iter().map(|i| i.name()).collect();
+        assert_eq!(math, vec!["add", "sub"]);
+        assert!(registry.find(Some("Counters"), "CountUp", None).is_some());
+    }
+
+    #[test]
+    fn registry_versions() {
+        let mut registry = PLCRegistry::new();
+        for version in ["1.9", "1.10", "1.2"] {
+            registry.register(RegistryItem::StFn(
+                add_plc().with_version(Some(version.to_string())),
+            ));
+        }
+        let latest = registry.find(Some("Math"), "add", None).unwrap();
+        assert_eq!(latest.version(), Some("1.10"));
+        let versions: Vec<_> = registry.items().iter().map(|i| i.version()).collect();
+        assert_eq!(versions, vec![Some("1.2"), Some("1.9"), Some("1.10")]);
+    }
+
+    #[test]
+    fn export_st() {
+        let dir = std::env::temp_dir().join(format!("plc-predicate-st-{}", std::process::id()));
+        let mut registry = PLCRegistry::new();
+        registry.register(RegistryItem::StFn(add_plc()));
+        registry.register(RegistryItem::StFn(sub_plc()));
+        registry.register(RegistryItem::StFn(scale_plc()));
+
+        registry.export_st(&dir).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("Math.add.st")).unwrap(),
+            ADD_ST
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("scale.st")).unwrap(),
+            SCALE_ST
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("manifest.csv")).unwrap(),
+            "order,file,namespace,name,version,description\n\
+             1,scale.st,,scale,,\n\
+             2,Math.add.st,Math,add,1.0,Adds two numbers\n\
+             3,Math.sub.st,Math,sub,1.0,\n"
+        );
+
+        registry
+            .export_st_with(dir.join("ns"), StLayout::PerNamespace)
+            .unwrap();
+        let math = std::fs::read_to_string(dir.join("ns").join("Math.st")).unwrap();
+        assert_eq!(math, format!("{}\n{}", ADD_ST, SUB_ST));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[derive(PlcType)]
+    #[plc(description = "A point on the plane", namespace = "Geo")]
+    #[allow(dead_code)]
+    struct Point {
+        x: f32,
+        #[plc(init = 1.5)]
+        y: f32,
+        #[plc(init = "origin")]
+        label: String,
+    }
+
+    #[plc_fn(st)]
+    fn norm(p: Point) -> f32 {
+        p.x.abs() + p.y.abs()
+    }
+
+    #[test]
+    fn struct_type() {
+        assert_eq!(
+            norm(Point {
+                x: -1.0,
+                y: 2.0,
+                label: String::new()
+            }),
+            3.0
+        );
+        assert_eq!(
+            Point::plc_type().to_st().unwrap(),
+            "(* A point on the plane *)\n\
+             (* namespace: Geo *)\n\
+             TYPE Point :\n\
+             STRUCT\n\
+             \x20   x : REAL;\n\
+             \x20   y : REAL := 1.500000;\n\
+             \x20   label : STRING := 'origin';\n\
+             END_STRUCT\n\
+             END_TYPE\n"
+        );
+        match TypedValue::from_rust_type("Point").unwrap() {
+            TypedValue::Struct(name, fields) => {
+                assert_eq!(name, "Point");
+                assert_eq!(fields.len(), 3);
+            }
+            other => panic!("expected a struct, got {:?}", other),
+        }
+
+        let mut registry = PLCRegistry::new();
+        registry.register(RegistryItem::StFn(norm_plc()));
+        registry.register(RegistryItem::StType(Point::plc_type()));
+        let order: Vec<_> = registry
+            .declaration_order()
+            .iter()
+            .map(|i| i.name())
+            .collect();
+        assert_eq!(order, vec!["Point", "norm"]);
+        assert!(PLCRegistry::all()
+            .items()
+            .iter()
+            .any(|i| matches!(i, RegistryItem::StType(t) if t.name() == "Point")));
+    }
+
+    #[derive(PlcType, Clone, Copy, Default, PartialEq)]
+    enum Mode {
+        Idle,
+        #[default]
+        Running,
+        #[allow(dead_code)]
+        Fault,
+    }
+
+    #[derive(PlcType)]
+    #[plc(namespace = "Geo")]
+    #[repr(i16)]
+    #[allow(dead_code)]
+    enum Level {
+        Low = 0,
+        High = 10,
+    }
+
+    #[plc_fn(st)]
+    fn is_running(mode: Mode) -> bool {
+        mode == Mode::Running
+    }
+
+    #[test]
+    fn enum_type() {
+        assert!(is_running(Mode::default()));
+        assert!(!is_running(Mode::Idle));
+        assert_eq!(
+            Mode::plc_type().to_st().unwrap(),
+            "TYPE Mode : (Idle, Running, Fault) := Running;\nEND_TYPE\n"
+        );
+        assert_eq!(
+            Level::plc_type().to_st().unwrap(),
+            "(* namespace: Geo *)\n\
+             TYPE Level : INT (Low := 0, High := 10) := Low;\n\
+             END_TYPE\n"
+        );
+        assert_eq!(
+            TypedValue::from(Mode::Idle).to_plc_literal().unwrap(),
+            "Mode#Idle"
+        );
+        assert_eq!(
+            TypedValue::from_rust_type("Mode").unwrap(),
+            TypedValue::from(Mode::Running)
+        );
+        // the default of the type is the `#[default]` variant
+        assert_eq!(
+            TypedValue::from(Mode::Fault).default_value(),
+            TypedValue::from(Mode::Running)
+        );
+        assert!(is_running_plc()
+            .to_st()
+            .contains("is_running := mode = Mode#Running;"));
+
+        let mut registry = PLCRegistry::new();
+        registry.register(RegistryItem::StFn(is_running_plc()));
+        registry.register(RegistryItem::StType(Mode::plc_type()));
+        let order: Vec<_> = registry
+            .declaration_order()
+            .iter()
+            .map(|i| i.name())
+            .collect();
+        assert_eq!(order, vec!["Mode", "is_running"]);
+    }
+
+    #[plc_fn(st)]
+    fn classify(code: i16, mode: Mode) -> i16 {
+        let weight = match mode {
+            Mode::Idle => 0,
+            Mode::Running | Mode::Fault => 1,
+        };
+        let class: i16 = match code {
+            0 => 0,
+            1..=5 | 7 => 1,
+            10..20 => {
+                let half = code / 2;
+                half + 1
+            }
+            _ => -1,
+        };
+        if class < 0 {
+            return -1;
+        }
+        let offset = if weight > 0 { 100 } else { 0 };
+        if class == 0 {
+            offset
+        } else if class > 5 {
+            offset + class * weight
+        } else {
+            offset + (if code > 3 { class } else { 0 })
+        }
+    }
+
+    #[test]
+    fn control_flow() {
+        assert_eq!(classify(3, Mode::Idle), 0);
+        assert_eq!(classify(12, Mode::Running), 107);
+        assert_eq!(classify(8, Mode::Running), -1);
+        assert_eq!(
+            classify_plc().to_st(),
+            "FUNCTION classify : INT\n\
+             VAR_INPUT\n\
+             \x20   code : INT;\n\
+             \x20   mode : Mode;\n\
+             END_VAR\n\
+             VAR\n\
+             \x20   weight : INT;\n\
+             \x20   half : INT;\n\
+             \x20   class : INT;\n\
+             \x20   offset : INT;\n\
+             \x20   _tmp1 : INT;\n\
+             END_VAR\n\
+             \x20   CASE mode OF\n\
+             \x20       Mode#Idle:\n\
+             \x20           weight := 0;\n\
+             \x20       Mode#Running, Mode#Fault:\n\
+             \x20           weight := 1;\n\
+             \x20   END_CASE;\n\
+             \x20   CASE code OF\n\
+             \x20       0:\n\
+             \x20           class := 0;\n\
+             \x20       1..5, 7:\n\
+             \x20           class := 1;\n\
+             \x20       10..19:\n\
+             \x20           half := code / 2;\n\
+             \x20           class := half + 1;\n\
+             \x20       ELSE\n\
+             \x20           class := -1;\n\
+             \x20   END_CASE;\n\
+             \x20   IF class < 0 THEN\n\
+             \x20       classify := -1;\n\
+             \x20       RETURN;\n\
+             \x20   END_IF;\n\
+             \x20   IF weight > 0 THEN\n\
+             \x20       offset := 100;\n\
+             \x20   ELSE\n\
+             \x20       offset := 0;\n\
+             \x20   END_IF;\n\
+             \x20   IF class = 0 THEN\n\
+             \x20       classify := offset;\n\
+             \x20   ELSIF class > 5 THEN\n\
+             \x20       classify := offset + class * weight;\n\
+             \x20   ELSE\n\
+             \x20       IF code > 3 THEN\n\
+             \x20           _tmp1 := class;\n\
+             \x20       ELSE\n\
+             \x20           _tmp1 := 0;\n\
+             \x20       END_IF;\n\
+             \x20       classify := offset + (_tmp1);\n\
+             \x20   END_IF;\n\
+             END_FUNCTION\n"
+        );
+    }
+
+    #[plc_fn(st)]
+    fn loops(samples: [f32; 4], limit: i16) -> f32 {
+        let mut sum: f32 = 0.0;
+        for x in samples.iter() {
+            sum += *x;
+        }
+        for (i, x) in samples.iter().enumerate() {
+            if i % 2 == 0 {
+                continue;
+            }
+            sum += x * 2.0;
+        }
+        for j in (0..=limit).step_by(2) {
+            sum += j as f32;
+        }
+        let mut n: i16 = 0;
+        while n < limit {
+            n += 3;
+        }
+        loop {
+            n -= 1;
+            if n <= 0 {
+                break;
+            }
+        }
+        let mut k: i16 = 0;
+        loop {
+            k += 1;
+            if k > 3 {
+                break;
+            }
+            sum += 1.0;
+        }
+        for _ in 0..3 {
+            sum -= 1.0;
+        }
+        sum + n as f32
+    }
+
+    #[test]
+    fn loops_st() {
+        assert_eq!(loops([1.0, 2.0, 3.0, 4.0], 5), 28.0);
+        assert_eq!(
+            loops_plc().to_st(),
+            "FUNCTION loops : REAL\n\
+             VAR_INPUT\n\
+             \x20   samples : ARRAY[0..3] OF REAL;\n\
+             \x20   limit : INT;\n\
+             END_VAR\n\
+             VAR\n\
+             \x20   sum : REAL;\n\
+             \x20   _tmp1 : DINT;\n\
+             \x20   i : DINT;\n\
+             \x20   j : INT;\n\
+             \x20   n : INT;\n\
+             \x20   k : INT;\n\
+             \x20   _tmp2 : DINT;\n\
+             END_VAR\n\
+             \x20   sum := 0.0;\n\
+             \x20   FOR _tmp1 := 0 TO 3 DO\n\
+             \x20       sum := sum + samples[_tmp1];\n\
+             \x20   END_FOR;\n\
+             \x20   FOR i := 0 TO 3 DO\n\
+             \x20       IF i MOD 2 = 0 THEN\n\
+             \x20           CONTINUE;\n\
+             \x20       END_IF;\n\
+             \x20       sum := sum + samples[i] * 2.0;\n\
+             \x20   END_FOR;\n\
+             \x20   FOR j := 0 TO limit BY 2 DO\n\
+             \x20       sum := sum + INT_TO_REAL(j);\n\
+             \x20   END_FOR;\n\
+             \x20   n := 0;\n\
+             \x20   WHILE n < limit DO\n\
+             \x20       n := n + 3;\n\
+             \x20   END_WHILE;\n\
+             \x20   REPEAT\n\
+             \x20       n := n - 1;\n\
+             \x20   UNTIL n <= 0\n\
+             \x20   END_REPEAT;\n\
+             \x20   k := 0;\n\
+             \x20   WHILE TRUE DO\n\
+             \x20       k := k + 1;\n\
+             \x20       IF k > 3 THEN\n\
+             \x20           EXIT;\n\
+             \x20       END_IF;\n\
+             \x20       sum := sum + 1.0;\n\
+             \x20   END_WHILE;\n\
+             \x20   FOR _tmp2 := 0 TO 2 DO\n\
+             \x20       sum := sum - 1.0;\n\
+             \x20   END_FOR;\n\
+             \x20   loops := sum + INT_TO_REAL(n);\n\
+             END_FUNCTION\n"
+        );
+    }
+
+    #[plc_fn(st)]
+    fn total(n: u32) -> u32 {
+        let mut sum: u32 = 0;
+        for i in 0..n {
+            sum += i;
+        }
+        sum
+    }
+
+    #[test]
+    fn empty_range_st() {
+        assert_eq!(total(0), 0);
+        assert_eq!(total(4), 6);
+        assert_eq!(
+            TOTAL_ST,
+            "FUNCTION total : UDINT\n\
+             VAR_INPUT\n    n : UDINT;\nEND_VAR\n\
+             VAR\n    sum : UDINT;\n    i : UDINT;\nEND_VAR\n\
+             \x20   sum := 0;\n\
+             \x20   IF n > 0 THEN\n\
+             \x20       FOR i := 0 TO n - 1 DO\n\
+             \x20           sum := sum + i;\n\
+             \x20       END_FOR;\n\
+             \x20   END_IF;\n\
+             \x20   total := sum;\n\
+             END_FUNCTION\n"
+        );
+    }
+
+    #[test]
+    fn ast_printer() {
+        use rust2plc::st::ast::{Arg, BinaryOp, Call, Expr, KeywordCase, Printer, Stmt};
+
+        let body = classify_plc().body().to_vec();
+        assert!(matches!(body[0], Stmt::Case { .. }));
+        assert!(
+            matches!(body.last(), Some(Stmt::If { branches, otherwise: Some(_) }) if branches.len() == 2)
+        );
+
+        let printer = Printer::new()
+            .with_indent(2)
+            .with_keyword_case(KeywordCase::Lower);
+        let st = is_running_plc().to_st_with(&printer);
+        assert_eq!(
+            st,
+            "function is_running : BOOL\n\
+             var_input\n\
+             \x20 mode : Mode;\n\
+             end_var\n\
+             \x20 is_running := mode = Mode#Running;\n\
+             end_function\n"
+        );
+
+        let tick = rust2plc::st::function_block::FunctionBlock::new("Blink").with_body(vec![
+            Stmt::Call(Call {
+                name: "timer".to_string(),
+                args: vec![
+                    Arg::Input(
+                        "IN".to_string(),
+                        Expr::unary(rust2plc::st::ast::UnaryOp::Not, Expr::var("q")),
+                    ),
+                    Arg::Output("Q".to_string(), Expr::var("done")),
+                ],
+            }),
+            Stmt::assign(
+                Expr::var("q"),
+                Expr::binary(
+                    BinaryOp::Mul,
+                    Expr::binary(BinaryOp::Add, Expr::var("a"), Expr::int(1)),
+                    Expr::int(-2),
+                ),
+            ),
+        ]);
+        assert_eq!(
+            tick.to_st(),
+            "FUNCTION_BLOCK Blink\n\
+             \x20   timer(IN := NOT q, Q => done);\n\
+             \x20   q := (a + 1) * -2;\n\
+             END_FUNCTION_BLOCK\n"
+        );
+    }
+
+    #[test]
+    fn parse_st() {
+        use rust2plc::registry::RegistryItem;
+        use rust2plc::st::parser;
+
+        // what the macros render reads back into the same POU
+        for st in [
+            loops_plc().to_st(),
+            classify_plc().to_st(),
+            scale_plc().to_st(),
+        ] {
+            let items = parser::parse(&st).unwrap();
+            let [RegistryItem::StFn(function)] = items.as_slice() else {
+                panic!("{:?}", items)
+            };
+            assert_eq!(function.to_st(), st);
+        }
+
+        let legacy = "\
+            TYPE Valve : (Closed, Open := 4) := Open;\n\
+            END_TYPE\n\
+            (* Fills the tank *)\n\
+            function_block Filler\n\
+            var_input\n\
+            \x20   level, limit : REAL := 0.5; // litres\n\
+            \x20   delay : TIME := T#1m30s;\n\
+            end_var\n\
+            var_output valve : Valve; end_var\n\
+            var\n\
+            \x20   mask : BYTE := 16#0F;\n\
+            \x20   step : INT := INT#3;\n\
+            \x20   steps : ARRAY[0..2] OF INT := [2(1), 7];\n\
+            \x20   timer : TON;\n\
+            end_var\n\
+            timer(IN := level < limit, PT := delay);\n\
+            if timer.Q and not (level >= limit) then valve := Valve#Open; end_if\n\
+            case step of 1, 3..5: step := step + 1; ELSE step := 0; end_case\n\
+            end_function_block\n";
+        let items = parser::parse(legacy).unwrap();
+        let [RegistryItem::StType(valve), RegistryItem::StFb(filler)] = items.as_slice() else {
+            panic!("{:?}", items)
+        };
+        assert_eq!(
+            valve.to_st().unwrap(),
+            "TYPE Valve : (Closed, Open := 4) := Open;\nEND_TYPE\n"
+        );
+        assert_eq!(filler.description(), Some("Fills the tank"));
+        assert_eq!(filler.inputs()[1].typed_value(), &TypedValue::Real(0.5));
+        assert_eq!(
+            filler.inputs()[2].typed_value(),
+            &TypedValue::Time(Duration::from_secs(90))
+        );
+        assert_eq!(filler.vars()[0].typed_value(), &TypedValue::Byte(15));
+        assert_eq!(filler.vars()[1].typed_value(), &TypedValue::Int(3));
+        assert_eq!(
+            filler.vars()[2].typed_value().to_plc_literal().unwrap(),
+            "[1, 1, 7]"
+        );
+        // the printed source reads back into the same source
+        let printed = format!("{}{}", valve.to_st().unwrap(), filler.to_st());
+        let reparsed = parser::parse(&printed).unwrap();
+        let reprinted: Vec<String> = reparsed.iter().map(|i| i.to_st().unwrap()).collect();
+        assert_eq!(reprinted.concat(), printed);
+        assert_eq!(
+            filler.to_st(),
+            "(* Fills the tank *)\n\
+             FUNCTION_BLOCK Filler\n\
+             VAR_INPUT\n\
+             \x20   level : REAL;\n\
+             \x20   limit : REAL;\n\
+             \x20   delay : TIME;\n\
+             END_VAR\n\
+             VAR_OUTPUT\n\
+             \x20   valve : Valve;\n\
+             END_VAR\n\
+             VAR\n\
+             \x20   mask : BYTE;\n\
+             \x20   step : INT;\n\
+             \x20   steps : ARRAY[0..2] OF INT;\n\
+             \x20   timer : TON;\n\
+             END_VAR\n\
+             \x20   timer(IN := level < limit, PT := delay);\n\
+             \x20   IF timer.Q AND NOT (level >= limit) THEN\n\
+             \x20       valve := Valve#Open;\n\
+             \x20   END_IF;\n\
+             \x20   CASE step OF\n\
+             \x20       1, 3..5:\n\
+             \x20           step := step + 1;\n\
+             \x20       ELSE\n\
+             \x20           step := 0;\n\
+             \x20   END_CASE;\n\
+             END_FUNCTION_BLOCK\n"
+        );
+
+        let err = parser::parse("FUNCTION f : INT\n    f := 1 +;\nEND_FUNCTION\n").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "parse error at line 2, column 13: expected an expression, found `;`"
+        );
+        let err =
+            parser::parse("PROGRAM p\nVAR x : SINT := 300; END_VAR\nEND_PROGRAM").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "parse error at line 2, column 17: 300 is not a valid SINT value"
+        );
+        let err =
+            parser::parse("PROGRAM p\nVAR RETAIN count : DINT; END_VAR\nEND_PROGRAM").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "parse error at line 2, column 5: RETAIN variables are not supported"
+        );
+        let err = parser::parse("PROGRAM p\nVAR start AT %IX0.0 : BOOL; END_VAR\nEND_PROGRAM")
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "parse error at line 2, column 11: located variables are not supported, \
+             found start AT %IX0.0"
+        );
+
+        // arrays keep their lower bound, the Rust translation indexes from 0
+        let pick = "FUNCTION pick : INT\n\
+                    VAR_INPUT\n    i : INT;\nEND_VAR\n\
+                    VAR\n    table : ARRAY[1..3] OF INT;\nEND_VAR\n\
+                    \x20   pick := table[i] + table[1];\n\
+                    END_FUNCTION\n";
+        let items = parser::parse(pick).unwrap();
+        assert_eq!(items[0].to_st().unwrap(), pick);
+        let source = rust2plc::st::rust::translate(pick).unwrap();
+        assert!(source.code().contains("table[(i - 1) as usize] + table[0]"));
+    }
+    #[plc_fn(st)]
+    pub fn ramp(target: f32, steps: i16, pause: &mut std::time::Duration) -> f32 {
+        let mut acc: f32 = 0.0;
+        let mut samples: [f32; 4] = Default::default();
+        for i in 1..=steps {
+            acc += target / steps as f32;
+            samples[i as usize] = acc;
+            if acc >= target {
+                *pause = std::time::Duration::from_millis(1500);
+                break;
+            }
+        }
+        acc.min(target)
+    }
+
+    #[test]
+    fn st_to_rust() {
+        use rust2plc::st::rust;
+
+        let legacy = "\
+            FUNCTION Ramp : REAL\n\
+            VAR_INPUT\n\
+            \x20   Target : REAL;\n\
+            \x20   Steps : INT;\n\
+            END_VAR\n\
+            VAR_IN_OUT\n\
+            \x20   Pause : TIME;\n\
+            END_VAR\n\
+            VAR\n\
+            \x20   i : INT;\n\
+            \x20   Acc : REAL;\n\
+            \x20   Samples : ARRAY[0..3] OF REAL;\n\
+            END_VAR\n\
+            \x20   FOR i := 1 TO Steps DO\n\
+            \x20       Acc := Acc + Target / INT_TO_REAL(Steps);\n\
+            \x20       Samples[i] := Acc;\n\
+            \x20       IF Acc >= Target THEN\n\
+            \x20           Pause := T#1s500ms;\n\
+            \x20           EXIT;\n\
+            \x20       END_IF;\n\
+            \x20   END_FOR;\n\
+            \x20   Ramp := MIN(Acc, Target);\n\
+            END_FUNCTION\n";
+        let source = rust::translate(legacy).unwrap();
+        assert!(source.warnings().is_empty());
+        assert_eq!(
+            source.code(),
+            "#[plc_fn(st)]\n\
+             pub fn ramp(target: f32, steps: i16, pause: &mut std::time::Duration) -> f32 {\n\
+             \x20   let mut acc: f32 = 0.0;\n\
+             \x20   let mut samples: [f32; 4] = Default::default();\n\
+             \x20   for i in 1..=steps {\n\
+             \x20       acc += target / steps as f32;\n\
+             \x20       samples[i as usize] = acc;\n\
+             \x20       if acc >= target {\n\
+             \x20           *pause = std::time::Duration::from_millis(1500);\n\
+             \x20           break;\n\
+             \x20       }\n\
+             \x20   }\n\
+             \x20   acc.min(target)\n\
+             }\n"
+        );
+        // the translation above, compiled back through the macro
+        let mut pause = Duration::ZERO;
+        assert_eq!(ramp(3.0, 2, &mut pause), 3.0);
+        assert_eq!(pause, Duration::from_millis(1500));
+        assert_eq!(
+            ramp_plc().to_st(),
+            "FUNCTION ramp : REAL\n\
+             VAR_INPUT\n\
+             \x20   target : REAL;\n\
+             \x20   steps : INT;\n\
+             END_VAR\n\
+             VAR_IN_OUT\n\
+             \x20   pause : TIME;\n\
+             END_VAR\n\
+             VAR\n\
+             \x20   acc : REAL;\n\
+             \x20   samples : ARRAY[0..3] OF REAL;\n\
+             \x20   _tmp1 : DINT;\n\
+             \x20   i : INT;\n\
+             END_VAR\n\
+             \x20   acc := 0.0;\n\
+             \x20   FOR _tmp1 := 0 TO 3 DO\n\
+             \x20       samples[_tmp1] := 0.0;\n\
+             \x20   END_FOR;\n\
+             \x20   FOR i := 1 TO steps DO\n\
+             \x20       acc := acc + target / INT_TO_REAL(steps);\n\
+             \x20       samples[i] := acc;\n\
+             \x20       IF acc >= target THEN\n\
+             \x20           pause := T#1s500ms;\n\
+             \x20           EXIT;\n\
+             \x20       END_IF;\n\
+             \x20   END_FOR;\n\
+             \x20   ramp := MIN(acc, target);\n\
+             END_FUNCTION\n"
+        );
+
+        let source = rust::translate(
+            "TYPE Valve : (CLOSED, OPEN_WIDE := 4);\nEND_TYPE\n\
+             FUNCTION_BLOCK Filler\n\
+             VAR_INPUT Mode : Valve; END_VAR\n\
+             VAR_OUTPUT State : Valve := OPEN_WIDE; END_VAR\n\
+             VAR Timer : TON; Since : DATE := D#2024-01-01; END_VAR\n\
+             \x20   Timer(IN := Mode = CLOSED, PT := T#5s);\n\
+             \x20   CASE Mode OF CLOSED: State := Valve#OPEN_WIDE; OPEN_WIDE: State := CLOSED; END_CASE;\n\
+             END_FUNCTION_BLOCK\n",
+        )
+        .unwrap();
+        assert!(source.code().contains(
+            "#[plc_fb]\n\
+             impl Filler {\n\
+             \x20   pub fn cycle(&mut self) {\n\
+             \x20       self.timer.in_ = self.mode == Valve::Closed;\n\
+             \x20       self.timer.pt = std::time::Duration::from_millis(5000);\n\
+             \x20       self.timer.cycle();\n\
+             \x20       match self.mode {\n\
+             \x20           Valve::Closed => {\n\
+             \x20               self.state = Valve::OpenWide;\n\
+             \x20           }\n"
+        ));
+        assert_eq!(
+            source.warnings(),
+            [
+                "Filler: the date and time value D#2024-01-01 is not translated",
+                "Filler: the call of instance Timer is written as field assignments and `cycle()`, \
+                 which the PLC macros do not translate",
+            ]
+        );
+    }
+
+    /// Validates a PLCopen export against the TC6 XML 2.01 schema with `xmllint`.
+    fn validate_tc6(xml: &str) {
+        use std::io::Write;
+        use std::process::{Command, Stdio};
+
+        let schema = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/plcopen/tc6_xml_v201.xsd"
+        );
+        assert!(
+            std::path::Path::new(schema).exists(),
+            "{} is missing, download tc6_xml_v201.xsd from plcopen.org",
+            schema
+        );
+        let mut child = Command::new("xmllint")
+            .args(["--noout", "--schema", schema, "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("xmllint is required to validate the PLCopen exports");
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(xml.as_bytes())
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(
+            output.status.success(),
+            "the export does not validate against TC6 XML 2.01:\n{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    #[test]
+    #[ignore = "needs xmllint and the official tc6_xml_v201.xsd from plcopen.org \
+                in tests/fixtures/plcopen"]
+    fn tc6_schema() {
+        use rust2plc::langs::PLCLang;
+        use rust2plc::st::program::Program;
+
+        let exports = [
+            demo_project().to_xml().unwrap(),
+            codesys_project().to_xml().unwrap(),
+            codesys_project()
+                .with_qualified_only(true)
+                .to_xml()
+                .unwrap(),
+            PlcOpenProject::new("Demo")
+                .with_language(PLCLang::LadderDiagram)
+                .with_item(RegistryItem::StFb(latch()))
+                .with_item(RegistryItem::StFn(add_plc()))
+                .to_xml()
+                .unwrap(),
+            PlcOpenProject::new("Demo")
+                .with_language(PLCLang::FunctionBlockDiagram)
+                .with_item(RegistryItem::StFn(scaled_plc()))
+                .to_xml()
+                .unwrap(),
+            PlcOpenProject::new("Demo")
+                .with_item(RegistryItem::StProgram(
+                    Program::new("Batch").with_chart(batch_chart()),
+                ))
+                .to_xml()
+                .unwrap(),
+            PlcOpenProject::new("Demo")
+                .with_language(PLCLang::InstructionList)
+                .with_item(RegistryItem::StFb(RTrig::plc()))
+                .to_xml()
+                .unwrap(),
+        ];
+        for xml in exports {
+            validate_tc6(&xml);
+        }
+    }
+
+    fn demo_project() -> PlcOpenProject {
+        let created = chrono::NaiveDate::from_ymd_opt(2024, 1, 1)
+            .unwrap()
+            .and_hms_opt(8, 30, 0)
+            .unwrap();
+        let config = Configuration::new("Plant")
+            .with_global("alarm", TypedValue::new_bool())
+            .with_resource(
+                Resource::new("Cpu", "PLC")
+                    .with_task(Task::cyclic("Fast", Duration::from_millis(10), 1))
+                    .with_program("main", "Line", Some("Fast"))
+                    .with_program("idle", "housekeeping", None),
+            );
+        PlcOpenProject::new("Demo")
+            .with_created(created)
+            .with_item(RegistryItem::StType(Level::plc_type()))
+            .with_item(RegistryItem::StType(Point::plc_type()))
+            .with_item(RegistryItem::StFn(add_plc()))
+            .with_item(RegistryItem::StFb(RTrig::plc()))
+            .with_item(RegistryItem::StConfig(config))
+    }
+
+    #[test]
+    fn plcopen_xml() {
+        let xml = demo_project().to_xml().unwrap();
+        assert!(xml.starts_with(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <project xmlns=\"http://www.plcopen.org/xml/tc6_0201\" xmlns:xhtml=\"http://www.w3.org/1999/xhtml\">\n\
+             \x20 <fileHeader companyName=\"\" productName=\"rust2plc\" productVersion=\"0.1.0\" creationDateTime=\"2024-01-01T08:30:00\"/>\n\
+             \x20 <contentHeader name=\"Demo\">\n"
+        ));
+        assert!(xml.contains(
+            "      <dataType name=\"Level\">\n\
+             \x20       <baseType>\n\
+             \x20         <enum>\n\
+             \x20           <values>\n\
+             \x20             <value name=\"Low\" value=\"0\"/>\n\
+             \x20             <value name=\"High\" value=\"10\"/>\n\
+             \x20           </values>\n\
+             \x20           <baseType>\n\
+             \x20             <INT/>\n\
+             \x20           </baseType>\n\
+             \x20         </enum>\n\
+             \x20       </baseType>\n\
+             \x20       <initialValue>\n\
+             \x20         <simpleValue value=\"Low\"/>\n\
+             \x20       </initialValue>\n"
+        ));
+        assert!(xml.contains(
+            "            <variable name=\"label\">\n\
+             \x20             <type>\n\
+             \x20               <string/>\n\
+             \x20             </type>\n\
+             \x20             <initialValue>\n\
+             \x20               <simpleValue value=\"&apos;origin&apos;\"/>\n\
+             \x20             </initialValue>\n\
+             \x20           </variable>\n"
+        ));
+        assert!(xml.contains(
+            "      <pou name=\"add\" pouType=\"function\">\n\
+             \x20       <interface>\n\
+             \x20         <returnType>\n\
+             \x20           <ULINT/>\n\
+             \x20         </returnType>\n\
+             \x20         <inputVars>\n\
+             \x20           <variable name=\"left\">\n\
+             \x20             <type>\n\
+             \x20               <ULINT/>\n\
+             \x20             </type>\n\
+             \x20           </variable>\n\
+             \x20           <variable name=\"right\">\n\
+             \x20             <type>\n\
+             \x20               <ULINT/>\n\
+             \x20             </type>\n\
+             \x20           </variable>\n\
+             \x20         </inputVars>\n\
+             \x20       </interface>\n\
+             \x20       <body>\n\
+             \x20         <ST>\n\
+             \x20           <xhtml:p><![CDATA[add := left + right;]]></xhtml:p>\n\
+             \x20         </ST>\n\
+             \x20       </body>\n\
+             \x20       <addData>\n\
+             \x20         <data name=\"urn:rust2plc:metadata\" handleUnknown=\"preserve\">\n\
+             \x20           <metadata namespace=\"Math\" version=\"1.0\"/>\n\
+             \x20         </data>\n\
+             \x20       </addData>\n\
+             \x20       <documentation>\n\
+             \x20         <xhtml:p><![CDATA[Adds two numbers]]></xhtml:p>\n\
+             \x20       </documentation>\n\
+             \x20     </pou>\n"
+        ));
+        assert!(xml.contains("<pou name=\"RTrig\" pouType=\"functionBlock\">"));
+        assert!(xml.contains("<![CDATA[q := clk AND NOT mem;\nmem := clk;]]>"));
+        assert!(xml.ends_with(
+            "  <instances>\n\
+             \x20   <configurations>\n\
+             \x20     <configuration name=\"Plant\">\n\
+             \x20       <resource name=\"Cpu\">\n\
+             \x20         <task name=\"Fast\" interval=\"T#0s10ms\" priority=\"1\">\n\
+             \x20           <pouInstance name=\"main\" typeName=\"Line\"/>\n\
+             \x20         </task>\n\
+             \x20         <pouInstance name=\"idle\" typeName=\"housekeeping\"/>\n\
+             \x20       </resource>\n\
+             \x20       <globalVars>\n\
+             \x20         <variable name=\"alarm\">\n\
+             \x20           <type>\n\
+             \x20             <BOOL/>\n\
+             \x20           </type>\n\
+             \x20         </variable>\n\
+             \x20       </globalVars>\n\
+             \x20     </configuration>\n\
+             \x20   </configurations>\n\
+             \x20 </instances>\n\
+             </project>\n"
+        ));
+
+        let mut registry = PLCRegistry::new();
+        registry.register(RegistryItem::StFn(norm_plc()));
+        registry.register(RegistryItem::StType(Point::plc_type()));
+        let xml = registry.to_plcopen_xml("Geo").unwrap();
+        assert!(xml.find("<dataType name=\"Point\">") < xml.find("<pou name=\"norm\""));
+        assert!(xml.contains("<derived name=\"Point\"/>"));
+        assert!(add_plc()
+            .to_plcopen_xml()
+            .unwrap()
+            .contains("<contentHeader name=\"add\">"));
+    }
+
+    const VENDOR_XML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<project xmlns="http://www.plcopen.org/xml/tc6_0201" xmlns:xhtml="http://www.w3.org/1999/xhtml">
+  <fileHeader companyName="Acme" productName="IDE" productVersion="3.5" creationDateTime="2024-05-02T10:00:00"/>
+  <contentHeader name="Bottling">
+    <coordinateInfo>
+      <fbd><scaling x="1" y="1"/></fbd>
+      <ld><scaling x="1" y="1"/></ld>
+      <sfc><scaling x="1" y="1"/></sfc>
+    </coordinateInfo>
+  </contentHeader>
+  <types>
+    <dataTypes>
+      <dataType name="Recipe">
+        <baseType>
+          <struct>
+            <variable name="volume">
+              <type><REAL/></type>
+              <initialValue><simpleValue value="0.33"/></initialValue>
+            </variable>
+            <variable name="steps">
+              <type>
+                <array>
+                  <dimension lower="1" upper="4"/>
+                  <baseType><INT/></baseType>
+                </array>
+              </type>
+              <initialValue>
+                <arrayValue>
+                  <value repetitionValue="3"><simpleValue value="1"/></value>
+                  <value><simpleValue value="7"/></value>
+                </arrayValue>
+              </initialValue>
+            </variable>
+          </struct>
+        </baseType>
+        <documentation>
+          <xhtml:p>How a bottle is filled.</xhtml:p>
+          <xhtml:p>Volumes are in litres.</xhtml:p>
+        </documentation>
+      </dataType>
+    </dataTypes>
+    <pous>
+      <pou name="Filler" pouType="functionBlock">
+        <interface>
+          <inputVars>
+            <variable name="start"><type><BOOL/></type></variable>
+          </inputVars>
+          <outputVars>
+            <variable name="done"><type><BOOL/></type></variable>
+          </outputVars>
+          <localVars>
+            <variable name="recipe">
+              <type><derived name="Recipe"/></type>
+              <initialValue>
+                <structValue>
+                  <value member="volume"><simpleValue value="0.5"/></value>
+                </structValue>
+              </initialValue>
+            </variable>
+            <variable name="timeout">
+              <type><TIME/></type>
+              <initialValue><simpleValue value="T#90s"/></initialValue>
+            </variable>
+          </localVars>
+        </interface>
+        <body>
+          <ST>
+            <xhtml:p><![CDATA[IF start THEN
+    done := recipe.volume > 0.0;
+END_IF;]]></xhtml:p>
+          </ST>
+        </body>
+        <documentation><xhtml:p>Fills one bottle &amp; reports.</xhtml:p></documentation>
+      </pou>
+    </pous>
+  </types>
+  <instances>
+    <configurations>
+      <configuration name="Line">
+        <resource name="Cpu">
+          <task name="Main" interval="PT0.05S" priority="2">
+            <pouInstance name="filler" typeName="Filler"/>
+          </task>
+        </resource>
+      </configuration>
+    </configurations>
+  </instances>
+</project>
+"#;
+
+    #[test]
+    fn plcopen_import() {
+        let items = [
+            RegistryItem::StType(Level::plc_type()),
+            RegistryItem::StType(Point::plc_type()),
+            RegistryItem::StFn(add_plc()),
+            RegistryItem::StFb(RTrig::plc()),
+        ];
+        let xml = items
+            .iter()
+            .fold(PlcOpenProject::new("Demo"), |p, i| p.with_item(i.clone()))
+            .to_xml()
+            .unwrap();
+        let imported = plcopen::import::parse(&xml).unwrap();
+        assert_eq!(imported.len(), items.len());
+        for (original, imported) in items.iter().zip(imported.iter()) {
+            assert_eq!(original.to_st().unwrap(), imported.to_st().unwrap());
+        }
+
+        let items = plcopen::import::parse(VENDOR_XML).unwrap();
+        let names: Vec<_> = items.iter().map(|i| i.name()).collect();
+        assert_eq!(names, vec!["Recipe", "Filler", "Line"]);
+        let RegistryItem::StFb(filler) = &items[1] else {
+            panic!("expected a function block")
+        };
+        assert_eq!(filler.description(), Some("Fills one bottle & reports."));
+        let vars: Vec<_> = filler.vars().iter().map(|v| v.typed_value()).collect();
+        assert_eq!(
+            vars,
+            vec![
+                &TypedValue::Struct(
+                    "Recipe".to_string(),
+                    vec![
+                        ("volume".to_string(), TypedValue::Real(0.5)),
+                        (
+                            "steps".to_string(),
+                            TypedValue::Array(
+                                [1, 1, 1, 7].map(|v| Box::new(TypedValue::Int(v))).to_vec(),
+                                Box::new(TypedValue::new_int()),
+                                1,
+                            )
+                        ),
+                    ]
+                ),
+                &TypedValue::Time(Duration::from_secs(90)),
+            ]
+        );
+        assert_eq!(
+            items[0].to_st().unwrap(),
+            "(* How a bottle is filled.\n\
+             Volumes are in litres. *)\n\
+             TYPE Recipe :\n\
+             STRUCT\n\
+             \x20   volume : REAL := 0.330000;\n\
+             \x20   steps : ARRAY[1..4] OF INT := [1, 1, 1, 7];\n\
+             END_STRUCT\n\
+             END_TYPE\n"
+        );
+        assert!(filler
+            .to_st()
+            .ends_with("    IF start THEN\n        done := recipe.volume > 0.0;\n    END_IF;\nEND_FUNCTION_BLOCK\n"));
+        assert!(items[2]
+            .to_st()
+            .unwrap()
+            .contains("TASK Main(INTERVAL := T#0s50ms, PRIORITY := 2);\n        PROGRAM filler WITH Main : Filler;"));
+
+        let broken = VENDOR_XML.replace("done := recipe.volume > 0.0;", "done := ;");
+        assert_eq!(
+            plcopen::import::parse(&broken).unwrap_err().to_string(),
+            "parse error at line 69, column 13: expected an expression, found `;`"
+        );
+        let ld = VENDOR_XML.replace("<ST>", "<LD>").replace("</ST>", "</LD>");
+        assert_eq!(
+            plcopen::import::parse(&ld).unwrap_err().to_string(),
+            "parse error at line 67, column 11: the body of Filler is written in LD, only ST bodies are imported"
+        );
+    }
+
+    #[plc_fn(st, description = "Settle time of a valve", version = "1.2")]
+    fn settle_time(open: bool, slow: bool, raw: i16) -> Duration {
+        let gain: f32 = scale(raw, 0.5, open);
+        if !open || gain < 0.0 {
+            Duration::from_secs(0)
+        } else if slow {
+            Duration::from_secs(90)
+        } else {
+            Duration::from_millis(1500)
+        }
+    }
+
+    #[plc_fn(scl)]
+    fn settle_delay(slow: bool) -> Duration {
+        if slow {
+            Duration::from_secs(90)
+        } else {
+            Duration::from_millis(1500)
+        }
+    }
+
+    #[test]
+    fn scl() {
+        assert_eq!(settle_time(true, true, 4), Duration::from_secs(90));
+        let printer = SclPrinter::new().with_function(&scale_plc());
+        assert_eq!(
+            printer.function(&settle_time_plc()).unwrap(),
+            "FUNCTION \"settle_time\" : Time\n\
+             TITLE = Settle time of a valve\n\
+             { S7_Optimized_Access := 'TRUE' }\n\
+             VERSION : 1.2\n\
+             \x20  VAR_INPUT\n\
+             \x20     open : Bool;\n\
+             \x20     slow : Bool;\n\
+             \x20     raw : Int;\n\
+             \x20  END_VAR\n\
+             \x20  VAR_TEMP\n\
+             \x20     gain : Real;\n\
+             \x20  END_VAR\n\
+             \n\
+             BEGIN\n\
+             \x20  REGION settle_time\n\
+             \x20     #gain := \"scale\"(raw := #raw, gain := 0.5, enabled := #open);\n\
+             \x20     IF NOT #open OR #gain < 0.0 THEN\n\
+             \x20        #settle_time := T#0s;\n\
+             \x20     ELSIF #slow THEN\n\
+             \x20        #settle_time := T#1m30s;\n\
+             \x20     ELSE\n\
+             \x20        #settle_time := T#1s500ms;\n\
+             \x20     END_IF;\n\
+             \x20  END_REGION\n\
+             END_FUNCTION\n"
+        );
+        assert!(PLCRegistry::all().find(None, "settle_time", None).is_some());
+        assert_eq!(settle_delay(false), Duration::from_millis(1500));
+        assert!(SETTLE_DELAY_SCL.contains(
+            "   REGION settle_delay\n\
+             \x20     IF #slow THEN\n\
+             \x20        #settle_delay := T#1m30s;\n\
+             \x20     ELSE\n\
+             \x20        #settle_delay := T#1s500ms;\n\
+             \x20     END_IF;\n\
+             \x20  END_REGION\n"
+        ));
+        // calls of functions with several inputs need the printer knowing them
+        assert_eq!(
+            settle_time_plc().to_scl().unwrap_err().to_string(),
+            "scale is called with positional arguments, SCL needs formal ones, \
+             make it known with `SclPrinter::with_function`"
+        );
+
+        assert!(RTrig::plc().to_scl().unwrap().contains(
+            "   VAR\n      mem : Bool;\n   END_VAR\n\n\
+             BEGIN\n\
+             \x20  REGION RTrig\n\
+             \x20     #q := #clk AND NOT #mem;\n\
+             \x20     #mem := #clk;\n\
+             \x20  END_REGION\n\
+             END_FUNCTION_BLOCK\n"
+        ));
+        assert_eq!(
+            Point::plc_type().to_scl().unwrap(),
+            "TYPE \"Point\"\n\
+             VERSION : 0.1\n\
+             \x20  STRUCT\n\
+             \x20     x : Real;\n\
+             \x20     y : Real := 1.500000;\n\
+             \x20     label : String := 'origin';\n\
+             \x20  END_STRUCT;\n\
+             \n\
+             END_TYPE\n"
+        );
+        assert!(Mode::plc_type().to_scl().is_err());
+
+        let s5 = printer.clone().with_time(SclTime::S5Time);
+        let settle = s5.function(&settle_time_plc()).unwrap();
+        assert!(settle.starts_with("FUNCTION \"settle_time\" : S5Time\n"));
+        assert!(settle.contains("#settle_time := S5T#1s500ms;"));
+        assert_eq!(
+            s5.literal(&TypedValue::Time(Duration::from_millis(10_010)))
+                .unwrap_err()
+                .to_string(),
+            "T#10s10ms cannot be represented as S5Time"
+        );
+        assert_eq!(
+            SclPrinter::new()
+                .literal(&TypedValue::Time(Duration::from_secs(30 * 86400)))
+                .unwrap_err()
+                .to_string(),
+            "T#30d exceeds the range of Time, use LTime"
+        );
+        let start = chrono::NaiveDate::from_ymd_opt(2024, 1, 1)
+            .unwrap()
+            .and_hms_opt(8, 30, 0)
+            .unwrap();
+        let printer = SclPrinter::new();
+        assert_eq!(
+            printer.type_name(&TypedValue::DateTime(start)).unwrap(),
+            "DTL"
+        );
+        assert_eq!(
+            printer.literal(&TypedValue::DateTime(start)).unwrap(),
+            "DTL#2024-01-01-08:30:00"
+        );
+    }
+
+    #[test]
+    fn twincat_export() {
+        let dir = std::env::temp_dir().join(format!("plc-predicate-tc-{}", std::process::id()));
+        let config = Configuration::new("Plant").with_global("alarm", TypedValue::new_bool());
+        let mut registry = PLCRegistry::new();
+        registry.register(RegistryItem::StFn(add_plc()));
+        registry.register(RegistryItem::StFb(RTrig::plc()));
+        registry.register(RegistryItem::StType(Level::plc_type()));
+        registry.register(RegistryItem::StConfig(config));
+
+        let files = registry.export_twincat(&dir).unwrap();
+        let paths: Vec<_> = files.iter().map(|f| f.path().to_str().unwrap()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "GVLs/Plant.TcGVL",
+                "POUs/RTrig.TcPOU",
+                "DUTs/Geo/Level.TcDUT",
+                "POUs/Math/add.TcPOU"
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("POUs/Math/add.TcPOU")).unwrap(),
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <TcPlcObject Version=\"1.1.0.1\" ProductVersion=\"3.1.4024.12\">\n\
+             \x20 <POU Name=\"add\" Id=\"{915102a0-1bde-5ac3-bb75-f44970e1c0d9}\" SpecialFunc=\"None\">\n\
+             \x20   <Declaration><![CDATA[(* Adds two numbers *)\n\
+             (* namespace: Math, version: 1.0 *)\n\
+             FUNCTION add : ULINT\n\
+             VAR_INPUT\n\
+             \x20   left : ULINT;\n\
+             \x20   right : ULINT;\n\
+             END_VAR\n\
+             ]]></Declaration>\n\
+             \x20   <Implementation>\n\
+             \x20     <ST><![CDATA[add := left + right;]]></ST>\n\
+             \x20   </Implementation>\n\
+             \x20 </POU>\n\
+             </TcPlcObject>\n"
+        );
+        assert_eq!(
+            files[2].xml(),
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+             <TcPlcObject Version=\"1.1.0.1\" ProductVersion=\"3.1.4024.12\">\n\
+             \x20 <DUT Name=\"Level\" Id=\"{6285b454-de24-599a-9164-fcb022bd4666}\">\n\
+             \x20   <Declaration><![CDATA[(* namespace: Geo *)\n\
+             TYPE Level :\n\
+             (\n\
+             \x20   Low := 0,\n\
+             \x20   High := 10\n\
+             ) INT := Low;\n\
+             END_TYPE\n\
+             ]]></Declaration>\n\
+             \x20 </DUT>\n\
+             </TcPlcObject>\n"
+        );
+        assert!(files[0].xml().contains(
+            "<Declaration><![CDATA[VAR_GLOBAL\n    alarm : BOOL;\nEND_VAR\n]]></Declaration>"
+        ));
+        assert!(files[1]
+            .xml()
+            .contains("<ST><![CDATA[q := clk AND NOT mem;\nmem := clk;]]></ST>"));
+
+        // The ids only depend on the namespace and the name.
+        assert_eq!(files[1].id(), twincat::guid(None, "RTrig"));
+        assert_ne!(twincat::guid(Some("Geo"), "RTrig"), files[1].id());
+        assert_eq!(registry.export_twincat(&dir).unwrap(), files);
+        assert!(RegistryItem::StConfig(Configuration::new("Empty"))
+            .to_twincat()
+            .unwrap()
+            .is_none());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn l5x_export() {
+        let source = "\
+            TYPE Recipe :\n\
+            STRUCT\n\
+            \x20   speed : REAL := 1.5;\n\
+            \x20   name : STRING[20];\n\
+            \x20   dosing : BOOL;\n\
+            \x20   rinse : BOOL;\n\
+            END_STRUCT\n\
+            END_TYPE\n\
+            FUNCTION_BLOCK Debounce\n\
+            VAR_INPUT raw : BOOL; END_VAR\n\
+            VAR_OUTPUT q : BOOL; END_VAR\n\
+            VAR edge : RTrig; hold : TIME := T#50ms; END_VAR\n\
+            \x20   edge(clk := raw, q => q);\n\
+            END_FUNCTION_BLOCK\n\
+            FUNCTION clamp : REAL\n\
+            VAR_INPUT x : REAL; END_VAR\n\
+            \x20   IF x < 0.0 THEN clamp := ABS(x) * INT_TO_REAL(2); ELSE clamp := x + TIME_TO_DINT(T#1s); END_IF;\n\
+            END_FUNCTION\n";
+        let config = Configuration::new("Plant")
+            .with_global("alarm", TypedValue::new_bool())
+            .with_resource(
+                Resource::new("Cpu", "PLC")
+                    .with_task(Task::cyclic("Fast", Duration::from_millis(10), 1))
+                    .with_program("Line", "Line", Some("Fast")),
+            );
+        let exported = chrono::NaiveDate::from_ymd_opt(2024, 1, 1)
+            .unwrap()
+            .and_hms_opt(8, 30, 0)
+            .unwrap();
+        let project = rust2plc::st::parser::parse(source)
+            .unwrap()
+            .into_iter()
+            .fold(
+                L5xProject::new("Line1")
+                    .with_exported(exported)
+                    .with_item(RegistryItem::StFb(RTrig::plc())),
+                |p, item| p.with_item(item),
+            )
+            .with_item(RegistryItem::StProgram(Line::plc()))
+            .with_item(RegistryItem::StConfig(config));
+        let xml = project.to_xml().unwrap();
+        assert!(xml.contains(
+            "<Controller Use=\"Target\" Name=\"Line1\" ProcessorType=\"1756-L85E\" MajorRev=\"33\" MinorRev=\"0\">"
+        ));
+        assert!(xml.contains(
+            "\x20     <DataType Name=\"STRING20\" Family=\"StringFamily\" Class=\"User\">\n\
+             \x20       <Members>\n\
+             \x20         <Member Name=\"LEN\" DataType=\"DINT\" Dimension=\"0\" Radix=\"Decimal\" Hidden=\"false\" ExternalAccess=\"Read/Write\"/>\n\
+             \x20         <Member Name=\"DATA\" DataType=\"SINT\" Dimension=\"20\" Radix=\"ASCII\" Hidden=\"false\" ExternalAccess=\"Read/Write\"/>\n"
+        ));
+        assert!(xml.contains(
+            "\x20         <Member Name=\"ZZZZZZZZZZRecipe0\" DataType=\"SINT\" Dimension=\"0\" Radix=\"Decimal\" Hidden=\"true\" ExternalAccess=\"Read/Write\"/>\n\
+             \x20         <Member Name=\"dosing\" DataType=\"BIT\" Dimension=\"0\" Radix=\"Decimal\" Hidden=\"false\" Target=\"ZZZZZZZZZZRecipe0\" BitNumber=\"0\" ExternalAccess=\"Read/Write\"/>\n\
+             \x20         <Member Name=\"rinse\" DataType=\"BIT\" Dimension=\"0\" Radix=\"Decimal\" Hidden=\"false\" Target=\"ZZZZZZZZZZRecipe0\" BitNumber=\"1\" ExternalAccess=\"Read/Write\"/>\n"
+        ));
+        assert!(xml.contains(
+            "<Parameter Name=\"raw\" TagType=\"Base\" DataType=\"BOOL\" Usage=\"Input\" Radix=\"Decimal\" Required=\"true\" Visible=\"true\" ExternalAccess=\"Read/Write\"/>"
+        ));
+        assert!(xml.contains(
+            "\x20         <LocalTag Name=\"edge\" DataType=\"RTrig\" Radix=\"NullType\" ExternalAccess=\"None\"/>\n\
+             \x20         <LocalTag Name=\"hold\" DataType=\"DINT\" Radix=\"Decimal\" ExternalAccess=\"None\">\n\
+             \x20           <DefaultData Format=\"Decorated\">\n\
+             \x20             <DataValue DataType=\"DINT\" Radix=\"Decimal\" Value=\"50\"/>\n"
+        ));
+        assert!(xml.contains(
+            "\x20             <Line Number=\"0\"><![CDATA[RTrig(edge, raw);]]></Line>\n\
+             \x20             <Line Number=\"1\"><![CDATA[q := edge.q;]]></Line>\n"
+        ));
+        assert!(xml.contains(
+            "\x20     <Program Name=\"clamp\" TestEdits=\"false\" MainRoutineName=\"Logic\" Disabled=\"false\" UseAsFolder=\"false\">\n\
+             \x20       <Tags>\n\
+             \x20         <Tag Name=\"x\" TagType=\"Base\" DataType=\"REAL\" Radix=\"Float\" Constant=\"false\" ExternalAccess=\"Read/Write\" Usage=\"Input\"/>\n\
+             \x20         <Tag Name=\"clamp\" TagType=\"Base\" DataType=\"REAL\" Radix=\"Float\" Constant=\"false\" ExternalAccess=\"Read/Write\" Usage=\"Output\"/>\n\
+             \x20       </Tags>\n\
+             \x20       <Routines>\n\
+             \x20         <Routine Name=\"Logic\" Type=\"ST\">\n\
+             \x20           <STContent>\n\
+             \x20             <Line Number=\"0\"><![CDATA[IF x < 0.0 THEN]]></Line>\n\
+             \x20             <Line Number=\"1\"><![CDATA[    clamp := ABS(x) * 2;]]></Line>\n\
+             \x20             <Line Number=\"2\"><![CDATA[ELSE]]></Line>\n\
+             \x20             <Line Number=\"3\"><![CDATA[    clamp := x + 1000;]]></Line>\n\
+             \x20             <Line Number=\"4\"><![CDATA[END_IF;]]></Line>\n"
+        ));
+        assert!(xml.contains(
+            "\x20     <Task Name=\"Fast\" Type=\"PERIODIC\" Rate=\"10\" Priority=\"1\" Watchdog=\"500\" DisableUpdateOutputs=\"false\" InhibitTask=\"false\">\n\
+             \x20       <ScheduledPrograms>\n\
+             \x20         <ScheduledProgram Name=\"Line\"/>\n"
+        ));
+        assert!(xml.contains(
+            "<Tag Name=\"alarm\" TagType=\"Base\" DataType=\"BOOL\" Radix=\"Decimal\" Constant=\"false\" ExternalAccess=\"Read/Write\"/>"
+        ));
+
+        // `count` of Line is a UDINT, the unsigned types arrived with revision 32.
+        assert_eq!(
+            project
+                .clone()
+                .with_software_revision(30, 11)
+                .to_xml()
+                .unwrap_err()
+                .to_string(),
+            "UDINT needs firmware revision 32 or later, the controller runs v30.11"
+        );
+        let label = Function::new("label", TypedValue::new_wstring(None));
+        assert_eq!(
+            L5xProject::new("Line1")
+                .with_item(RegistryItem::StFn(label))
+                .to_xml()
+                .unwrap_err()
+                .to_string(),
+            "WSTRING has no Logix counterpart"
+        );
+    }
+
+    fn codesys_project() -> rust2plc::plcopen::codesys::CodesysProject {
+        use rust2plc::plcopen::codesys::{CodesysProject, LibraryReference};
+        use rust2plc::st::ast::{Expr, Stmt};
+
+        let created = chrono::NaiveDate::from_ymd_opt(2024, 1, 1)
+            .unwrap()
+            .and_hms_opt(8, 30, 0)
+            .unwrap();
+        let twice = Function::new("twice", TypedValue::new_ulint())
+            .with_input(rust2plc::var::Value::Input(
+                "x".to_string(),
+                TypedValue::new_ulint(),
+            ))
+            .with_body(vec![Stmt::assign(
+                Expr::var("twice"),
+                Expr::call("add", vec![Expr::var("x"), Expr::var("x")]),
+            )]);
+        CodesysProject::new("Demo")
+            .with_created(created)
+            .with_library(
+                LibraryReference::new("Math", "Math Utilities", "ACME").with_version("1.0.0.0"),
+            )
+            .with_item(RegistryItem::StFn(add_plc()))
+            .with_item(RegistryItem::StType(Level::plc_type()))
+            .with_item(RegistryItem::StFb(RTrig::plc()))
+            .with_item(RegistryItem::StFn(twice))
+    }
+
+    #[test]
+    fn codesys_export() {
+        let project = codesys_project();
+        let xml = project.clone().to_xml().unwrap();
+        assert!(!xml.contains("<pou name=\"add\""));
+        assert!(xml.contains("twice := add(x, x);"));
+        assert!(!xml.contains("qualified_only"));
+        assert!(xml.ends_with(
+            "  <addData>\n\
+             \x20   <data name=\"http://www.3s-software.com/plcopenxml/projectstructure\" handleUnknown=\"discard\">\n\
+             \x20     <ProjectStructure>\n\
+             \x20       <Object Name=\"RTrig\" ObjectId=\"074bc970-6190-5e94-b7ad-9a8568800690\"/>\n\
+             \x20       <Object Name=\"twice\" ObjectId=\"bc0948d2-41f7-53aa-81d5-00463d5e2bf6\"/>\n\
+             \x20       <Folder Name=\"Geo\">\n\
+             \x20         <Object Name=\"Level\" ObjectId=\"5b23cb7b-b7f6-5d75-b7d4-b8f8421aa421\"/>\n\
+             \x20       </Folder>\n\
+             \x20     </ProjectStructure>\n\
+             \x20   </data>\n\
+             \x20   <data name=\"http://www.3s-software.com/plcopenxml/libraries\" handleUnknown=\"implementation\">\n\
+             \x20     <Libraries>\n\
+             \x20       <Library Name=\"#Math Utilities\" Namespace=\"Math\" HideWhenReferencedAsDependency=\"false\" \
+             PublishSymbolsInContainer=\"false\" SystemLibrary=\"false\" LinkAllContent=\"false\" \
+             DefaultResolution=\"Math Utilities, 1.0.0.0 (ACME)\" QualifiedOnly=\"false\"/>\n\
+             \x20     </Libraries>\n\
+             \x20   </data>\n\
+             \x20 </addData>\n\
+             </project>\n"
+        ));
+
+        let xml = project.with_qualified_only(true).to_xml().unwrap();
+        assert!(xml.contains("twice := Math.add(x, x);"));
+        assert!(xml.contains("QualifiedOnly=\"true\""));
+        assert!(xml.contains(
+            "          <data name=\"http://www.3s-software.com/plcopenxml/attributes\" handleUnknown=\"implementation\">\n\
+             \x20           <Attributes>\n\
+             \x20             <Attribute Name=\"qualified_only\" Value=\"\"/>\n\
+             \x20           </Attributes>\n\
+             \x20         </data>\n"
+        ));
+    }
+
+    #[plc_fn(ld, description = "Motor permit")]
+    fn permit(auto: bool, manual: bool, fault: bool, estop: bool) -> bool {
+        (auto || manual) && !(fault || estop)
+    }
+
+    fn latch() -> rust2plc::st::function_block::FunctionBlock {
+        use rust2plc::st::ast::{Arg, Branch, Call, Expr, Stmt};
+        use rust2plc::st::function_block::FunctionBlock;
+        use rust2plc::var::Value;
+
+        let set_reset = |cond: Expr, value: bool| Stmt::If {
+            branches: vec![Branch {
+                cond,
+                body: vec![Stmt::assign(Expr::var("run"), Expr::bool(value))],
+            }],
+            otherwise: None,
+        };
+        FunctionBlock::new("Latch")
+            .with_input(Value::Input("start".to_string(), TypedValue::new_bool()))
+            .with_input(Value::Input("stop".to_string(), TypedValue::new_bool()))
+            .with_output(Value::Output("run".to_string(), TypedValue::new_bool()))
+            .with_var(Value::Local(
+                "edge".to_string(),
+                TypedValue::UserDefined("R_TRIG".to_string(), None),
+            ))
+            .with_body(vec![
+                Stmt::Call(Call {
+                    name: "edge".to_string(),
+                    args: vec![Arg::Input("CLK".to_string(), Expr::var("start"))],
+                }),
+                set_reset(
+                    Expr::Field(Box::new(Expr::var("edge")), "Q".to_string()),
+                    true,
+                ),
+                set_reset(Expr::var("stop"), false),
+            ])
+    }
+
+    #[test]
+    fn ladder() {
+        use rust2plc::langs::PLCLang;
+
+        assert!(permit(true, false, false, false));
+        assert_eq!(
+            PERMIT_LD,
+            "|-+-[auto]--+-[/fault]-[/estop]-(permit)-|\n\
+             | +-[manual]+                            |\n"
+        );
+        assert_eq!(
+            RTrig::plc().to_ladder().unwrap().to_ascii(),
+            "|-[clk]-[/mem]-(q)---|\n\
+             |                    |\n\
+             |-[clk]--------(mem)-|\n"
+        );
+
+        let latch = latch();
+        assert_eq!(
+            latch.to_ladder().unwrap().to_ascii(),
+            "|-[P start]-(S run)-|\n\
+             |                   |\n\
+             |-[stop]----(R run)-|\n"
+        );
+
+        let xml = PlcOpenProject::new("Demo")
+            .with_language(PLCLang::LadderDiagram)
+            .with_item(RegistryItem::StFb(latch))
+            .with_item(RegistryItem::StFn(add_plc()))
+            .to_xml()
+            .unwrap();
+        assert!(xml.contains(
+            "        <body>\n\
+             \x20         <LD>\n\
+             \x20           <leftPowerRail localId=\"1\">\n\
+             \x20             <position x=\"0\" y=\"0\"/>\n\
+             \x20             <connectionPointOut formalParameter=\"\"/>\n\
+             \x20           </leftPowerRail>\n\
+             \x20           <contact localId=\"2\" negated=\"false\" edge=\"rising\">\n\
+             \x20             <position x=\"60\" y=\"0\"/>\n\
+             \x20             <connectionPointIn>\n\
+             \x20               <connection refLocalId=\"1\"/>\n\
+             \x20             </connectionPointIn>\n\
+             \x20             <connectionPointOut/>\n\
+             \x20             <variable>start</variable>\n\
+             \x20           </contact>\n\
+             \x20           <coil localId=\"3\" negated=\"false\" storage=\"set\">\n\
+             \x20             <position x=\"120\" y=\"0\"/>\n\
+             \x20             <connectionPointIn>\n\
+             \x20               <connection refLocalId=\"2\"/>\n\
+             \x20             </connectionPointIn>\n\
+             \x20             <connectionPointOut/>\n\
+             \x20             <variable>run</variable>\n\
+             \x20           </coil>\n\
+             \x20           <rightPowerRail localId=\"4\">\n\
+             \x20             <position x=\"180\" y=\"0\"/>\n\
+             \x20             <connectionPointIn>\n\
+             \x20               <connection refLocalId=\"3\"/>\n\
+             \x20             </connectionPointIn>\n\
+             \x20           </rightPowerRail>\n\
+             \x20           <leftPowerRail localId=\"5\">\n\
+             \x20             <position x=\"0\" y=\"80\"/>\n"
+        ));
+        assert!(xml.contains("storage=\"reset\""));
+        // add is arithmetic and keeps its ST body.
+        assert!(xml.contains("<xhtml:p><![CDATA[add := left + right;]]></xhtml:p>"));
+    }
+
+    #[plc_fn(fbd)]
+    fn scaled(raw: i16, gain: i16, lo: i16, hi: i16) -> i16 {
+        (raw * gain + 10).clamp(lo, hi)
+    }
+
+    #[plc_fn(fbd)]
+    fn pick(manual: bool, setpoint: i16, auto: i16) -> i16 {
+        if manual {
+            setpoint
+        } else {
+            auto
+        }
+    }
+
+    #[test]
+    fn fbd() {
+        use rust2plc::langs::PLCLang;
+
+        assert_eq!(scaled(3, 2, 0, 100), 16);
+        assert_eq!(pick(false, 1, 2), 2);
+        let layout = |f: Function| -> Vec<String> {
+            f.to_fbd()
+                .unwrap()
+                .nodes()
+                .iter()
+                .map(|n| format!("{} {} {}:{}", n.id, n.label(), n.column, n.row))
+                .collect()
+        };
+        assert_eq!(
+            layout(scaled_plc()),
+            vec![
+                "1 lo 2:0",
+                "2 raw 0:1",
+                "3 gain 0:2",
+                "4 MUL 1:1",
+                "5 10 1:3",
+                "6 ADD 2:1",
+                "7 hi 2:4",
+                "8 LIMIT 3:0",
+                "9 scaled 4:0"
+            ]
+        );
+        assert_eq!(
+            layout(pick_plc()),
+            vec![
+                "1 manual 0:0",
+                "2 auto 0:1",
+                "3 setpoint 0:2",
+                "4 SEL 1:0",
+                "5 pick 2:0"
+            ]
+        );
+        assert!(SCALED_FBD.starts_with(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"690\" height=\"175\" \
+             viewBox=\"-15 -15 690 175\" font-family=\"monospace\" font-size=\"11\">\n"
+        ));
+        assert!(SCALED_FBD.contains(
+            "  <rect x=\"420\" y=\"0\" width=\"80\" height=\"90\"/>\n\
+             \x20 <text x=\"460\" y=\"49\" text-anchor=\"middle\">LIMIT</text>\n\
+             \x20 <text x=\"423\" y=\"18\" text-anchor=\"start\" class=\"pin\">MN</text>\n\
+             \x20 <text x=\"423\" y=\"48\" text-anchor=\"start\" class=\"pin\">IN</text>\n\
+             \x20 <text x=\"423\" y=\"78\" text-anchor=\"start\" class=\"pin\">MX</text>\n\
+             \x20 <text x=\"497\" y=\"18\" text-anchor=\"end\" class=\"pin\">OUT</text>\n"
+        ));
+        assert!(SCALED_FBD.contains("  <polyline points=\"380,135 400,135 400,75 420,75\"/>\n"));
+
+        let xml = PlcOpenProject::new("Demo")
+            .with_language(PLCLang::FunctionBlockDiagram)
+            .with_item(RegistryItem::StFn(scaled_plc()))
+            .to_xml()
+            .unwrap();
+        assert!(xml.contains(
+            "            <block localId=\"8\" typeName=\"LIMIT\" height=\"90\" width=\"80\">\n\
+             \x20             <position x=\"420\" y=\"0\"/>\n\
+             \x20             <inputVariables>\n\
+             \x20               <variable formalParameter=\"MN\">\n\
+             \x20                 <connectionPointIn>\n\
+             \x20                   <relPosition x=\"0\" y=\"15\"/>\n\
+             \x20                   <connection refLocalId=\"1\"/>\n\
+             \x20                 </connectionPointIn>\n\
+             \x20               </variable>\n\
+             \x20               <variable formalParameter=\"IN\">\n\
+             \x20                 <connectionPointIn>\n\
+             \x20                   <relPosition x=\"0\" y=\"45\"/>\n\
+             \x20                   <connection refLocalId=\"6\" formalParameter=\"OUT\"/>\n\
+             \x20                 </connectionPointIn>\n\
+             \x20               </variable>\n"
+        ));
+        assert!(xml.contains(
+            "            <outVariable localId=\"9\" height=\"20\" width=\"100\">\n\
+             \x20             <position x=\"560\" y=\"5\"/>\n\
+             \x20             <connectionPointIn>\n\
+             \x20               <relPosition x=\"0\" y=\"10\"/>\n\
+             \x20               <connection refLocalId=\"8\" formalParameter=\"OUT\"/>\n\
+             \x20             </connectionPointIn>\n\
+             \x20             <expression>scaled</expression>\n\
+             \x20           </outVariable>\n"
+        ));
+    }
+
+    fn batch_chart() -> rust2plc::sfc::Sfc {
+        use rust2plc::sfc::{Action, Qualifier, Sfc, Step, Transition};
+        use rust2plc::st::ast::{BinaryOp, Expr, Stmt};
+        use rust2plc_macro::plc_expr;
+        use std::time::Duration;
+
+        Sfc::new()
+            .with_step(Step::initial("Idle"))
+            .with_step(
+                Step::new("Fill")
+                    .with_action(Qualifier::N, "fill")
+                    .with_action(Qualifier::D(Duration::from_secs(30)), "alarm"),
+            )
+            .with_step(Step::new("HeatA").with_action(Qualifier::S, "heater_a"))
+            .with_step(Step::new("HeatB").with_action(Qualifier::P, "count"))
+            .with_step(Step::new("Drain").with_action(Qualifier::R, "heater_a"))
+            .with_transition(Transition::new(
+                ["Idle"],
+                ["Fill"],
+                plc_expr!(start && !stop),
+            ))
+            .with_transition(Transition::new(
+                ["Fill"],
+                ["HeatA", "HeatB"],
+                plc_expr!(level >= 80),
+            ))
+            .with_transition(Transition::new(
+                ["HeatA", "HeatB"],
+                ["Drain"],
+                plc_expr!(temp_a > 60.0 && temp_b > 60.0),
+            ))
+            .with_transition(
+                Transition::new(["Drain"], ["Idle"], plc_expr!(level == 0)).with_priority(1),
+            )
+            .with_transition(
+                Transition::new(["Drain"], ["Fill"], plc_expr!(restart)).with_priority(2),
+            )
+            .with_action(Action::new(
+                "fill",
+                vec![Stmt::assign(Expr::var("valve"), Expr::bool(true))],
+            ))
+            .with_action(Action::new(
+                "count",
+                vec![Stmt::assign(
+                    Expr::var("batches"),
+                    Expr::binary(BinaryOp::Add, Expr::var("batches"), Expr::int(1)),
+                )],
+            ))
+    }
+
+    #[test]
+    fn sfc() {
+        use rust2plc::plcopen::export::PlcOpenProject;
+        use rust2plc::registry::RegistryItem;
+        use rust2plc::sfc::Transition;
+        use rust2plc::st::program::Program;
+        use rust2plc::types::TypedValue;
+        use rust2plc::var::Value;
+        use rust2plc_macro::plc_expr;
+
+        let chart = batch_chart();
+        chart.validate().unwrap();
+        let batch = Program::new("Batch")
+            .with_input(Value::Input("start".to_string(), TypedValue::Bool(false)))
+            .with_var(Value::Local("batches".to_string(), TypedValue::DInt(0)))
+            .with_chart(chart.clone());
+        assert_eq!(
+            batch.to_st(),
+            "PROGRAM Batch\n\
+             VAR_INPUT\n\
+             \x20   start : BOOL;\n\
+             END_VAR\n\
+             VAR\n\
+             \x20   batches : DINT;\n\
+             END_VAR\n\
+             \x20   INITIAL_STEP Idle:\n\
+             \x20   END_STEP\n\
+             \n\
+             \x20   STEP Fill:\n\
+             \x20       fill(N);\n\
+             \x20       alarm(D, T#30s);\n\
+             \x20   END_STEP\n\
+             \n\
+             \x20   STEP HeatA:\n\
+             \x20       heater_a(S);\n\
+             \x20   END_STEP\n\
+             \n\
+             \x20   STEP HeatB:\n\
+             \x20       count(P);\n\
+             \x20   END_STEP\n\
+             \n\
+             \x20   STEP Drain:\n\
+             \x20       heater_a(R);\n\
+             \x20   END_STEP\n\
+             \n\
+             \x20   TRANSITION FROM Idle TO Fill := start AND NOT stop;\n\
+             \x20   END_TRANSITION\n\
+             \n\
+             \x20   TRANSITION FROM Fill TO (HeatA, HeatB) := level >= 80;\n\
+             \x20   END_TRANSITION\n\
+             \n\
+             \x20   TRANSITION FROM (HeatA, HeatB) TO Drain := temp_a > 60.0 AND temp_b > 60.0;\n\
+             \x20   END_TRANSITION\n\
+             \n\
+             \x20   TRANSITION (PRIORITY := 1) FROM Drain TO Idle := level = 0;\n\
+             \x20   END_TRANSITION\n\
+             \n\
+             \x20   TRANSITION (PRIORITY := 2) FROM Drain TO Fill := restart;\n\
+             \x20   END_TRANSITION\n\
+             \n\
+             \x20   ACTION fill:\n\
+             \x20       valve := TRUE;\n\
+             \x20   END_ACTION\n\
+             \n\
+             \x20   ACTION count:\n\
+             \x20       batches := batches + 1;\n\
+             \x20   END_ACTION\n\
+             END_PROGRAM\n"
+        );
+
+        let xml = PlcOpenProject::new("Demo")
+            .with_item(RegistryItem::StProgram(batch))
+            .to_xml()
+            .unwrap();
+        assert!(xml.contains(
+            "        <actions>\n\
+             \x20         <action name=\"fill\">\n\
+             \x20           <body>\n\
+             \x20             <ST>\n\
+             \x20               <xhtml:p><![CDATA[valve := TRUE;]]></xhtml:p>\n"
+        ));
+        assert!(xml.contains(
+            "            <step localId=\"2\" name=\"Fill\" initialStep=\"false\">\n\
+             \x20             <position x=\"0\" y=\"180\"/>\n\
+             \x20             <connectionPointIn>\n\
+             \x20               <connection refLocalId=\"14\"/>\n\
+             \x20             </connectionPointIn>\n\
+             \x20             <connectionPointOut/>\n\
+             \x20             <connectionPointOutAction/>\n\
+             \x20           </step>\n\
+             \x20           <actionBlock localId=\"15\">\n\
+             \x20             <position x=\"80\" y=\"180\"/>\n\
+             \x20             <connectionPointIn>\n\
+             \x20               <connection refLocalId=\"2\"/>\n\
+             \x20             </connectionPointIn>\n\
+             \x20             <action localId=\"0\" qualifier=\"N\">\n\
+             \x20               <relPosition x=\"0\" y=\"0\"/>\n\
+             \x20               <reference name=\"fill\"/>\n\
+             \x20             </action>\n\
+             \x20             <action localId=\"0\" qualifier=\"D\" duration=\"T#30s\">\n"
+        ));
+        assert!(xml.contains(
+            "            <simultaneousConvergence localId=\"10\">\n\
+             \x20             <position x=\"0\" y=\"420\"/>\n\
+             \x20             <connectionPointIn>\n\
+             \x20               <connection refLocalId=\"3\"/>\n\
+             \x20             </connectionPointIn>\n\
+             \x20             <connectionPointIn>\n\
+             \x20               <connection refLocalId=\"4\"/>\n\
+             \x20             </connectionPointIn>\n\
+             \x20             <connectionPointOut/>\n\
+             \x20           </simultaneousConvergence>\n"
+        ));
+        assert!(xml.contains(
+            "            <transition localId=\"13\" priority=\"2\">\n\
+             \x20             <position x=\"160\" y=\"660\"/>\n\
+             \x20             <connectionPointIn>\n\
+             \x20               <connection refLocalId=\"6\"/>\n\
+             \x20             </connectionPointIn>\n\
+             \x20             <connectionPointOut/>\n\
+             \x20             <condition>\n\
+             \x20               <inline name=\"\">\n\
+             \x20                 <ST>\n\
+             \x20                   <xhtml:p><![CDATA[restart]]></xhtml:p>\n"
+        ));
+
+        let broken = chart.with_transition(Transition::new(["Drain"], ["Rinse"], plc_expr!(rinse)));
+        assert_eq!(
+            broken.validate().unwrap_err().to_string(),
+            "a transition refers to the unknown step Rinse"
+        );
+    }
+
+    #[plc_fn(il)]
+    fn dose(level: i16, target: i16, gain: i16, enable: bool, hold: bool) -> i16 {
+        let mut steps = 0;
+        if enable && !(hold || level > target) {
+            steps = (target - level) * (gain + 1);
+        } else if level > target {
+            steps = -1;
+        }
+        while steps > 100 {
+            steps /= 2;
+        }
+        steps.clamp(-1, 100)
+    }
+
+    #[test]
+    fn il() {
+        use rust2plc::langs::PLCLang;
+        use rust2plc::st::ast::{Arg, BinaryOp, Call, Expr, Literal, Stmt, UnaryOp};
+        use rust2plc::st::function_block::FunctionBlock;
+        use rust2plc::var::Value;
+
+        assert_eq!(dose(10, 20, 1, true, false), 20);
+        assert_eq!(
+            DOSE_IL,
+            "FUNCTION dose : INT\n\
+             VAR_INPUT\n\
+             \x20   level : INT;\n\
+             \x20   target : INT;\n\
+             \x20   gain : INT;\n\
+             \x20   enable : BOOL;\n\
+             \x20   hold : BOOL;\n\
+             END_VAR\n\
+             VAR\n\
+             \x20   steps : INT;\n\
+             END_VAR\n\
+             \x20   LD     0\n\
+             \x20   ST     steps\n\
+             \x20   LD     enable\n\
+             \x20   ANDN(\n\
+             \x20       LD     hold\n\
+             \x20       OR(\n\
+             \x20           LD     level\n\
+             \x20           GT     target\n\
+             \x20       )\n\
+             \x20   )\n\
+             \x20   JMPCN  IF1_1\n\
+             \x20   LD     target\n\
+             \x20   SUB    level\n\
+             \x20   MUL(\n\
+             \x20       LD     gain\n\
+             \x20       ADD    1\n\
+             \x20   )\n\
+             \x20   ST     steps\n\
+             \x20   JMP    IF1_END\n\
+             IF1_1:\n\
+             \x20   LD     level\n\
+             \x20   GT     target\n\
+             \x20   JMPCN  IF1_END\n\
+             \x20   LD     -1\n\
+             \x20   ST     steps\n\
+             IF1_END:\n\
+             WHILE1:\n\
+             \x20   LD     steps\n\
+             \x20   GT     100\n\
+             \x20   JMPCN  WHILE1_END\n\
+             \x20   LD     steps\n\
+             \x20   DIV    2\n\
+             \x20   ST     steps\n\
+             \x20   JMP    WHILE1\n\
+             WHILE1_END:\n\
+             \x20   LD     -1\n\
+             \x20   LIMIT  steps, 100\n\
+             \x20   ST     dose\n\
+             END_FUNCTION\n"
+        );
+        assert!(RTrig::plc().to_il().unwrap().ends_with(
+            "    LD     clk\n\
+             \x20   ANDN   mem\n\
+             \x20   ST     q\n\
+             \x20   LD     clk\n\
+             \x20   ST     mem\n\
+             END_FUNCTION_BLOCK\n"
+        ));
+
+        let pulse = FunctionBlock::new("Pulse")
+            .with_input(Value::Input("run".to_string(), TypedValue::new_bool()))
+            .with_input(Value::Input("fault".to_string(), TypedValue::new_bool()))
+            .with_output(Value::Output("done".to_string(), TypedValue::new_bool()))
+            .with_var(Value::Local(
+                "timer".to_string(),
+                TypedValue::UserDefined("TON".to_string(), None),
+            ))
+            .with_body(vec![Stmt::Call(Call {
+                name: "timer".to_string(),
+                args: vec![
+                    Arg::Input(
+                        "IN".to_string(),
+                        Expr::binary(
+                            BinaryOp::And,
+                            Expr::var("run"),
+                            Expr::unary(UnaryOp::Not, Expr::var("fault")),
+                        ),
+                    ),
+                    Arg::Input(
+                        "PT".to_string(),
+                        Expr::Literal(Literal::Typed {
+                            ty: "T".to_string(),
+                            value: "5s".to_string(),
+                        }),
+                    ),
+                    Arg::Output("Q".to_string(), Expr::var("done")),
+                ],
+            })]);
+        assert!(pulse.to_il().unwrap().ends_with(
+            "    LD     run\n\
+             \x20   ANDN   fault\n\
+             \x20   ST     timer.IN\n\
+             \x20   CAL    timer(PT := T#5s)\n\
+             \x20   LD     timer.Q\n\
+             \x20   ST     done\n\
+             END_FUNCTION_BLOCK\n"
+        ));
+        let xml = PlcOpenProject::new("Demo")
+            .with_language(PLCLang::InstructionList)
+            .with_item(RegistryItem::StFb(RTrig::plc()))
+            .to_xml()
+            .unwrap();
+        assert!(xml.contains(
+            "          <IL>\n\
+             \x20           <xhtml:p><![CDATA[LD     clk\n\
+             ANDN   mem\n\
+             ST     q\n\
+             LD     clk\n\
+             ST     mem]]></xhtml:p>\n\
+             \x20         </IL>\n"
+        ));
+
+        let positional =
+            Function::new("bad", TypedValue::new_bool()).with_body(vec![Stmt::Call(Call {
+                name: "timer".to_string(),
+                args: vec![Arg::Value(Expr::var("run"))],
+            })]);
+        assert_eq!(
+            positional.to_il().unwrap_err().to_string(),
+            "timer is invoked with positional arguments, CAL needs formal ones"
+        );
+    }
+
+    #[plc_fn(awl, block = "FC10", description = "Dosing steps")]
+    fn dose_steps(level: i16, target: i16, gain: i16, enable: bool) -> i16 {
+        let mut steps: i16 = 0;
+        if enable && (level < target || gain > 10) {
+            steps = (target - level) * (gain + 1);
+        }
+        while steps > 100 {
+            steps /= 2;
+        }
+        steps
+    }
+
+    #[plc_program(awl, block = "FB5", description = "Filling station")]
+    #[derive(Default)]
+    struct Station {
+        #[input]
+        level: i16,
+        #[input]
+        stop: bool,
+        #[output]
+        valve: bool,
+        fills: i16,
+    }
+
+    #[plc_program]
+    impl Station {
+        fn cycle(&mut self) {
+            self.valve = self.level < 80 && !self.stop;
+            if self.valve {
+                self.fills += 1;
+            }
+        }
+    }
+
+    /// The project of the STL and MC7 tests: a UDT, a function, two function blocks, one
+    /// with a timer and a call, and a program with its data block and OB 1.
+    fn awl_project() -> rust2plc::awl::AwlProject {
+        use rust2plc::awl::{AwlProject, BlockKind, BlockNumber};
+        use rust2plc::st::ast::{Arg, BinaryOp, Call, Expr, Literal, Stmt, UnaryOp};
+        use rust2plc::st::function_block::FunctionBlock;
+        use rust2plc::var::Value;
+
+        let batch = FunctionBlock::new("Batch")
+            .with_input(Value::Input("run".to_string(), TypedValue::new_bool()))
+            .with_input(Value::Input("level".to_string(), TypedValue::new_int()))
+            .with_output(Value::Output("done".to_string(), TypedValue::new_bool()))
+            .with_output(Value::Output("steps".to_string(), TypedValue::new_dint()))
+            .with_var(Value::Local(
+                "timer".to_string(),
+                TypedValue::new_user_defined("TON"),
+            ))
+            .with_body(vec![
+                Stmt::Call(Call {
+                    name: "timer".to_string(),
+                    args: vec![
+                        Arg::Input(
+                            "IN".to_string(),
+                            Expr::binary(
+                                BinaryOp::And,
+                                Expr::var("run"),
+                                Expr::unary(UnaryOp::Not, Expr::var("done")),
+                            ),
+                        ),
+                        Arg::Input(
+                            "PT".to_string(),
+                            Expr::Literal(Literal::Typed {
+                                ty: "T".to_string(),
+                                value: "5s".to_string(),
+                            }),
+                        ),
+                    ],
+                }),
+                Stmt::assign(
+                    Expr::var("done"),
+                    Expr::Field(Box::new(Expr::var("timer")), "Q".to_string()),
+                ),
+                Stmt::assign(
+                    Expr::var("steps"),
+                    Expr::binary(
+                        BinaryOp::Add,
+                        Expr::call(
+                            "dose_steps",
+                            vec![
+                                Expr::var("level"),
+                                Expr::int(50),
+                                Expr::binary(BinaryOp::Mul, Expr::var("level"), Expr::int(2)),
+                                Expr::bool(true),
+                            ],
+                        ),
+                        Expr::int(1),
+                    ),
+                ),
+            ]);
+        AwlProject::new()
+            .with_item(RegistryItem::StType(Point::plc_type()))
+            .with_item(RegistryItem::StFn(dose_steps_plc()))
+            .with_item(RegistryItem::StFb(RTrig::plc()))
+            .with_item(RegistryItem::StFb(batch))
+            .with_item(RegistryItem::StProgram(Station::plc()))
+            .with_block("RTrig", BlockNumber::new(BlockKind::Fb, 2))
+    }
+
+    #[test]
+    fn awl() {
+        use rust2plc::awl::{BlockKind, BlockNumber};
+
+        assert_eq!(dose_steps(10, 20, 1, true), 20);
+        let mut station = Station {
+            level: 50,
+            ..Default::default()
+        };
+        station.cycle();
+        assert!(station.valve && !station.stop);
+        assert_eq!(station.fills, 1);
+        assert_eq!(
+            DOSE_STEPS_AWL,
+            "FUNCTION FC 10 : INT\n\
+             TITLE = Dosing steps\n\
+             VERSION : 0.1\n\
+             \n\
+             VAR_INPUT\n\
+             \x20 level : INT;\n\
+             \x20 target : INT;\n\
+             \x20 gain : INT;\n\
+             \x20 enable : BOOL;\n\
+             END_VAR\n\
+             VAR_TEMP\n\
+             \x20 steps : INT;\n\
+             \x20 tmp1 : INT;\n\
+             END_VAR\n\
+             BEGIN\n\
+             NETWORK\n\
+             TITLE = steps := 0;\n\
+             \x20     L     0;\n\
+             \x20     T     #steps;\n\
+             NETWORK\n\
+             TITLE = IF enable AND (level < target OR gain > 10) THEN\n\
+             \x20     A     #enable;\n\
+             \x20     A(    ;\n\
+             \x20     O(    ;\n\
+             \x20     L     #level;\n\
+             \x20     L     #target;\n\
+             \x20     <I    ;\n\
+             \x20     )     ;\n\
+             \x20     O(    ;\n\
+             \x20     L     #gain;\n\
+             \x20     L     10;\n\
+             \x20     >I    ;\n\
+             \x20     )     ;\n\
+             \x20     )     ;\n\
+             \x20     JCN   J001;\n\
+             \x20     L     #gain;\n\
+             \x20     L     1;\n\
+             \x20     +I    ;\n\
+             \x20     T     #tmp1;\n\
+             \x20     L     #target;\n\
+             \x20     L     #level;\n\
+             \x20     -I    ;\n\
+             \x20     L     #tmp1;\n\
+             \x20     *I    ;\n\
+             \x20     T     #steps;\n\
+             J001: NOP   0;\n\
+             NETWORK\n\
+             TITLE = WHILE steps > 100 DO\n\
+             J002: NOP   0;\n\
+             \x20     L     #steps;\n\
+             \x20     L     100;\n\
+             \x20     >I    ;\n\
+             \x20     JCN   J003;\n\
+             \x20     L     #steps;\n\
+             \x20     L     2;\n\
+             \x20     /I    ;\n\
+             \x20     T     #steps;\n\
+             \x20     JU    J002;\n\
+             J003: NOP   0;\n\
+             NETWORK\n\
+             TITLE = dose_steps := steps;\n\
+             \x20     L     #steps;\n\
+             \x20     T     #RET_VAL;\n\
+             END_FUNCTION\n"
+        );
+        assert_eq!(dose_steps_plc().block(), Some("FC10"));
+        assert_eq!(Station::plc().block(), Some("FB5"));
+
+        let project = awl_project();
+        let awl = project.to_awl().unwrap();
+        assert!(awl.starts_with(
+            "TYPE UDT 1\n\
+             TITLE = A point on the plane\n\
+             VERSION : 0.1\n\
+             \n\
+             \x20 STRUCT\n\
+             \x20   x : REAL;\n\
+             \x20   y : REAL := 1.500000e+000;\n\
+             \x20   label : STRING [254] := 'origin';\n\
+             \x20 END_STRUCT ;\n\
+             END_TYPE\n\
+             \n\
+             FUNCTION FC 10 : INT\n"
+        ));
+        assert!(awl.contains("FUNCTION_BLOCK FB 2\nTITLE = Rising edge detector\n"));
+        assert!(awl.contains(
+            "FUNCTION_BLOCK FB 1\n\
+             VERSION : 0.1\n\
+             \n\
+             VAR_INPUT\n\
+             \x20 run : BOOL;\n\
+             \x20 level : INT;\n\
+             END_VAR\n\
+             VAR_OUTPUT\n\
+             \x20 done : BOOL;\n\
+             \x20 steps : DINT;\n\
+             END_VAR\n\
+             VAR\n\
+             \x20 timer : SFB 4;\n\
+             END_VAR\n\
+             VAR_TEMP\n\
+             \x20 tmp1 : BOOL;\n\
+             \x20 tmp2 : INT;\n\
+             \x20 tmp3 : INT;\n\
+             END_VAR\n\
+             BEGIN\n\
+             NETWORK\n\
+             TITLE = timer(IN := run AND NOT done, PT := T#5s);\n\
+             \x20     A     #run;\n\
+             \x20     AN    #done;\n\
+             \x20     =     #tmp1;\n\
+             \x20     CALL  #timer (IN := #tmp1, PT := T#5s);\n\
+             NETWORK\n\
+             TITLE = done := timer.Q;\n\
+             \x20     A     #timer.Q;\n\
+             \x20     =     #done;\n\
+             NETWORK\n\
+             TITLE = steps := dose_steps(level, 50, level * 2, TRUE) + 1;\n\
+             \x20     L     #level;\n\
+             \x20     L     2;\n\
+             \x20     *I    ;\n\
+             \x20     T     #tmp2;\n\
+             \x20     CALL  FC 10 (level := #level, target := 50, gain := #tmp2, enable := TRUE, RET_VAL := #tmp3);\n\
+             \x20     L     #tmp3;\n\
+             \x20     L     1;\n\
+             \x20     +I    ;\n\
+             \x20     ITD   ;\n\
+             \x20     T     #steps;\n\
+             END_FUNCTION_BLOCK\n"
+        ));
+        assert!(awl.contains(
+            "NETWORK\n\
+             TITLE = valve := level < 80 AND NOT stop;\n\
+             \x20     A(    ;\n\
+             \x20     L     #level;\n\
+             \x20     L     80;\n\
+             \x20     <I    ;\n\
+             \x20     )     ;\n\
+             \x20     AN    #stop;\n\
+             \x20     =     #valve;\n"
+        ));
+        assert!(awl.ends_with(
+            "DATA_BLOCK DB 5\n\
+             TITLE = Filling station\n\
+             VERSION : 0.1\n\
+             \n\
+             \x20 FB 5\n\
+             BEGIN\n\
+             END_DATA_BLOCK\n\
+             \n\
+             ORGANIZATION_BLOCK OB 1\n\
+             TITLE = Main program cycle\n\
+             VERSION : 0.1\n\
+             \n\
+             VAR_TEMP\n\
+             \x20 OB1_EV_CLASS : BYTE;\n\
+             \x20 OB1_SCAN_1 : BYTE;\n\
+             \x20 OB1_PRIORITY : BYTE;\n\
+             \x20 OB1_OB_NUMBR : BYTE;\n\
+             \x20 OB1_RESERVED_1 : BYTE;\n\
+             \x20 OB1_RESERVED_2 : BYTE;\n\
+             \x20 OB1_PREV_CYCLE : INT;\n\
+             \x20 OB1_MIN_CYCLE : INT;\n\
+             \x20 OB1_MAX_CYCLE : INT;\n\
+             \x20 OB1_DATE_TIME : DATE_AND_TIME;\n\
+             END_VAR\n\
+             BEGIN\n\
+             NETWORK\n\
+             TITLE = Station\n\
+             \x20     CALL  FB 5 , DB 5;\n\
+             END_ORGANIZATION_BLOCK\n"
+        ));
+
+        assert_eq!(
+            "fc 7".parse::<BlockNumber>().unwrap(),
+            BlockNumber::new(BlockKind::Fc, 7)
+        );
+        assert_eq!(
+            project
+                .clone()
+                .with_block("Batch", BlockNumber::new(BlockKind::Fb, 5))
+                .to_awl()
+                .unwrap_err()
+                .to_string(),
+            "FB 5 is assigned to both Batch and Station"
+        );
+        assert_eq!(
+            project
+                .with_instance_db("Station", BlockNumber::new(BlockKind::Fc, 5))
+                .to_awl()
+                .unwrap_err()
+                .to_string(),
+            "the instance of Station is a data block, not FC 5"
+        );
+        assert_eq!(
+            add_plc().to_awl().unwrap_err().to_string(),
+            "ULINT has no S7-300/400 counterpart"
+        );
+    }
+
+    #[plc_fn(st)]
+    fn saturate(raw: i32, gain: f32) -> i16 {
+        let clipped = raw.clamp(i16::MIN as i32, i16::MAX as i32);
+        if gain >= f32::MAX {
+            return u8::MAX as i16;
+        }
+        clipped as i16
+    }
+
+    #[test]
+    fn numeric_constants() {
+        assert_eq!(
+            SATURATE_ST,
+            "FUNCTION saturate : INT\n\
+             VAR_INPUT\n    raw : DINT;\n    gain : REAL;\nEND_VAR\n\
+             VAR\n    clipped : DINT;\nEND_VAR\n\
+             \x20   clipped := LIMIT(INT_TO_DINT(-32768), raw, INT_TO_DINT(32767));\n\
+             \x20   IF gain >= 3.4028234663852886E38 THEN\n\
+             \x20       saturate := USINT_TO_INT(255);\n\
+             \x20       RETURN;\n\
+             \x20   END_IF;\n\
+             \x20   saturate := DINT_TO_INT(clipped);\n\
+             END_FUNCTION\n"
+        );
+        assert_eq!(saturate(70000, 1.0), 32767);
+    }
+
+    #[plc_fn(st)]
+    fn skip_odd(limit: i16) -> i16 {
+        let mut n: i16 = 0;
+        let mut sum: i16 = 0;
+        loop {
+            n += 1;
+            if n % 2 == 1 {
+                continue;
+            }
+            sum += n;
+            if n >= limit {
+                break;
+            }
+        }
+        sum
+    }
+
+    #[test]
+    fn loop_with_continue() {
+        assert_eq!(skip_odd(6), 12);
+        assert_eq!(
+            skip_odd_plc().to_st(),
+            "FUNCTION skip_odd : INT\n\
+             VAR_INPUT\n    limit : INT;\nEND_VAR\n\
+             VAR\n    n : INT;\n    sum : INT;\nEND_VAR\n\
+             \x20   n := 0;\n\
+             \x20   sum := 0;\n\
+             \x20   WHILE TRUE DO\n\
+             \x20       n := n + 1;\n\
+             \x20       IF n MOD 2 = 1 THEN\n\
+             \x20           CONTINUE;\n\
+             \x20       END_IF;\n\
+             \x20       sum := sum + n;\n\
+             \x20       IF n >= limit THEN\n\
+             \x20           EXIT;\n\
+             \x20       END_IF;\n\
+             \x20   END_WHILE;\n\
+             \x20   skip_odd := sum;\n\
+             END_FUNCTION\n"
+        );
+    }
+
+    mod math {
+        pub fn add(left: i16, right: i16) -> i16 {
+            left + right
+        }
+    }
+
+    mod other {
+        pub fn add(left: i16, right: i16) -> i16 {
+            left - right
+        }
+    }
+
+    #[plc_fn(st)]
+    fn offsets(value: i16) -> i16 {
+        math::add(value, self::other::add(value, 1))
+    }
+
+    #[test]
+    fn module_calls() {
+        assert_eq!(offsets(5), 9);
+        assert_eq!(
+            OFFSETS_ST,
+            "FUNCTION offsets : INT\n\
+             VAR_INPUT\n    value : INT;\nEND_VAR\n\
+             \x20   offsets := math.add(value, other.add(value, 1));\n\
+             END_FUNCTION\n"
+        );
+    }
+
+    #[plc_fn(st)]
+    pub fn sums(step: i16) -> i16 {
+        let mut n: i16 = 0;
+        let mut acc: i16 = 0;
+        {
+            let mut i: i16 = 10;
+            while i >= 1 {
+                acc += i;
+                i -= 3;
+            }
+        }
+        for i in (0..=8).step_by(4) {
+            acc += i;
+        }
+        {
+            let mut i: i16 = 0;
+            while i <= 9 {
+                if i == 4 {
+                    i += 2;
+                }
+                acc += i;
+                i += 1;
+            }
+        }
+        {
+            let i_step = step;
+            let i_end = 6;
+            let mut i = 0;
+            while (i_step > 0 && i <= i_end) || (i_step < 0 && i >= i_end) {
+                if i == 2 {
+                    i += i_step;
+                    continue;
+                }
+                acc += i;
+                i += i_step;
+            }
+        }
+        loop {
+            n += 1;
+            if n % 2 == 0 {
+                if n >= 5 {
+                    break;
+                }
+                continue;
+            }
+            if n > 100 {
+                break;
+            }
+            acc += 1;
+            if n >= 5 {
+                break;
+            }
+        }
+        acc
+    }
+
+    #[test]
+    fn st_to_rust_loops() {
+        use rust2plc::st::rust;
+
+        let legacy = "\
+            FUNCTION Sums : INT\n\
+            VAR_INPUT\n    Step : INT;\nEND_VAR\n\
+            VAR\n    i : INT;\n    n : INT;\n    Acc : INT;\nEND_VAR\n\
+            \x20   FOR i := 10 TO 1 BY -3 DO\n\
+            \x20       Acc := Acc + i;\n\
+            \x20   END_FOR;\n\
+            \x20   FOR i := 0 TO 8 BY 4 DO\n\
+            \x20       Acc := Acc + i;\n\
+            \x20   END_FOR;\n\
+            \x20   FOR i := 0 TO 9 DO\n\
+            \x20       IF i = 4 THEN\n\
+            \x20           i := i + 2;\n\
+            \x20       END_IF;\n\
+            \x20       Acc := Acc + i;\n\
+            \x20   END_FOR;\n\
+            \x20   FOR i := 0 TO 6 BY Step DO\n\
+            \x20       IF i = 2 THEN\n\
+            \x20           CONTINUE;\n\
+            \x20       END_IF;\n\
+            \x20       Acc := Acc + i;\n\
+            \x20   END_FOR;\n\
+            \x20   REPEAT\n\
+            \x20       n := n + 1;\n\
+            \x20       IF n MOD 2 = 0 THEN\n\
+            \x20           CONTINUE;\n\
+            \x20       END_IF;\n\
+            \x20       IF n > 100 THEN\n\
+            \x20           EXIT;\n\
+            \x20       END_IF;\n\
+            \x20       Acc := Acc + 1;\n\
+            \x20   UNTIL n >= 5\n\
+            \x20   END_REPEAT;\n\
+            \x20   Sums := Acc;\n\
+            END_FUNCTION\n";
+        let source = rust::translate(legacy).unwrap();
+        assert!(source.warnings().is_empty());
+        // CONTINUE runs the step or the UNTIL check first, the macro takes no labels
+        assert_eq!(
+            source.code(),
+            "#[plc_fn(st)]\n\
+             pub fn sums(step: i16) -> i16 {\n\
+             \x20   let mut n: i16 = 0;\n\
+             \x20   let mut acc: i16 = 0;\n\
+             \x20   {\n\
+             \x20       let mut i: i16 = 10;\n\
+             \x20       while i >= 1 {\n\
+             \x20           acc += i;\n\
+             \x20           i -= 3;\n\
+             \x20       }\n\
+             \x20   }\n\
+             \x20   for i in (0..=8).step_by(4) {\n\
+             \x20       acc += i;\n\
+             \x20   }\n\
+             \x20   {\n\
+             \x20       let mut i: i16 = 0;\n\
+             \x20       while i <= 9 {\n\
+             \x20           if i == 4 {\n\
+             \x20               i += 2;\n\
+             \x20           }\n\
+             \x20           acc += i;\n\
+             \x20           i += 1;\n\
+             \x20       }\n\
+             \x20   }\n\
+             \x20   {\n\
+             \x20       let i_step = step;\n\
+             \x20       let i_end = 6;\n\
+             \x20       let mut i = 0;\n\
+             \x20       while (i_step > 0 && i <= i_end) || (i_step < 0 && i >= i_end) {\n\
+             \x20           if i == 2 {\n\
+             \x20               i += i_step;\n\
+             \x20               continue;\n\
+             \x20           }\n\
+             \x20           acc += i;\n\
+             \x20           i += i_step;\n\
+             \x20       }\n\
+             \x20   }\n\
+             \x20   loop {\n\
+             \x20       n += 1;\n\
+             \x20       if n % 2 == 0 {\n\
+             \x20           if n >= 5 {\n\
+             \x20               break;\n\
+             \x20           }\n\
+             \x20           continue;\n\
+             \x20       }\n\
+             \x20       if n > 100 {\n\
+             \x20           break;\n\
+             \x20       }\n\
+             \x20       acc += 1;\n\
+             \x20       if n >= 5 {\n\
+             \x20           break;\n\
+             \x20       }\n\
+             \x20   }\n\
+             \x20   acc\n\
+             }\n"
+        );
+        // the translation above, compiled back through the macro
+        assert_eq!(sums(2), 83);
+
+        // an unsigned counter stops before it would wrap below the end
+        let down = rust::translate(
+            "FUNCTION Down : UINT\nVAR_INPUT Low : UINT; END_VAR\nVAR k : UINT; END_VAR\n\
+             \x20   FOR k := 9 TO Low BY -2 DO\n        Down := Down + k;\n    END_FOR;\n\
+             END_FUNCTION\n",
+        )
+        .unwrap();
+        assert!(down.code().contains(
+            "        while k >= low {\n\
+             \x20           result += k;\n\
+             \x20           if k < low + 2 {\n\
+             \x20               break;\n\
+             \x20           }\n\
+             \x20           k -= 2;\n\
+             \x20       }\n"
+        ));
+
+        let err = rust::translate(
+            "FUNCTION Since : BOOL\nVAR d : DATE; END_VAR\n    Since := d = D#2024-01-01;\nEND_FUNCTION\n",
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Since: the literal D#2024-01-01 has no Rust counterpart"
+        );
+    }
+
+    #[test]
+    fn mc7() {
+        use rust2plc::awl::{BlockKind, BlockNumber};
+        use rust2plc::s7::mc7::{self, Language};
+
+        let dir = std::env::temp_dir().join(format!("plc-predicate-mc7-{}", std::process::id()));
+        let blocks = awl_project().export_mc7(&dir).unwrap();
+        // snapshots of earlier exports, nothing here has been compared with STEP 7
+        let fixtures: [(&str, &[u8]); 6] = [
+            ("FC10.mc7", include_bytes!("../tests/fixtures/mc7/FC10.mc7")),
+            ("FB2.mc7", include_bytes!("../tests/fixtures/mc7/FB2.mc7")),
+            ("FB1.mc7", include_bytes!("../tests/fixtures/mc7/FB1.mc7")),
+            ("FB5.mc7", include_bytes!("../tests/fixtures/mc7/FB5.mc7")),
+            ("DB5.mc7", include_bytes!("../tests/fixtures/mc7/DB5.mc7")),
+            ("OB1.mc7", include_bytes!("../tests/fixtures/mc7/OB1.mc7")),
+        ];
+        assert_eq!(blocks.len(), fixtures.len());
+        for (block, (name, fixture)) in blocks.iter().zip(fixtures) {
+            assert_eq!(block.file_name(), name);
+            assert_eq!(std::fs::read(dir.join(name)).unwrap(), fixture, "{}", name);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(blocks[2].local_data(), 10);
+        assert_eq!(blocks[4].language(), Language::Db);
+
+        let blocks = mc7::assemble(
+            "TYPE UDT 1\n\
+             \x20 STRUCT\n\
+             \x20   x : REAL;\n\
+             \x20   y : REAL := 1.500000e+000;\n\
+             \x20   label : STRING [4] := 'ab';\n\
+             \x20 END_STRUCT ;\n\
+             END_TYPE\n\
+             \n\
+             FUNCTION_BLOCK FB 3\n\
+             VERSION : 1.2\n\
+             AUTHOR : plc\n\
+             \n\
+             VAR\n\
+             \x20 p : UDT 1;\n\
+             \x20 ready : BOOL;\n\
+             \x20 on : BOOL := TRUE;\n\
+             \x20 gains : ARRAY [1 .. 3] OF INT := 1, -2;\n\
+             \x20 wait : TIME := T#1s;\n\
+             END_VAR\n\
+             BEGIN\n\
+             NETWORK\n\
+             \x20     L     #gains[3];\n\
+             \x20     L     1;\n\
+             \x20     +I    ;\n\
+             \x20     T     #gains[1];\n\
+             \x20     AN    #on;\n\
+             \x20     =     #on;\n\
+             END_FUNCTION_BLOCK\n\
+             \n\
+             DATA_BLOCK DB 3\n\
+             \x20 FB 3\n\
+             BEGIN\n\
+             END_DATA_BLOCK\n",
+        )
+        .unwrap();
+        assert_eq!(blocks.len(), 2);
+        let interface = [
+            0x00, 0x19, 0x11, 0x04, 0x03, 0x08, 0x04, 0x08, 0x0C, 0x13, 0x0C, 0x04, 0x01, 0x04,
+            0x01, 0x0C, 0x10, 0x0C, 0x01, 0x00, 0x01, 0x00, 0x03, 0x05, 0x04, 0x0B, 0x0C, 0x00,
+            0x11, 0x3F, 0xC0, 0x00, 0x00, 0x04, 0x02, b'a', b'b', 0x01, 0x00, 0x01, 0xFF, 0xFE,
+            0x00, 0x00, 0x03, 0xE8,
+        ];
+        assert_eq!(
+            blocks[0].code(),
+            [
+                0xE1, 0x50, 0x00, 0x14, 0x30, 0x03, 0x00, 0x01, 0x79, 0x00, 0xE5, 0x50, 0x00, 0x10,
+                0xC1, 0x51, 0x00, 0x0E, 0xC6, 0x51, 0x00, 0x0E, 0x65, 0x00
+            ]
+        );
+        assert_eq!(blocks[0].interface(), interface);
+        assert_eq!(blocks[1].number(), BlockNumber::new(BlockKind::Db, 3));
+        assert_eq!(
+            blocks[1].code(),
+            [
+                0x00, 0x00, 0x00, 0x00, 0x3F, 0xC0, 0x00, 0x00, 0x04, 0x02, b'a', b'b', 0x00, 0x00,
+                0x02, 0x00, 0x00, 0x01, 0xFF, 0xFE, 0x00, 0x00, 0x00, 0x00, 0x03, 0xE8
+            ]
+        );
+        assert_eq!(blocks[1].interface(), interface);
+        let bytes = blocks[0].to_bytes().unwrap();
+        assert_eq!(bytes.len(), 36 + 24 + 46 + 36);
+        assert_eq!(
+            &bytes[..12],
+            [0x70, 0x70, 0x01, 0x01, 0x01, 0x0E, 0x00, 0x03, 0x00, 0x00, 0x00, 0x8E]
+        );
+        assert_eq!(
+            &bytes[bytes.len() - 36..bytes.len() - 24],
+            *b"plc\0\0\0\0\0\0\0\0\0"
+        );
+        assert_eq!(
+            &bytes[bytes.len() - 12..bytes.len() - 8],
+            [0x12, 0x00, 0x00, 0x00]
+        );
+
+        let error = |awl: &str| mc7::assemble(awl).unwrap_err().to_string();
+        assert_eq!(
+            error("FUNCTION FC 1 : VOID\nBEGIN\nNETWORK\n      A     \"Start\";\nEND_FUNCTION\n"),
+            "parse error at line 4, column 7: \"Start\" is a symbol, MC7 needs its absolute address"
+        );
+        assert_eq!(
+            error("FUNCTION FC 1 : VOID\nBEGIN\nNETWORK\n      LAR1  ;\nEND_FUNCTION\n"),
+            "parse error at line 4, column 7: LAR1 has no MC7 encoding"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}\nFUNCTION FC 2 : VOID\nBEGIN\nNETWORK\n      CALL  FC 10 (level := 1);\nEND_FUNCTION\n",
+                DOSE_STEPS_AWL
+            )),
+            "parse error at line 69, column 7: the call of FC 10 has no actual parameter for target"
+        );
+        assert_eq!(
+            mc7::Block::new(
+                BlockNumber::new(BlockKind::Udt, 1),
+                Language::Awl,
+                vec![],
+                vec![]
+            )
+            .to_bytes()
+            .unwrap_err()
+            .to_string(),
+            "UDT 1 exists in the offline project only, the CPU has no block for it"
+        );
+    }
 }